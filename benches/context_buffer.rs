@@ -0,0 +1,98 @@
+//! Benchmarks of reading wide lines through a `ContextBuffer`, as the pager
+//! does when scrolling and filtering, run with `cargo bench`.
+//!
+//! The benchmarks use only the standard library, timing each case over a
+//! number of rounds and reporting the fastest, so that they run on stable
+//! Rust without a benchmark harness.
+
+extern crate filterless;
+
+use std::time::{Duration, Instant};
+
+use filterless::{ContextBuffer, FilterPredicate, LineBuffer};
+
+/// Number of lines of input read by each case.
+const NUM_LINES: usize = 20_000;
+/// Bytes of text in each line, as in a log of wide JSON records.
+const LINE_BYTES: usize = 2_000;
+/// Number of times each case is run.
+const ROUNDS: usize = 10;
+
+
+/// Returns the lines of the input: wide lines, one in a hundred of which
+/// contains `ERROR`.
+fn lines() -> Vec<String> {
+    (0..NUM_LINES)
+        .map(|i| {
+            let level = if i % 100 == 0 { "ERROR" } else { "INFO" };
+            let line = format!("{:06} {} request handled ", i, level);
+            let padding = "x".repeat(LINE_BYTES.saturating_sub(line.len()));
+            line + &padding
+        })
+        .collect()
+}
+
+type Lines<'a> = std::iter::Cloned<std::slice::Iter<'a, String>>;
+
+/// Returns a `LineBuffer` which has already cached `lines`, as it has once
+/// the input has been paged through.
+fn cached(lines: &[String]) -> LineBuffer<Lines<'_>> {
+    let mut line_buffer = LineBuffer::new(lines.iter().cloned());
+    line_buffer.read_to_end();
+    line_buffer
+}
+
+/// Reads every line of `context_buffer`, returning the number read and the
+/// `LineBuffer`, which is dropped once the time is taken.
+fn read(mut context_buffer: ContextBuffer<Lines<'_>>) -> (usize, LineBuffer<Lines<'_>>) {
+    let count = context_buffer.by_ref().count();
+    (count, context_buffer.into_line_buffer())
+}
+
+/// Runs `case` `ROUNDS` times, each on a `LineBuffer` which has cached
+/// `lines`, reporting the fastest run along with the time it took per line
+/// of input. `case` returns the number of lines it read.
+fn bench<'a, F>(name: &str, lines: &'a [String], mut case: F)
+    where F: FnMut(LineBuffer<Lines<'a>>) -> (usize, LineBuffer<Lines<'a>>) {
+    let mut fastest = Duration::from_secs(u64::MAX);
+    let mut count = 0;
+    for _ in 0..ROUNDS {
+        let line_buffer = cached(lines);
+        let started = Instant::now();
+        let (read, _line_buffer) = case(line_buffer);
+        fastest = fastest.min(started.elapsed());
+        count = read;
+    }
+
+    println!("{:<28} {:>8.2}ms {:>8.1}ns/line  ({} lines out)",
+             name, fastest.as_secs_f64() * 1000.0,
+             fastest.as_secs_f64() * 1e9 / NUM_LINES as f64, count);
+}
+
+fn main() {
+    let lines = lines();
+
+    let filter = |pattern: &str, context_lines| {
+        Some(FilterPredicate::new(pattern.to_owned(), context_lines))
+    };
+
+    // every line copied out of the cache once, for reference
+    bench("copy of each line", &lines, |mut line_buffer| {
+        let count = (1..=NUM_LINES).filter(|&n| line_buffer.get(n).is_some()).count();
+        (count, line_buffer)
+    });
+    bench("unfiltered", &lines, |line_buffer| read(ContextBuffer::new(None, line_buffer)));
+    bench("filtered, sparse matches", &lines, |line_buffer| {
+        read(ContextBuffer::new(filter("ERROR", 0), line_buffer))
+    });
+    bench("filtered, 3 context lines", &lines, |line_buffer| {
+        read(ContextBuffer::new(filter("ERROR", 3), line_buffer))
+    });
+    bench("filtered, no matches", &lines, |line_buffer| {
+        read(ContextBuffer::new(filter("FATAL", 3), line_buffer))
+    });
+    bench("inverse filter", &lines, |line_buffer| {
+        let inverse = filter("ERROR", 1).map(|predicate| predicate.with_inverse(true));
+        read(ContextBuffer::new(inverse, line_buffer))
+    });
+}
@@ -0,0 +1,197 @@
+//! Feeds arbitrary input through `LineBuffer`, `ContextBuffer` and
+//! `WindowBuffer`, filtered by an arbitrary predicate, while moving the
+//! window about, and checks each line shown against the input.
+//!
+//! The fuzz input is decoded as:
+//!
+//! * 1 byte: height of the window, from 1 to 8
+//! * 1 byte: number of context lines, from 0 to 3
+//! * 1 byte: kind of matcher, and whether case and accents are ignored
+//! * 1 byte: length of the filter string, at most 7; 0 for no filter
+//! * the filter string
+//! * 1 byte: number of moves of the window, at most 31
+//! * 2 bytes per move: what the move is, and its argument
+//! * the lines of the input, separated by `\n`
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate filterless;
+
+use filterless::{matcher, FilteredLine, FilterPredicate, MatchOptions, WindowBuffer};
+
+/// Width of the window, which has no bearing on which lines are shown.
+const WIDTH: usize = 80;
+
+/// Bytes of the fuzz input, read from the front.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    /// Returns the next byte, or 0 once the input is used up.
+    fn byte(&mut self) -> u8 {
+        self.take(1).first().cloned().unwrap_or(0)
+    }
+
+    /// Returns the next `len` bytes, or as many as remain.
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        let (taken, rest) = self.0.split_at(len.min(self.0.len()));
+        self.0 = rest;
+        taken
+    }
+}
+
+/// Returns the predicate described by the fuzz input, or `None` if it
+/// describes no filter or an invalid one.
+fn predicate(bytes: &mut Bytes) -> Option<FilterPredicate> {
+    let context_lines = (bytes.byte() % 4) as usize;
+    let flags = bytes.byte();
+    let len = (bytes.byte() % 8) as usize;
+    let pattern = String::from_utf8_lossy(bytes.take(len)).into_owned();
+
+    let kinds = matcher::kinds();
+    let kind = &kinds[(flags & 0x0f) as usize % kinds.len()];
+    let options = MatchOptions {
+        ignore_case: flags & 0x10 != 0,
+        ignore_accents: flags & 0x20 != 0,
+    };
+
+    if len == 0 {
+        return None;
+    }
+    FilterPredicate::with_kind(kind, &pattern, &options, context_lines).ok()
+}
+
+/// Checks that `lines`, as shown by a window of `height` lines, are the lines
+/// of `input` which `predicate` selects, in order, along with any revealed
+/// within gaps if `revealed`.
+fn check_page(lines: &[FilteredLine], input: &[String], predicate: &Option<FilterPredicate>,
+              height: usize, revealed: bool) {
+    assert!(lines.len() <= height, "page of {} lines in a window of {}", lines.len(), height);
+
+    let mut prev: Option<&FilteredLine> = None;
+    let mut prev_line_num: Option<usize> = None;
+    for line in lines {
+        check_line(line, input, predicate, revealed);
+
+        match (prev, line.line_num()) {
+            (Some(&FilteredLine::Gap { .. }), None) => panic!("consecutive gaps in {:?}", lines),
+            (Some(&FilteredLine::Gap { from, to }), Some(line_num)) => {
+                assert_eq!(line_num, to + 1, "gap doesn't end before its next line in {:?}",
+                           lines);
+                if let Some(prev_line_num) = prev_line_num {
+                    assert_eq!(from, prev_line_num + 1,
+                               "gap doesn't start after its previous line in {:?}", lines);
+                }
+            },
+            (Some(_), Some(line_num)) => {
+                let prev_line_num = prev_line_num.unwrap();
+                assert!(line_num > prev_line_num, "lines out of order in {:?}", lines);
+                if predicate.is_none() {
+                    assert_eq!(line_num, prev_line_num + 1, "line skipped in {:?}", lines);
+                }
+            },
+            _ => {},
+        }
+
+        prev = Some(line);
+        if let Some(line_num) = line.line_num() {
+            prev_line_num = Some(line_num);
+        }
+    }
+}
+
+/// Checks that `line` is a line of `input` classified as `predicate` would,
+/// allowing for context lines far from matches if lines were `revealed`.
+fn check_line(line: &FilteredLine, input: &[String], predicate: &Option<FilterPredicate>,
+              revealed: bool) {
+    let (line_num, text) = match *line {
+        FilteredLine::Gap { from, to } => {
+            assert!(predicate.is_some(), "gap without a filter");
+            assert!(1 <= from && from <= to && to <= input.len(), "gap of lines {} to {} of {}",
+                    from, to, input.len());
+            return;
+        },
+        FilteredLine::ContextLine((line_num, ref text)) |
+        FilteredLine::MatchLine((line_num, ref text)) |
+        FilteredLine::UnfilteredLine((line_num, ref text)) => (line_num, text),
+    };
+
+    assert!(1 <= line_num && line_num <= input.len(), "line {} of {}", line_num, input.len());
+    assert_eq!(text, &input[line_num - 1], "text of line {}", line_num);
+
+    let predicate = match *predicate {
+        Some(ref predicate) => predicate,
+        None => {
+            assert!(matches!(*line, FilteredLine::UnfilteredLine(_)), "{:?} without a filter", line);
+            return;
+        },
+    };
+
+    let is_match = |line_num: usize| predicate.matcher.is_match(&input[line_num - 1]);
+    match *line {
+        FilteredLine::MatchLine(_) => assert!(is_match(line_num), "{:?} doesn't match", line),
+        FilteredLine::ContextLine(_) => {
+            assert!(!is_match(line_num), "{:?} matches", line);
+            let first = line_num.saturating_sub(predicate.context_lines).max(1);
+            let last = (line_num + predicate.context_lines).min(input.len());
+            assert!(revealed || (first..last + 1).any(is_match), "{:?} isn't near a match",
+                    line);
+        },
+        _ => panic!("{:?} with a filter", line),
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut bytes = Bytes(data);
+    let mut height = (bytes.byte() % 8) as usize + 1;
+    let mut predicate = predicate(&mut bytes);
+    let num_moves = (bytes.byte() % 32) as usize;
+    let moves = bytes.take(num_moves * 2);
+    let input: Vec<String> = String::from_utf8_lossy(bytes.take(data.len()))
+        .split('\n')
+        .map(|line| line.to_owned())
+        .collect();
+
+    let mut window = WindowBuffer::new(input.clone().into_iter(), predicate.clone(), WIDTH, height);
+    let mut revealed = false;
+
+    for step in moves.chunks(2) {
+        let (op, arg) = (step[0], step.get(1).cloned().unwrap_or(0) as usize);
+        let page = match op % 12 {
+            0 => window.next_page(),
+            1 => window.prev_page(),
+            2 => window.current_page(),
+            3 => window.last_page(),
+            4 => window.goto_line(arg),
+            5 => window.goto_fraction(arg as f64 / 255.0),
+            6 => window.next_line().into_iter().collect(),
+            7 => window.prev_line().into_iter().collect(),
+            8 => {
+                height = arg % 8 + 1;
+                window.resize(WIDTH, height);
+                window.current_page()
+            },
+            9 => {
+                revealed |= window.reveal_context(arg % height, arg / 8 + 1).is_some();
+                window.current_page()
+            },
+            10 => {
+                revealed |= window.expand_gap(arg % height).is_some();
+                window.current_page()
+            },
+            _ => {
+                revealed = false;
+                predicate = if predicate.is_some() {
+                    None
+                } else {
+                    Some(FilterPredicate::new(String::from_utf8_lossy(&[arg as u8]).into_owned(),
+                                              arg % 4))
+                };
+                window.set_predicate(predicate.clone());
+                window.current_page()
+            },
+        };
+        check_page(&page, &input, &predicate, height, revealed);
+    }
+});
@@ -0,0 +1,106 @@
+//! Copying of text to the system clipboard, either by the terminal itself
+//! through an OSC 52 escape sequence, which works over SSH and within tmux
+//! when it's allowed to set the clipboard, or by a command such as
+//! `xclip -selection clipboard` to which the text is piped.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Most bytes of text copied by an OSC 52 escape sequence: those whose
+/// encoding fits within the 100,000 bytes beyond which some terminals
+/// silently drop the sequence.
+const MAX_OSC52_BYTES: usize = 74_994;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+
+/// Copies `text` to the system clipboard.
+///
+/// ### Parameters
+/// * `text`: the text to copy
+/// * `command`: shell command to pipe the text to, or `None` to have the
+///   terminal copy it
+pub fn copy(text: &str, command: Option<&str>) -> Result<(), String> {
+    match command {
+        Some(command) => pipe_to(text, command),
+        None => {
+            if text.len() > MAX_OSC52_BYTES {
+                return Err(format!("Can't copy more than {} bytes without --clipboard-command",
+                                   MAX_OSC52_BYTES));
+            }
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            write!(stdout, "{}", osc52(text))
+                .and_then(|_| stdout.flush())
+                .map_err(|e| format!("Can't copy: {}", e))
+        },
+    }
+}
+
+/// Runs `command` with `sh -c`, writing `text` to its standard input.
+fn pipe_to(text: &str, command: &str) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("{}: {}", command, e))?;
+
+    let written = child.stdin.take().map_or(Ok(()), |mut stdin| stdin.write_all(text.as_bytes()));
+    let status = child.wait().map_err(|e| format!("{}: {}", command, e))?;
+    written.map_err(|e| format!("{}: {}", command, e))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{}: {}", command, status))
+    }
+}
+
+/// Returns the OSC 52 escape sequence which sets the clipboard to `text`.
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+/// Returns the standard, padded base64 encoding of `bytes`.
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base64, copy, osc52};
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe]), "//4=");
+        assert_eq!(osc52("hi"), "\x1b]52;c;aGk=\x07");
+    }
+
+    #[test]
+    fn test_copy_with_command() {
+        assert_eq!(copy("text", Some("test \"$(cat)\" = text")), Ok(()));
+        assert!(copy("text", Some("exit 3")).unwrap_err().contains("3"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Commands run at the `:` prompt and over the control socket, each
+//! registered by name along with the arguments it takes, so that every
+//! command is parsed, checked and dispatched alike.
+
+/// Command run by name, e.g. `goto 120`, acting upon a context `C`.
+pub struct Command<C> {
+    /// name by which the command is run
+    pub name: &'static str,
+    /// arguments taken, as shown in usage messages, from which the number of
+    /// arguments allowed is told: `FILE` is required, `[N]` optional, and
+    /// `PATTERN...` or `[ARG...]` may be any number of words
+    pub args: &'static str,
+    /// what the command does
+    pub description: &'static str,
+    /// runs the command, returning a message describing the outcome, if any
+    pub run: fn(&mut C, &Args) -> Result<Option<String>, String>,
+}
+
+/// Arguments given to a command.
+pub struct Args<'a> {
+    /// the arguments, split by whitespace
+    pub words: Vec<&'a str>,
+    /// the arguments as typed, less surrounding whitespace, e.g. a pattern
+    /// which contains spaces
+    pub text: &'a str,
+}
+
+impl<C> Command<C> {
+    /// Returns the message given when the command is run with the wrong
+    /// number of arguments, e.g. `usage: gaps [N]`.
+    pub fn usage(&self) -> String {
+        if self.args.is_empty() {
+            format!("usage: {}", self.name)
+        } else {
+            format!("usage: {} {}", self.name, self.args)
+        }
+    }
+
+    /// Returns the least and the most number of arguments the command takes,
+    /// or `None` for the most if it takes any number.
+    fn arity(&self) -> (usize, Option<usize>) {
+        let mut least = 0;
+        let mut most = Some(0);
+        for arg in self.args.split_whitespace() {
+            let optional = arg.starts_with('[') && arg.ends_with(']');
+            if !optional {
+                least += 1;
+            }
+            most = if arg.contains("...") { None } else { most.map(|most| most + 1) };
+        }
+        (least, most)
+    }
+}
+
+/// Runs the command on a command line, e.g. `context 5`, returning a message
+/// describing the outcome, if any, or an error if there's no such command or
+/// it's given the wrong number of arguments.
+///
+/// ### Parameters
+/// * `commands`: the commands which may be run
+/// * `line`: the command line, its first word naming the command
+/// * `context`: what the command acts upon
+pub fn run<C>(commands: &[Command<C>], line: &str, context: &mut C)
+              -> Result<Option<String>, String> {
+    let line = line.trim();
+    let (name, text) = match line.find(char::is_whitespace) {
+        Some(end) => (&line[..end], line[end..].trim()),
+        None => (line, ""),
+    };
+    if name.is_empty() {
+        return Ok(None);
+    }
+
+    let command = commands.iter()
+        .find(|command| command.name == name)
+        .ok_or_else(|| format!("unknown command `{}`", name))?;
+    let args = Args { words: text.split_whitespace().collect(), text: text };
+    let (least, most) = command.arity();
+    if args.words.len() < least || most.is_some_and(|most| args.words.len() > most) {
+        return Err(command.usage());
+    }
+    (command.run)(context, &args)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{run, Args, Command};
+
+    fn echo(said: &mut Vec<String>, args: &Args) -> Result<Option<String>, String> {
+        said.push(args.text.to_owned());
+        Ok(Some(args.words.join("|")))
+    }
+
+    fn command(name: &'static str, args: &'static str) -> Command<Vec<String>> {
+        Command { name: name, args: args, description: "", run: echo }
+    }
+
+    #[test]
+    fn test_run() {
+        let commands = [
+            command("first", ""),
+            command("gaps", "[N]"),
+            command("quiet", "LINES PATTERN..."),
+            command("sort", "[-]COLUMN [DELIMITER]"),
+        ];
+        let mut said = Vec::new();
+
+        assert_eq!(run(&commands, " quiet 30  heart  beat ", &mut said),
+                   Ok(Some("30|heart|beat".to_owned())));
+        assert_eq!(said, vec!["30  heart  beat"]);
+        assert_eq!(run(&commands, "sort -4", &mut said), Ok(Some("-4".to_owned())));
+        assert_eq!(run(&commands, "gaps", &mut said), Ok(Some(String::new())));
+        assert_eq!(run(&commands, "  ", &mut said), Ok(None));
+
+        assert_eq!(run(&commands, "quiet 30", &mut said),
+                   Err("usage: quiet LINES PATTERN...".to_owned()));
+        assert_eq!(run(&commands, "sort", &mut said),
+                   Err("usage: sort [-]COLUMN [DELIMITER]".to_owned()));
+        assert_eq!(run(&commands, "gaps 1 2", &mut said), Err("usage: gaps [N]".to_owned()));
+        assert_eq!(run(&commands, "first 1", &mut said), Err("usage: first".to_owned()));
+        assert_eq!(run(&commands, "last", &mut said), Err("unknown command `last`".to_owned()));
+        assert_eq!(said.len(), 3);
+    }
+}
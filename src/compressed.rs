@@ -0,0 +1,202 @@
+//! Transparent decompression of inputs compressed with gzip, bzip2 or xz,
+//! which are recognized by their magic bytes, or failing that by the
+//! extension of their file name, and decompressed as they're read.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+#[cfg(feature = "compressed")]
+use bzip2::read::MultiBzDecoder;
+#[cfg(feature = "compressed")]
+use flate2::read::MultiGzDecoder;
+#[cfg(feature = "compressed")]
+use xz2::read::XzDecoder;
+
+/// Number of leading bytes by which every format is recognized.
+const MAGIC_LEN: usize = 6;
+/// Extensions of the names of datasets of JSON lines, which are paged in JSON
+/// mode with the `dataset` feature
+#[cfg(feature = "dataset")]
+const DATASET_EXTENSIONS: &[&str] = &["jsonl", "ndjson"];
+
+
+/// Format in which an input is compressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+}
+
+/// Formats with their magic bytes and the extensions of their file names.
+const FORMATS: &[(Compression, &[u8], &str)] = &[
+    (Compression::Gzip, b"\x1f\x8b", "gz"),
+    (Compression::Bzip2, b"BZh", "bz2"),
+    (Compression::Xz, b"\xfd7zXZ\x00", "xz"),
+];
+
+impl Compression {
+    /// Returns the format in which an input is compressed, if it is.
+    ///
+    /// ### Parameters
+    /// * `head`: the first bytes of the input, which are enough to tell if
+    ///   there are at least `MAGIC_LEN` of them
+    /// * `path`: path of the input file, by whose extension the format is
+    ///   told if there are too few bytes, e.g. of a file just created
+    pub fn detect(head: &[u8], path: Option<&Path>) -> Option<Compression> {
+        let by_magic = FORMATS.iter().find(|&&(_, magic, _)| head.starts_with(magic));
+        if let Some(&(compression, _, _)) = by_magic {
+            return Some(compression);
+        }
+        if head.len() >= MAGIC_LEN {
+            return None;
+        }
+
+        let extension = path.and_then(Path::extension).and_then(|ext| ext.to_str())?;
+        FORMATS.iter()
+            .find(|&&(_, _, ext)| ext == extension)
+            .map(|&(compression, _, _)| compression)
+    }
+
+    /// Returns the format in which a file is compressed, if it is, leaving
+    /// the position from which the file is read alone.
+    ///
+    /// ### Parameters
+    /// * `file`: the file, opened for reading
+    /// * `path`: path by which the file was opened
+    pub fn of_file(file: &File, path: &Path) -> io::Result<Option<Compression>> {
+        let mut head = [0; MAGIC_LEN];
+        let mut len = 0;
+        while len < head.len() {
+            match file.read_at(&mut head[len..], len as u64)? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        Ok(Compression::detect(&head[..len], Some(path)))
+    }
+}
+
+/// Returns `true` if `path` names a dataset of JSON lines, i.e. ends with one
+/// of `DATASET_EXTENSIONS`, followed by the extension of a compression format
+/// if it's compressed, e.g. `train.jsonl.gz`.
+#[cfg(feature = "dataset")]
+pub fn is_dataset(path: &Path) -> bool {
+    fn extension(path: &Path) -> Option<&str> {
+        path.extension()?.to_str()
+    }
+
+    let compressed = extension(path)
+        .is_some_and(|extension| FORMATS.iter().any(|&(_, _, ext)| ext == extension));
+    let name = if compressed { path.file_stem() } else { path.file_name() };
+    name.and_then(|name| extension(Path::new(name)))
+        .is_some_and(|ext| DATASET_EXTENSIONS.contains(&ext))
+}
+
+/// Wraps `reader` in a decompressing reader if what it reads is compressed,
+/// e.g. so that `app.log.gz` is paged like `app.log`.
+///
+/// Returns an error if the input is compressed but support for compressed
+/// inputs wasn't compiled in.
+///
+/// ### Parameters
+/// * `reader`: reader of the input, which is peeked at without consuming
+///   anything
+/// * `path`: path of the input file, if it's read from one
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R, path: Option<&Path>)
+                                       -> io::Result<Box<dyn BufRead + 'a>> {
+    let compression = {
+        let head = reader.fill_buf()?;
+        Compression::detect(&head[..head.len().min(MAGIC_LEN)], path)
+    };
+
+    match compression {
+        Some(compression) => Ok(Box::new(BufReader::new(decoder(reader, compression)?))),
+        None => Ok(Box::new(reader)),
+    }
+}
+
+/// Returns a reader of what `reader` reads decompressed, concatenated
+/// streams included, as written by e.g. `gzip -c a b > ab.gz`.
+#[cfg(feature = "compressed")]
+fn decoder<'a, R: BufRead + 'a>(reader: R, compression: Compression)
+                                -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match compression {
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+    })
+}
+
+#[cfg(not(feature = "compressed"))]
+fn decoder<'a, R: BufRead + 'a>(_reader: R, compression: Compression)
+                                -> io::Result<Box<dyn Read + 'a>> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+        "input is compressed with {:?}, but filterless was built without the `compressed` \
+         feature", compression)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::Compression;
+
+    #[test]
+    fn test_detect() {
+        let gz = Path::new("app.log.gz");
+        assert_eq!(Compression::detect(b"\x1f\x8b\x08\x00\x00\x00", None),
+                   Some(Compression::Gzip));
+        assert_eq!(Compression::detect(b"BZh91AY&SY", Some(gz)), Some(Compression::Bzip2));
+        assert_eq!(Compression::detect(b"\xfd7zXZ\x00\x00", None), Some(Compression::Xz));
+        // magic bytes trump a misleading extension
+        assert_eq!(Compression::detect(b"plain text", Some(gz)), None);
+        // the extension tells the format of files too short to tell by
+        assert_eq!(Compression::detect(b"", Some(gz)), Some(Compression::Gzip));
+        assert_eq!(Compression::detect(b"", Some(Path::new("app.log"))), None);
+        assert_eq!(Compression::detect(b"ab", None), None);
+    }
+
+    #[cfg(feature = "dataset")]
+    #[test]
+    fn test_is_dataset() {
+        use super::is_dataset;
+
+        assert!(is_dataset(Path::new("a.jsonl")));
+        assert!(is_dataset(Path::new("data/a.ndjson.xz")));
+        assert!(is_dataset(Path::new("a.jsonl.gz")));
+        assert!(!is_dataset(Path::new("a.json.gz")));
+        assert!(!is_dataset(Path::new("a.log.gz")));
+        assert!(!is_dataset(Path::new("jsonl")));
+        assert!(!is_dataset(Path::new("jsonl.gz")));
+    }
+
+    #[cfg(feature = "compressed")]
+    #[test]
+    fn test_decompress() {
+        use std::io::{BufRead, Cursor, Write};
+        use flate2::write::GzEncoder;
+        use flate2::Compression as Level;
+
+        use super::decompress;
+
+        // two concatenated streams
+        let mut compressed = Vec::new();
+        for text in &["one\ntwo\n", "three\n"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+            encoder.write_all(text.as_bytes()).unwrap();
+            compressed.extend(encoder.finish().unwrap());
+        }
+
+        let lines: Vec<String> = decompress(Cursor::new(compressed), None).unwrap()
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert_eq!(lines, vec!["one", "two", "three"]);
+
+        let plain = decompress(Cursor::new(b"one\n".to_vec()), None).unwrap();
+        assert_eq!(plain.lines().next().unwrap().unwrap(), "one");
+    }
+}
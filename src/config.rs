@@ -0,0 +1,243 @@
+//! Shareable configuration: the color theme, filter presets and key bindings.
+//!
+//! Configuration files are a subset of TOML:
+//!
+//! ```text
+//! [theme]
+//! match = "white red bold"
+//!
+//! [presets]
+//! req = 'request_id=%1'
+//!
+//! [keys]
+//! next_page = 'pgdn space'
+//! ```
+
+use std::borrow::Cow;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use keymap::Keymap;
+use preset::Presets;
+use read_only;
+use theme::Theme;
+
+
+/// Sections of a configuration file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Section {
+    Theme,
+    Presets,
+    Keys,
+}
+
+/// Settings which may be exported to a file and imported by another session.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub theme: Theme,
+    pub presets: Presets,
+    pub keys: Keymap,
+}
+
+impl Config {
+    pub fn new(theme: Theme, presets: Presets, keys: Keymap) -> Config {
+        Config { theme: theme, presets: presets, keys: keys }
+    }
+
+    /// Returns the path of the user's configuration file, read at startup if
+    /// it exists: `filterless/config` within `$XDG_CONFIG_HOME`, or else
+    /// within `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|dir| dir.join("filterless").join("config"))
+    }
+
+    /// Writes this configuration to a file.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the file to create or overwrite
+    pub fn export(&self, path: &str) -> Result<(), String> {
+        read_only::create(path)
+            .and_then(|mut file| write!(file, "{}", self))
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Reads a configuration file, merging it into this configuration.
+    ///
+    /// Styles, presets and the keys of actions named in the file replace those
+    /// of the same name; all others are left as they are.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the configuration file
+    pub fn import(&mut self, path: &str) -> Result<(), String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path, e))?;
+
+        // merge into a copy so a malformed file leaves the configuration as
+        // it was
+        let mut merged = self.clone();
+        merged.merge(&text).map_err(|e| format!("{}:{}", path, e))?;
+        *self = merged;
+        Ok(())
+    }
+
+    /// Merges the text of a configuration file into this configuration.
+    fn merge(&mut self, text: &str) -> Result<(), String> {
+        let mut section = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                section = match trimmed[1..trimmed.len() - 1].trim() {
+                    "theme" => Some(Section::Theme),
+                    "presets" => Some(Section::Presets),
+                    "keys" => Some(Section::Keys),
+                    other => return Err(format!("{}: unknown section `{}`", i + 1, other)),
+                };
+                continue;
+            }
+
+            let result = match section {
+                Some(Section::Theme) => self.theme.apply_line(trimmed),
+                Some(Section::Presets) => self.presets.apply_line(trimmed),
+                Some(Section::Keys) => self.keys.apply_line(trimmed),
+                None => Err("expected `[theme]`, `[presets]` or `[keys]`".to_owned()),
+            };
+            result.map_err(|e| format!("{}: {}", i + 1, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[theme]")?;
+        write!(f, "{}", self.theme)?;
+        writeln!(f)?;
+        writeln!(f, "[presets]")?;
+        for (name, template) in self.presets.iter() {
+            writeln!(f, "{} = {}", name, quote(template))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "[keys]")?;
+        write!(f, "{}", self.keys)
+    }
+}
+
+/// Returns `value` as a TOML string literal.
+pub fn quote(value: &str) -> String {
+    if !value.contains('\'') {
+        format!("'{}'", value)
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// Returns the contents of a string quoted by `quote()`, or `value` itself
+/// if it isn't quoted.
+///
+/// Within double quotes only `\\` and `\"` are unescaped; other backslashes
+/// are kept as they are.
+pub fn unquote<'a>(value: &'a str) -> Cow<'a, str> {
+    let quote = match value.chars().next() {
+        Some(quote @ '\'') | Some(quote @ '"') if value.len() >= 2 && value.ends_with(quote) => {
+            quote
+        },
+        _ => return Cow::Borrowed(value),
+    };
+
+    // both ends are ASCII quotes, so the slice is on char boundaries
+    let inner = &value[1..value.len() - 1];
+    if quote == '\'' || !inner.contains('\\') {
+        return Cow::Borrowed(inner);
+    }
+
+    let mut unescaped = String::with_capacity(inner.len());
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&'\\')) | ('\\', Some(&'"')) => {
+                unescaped.push(chars.next().unwrap());
+            },
+            _ => unescaped.push(c),
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+#[cfg(test)]
+mod test {
+    use ncurses;
+
+    use keymap::{Action, Keymap};
+    use preset::Presets;
+    use theme::{Style, Theme};
+
+    use super::{Config, quote, unquote};
+
+    #[test]
+    fn test_quote() {
+        for value in &["plain", "with 'single'", "with \"double\" and \\d+", ""] {
+            assert_eq!(unquote(&quote(value)), *value);
+        }
+
+        assert_eq!(unquote("bare words"), "bare words");
+        assert_eq!(unquote("\"\\d+\""), "\\d+");
+        // unquoted values may end with any character
+        assert_eq!(unquote("café"), "café");
+        assert_eq!(unquote("é ü"), "é ü");
+        assert_eq!(unquote("'é"), "'é");
+        assert_eq!(unquote("\""), "\"");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut presets = Presets::new();
+        presets.insert("req".to_owned(), "request_id=%1".to_owned());
+        presets.insert("quoted".to_owned(), "say 'hi' \\w+".to_owned());
+        let mut theme = Theme::default();
+        theme.match_style = Style::new(ncurses::COLOR_WHITE, -1, ncurses::A_BOLD());
+        let mut keys = Keymap::default();
+        keys.apply_line("quit = 'q ctrl-c'").unwrap();
+        let config = Config::new(theme, presets, keys);
+
+        let mut imported = Config::new(Theme::monochrome(), Presets::new(), Keymap::default());
+        imported.merge(&config.to_string()).unwrap();
+
+        assert_eq!(imported.to_string(), config.to_string());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut presets = Presets::new();
+        presets.insert("req".to_owned(), "request_id=%1".to_owned());
+        presets.insert("user".to_owned(), "user=%1".to_owned());
+        let mut config = Config::new(Theme::default(), presets, Keymap::default());
+
+        config.merge("[theme]\ngap = 'blue default'\n\n[presets]\nuser = 'uid=%1'\n\n\
+                      [keys]\nnext_line = 'j down'\n").unwrap();
+
+        assert_eq!(config.theme.gap_style, Style::new(ncurses::COLOR_BLUE, -1, 0));
+        assert_eq!(config.theme.match_style, Theme::default().match_style);
+        assert_eq!(config.presets.expand("req", &["a"]).unwrap(), "request_id=a");
+        assert_eq!(config.presets.expand("user", &["b"]).unwrap(), "uid=b");
+        assert_eq!(config.keys.keys(Action::NextLine), vec!['j' as i32, ncurses::KEY_DOWN]);
+        assert_eq!(config.keys.action('k' as i32), Some(Action::PrevLine));
+
+        assert!(config.merge("match = red black").is_err());
+        assert!(config.merge("[colors]").is_err());
+    }
+}
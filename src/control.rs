@@ -0,0 +1,165 @@
+//! Control socket through which other processes, e.g. editors and scripts,
+//! drive a running pager: each line written to the socket is a command,
+//! answered with a line beginning `ok` or `error:`.
+//!
+//! ```text
+//! $ echo 'filter test_parse_dates' | nc -U /tmp/fl.sock
+//! ok Filter: test_parse_dates
+//! ```
+//!
+//! Connections are served from background threads; the commands themselves
+//! are handed to the pager's event loop, which polls for them.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use read_only;
+
+/// Longest a connection waits for the pager to run a command, e.g. while the
+/// pager is busy at a prompt.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// Command received on the control socket, awaiting its outcome.
+pub struct Request {
+    /// the command line, without its line ending
+    pub command: String,
+    /// where the outcome is sent back to the connection
+    reply: Sender<Result<Option<String>, String>>,
+}
+
+impl Request {
+    /// Answers the connection which sent the command.
+    ///
+    /// ### Parameters
+    /// * `result`: a message describing the outcome, if any, or an error
+    pub fn reply(self, result: Result<Option<String>, String>) {
+        // case: the connection timed out or closed
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Listening control socket, removed when dropped.
+pub struct ControlSocket {
+    path: PathBuf,
+    requests: Receiver<Request>,
+}
+
+impl ControlSocket {
+    /// Listens on a Unix socket at `path`, replacing a socket left behind by
+    /// a process which is no longer listening on it.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the socket to create
+    pub fn bind(path: &Path) -> io::Result<ControlSocket> {
+        read_only::check()?;
+        let is_socket = fs::symlink_metadata(path).map(|m| m.file_type().is_socket());
+        if let Ok(true) = is_socket {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "already in use"));
+            }
+            fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || serve(stream, &sender));
+            }
+        });
+
+        Ok(ControlSocket { path: path.to_owned(), requests: receiver })
+    }
+
+    /// Returns the next command received, if any, without waiting.
+    pub fn poll(&self) -> Option<Request> {
+        self.requests.try_recv().ok()
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Hands each command read from a connection to the pager, answering it
+/// with the outcome, until the connection is closed.
+fn serve(stream: UnixStream, requests: &Sender<Request>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let command = line?;
+        if command.trim().is_empty() {
+            continue;
+        }
+
+        let (reply, outcome) = mpsc::channel();
+        if requests.send(Request { command: command, reply: reply }).is_err() {
+            return writeln!(writer, "error: shutting down");
+        }
+
+        match outcome.recv_timeout(REPLY_TIMEOUT) {
+            Ok(Ok(Some(message))) => writeln!(writer, "ok {}", message)?,
+            Ok(Ok(None)) => writeln!(writer, "ok")?,
+            Ok(Err(err)) => writeln!(writer, "error: {}", err)?,
+            Err(_) => writeln!(writer, "error: timed out")?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::process;
+    use std::thread;
+
+    use super::ControlSocket;
+
+    #[test]
+    fn test_control_socket() {
+        let path = env::temp_dir().join(format!("filterless-test-{}.sock", process::id()));
+        let socket = ControlSocket::bind(&path).unwrap();
+        assert!(ControlSocket::bind(&path).is_err());
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        let client = thread::spawn(move || {
+            stream.write_all(b"goto 5\n\nfilter [\n").unwrap();
+            let lines: Vec<String> = BufReader::new(stream).lines().take(2)
+                .map(|line| line.unwrap())
+                .collect();
+            lines
+        });
+
+        let next_request = || loop {
+            match socket.poll() {
+                Some(request) => return request,
+                None => thread::yield_now(),
+            }
+        };
+
+        let goto = next_request();
+        assert_eq!(goto.command, "goto 5");
+        goto.reply(Ok(None));
+        let filter = next_request();
+        assert_eq!(filter.command, "filter [");
+        filter.reply(Err("invalid filter".to_owned()));
+
+        assert_eq!(client.join().unwrap(), vec!["ok", "error: invalid filter"]);
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
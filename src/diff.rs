@@ -0,0 +1,138 @@
+//! Highlighting of the words in which a line differs from the line before
+//! it, where the two are alike, e.g. a request retried with a new attempt
+//! number and latency, so that the fields which changed stand out.
+
+use filterless::matcher::Span;
+
+/// Least share of their words two lines have in common, in order, for them
+/// to be alike enough that the words of one which differ are highlighted.
+const MIN_SIMILARITY: f64 = 0.6;
+
+/// Most words between the common beginning and end of two lines which are
+/// compared word by word; where there are more, they're all taken to differ.
+const MAX_WORDS: usize = 256;
+
+
+/// Returns the spans of the words of `line`: runs of letters, digits and
+/// underscores, and each other character but whitespace, e.g. `=` or `/`.
+fn words(line: &str) -> Vec<Span> {
+    let mut words: Vec<Span> = Vec::new();
+    let mut in_word = false;
+    for (idx, c) in line.char_indices() {
+        let end = idx + c.len_utf8();
+        let is_word = c.is_alphanumeric() || c == '_';
+        match words.last_mut() {
+            Some(&mut (_, ref mut last_end)) if is_word && in_word => *last_end = end,
+            _ if !c.is_whitespace() => words.push((idx, end)),
+            _ => {},
+        }
+        in_word = is_word;
+    }
+    words
+}
+
+/// Returns, for each of the words `b`, whether it's among the longest run of
+/// words, in order, which `a` and `b` have in common.
+fn common<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<bool> {
+    // lengths[i][j] is the length of the longest run common to a[i..], b[j..]
+    let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut in_common = vec![false; b.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            in_common[j] = true;
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    in_common
+}
+
+/// Returns the spans of the words of `line` in which it differs from `prev`,
+/// with the whitespace between consecutive ones, or none if the two lines
+/// aren't alike.
+///
+/// ### Parameters
+/// * `line`: text of the line whose words are highlighted
+/// * `prev`: text of the line before it
+pub fn changed_words(line: &str, prev: &str) -> Vec<Span> {
+    let prev: Vec<&str> = words(prev).into_iter().map(|(start, end)| &prev[start..end]).collect();
+    let spans = words(line);
+    let cur: Vec<&str> = spans.iter().map(|&(start, end)| &line[start..end]).collect();
+
+    let prefix = cur.iter().zip(&prev).take_while(|&(a, b)| a == b).count();
+    let suffix = cur[prefix..].iter().rev().zip(prev[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let (cur_middle, prev_middle) = (&cur[prefix..cur.len() - suffix],
+                                     &prev[prefix..prev.len() - suffix]);
+    let in_common = if cur_middle.len() <= MAX_WORDS && prev_middle.len() <= MAX_WORDS {
+        common(prev_middle, cur_middle)
+    } else {
+        vec![false; cur_middle.len()]
+    };
+
+    let num_common = prefix + suffix + in_common.iter().filter(|&&common| common).count();
+    let total = cur.len() + prev.len();
+    if total == 0 || (2 * num_common) as f64 / (total as f64) < MIN_SIMILARITY {
+        return Vec::new();
+    }
+
+    let mut changed: Vec<Span> = Vec::new();
+    let differing = spans[prefix..spans.len() - suffix].iter().zip(&in_common)
+        .filter(|&(_, &common)| !common)
+        .map(|(&span, _)| span);
+    for (start, end) in differing {
+        match changed.last_mut() {
+            Some(&mut (_, ref mut last_end)) if line[*last_end..start].trim().is_empty() => {
+                *last_end = end;
+            },
+            _ => changed.push((start, end)),
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod test {
+    use super::{changed_words, words};
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("GET /api?id=42  é_1"),
+                   vec![(0, 3), (4, 5), (5, 8), (8, 9), (9, 11), (11, 12), (12, 14), (16, 20)]);
+        assert_eq!(words(""), vec![]);
+    }
+
+    #[test]
+    fn test_changed_words() {
+        let prev = "10:00:01 retry attempt=1 latency=120ms path=/api/orders";
+        let line = "10:00:03 retry attempt=2 latency=95ms path=/api/orders";
+        let changed: Vec<&str> = changed_words(line, prev).into_iter()
+            .map(|(start, end)| &line[start..end])
+            .collect();
+        assert_eq!(changed, vec!["03", "2", "95ms"]);
+
+        // consecutive words which differ are highlighted as one
+        assert_eq!(changed_words("a b c d new words e", "a b c d old text e"),
+                   vec![(8, 17)]);
+
+        assert_eq!(changed_words("same", "same"), vec![]);
+        assert_eq!(changed_words("entirely different", "nothing alike at all"), vec![]);
+        assert_eq!(changed_words("", ""), vec![]);
+    }
+}
@@ -0,0 +1,63 @@
+//! Errors which stop the program, which are reported to the user as a
+//! message rather than by panicking.
+
+use std::fmt;
+use std::io;
+use std::result;
+
+
+/// Error which stops the program, e.g. an input file which can't be opened
+/// or an invalid argument.
+///
+/// It's reported by `main()` as `filterless: MESSAGE` on the standard error,
+/// after the terminal has been restored if it was set up, and the program
+/// exits with a non-zero status.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    message: String,
+}
+
+/// Result of a step of the program which may stop it.
+pub type Result<T> = result::Result<T, Error>;
+
+impl Error {
+    /// Creates an `Error` described by `message`, e.g. `app.log: No such
+    /// file or directory`.
+    pub fn new<S: Into<String>>(message: S) -> Error {
+        Error { message: message.into() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::new(message)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::new(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::Error;
+
+    #[test]
+    fn test_error() {
+        let err = Error::from(format!("{}: {}", "app.log", "No such file or directory"));
+        assert_eq!(err.to_string(), "app.log: No such file or directory");
+
+        let io_err = io::Error::new(io::ErrorKind::InvalidData, "not a terminal");
+        assert_eq!(Error::from(io_err), Error::new("not a terminal"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Input read from the output of a command run by the pager, e.g.
+//! `journalctl -f`, so that no shell pipe is needed and the command is
+//! stopped when the pager quits.
+
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use sample;
+
+/// Tag prefixed to the lines the command writes to its standard error.
+pub const STDERR_TAG: &str = "[stderr] ";
+
+
+/// Command run to produce the input.
+///
+/// Its output is read by background threads, so that the pager is never
+/// kept waiting for the command to write. The command is run in a process
+/// group of its own, which is terminated when this is dropped if the
+/// command is still running, so that no process it started is left behind.
+pub struct Exec {
+    /// the command line, as run by the shell
+    command: String,
+    child: Child,
+    /// how the command exited, once it has
+    exit_status: Option<ExitStatus>,
+}
+
+/// Lines written by a command, yielded as they're read; the iterator returns
+/// `None` while there are none to be had, and yields more once the command
+/// writes them, like a file being followed.
+pub struct ExecLines {
+    receiver: Receiver<String>,
+}
+
+impl Exec {
+    /// Runs `command` with the shell, returning it along with the lines it
+    /// writes to its standard output.
+    ///
+    /// ### Parameters
+    /// * `command`: the command line, e.g. `journalctl -f`
+    /// * `with_stderr`: whether the lines the command writes to its standard
+    ///   error are read as well, interleaved with the others as they come and
+    ///   tagged with `STDERR_TAG`
+    pub fn spawn(command: &str, with_stderr: bool) -> io::Result<(Exec, ExecLines)> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(if with_stderr { Stdio::piped() } else { Stdio::null() })
+            .process_group(0)
+            .spawn()?;
+
+        let (sender, receiver) = mpsc::channel();
+        if let Some(stderr) = child.stderr.take() {
+            let sender = sender.clone();
+            thread::spawn(move || forward(stderr, STDERR_TAG, &sender));
+        }
+        if let Some(stdout) = child.stdout.take() {
+            thread::spawn(move || forward(stdout, "", &sender));
+        }
+
+        let exec = Exec {
+            command: command.to_owned(),
+            child: child,
+            exit_status: None,
+        };
+        Ok((exec, ExecLines { receiver: receiver }))
+    }
+
+    /// Returns the command line.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns how the command exited the first time it's found to have
+    /// exited, or `None` if it's still running or was already found to have
+    /// exited.
+    pub fn poll_exit(&mut self) -> Option<ExitStatus> {
+        if self.exit_status.is_some() {
+            return None;
+        }
+        self.exit_status = self.child.try_wait().ok().and_then(|status| status);
+        self.exit_status
+    }
+}
+
+impl Drop for Exec {
+    fn drop(&mut self) {
+        if self.exit_status.is_none() && self.child.try_wait().ok().and_then(|s| s).is_none() {
+            unsafe {
+                libc::kill(-(self.child.id() as libc::pid_t), libc::SIGTERM);
+            }
+        }
+        let _ = self.child.wait();
+    }
+}
+
+impl Iterator for ExecLines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Sends each line read from `output` prefixed with `tag` until the output
+/// ends or no one is left to receive the lines.
+fn forward<R: Read>(output: R, tag: &str, sender: &Sender<String>) {
+    let mut reader = BufReader::new(output);
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {
+                let line = format!("{}{}", tag, sample::decode(&buf));
+                if sender.send(line).is_err() {
+                    return;
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::{Exec, STDERR_TAG};
+
+    #[test]
+    fn test_exec() {
+        let (mut exec, mut lines) = Exec::spawn("echo one; echo two >&2; echo three", true)
+            .unwrap();
+        assert_eq!(exec.command(), "echo one; echo two >&2; echo three");
+
+        let start = Instant::now();
+        let mut read = Vec::new();
+        while read.len() < 3 && start.elapsed() < Duration::from_secs(5) {
+            match lines.next() {
+                Some(line) => read.push(line),
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        read.sort();
+        let stderr = format!("{}two", STDERR_TAG);
+        assert_eq!(read, vec![stderr, "one".to_owned(), "three".to_owned()]);
+
+        let mut status = None;
+        while status.is_none() && start.elapsed() < Duration::from_secs(5) {
+            status = exec.poll_exit();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(status.unwrap().success());
+        assert_eq!(exec.poll_exit(), None);
+        assert_eq!(lines.next(), None);
+    }
+}
@@ -0,0 +1,353 @@
+//! Writing of filtered lines to files.
+
+use std::io::{self, Write};
+
+use filterless::{split_spans, FilteredLine, Matcher};
+
+use text;
+use theme::Theme;
+
+
+/// CSS colors of the default foreground and background of exported HTML,
+/// those of xterm's dark palette.
+const HTML_FG: &str = "#e5e5e5";
+const HTML_BG: &str = "#000000";
+
+/// Settings which affect which lines are exported and how.
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// whether to include context lines and gap markers along with matches
+    pub context: bool,
+    /// whether to prefix lines with their line numbers
+    pub line_numbers: bool,
+}
+
+impl ExportOptions {
+    pub fn default() -> ExportOptions {
+        ExportOptions {
+            context: false,
+            line_numbers: false,
+        }
+    }
+}
+
+/// Writes filtered lines to `out`, returning the number of lines written.
+///
+/// Lines are written in the style of `grep`: when line numbers are enabled a
+/// match is prefixed with `NUM:` and a context line with `NUM-`, and gaps
+/// between context groups are written as `--`; gaps before the first group
+/// or after the last aren't written.
+///
+/// ### Parameters
+/// * `out`: destination of the lines
+/// * `lines`: filtered lines, in order
+/// * `options`: which lines to write and how
+pub fn write_lines<'a, W, I>(out: &mut W, lines: I, options: &ExportOptions) -> io::Result<usize>
+    where W: Write, I: IntoIterator<Item=&'a FilteredLine> {
+
+    let mut written = 0;
+    let mut lines = lines.into_iter().peekable();
+
+    while let Some(filtered_line) = lines.next() {
+        let (line_num, line, separator) = match *filtered_line {
+            FilteredLine::Gap { .. } => {
+                let between_groups = written > 0 && lines.peek().is_some_and(|line| !line.is_gap());
+                if options.context && between_groups {
+                    writeln!(out, "--")?;
+                    written += 1;
+                }
+                continue;
+            },
+            FilteredLine::ContextLine((line_num, ref line)) => {
+                if !options.context {
+                    continue;
+                }
+                (line_num, line, '-')
+            },
+            FilteredLine::MatchLine((line_num, ref line)) |
+            FilteredLine::UnfilteredLine((line_num, ref line)) => (line_num, line, ':'),
+        };
+
+        if options.line_numbers {
+            writeln!(out, "{}{}{}", line_num, separator, line)?;
+        } else {
+            writeln!(out, "{}", line)?;
+        }
+        written += 1;
+    }
+
+    out.flush()?;
+    Ok(written)
+}
+
+/// Writes filtered lines to `out` along with how each was classified,
+/// returning the number of lines written.
+///
+/// Each line is written as by `FilteredLine`'s `Display` impl: prefixed with
+/// `M` for a match, `C` for a context line or `U` when no filter is in use,
+/// followed by its line number; gaps between context groups are written as
+/// `G` followed by the numbers of the first and last lines skipped, e.g.
+/// `G 00003-00008`.
+///
+/// ### Parameters
+/// * `out`: destination of the lines
+/// * `lines`: filtered lines, in order
+pub fn write_annotated<'a, W, I>(out: &mut W, lines: I) -> io::Result<usize>
+    where W: Write, I: IntoIterator<Item=&'a FilteredLine> {
+
+    let mut written = 0;
+
+    for filtered_line in lines {
+        writeln!(out, "{}", filtered_line)?;
+        written += 1;
+    }
+
+    out.flush()?;
+    Ok(written)
+}
+
+/// Writes each context group of filtered lines to `out` as a fenced
+/// Markdown code block headed by where it's from, e.g. `app.log:12-18`, for
+/// pasting into issues and reviews, returning the number of blocks written.
+///
+/// Context lines are always included, since they're what make up a group. A
+/// block is fenced with more backticks than any run of them in its lines.
+///
+/// ### Parameters
+/// * `out`: destination of the blocks
+/// * `lines`: filtered lines, in order
+/// * `source`: name of the input, given in each block's header
+pub fn write_markdown<'a, W, I>(out: &mut W, lines: I, source: &str) -> io::Result<usize>
+    where W: Write, I: IntoIterator<Item=&'a FilteredLine> {
+
+    let mut written = 0;
+    let mut group: Vec<(usize, &str)> = Vec::new();
+    let mut lines = lines.into_iter().peekable();
+
+    while let Some(filtered_line) = lines.next() {
+        match *filtered_line {
+            FilteredLine::Gap { .. } => {},
+            FilteredLine::ContextLine((line_num, ref line)) |
+            FilteredLine::MatchLine((line_num, ref line)) |
+            FilteredLine::UnfilteredLine((line_num, ref line)) => group.push((line_num, line)),
+        }
+
+        let at_group_end = lines.peek().is_none_or(|line| line.is_gap());
+        if at_group_end && !group.is_empty() {
+            if written > 0 {
+                writeln!(out)?;
+            }
+            write_block(out, &group, source)?;
+            written += 1;
+            group.clear();
+        }
+    }
+
+    out.flush()?;
+    Ok(written)
+}
+
+/// Writes filtered lines to `out` as a standalone HTML page drawing them as
+/// the pager would, with matches highlighted in the colors of `theme`, for
+/// sharing with people who won't run the pager, returning the number of
+/// lines written.
+///
+/// ### Parameters
+/// * `out`: destination of the page
+/// * `lines`: filtered lines, in order
+/// * `matcher`: matcher whose matches are highlighted, if any
+/// * `theme`: colors and attributes in which lines are drawn
+/// * `title`: title of the page, e.g. the name of the input
+/// * `line_numbers`: whether to prefix lines with their line numbers
+pub fn write_html<'a, W, I>(out: &mut W, lines: I, matcher: Option<&Matcher>, theme: &Theme,
+                            title: &str, line_numbers: bool) -> io::Result<usize>
+    where W: Write, I: IntoIterator<Item=&'a FilteredLine>, I::IntoIter: Clone {
+
+    let lines = lines.into_iter();
+    let num_digits = lines.clone()
+        .filter_map(FilteredLine::line_num)
+        .max()
+        .map_or(1, |line_num| line_num.to_string().len());
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape_html(title))?;
+    writeln!(out, "<style>\nbody {{ color: {}; background: {}; }}", HTML_FG, HTML_BG)?;
+    write!(out, "{}", theme.css(HTML_FG, HTML_BG))?;
+    writeln!(out, "</style>\n</head>\n<body>\n<pre>")?;
+
+    let mut written = 0;
+
+    for filtered_line in lines {
+        written += 1;
+        let (line_num, line) = match *filtered_line {
+            FilteredLine::Gap { .. } => {
+                let skipped = text::describe_skipped(filtered_line.skipped().unwrap_or(0));
+                writeln!(out, "<span class=\"gap\">----- {} -----</span>", skipped)?;
+                continue;
+            },
+            FilteredLine::ContextLine((line_num, ref line)) |
+            FilteredLine::MatchLine((line_num, ref line)) |
+            FilteredLine::UnfilteredLine((line_num, ref line)) => (line_num, line),
+        };
+
+        if line_numbers {
+            write!(out, "<span class=\"line_num\">{:>1$} </span>", line_num, num_digits)?;
+        }
+        match (filtered_line, matcher) {
+            (&FilteredLine::ContextLine(_), _) => {
+                write!(out, "<span class=\"context\">{}</span>", escape_html(line))?;
+            },
+            (_, Some(matcher)) => {
+                for (frag, is_match) in split_spans(line, &matcher.spans(line)) {
+                    if is_match {
+                        write!(out, "<span class=\"match\">{}</span>", escape_html(frag))?;
+                    } else {
+                        write!(out, "{}", escape_html(frag))?;
+                    }
+                }
+            },
+            (_, None) => write!(out, "{}", escape_html(line))?,
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out, "</pre>\n</body>\n</html>")?;
+    out.flush()?;
+    Ok(written)
+}
+
+/// Returns `text` with the characters special to HTML escaped.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes a single context group as a fenced Markdown code block.
+fn write_block<W: Write>(out: &mut W, group: &[(usize, &str)], source: &str) -> io::Result<()> {
+    let first = group[0].0;
+    let last = group[group.len() - 1].0;
+    if first == last {
+        writeln!(out, "`{}:{}`", source, first)?;
+    } else {
+        writeln!(out, "`{}:{}-{}`", source, first, last)?;
+    }
+
+    let longest_run = group.iter().map(|&(_, line)| longest_backtick_run(line)).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    writeln!(out, "{}", fence)?;
+    for &(_, line) in group {
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out, "{}", fence)
+}
+
+/// Returns the length of the longest run of backticks in `line`.
+fn longest_backtick_run(line: &str) -> usize {
+    line.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use filterless::{FilteredLine, Matcher, MatchOptions};
+
+    use theme::Theme;
+
+    use super::{ExportOptions, write_annotated, write_html, write_lines, write_markdown};
+
+    fn lines() -> Vec<FilteredLine> {
+        vec![
+            FilteredLine::ContextLine((1, "before".to_owned())),
+            FilteredLine::MatchLine((2, "match".to_owned())),
+            FilteredLine::Gap { from: 3, to: 8 },
+            FilteredLine::MatchLine((9, "again".to_owned())),
+        ]
+    }
+
+    fn export(options: &ExportOptions) -> (usize, String) {
+        let mut out = Vec::new();
+        let written = write_lines(&mut out, &lines(), options).unwrap();
+        (written, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_write_matches() {
+        let options = ExportOptions::default();
+        assert_eq!(export(&options), (2, "match\nagain\n".to_owned()));
+    }
+
+    #[test]
+    fn test_write_context_and_line_numbers() {
+        let options = ExportOptions { context: true, line_numbers: true };
+        assert_eq!(export(&options), (4, "1-before\n2:match\n--\n9:again\n".to_owned()));
+
+        let lines = vec![
+            FilteredLine::Gap { from: 1, to: 2 },
+            FilteredLine::ContextLine((3, "x".to_owned())),
+            FilteredLine::MatchLine((4, "foo".to_owned())),
+            FilteredLine::Gap { from: 5, to: 6 },
+        ];
+        let mut out = Vec::new();
+        assert_eq!(write_lines(&mut out, &lines, &options).unwrap(), 2);
+        assert_eq!(String::from_utf8(out).unwrap(), "3-x\n4:foo\n");
+    }
+
+    #[test]
+    fn test_write_markdown() {
+        let mut out = Vec::new();
+        let mut lines = lines();
+        lines.push(FilteredLine::ContextLine((10, "```rust".to_owned())));
+
+        assert_eq!(write_markdown(&mut out, &lines, "app.log").unwrap(), 2);
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "`app.log:1-2`\n```\nbefore\nmatch\n```\n\n\
+                    `app.log:9-10`\n````\nagain\n```rust\n````\n");
+
+        let mut out = Vec::new();
+        assert_eq!(write_markdown(&mut out, &[FilteredLine::Gap { from: 1, to: 2 }], "-").unwrap(),
+                   0);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_write_annotated() {
+        let mut out = Vec::new();
+        let mut lines = lines();
+        lines.push(FilteredLine::UnfilteredLine((10, "plain".to_owned())));
+
+        assert_eq!(write_annotated(&mut out, &lines).unwrap(), 5);
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "C 00001: before\nM 00002: match\nG 00003-00008\nM 00009: again\n\
+                    U 00010: plain\n");
+    }
+
+    #[test]
+    fn test_write_html() {
+        let mut out = Vec::new();
+        let mut lines = lines();
+        lines.push(FilteredLine::MatchLine((10, "a <match> & more".to_owned())));
+        let matcher = Matcher::build("substring", "match", &MatchOptions::default()).unwrap();
+
+        let written = write_html(&mut out, &lines, Some(&matcher), &Theme::default(), "a&b", true);
+        assert_eq!(written.unwrap(), 5);
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.contains("<title>a&amp;b</title>"));
+        assert!(html.contains(".match { color: #000000; background: #cdcd00; }"));
+        assert!(html.ends_with(
+            "<span class=\"line_num\"> 1 </span><span class=\"context\">before</span>\n\
+             <span class=\"line_num\"> 2 </span><span class=\"match\">match</span>\n\
+             <span class=\"gap\">----- 6 lines skipped -----</span>\n\
+             <span class=\"line_num\"> 9 </span>again\n\
+             <span class=\"line_num\">10 </span>a &lt;<span class=\"match\">match</span>&gt; \
+             &amp; more\n</pre>\n</body>\n</html>\n"));
+    }
+}
@@ -0,0 +1,89 @@
+//! Help screen shown over the page with `?`, listing the keys bound to each
+//! action, as generated from the keymap, and the state of the options.
+
+use keymap::Keymap;
+use text;
+
+/// Columns between those in which the help is laid out.
+const GUTTER: usize = 3;
+
+
+/// Lays out the help screen in rows of at most `width` columns, as many as
+/// fit in `height` rows, the last of which says how to close it.
+///
+/// The keys of each action and the state of each option are listed in
+/// columns as wide as the longest entry, filled top to bottom.
+///
+/// ### Parameters
+/// * `keymap`: the keys bound to actions
+/// * `options`: the name and state of each option, e.g. `("line numbers",
+///   "on")`
+/// * `width`: number of columns of the screen
+/// * `height`: number of rows of the screen
+pub fn lines(keymap: &Keymap, options: &[(&str, String)], width: usize, height: usize)
+             -> Vec<String> {
+    let bindings = keymap.help();
+    let keys_width = bindings.iter()
+        .map(|&(ref keys, _)| text::width(&keys.join(" ")))
+        .max()
+        .unwrap_or(0);
+    let mut entries: Vec<String> = bindings.iter()
+        .map(|&(ref keys, description)| {
+            let keys = keys.join(" ");
+            let padding = keys_width - text::width(&keys);
+            format!("{}{}  {}", keys, " ".repeat(padding), description)
+        })
+        .collect();
+    entries.push(String::new());
+    entries.push("Options".to_owned());
+    entries.extend(options.iter().map(|&(name, ref state)| format!("{}: {}", name, state)));
+
+    let mut rows = columns(&entries, width, height.saturating_sub(1));
+    rows.truncate(height.saturating_sub(1));
+    if height > 0 {
+        rows.push("Press any key to close this help".to_owned());
+    }
+    rows.into_iter().map(|row| text::truncate(&row, width).to_owned()).collect()
+}
+
+/// Lays out `entries` in as many columns as fit in `width`, filled top to
+/// bottom, in as few rows as they fit in, or in `height` rows if they don't
+/// fit, leaving out the entries which don't.
+fn columns(entries: &[String], width: usize, height: usize) -> Vec<String> {
+    let column_width = entries.iter().map(|entry| text::width(entry)).max().unwrap_or(0);
+    let num_columns = ((width + GUTTER) / (column_width + GUTTER)).max(1);
+    let num_rows = entries.len().div_ceil(num_columns).min(height).max(1);
+
+    (0..num_rows)
+        .map(|row| {
+            let cells: Vec<&String> = entries.iter().skip(row).step_by(num_rows)
+                .take(num_columns)
+                .collect();
+            let mut line = String::new();
+            for (i, cell) in cells.iter().enumerate() {
+                line.push_str(cell);
+                if i + 1 < cells.len() {
+                    let padding = column_width - text::width(cell) + GUTTER;
+                    line.push_str(&" ".repeat(padding));
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::columns;
+
+    #[test]
+    fn test_columns() {
+        let entries: Vec<String> = ["a", "bb", "c", "d", "e"].iter()
+            .map(|entry| entry.to_string())
+            .collect();
+        assert_eq!(columns(&entries, 12, 10), vec!["a    c    e", "bb   d"]);
+        assert_eq!(columns(&entries, 2, 10), vec!["a", "bb", "c", "d", "e"]);
+        // entries which don't fit are left out
+        assert_eq!(columns(&entries, 7, 2), vec!["a    c", "bb   d"]);
+    }
+}
@@ -0,0 +1,129 @@
+//! Highlight patterns, whose occurrences are drawn in colors of their own
+//! within whatever lines are shown, independently of the filter.
+
+use filterless::Matcher;
+use filterless::matcher::Span;
+
+use syntax;
+use theme::{Role, HIGHLIGHTS};
+
+
+/// Pattern whose occurrences are highlighted.
+struct Highlight {
+    /// the pattern as given
+    pattern: String,
+    matcher: Matcher,
+    /// index of the style in which occurrences are drawn
+    style: usize,
+}
+
+/// Registry of highlight patterns, each drawn in a style of its own as long
+/// as there are no more patterns than styles.
+#[derive(Default)]
+pub struct Highlights(Vec<Highlight>);
+
+impl Highlights {
+    /// Highlights the occurrences of `pattern`, in the first style not used
+    /// by another pattern, replacing any earlier highlight of the same
+    /// pattern.
+    ///
+    /// Returns the role in which occurrences are drawn.
+    ///
+    /// ### Parameters
+    /// * `pattern`: the pattern as given, by which it can be removed
+    /// * `matcher`: matcher of the occurrences of `pattern`
+    pub fn add(&mut self, pattern: &str, matcher: Matcher) -> Role {
+        self.remove(pattern);
+        let style = (0..HIGHLIGHTS)
+            .find(|&style| self.0.iter().all(|highlight| highlight.style != style))
+            .unwrap_or(self.0.len() % HIGHLIGHTS);
+
+        self.0.push(Highlight { pattern: pattern.to_owned(), matcher: matcher, style: style });
+        Role::Highlight(style)
+    }
+
+    /// Stops highlighting `pattern`, returning `false` if it wasn't.
+    pub fn remove(&mut self, pattern: &str) -> bool {
+        let len = self.0.len();
+        self.0.retain(|highlight| highlight.pattern != pattern);
+        self.0.len() != len
+    }
+
+    /// Stops highlighting every pattern.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns the patterns highlighted, in the order in which they were
+    /// added.
+    pub fn patterns(&self) -> Vec<&str> {
+        self.0.iter().map(|highlight| highlight.pattern.as_str()).collect()
+    }
+
+    /// Returns the byte ranges of the occurrences of the patterns within
+    /// `line` and the roles in which they're drawn, ascending and
+    /// non-overlapping; where occurrences overlap, those of the pattern
+    /// added last are drawn.
+    pub fn spans(&self, line: &str) -> Vec<(Span, Role)> {
+        let mut spans = Vec::new();
+        for highlight in &self.0 {
+            let role = Role::Highlight(highlight.style);
+            let over: Vec<(Span, Role)> = highlight.matcher.spans(line).into_iter()
+                .map(|span| (span, role))
+                .collect();
+            if over.is_empty() {
+                continue;
+            }
+
+            spans = syntax::compose(line.len(), &spans, &over).into_iter()
+                .filter_map(|(span, role)| role.map(|role| (span, role)))
+                .collect();
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use filterless::Matcher;
+
+    use theme::{Role, HIGHLIGHTS};
+
+    use super::Highlights;
+
+    #[test]
+    fn test_highlights() {
+        let mut highlights = Highlights::default();
+        let substring = |text: &str| Matcher::substring(text.to_owned());
+
+        assert_eq!(highlights.add("db", substring("db")), Role::Highlight(0));
+        assert_eq!(highlights.add("timeout", substring("timeout")), Role::Highlight(1));
+        assert_eq!(highlights.spans("db timeout in db"), vec![
+            ((0, 2), Role::Highlight(0)),
+            ((3, 10), Role::Highlight(1)),
+            ((14, 16), Role::Highlight(0)),
+        ]);
+
+        // the pattern added last is drawn where occurrences overlap
+        assert_eq!(highlights.add("out in", substring("out in")), Role::Highlight(2));
+        assert_eq!(highlights.spans("timeout in"), vec![
+            ((0, 4), Role::Highlight(1)),
+            ((4, 10), Role::Highlight(2)),
+        ]);
+
+        // the style of a removed pattern is reused first
+        assert!(highlights.remove("db"));
+        assert!(!highlights.remove("db"));
+        assert_eq!(highlights.add("x", substring("x")), Role::Highlight(0));
+        assert_eq!(highlights.patterns(), vec!["timeout", "out in", "x"]);
+
+        // styles are shared once every one is in use
+        for i in 0..HIGHLIGHTS {
+            highlights.add(&i.to_string(), substring(&i.to_string()));
+        }
+        assert_eq!(highlights.patterns().len(), HIGHLIGHTS + 3);
+
+        highlights.clear();
+        assert_eq!(highlights.spans("timeout"), vec![]);
+    }
+}
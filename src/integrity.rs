@@ -0,0 +1,190 @@
+//! Checks that a file being followed still holds the bytes already read from
+//! it, so that the pager can warn when the file is rotated, truncated or
+//! rewritten under it rather than stitch lines of the old and new contents
+//! together.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::fs::{FileExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Most bytes at either end of those read which are compared with the file.
+const SAMPLE_BYTES: usize = 4096;
+
+
+/// Record of the bytes read from a file so far.
+#[derive(Default)]
+struct Consumed {
+    /// number of bytes read
+    len: u64,
+    /// the first bytes read
+    head: Vec<u8>,
+    /// the last bytes read
+    tail: Vec<u8>,
+}
+
+/// Reader of a file which records what's read for an `Integrity` to check.
+pub struct Tracked {
+    file: File,
+    consumed: Arc<Mutex<Consumed>>,
+}
+
+impl Read for Tracked {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.file.read(buf)?;
+        let mut consumed = self.consumed.lock().unwrap();
+
+        consumed.len += n as u64;
+        let head_len = consumed.head.len();
+        if head_len < SAMPLE_BYTES {
+            let more = n.min(SAMPLE_BYTES - head_len);
+            consumed.head.extend_from_slice(&buf[..more]);
+        }
+        consumed.tail.extend_from_slice(&buf[..n]);
+        let excess = consumed.tail.len().saturating_sub(SAMPLE_BYTES);
+        consumed.tail.drain(..excess);
+
+        Ok(n)
+    }
+}
+
+/// Way in which a file no longer holds the bytes read from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    /// another file now has the file's path, e.g. after log rotation
+    Replaced,
+    /// the file is now shorter than the bytes read from it
+    Truncated,
+    /// bytes already read were overwritten
+    Rewritten,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            Change::Replaced => "was replaced, e.g. by log rotation",
+            Change::Truncated => "was truncated",
+            Change::Rewritten => "was rewritten where it had been read",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// Checker that a file still holds the bytes read from it through a
+/// `Tracked` reader.
+///
+/// Only the first and last few kilobytes read are compared, which catches
+/// files being rewritten from the start, e.g. by an editor, or reused after
+/// being truncated.
+pub struct Integrity {
+    /// path by which the file was opened
+    path: PathBuf,
+    /// the file, read with positioned reads which leave the reader's
+    /// position alone
+    file: File,
+    /// device and inode numbers of the file
+    id: (u64, u64),
+    consumed: Arc<Mutex<Consumed>>,
+}
+
+impl Integrity {
+    /// Wraps `file`, opened from `path`, in a reader whose reads can be
+    /// checked against the file later.
+    ///
+    /// ### Parameters
+    /// * `file`: the file to read
+    /// * `path`: path by which the file was opened
+    pub fn track(file: File, path: &Path) -> io::Result<(Tracked, Integrity)> {
+        let metadata = file.metadata()?;
+        let consumed = Arc::new(Mutex::new(Consumed::default()));
+        let integrity = Integrity {
+            path: path.to_owned(),
+            file: file.try_clone()?,
+            id: (metadata.dev(), metadata.ino()),
+            consumed: consumed.clone(),
+        };
+
+        Ok((Tracked { file: file, consumed: consumed }, integrity))
+    }
+
+    /// Returns the path by which the file was opened.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns how the file no longer holds the bytes read from it, if it
+    /// doesn't.
+    ///
+    /// A path which can't be read, e.g. for a moment during log rotation,
+    /// isn't taken as a change.
+    pub fn check(&self) -> Option<Change> {
+        if let Ok(metadata) = fs::metadata(&self.path) {
+            if (metadata.dev(), metadata.ino()) != self.id {
+                return Some(Change::Replaced);
+            }
+        }
+
+        let consumed = self.consumed.lock().unwrap();
+        let len = self.file.metadata().ok()?.len();
+        if len < consumed.len {
+            return Some(Change::Truncated);
+        }
+
+        let tail_start = consumed.len - consumed.tail.len() as u64;
+        let unchanged = self.holds(0, &consumed.head) && self.holds(tail_start, &consumed.tail);
+        if unchanged { None } else { Some(Change::Rewritten) }
+    }
+
+    /// Returns whether the file holds `bytes` at `offset`, or can't be read.
+    fn holds(&self, offset: u64, bytes: &[u8]) -> bool {
+        let mut found = vec![0; bytes.len()];
+        match self.file.read_exact_at(&mut found, offset) {
+            Ok(()) => found == bytes,
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => false,
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{BufRead, BufReader, Write};
+    use std::process;
+
+    use super::{Change, Integrity};
+
+    #[test]
+    fn test_check() {
+        let path = env::temp_dir().join(format!("filterless-integrity-{}", process::id()));
+        fs::write(&path, "one\ntwo\n").unwrap();
+
+        let (tracked, integrity) = Integrity::track(File::open(&path).unwrap(), &path).unwrap();
+        let mut lines = BufReader::new(tracked).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "one");
+        assert_eq!(integrity.check(), None);
+
+        // appending lines is expected
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"three\n").unwrap();
+        assert_eq!(integrity.check(), None);
+        assert_eq!(lines.nth(1).unwrap().unwrap(), "three");
+
+        OpenOptions::new().write(true).open(&path).unwrap().write_all(b"ONE").unwrap();
+        assert_eq!(integrity.check(), Some(Change::Rewritten));
+
+        fs::write(&path, "one\n").unwrap();
+        assert_eq!(integrity.check(), Some(Change::Truncated));
+
+        let moved = path.with_extension("1");
+        fs::rename(&path, &moved).unwrap();
+        assert_eq!(integrity.check(), Some(Change::Truncated));
+        fs::write(&path, "").unwrap();
+        assert_eq!(integrity.check(), Some(Change::Replaced));
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&moved).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+use super::matcher::Matcher;
+
+
+/// Adapter of an iterator of lines which yields only the blocks of lines
+/// delimited by a line matching one pattern and the next line matching
+/// another, e.g. the log lines of individual requests or test cases.
+///
+/// Whether a line belongs to a block depends on the lines before it, so the
+/// lines are classified as they're read, before they're numbered; the lines
+/// yielded are numbered as lines of the blocks. A block which hasn't ended
+/// when the input does runs to its end, and goes on if more lines are read
+/// later, e.g. while following.
+pub struct Between<I: Iterator<Item=String>> {
+    lines: I,
+    /// matcher of the lines which begin a block
+    start: Matcher,
+    /// matcher of the lines which end a block
+    end: Matcher,
+    /// whether the lines beginning and ending blocks are yielded too
+    inclusive: bool,
+    /// whether the last line read lies within a block
+    in_block: bool,
+}
+
+impl<I: Iterator<Item=String>> Between<I> {
+    /// Creates a `Between`.
+    ///
+    /// ### Parameters
+    /// * `lines`: the lines of the input
+    /// * `start`: matcher of the lines which begin a block; within a block,
+    ///   they're like any other line
+    /// * `end`: matcher of the lines which end a block; the line beginning a
+    ///   block can't also end it
+    /// * `inclusive`: whether the lines beginning and ending blocks are
+    ///   yielded along with those between them
+    pub fn new(lines: I, start: Matcher, end: Matcher, inclusive: bool) -> Between<I> {
+        Between {
+            lines: lines,
+            start: start,
+            end: end,
+            inclusive: inclusive,
+            in_block: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item=String>> Iterator for Between<I> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        for line in self.lines.by_ref() {
+            if !self.in_block {
+                if self.start.is_match(&line) {
+                    self.in_block = true;
+                    if self.inclusive {
+                        return Some(line);
+                    }
+                }
+            } else if self.end.is_match(&line) {
+                self.in_block = false;
+                if self.inclusive {
+                    return Some(line);
+                }
+            } else {
+                return Some(line);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::iter;
+    use std::sync::mpsc;
+
+    use super::super::matcher::Matcher;
+    use super::Between;
+
+    const LINES: &[&str] = &[
+        "noise",
+        "BEGIN test_a",
+        "a1",
+        "BEGIN nested",
+        "END",
+        "noise",
+        "END",
+        "BEGIN test_b",
+        "b1",
+    ];
+
+    fn between(inclusive: bool) -> Vec<String> {
+        let lines = LINES.iter().map(|line| line.to_string());
+        let start = Matcher::substring("BEGIN".to_owned());
+        let end = Matcher::substring("END".to_owned());
+        Between::new(lines, start, end, inclusive).collect()
+    }
+
+    #[test]
+    fn test_between() {
+        assert_eq!(between(true), vec!["BEGIN test_a", "a1", "BEGIN nested", "END",
+                                       "BEGIN test_b", "b1"]);
+        assert_eq!(between(false), vec!["a1", "BEGIN nested", "b1"]);
+
+        // a block goes on once more lines are read
+        let (sender, receiver) = mpsc::channel();
+        let lines = iter::from_fn(move || receiver.try_recv().ok());
+        let start = Matcher::substring("BEGIN".to_owned());
+        let end = Matcher::substring("END".to_owned());
+        let mut between = Between::new(lines, start, end, false);
+        sender.send("BEGIN".to_owned()).unwrap();
+        assert_eq!(between.next(), None);
+        sender.send("more".to_owned()).unwrap();
+        assert_eq!(between.next(), Some("more".to_owned()));
+    }
+}
@@ -1,8 +1,10 @@
 use std::collections::VecDeque;
-use std::iter::{Iterator, repeat};
+use std::cmp::max;
+use std::iter::{Iterator, from_fn, repeat, repeat_n};
 
-use super::line_buffer::LineBuffer;
+use super::line_buffer::{IterDirection, LineBuffer};
 use super::iter::{ContextLine, FilteredLine, FilterPredicate, Gap};
+use super::match_index::MatchIndex;
 
 /// Buffer for providing visibility into past, present, and future lines
 /// produced by an iterator.
@@ -14,13 +16,31 @@ use super::iter::{ContextLine, FilteredLine, FilterPredicate, Gap};
 /// lines are pushed to the back of the deque and old lines are popped from the
 /// beginning. In this way the "current" line always resides in the exact
 /// middle of the deque.
+///
+/// The deque holds each line's number and whether it matches rather than its
+/// text, which is classified where it lies in the `LineBuffer`'s cache and only
+/// copied out of it if the line is returned, so that lines skipped by a filter
+/// are never copied.
 pub struct ContextBuffer<T: Iterator<Item=String>> {
     filter_predicate: Option<FilterPredicate>,
     /// earlier lines in lower indexes
     buffer: VecDeque<Option<ContextLine>>,
     /// underlying iterator
     iter: LineBuffer<T>,
-    gap: Gap
+    gap: Gap,
+    /// number of the line returned last, including lines on the near side of
+    /// `boundary`
+    last_line_num: Option<usize>,
+    /// direction in which `iter` proceeds through the input
+    direction: IterDirection,
+    /// number of the first line to emit; lines on the near side of it, which
+    /// are only read to establish context, are discarded
+    boundary: Option<usize>,
+    /// index of the lines matching `filter_predicate`, if one is being built
+    index: Option<MatchIndex>,
+    /// whether the search for the next match is waiting on `index` to scan
+    /// further
+    stalled: bool,
 }
 
 impl<T: Iterator<Item=String>> ContextBuffer<T> {
@@ -28,14 +48,13 @@ impl<T: Iterator<Item=String>> ContextBuffer<T> {
            mut iter: LineBuffer<T>) -> ContextBuffer<T> {
 
         let buffer = match filter_predicate {
-            Some(FilterPredicate{ ref filter_string, ref context_lines }) => {
-                let capacity = context_lines * 2 + 1;
-                repeat(None)
-                    .take(context_lines + 1)
-                    .chain((&mut iter).map(|numbered_line| {
-                        Some(ContextLine::from_numbered_line(
-                                numbered_line.to_owned(), &filter_string))
-                    }))
+            Some(ref predicate) => {
+                let capacity = predicate.context_lines * 2 + 1;
+                let lines = from_fn(|| iter.next_with(|line_num, line| {
+                    ContextLine::classify(line_num, line, predicate)
+                }));
+                repeat_n(None, predicate.context_lines + 1)
+                    .chain(lines.map(Some))
                     .chain(repeat(None))
                     .take(capacity)
                     .collect()
@@ -50,111 +69,281 @@ impl<T: Iterator<Item=String>> ContextBuffer<T> {
             buffer: buffer,
             iter: iter,
             gap: Gap::None,
+            last_line_num: None,
+            direction: IterDirection::FORWARD,
+            boundary: None,
+            index: None,
+            stalled: false,
+        }
+    }
+
+    /// Creates a `ContextBuffer` which emits the lines of `iter` beginning
+    /// with line `line_num` and proceeding in `direction`.
+    ///
+    /// Lines up to `context_lines` beyond `line_num` on the near side are
+    /// read so that lines are classified as they would be when reading the
+    /// input from the start. Gaps are never emitted before the first line.
+    ///
+    /// ### Parameters
+    /// * `filter_predicate`: optional filtering criteria
+    /// * `iter`: line source
+    /// * `line_num`: 1-indexed number of the first line to emit
+    /// * `direction`: direction in which to proceed from `line_num`
+    pub fn starting_at(filter_predicate: Option<FilterPredicate>, mut iter: LineBuffer<T>,
+                       line_num: usize, direction: IterDirection) -> ContextBuffer<T> {
+        let context_lines = filter_predicate.as_ref().map(|p| p.context_lines).unwrap_or(0);
+
+        let seek_line = match direction {
+            IterDirection::FORWARD => max(line_num.saturating_sub(context_lines), 1),
+            IterDirection::BACKWARD => {
+                let seek_line = line_num + context_lines;
+                if iter.get(seek_line).is_some() { seek_line } else { iter.len() }
+            },
+        };
+        iter.seek(Some(seek_line), Some(direction.clone()));
+
+        let mut context_buffer = ContextBuffer::new(filter_predicate, iter);
+        context_buffer.direction = direction;
+        context_buffer.boundary = Some(line_num);
+        context_buffer
+    }
+
+    /// Sets the index consulted to skip lines which don't match
+    /// `filter_predicate`.
+    ///
+    /// While the index is being built, the iterator returns `None` when it
+    /// reaches lines which haven't been scanned yet, and resumes where it left
+    /// off once they have.
+    pub fn set_index(&mut self, index: Option<MatchIndex>) {
+        self.index = index;
+    }
+
+    /// Returns `true` if `line` falls on the near side of `boundary`.
+    fn before_boundary(&self, line: &FilteredLine) -> bool {
+        match (self.boundary, line.line_num()) {
+            (Some(_), None) => true,
+            (Some(boundary), Some(line_num)) => match self.direction {
+                IterDirection::FORWARD => line_num < boundary,
+                IterDirection::BACKWARD => line_num > boundary,
+            },
+            (None, _) => false,
         }
     }
 
     /// Returns `True` if any lines in `buffer` match the filter.
     fn buffer_has_matches(&self) -> bool {
         self.buffer.iter()
-            .map(|maybe_elt| {
-                match maybe_elt {
-                    &Some(ContextLine::Match(_)) => true,
-                    _ => false,
-                }
-            })
-            .any(|m| m)
+            .any(|maybe_elt| matches!(maybe_elt, &Some(ContextLine::Match(_))))
     }
 
-    fn fill_buffer(&mut self) {
+    /// Returns `true` if the current line is to be emitted: if it lies within
+    /// the context of a match, or outside that of every match if the filter
+    /// is inverse.
+    fn shows_cur_line(&self) -> bool {
+        let inverse = self.filter_predicate.as_ref().is_some_and(|p| p.inverse);
+        self.buffer_has_matches() != inverse
+    }
+
+    /// Reads the next line off the underlying iterator, classified by
+    /// `filter_predicate` without copying its text.
+    fn read_line(&mut self) -> Option<ContextLine> {
         match self.filter_predicate {
-            Some(FilterPredicate{ ref filter_string, .. }) => {
-                let item = self.iter.next().map(|numbered_line| {
-                    ContextLine::from_numbered_line(numbered_line.to_owned(),
-                    &filter_string)
-                });
-                self.buffer.pop_front();
-                self.buffer.push_back(item);
-
-                while !self.buffer_has_matches() {
-                    if let Some(numbered_line) = self.iter.next() {
-                        let context_line = ContextLine::from_numbered_line(
-                            numbered_line.to_owned(), &filter_string);
-
-                        if let ContextLine::Match(_) = context_line {
-                            self.gap = Gap::Current;
-                        };
-
-                        self.buffer.pop_front();
-                        self.buffer.push_back(Some(context_line));
-                    } else {
-                        self.buffer.clear();
-                        break;
-                    }
-                }
-            },
+            Some(ref predicate) => self.iter.next_with(|line_num, line| {
+                ContextLine::classify(line_num, line, predicate)
+            }),
+            None => self.iter.next_with(|line_num, _| ContextLine::NoMatch(line_num)),
+        }
+    }
+
+    fn fill_buffer(&mut self) {
+        let (context_lines, inverse) = match self.filter_predicate {
+            Some(ref predicate) => (predicate.context_lines, predicate.inverse),
             None => {
                 self.buffer.pop_front();
-                if let Some(numbered_line) = self.iter.next() {
-                    let context_line = ContextLine::NoMatch(
-                        numbered_line.to_owned());
+                if let Some(context_line) = self.read_line() {
                     self.buffer.push_back(Some(context_line));
                 }
+                return;
+            },
+        };
+
+        if self.buffer.is_empty() {
+            // case: the iterator was previously exhausted; start over
+            // with an empty context in case it has since grown
+            let capacity = context_lines * 2 + 1;
+            self.buffer.extend(repeat_n(None, capacity));
+        }
+
+        if self.stalled {
+            // case: resuming the search for a match where it was left off
+            self.stalled = false;
+        } else {
+            let item = self.read_line();
+            self.buffer.pop_front();
+            self.buffer.push_back(item);
+        }
+
+        // whether a line has passed through the middle of the buffer
+        // without being emitted
+        let mut skipped = false;
+
+        while !self.shows_cur_line() {
+            skipped = self.skip_unmatched(context_lines) || skipped;
+            if self.stalled {
+                return;
+            }
+
+            let item = match self.read_line() {
+                Some(context_line) => Some(context_line),
+                // case: the lines after the current one, of an inverse
+                // filter, may yet lie far enough from the last match
+                None if inverse
+                    && self.buffer.iter().skip(context_lines + 1).any(Option::is_some) => None,
+                None => {
+                    self.buffer.clear();
+                    break;
+                },
+            };
+
+            skipped = skipped || self.buffer[context_lines].is_some();
+
+            self.buffer.pop_front();
+            self.buffer.push_back(item);
+
+            if skipped && self.shows_cur_line() {
+                self.gap = Gap::Current;
             }
         }
     }
 
-    fn classify_cur_line(&self) -> Option<FilteredLine> {
-        match self.filter_predicate {
-            Some(FilterPredicate{ ref context_lines, .. }) => {
-                let cur_idx = context_lines;
-                self.buffer.get(*cur_idx)
-                    .and_then(|maybe_context_line| {
-                        maybe_context_line.as_ref().map(|context_line| {
-                            context_line.to_filtered_line(&self.filter_predicate)
-                        })
-                    })
+    /// Uses the match index, if any, to skip over lines known not to match
+    /// or to lie within the context of a match, returning `true` if any
+    /// lines were skipped. Lines aren't skipped if the filter is inverse,
+    /// as those far from matches are the ones emitted.
+    ///
+    /// If reading forward and the next line hasn't been scanned yet, sets
+    /// `stalled` rather than waiting for it to be. Reading backward proceeds
+    /// line by line until the index covers the lines to be read.
+    fn skip_unmatched(&mut self, context_lines: usize) -> bool {
+        let span = context_lines * 2;
+        let last = self.iter.last_iter_line();
+        let index = match (&self.index, &self.filter_predicate) {
+            (_, &Some(FilterPredicate { inverse: true, .. })) | (&None, _) => return false,
+            (&Some(ref index), _) => index,
+        };
+
+        match self.direction {
+            IterDirection::FORWARD => {
+                let (next, scanned, done) = index.next_after(last);
+                if next.is_none() && !done && last >= scanned {
+                    self.stalled = true;
+                    return false;
+                }
+
+                // the first line which may match
+                let target = next.unwrap_or(scanned + 1);
+                if target > last + 1 + span {
+                    self.iter.seek(Some(target - span), None);
+                    return true;
+                }
             },
-            None => {
-                let cur_idx = 0;
-                self.buffer.get(cur_idx)
-                    .and_then(|maybe_context_line| {
-                        maybe_context_line.as_ref().map(|context_line| {
-                            context_line.to_filtered_line(&self.filter_predicate)
-                        })
-                    })
+            IterDirection::BACKWARD => {
+                let (prev, covered) = index.prev_before(last);
+                if !covered {
+                    return false;
+                }
+
+                let target = prev.unwrap_or(0);
+                if target + span + 1 < last {
+                    self.iter.seek(Some(target + span), None);
+                    return true;
+                }
             },
         }
+
+        false
+    }
+
+    /// Returns the current line, in the middle of `buffer`, if any.
+    fn cur_line(&self) -> Option<&ContextLine> {
+        if self.stalled {
+            return None;
+        }
+
+        let cur_idx = self.filter_predicate.as_ref().map_or(0, |p| p.context_lines);
+        self.buffer.get(cur_idx).and_then(Option::as_ref)
+    }
+
+    /// Returns the current line as it's emitted, its text copied out of the
+    /// `LineBuffer`.
+    fn classify_cur_line(&mut self) -> Option<FilteredLine> {
+        let context_line = self.cur_line().cloned()?;
+        let (_, text) = self.iter.get(context_line.line_num())?;
+        Some(context_line.to_filtered_line(text, &self.filter_predicate))
+    }
+
+    /// Returns the inner `LineBuffer`.
+    pub fn line_buffer(&self) -> &LineBuffer<T> {
+        &self.iter
+    }
+
+    /// Returns the inner `LineBuffer`.
+    pub fn line_buffer_mut(&mut self) -> &mut LineBuffer<T> {
+        &mut self.iter
     }
 
     /// Consumes this `ContextBuffer`, returning the inner `LineBuffer`.
     pub fn into_line_buffer(self) -> LineBuffer<T> {
         self.iter
     }
+
+    /// Returns the gap standing for the lines skipped between the line
+    /// returned last and the current line.
+    fn gap_before_cur_line(&self) -> FilteredLine {
+        let last = self.last_line_num;
+        let next = self.cur_line().map(ContextLine::line_num);
+        let (from, to) = match self.direction {
+            IterDirection::FORWARD => (last.map_or(1, |n| n + 1), next.map_or(0, |n| n - 1)),
+            IterDirection::BACKWARD => {
+                (next.map_or(0, |n| n + 1), last.map_or(self.iter.len(), |n| n - 1))
+            },
+        };
+
+        FilteredLine::Gap { from: from, to: to }
+    }
+
+    /// Returns the next line, including those on the near side of
+    /// `boundary`.
+    fn next_line(&mut self) -> Option<FilteredLine> {
+        if let Gap::None = self.gap {
+            self.fill_buffer();
+            if let Gap::Current = self.gap {
+                self.gap = Gap::Previous;
+                return Some(self.gap_before_cur_line());
+            }
+        }
+
+        self.gap = Gap::None;
+        let line = self.classify_cur_line();
+        if let Some(line_num) = line.as_ref().and_then(|line| line.line_num()) {
+            self.last_line_num = Some(line_num);
+        }
+        line
+    }
 }
 
 impl<T: Iterator<Item = String>> Iterator for ContextBuffer<T> {
     type Item = FilteredLine;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.gap {
-            Gap::None => {
-                self.fill_buffer();
-                match self.gap {
-                    Gap::Current => {
-                        self.gap = Gap::Previous;
-                        Some(FilteredLine::Gap)
-                    },
-                    _ => {
-                        self.gap = Gap::None;
-                        self.classify_cur_line()
-                    },
-                }
-            },
-            _ => {
-                self.gap = Gap::None;
-                self.classify_cur_line()
-            },
+        while let Some(line) = self.next_line() {
+            if !self.before_boundary(&line) {
+                self.boundary = None;
+                return Some(line);
+            }
         }
+
+        None
     }
 }
 
@@ -163,7 +352,7 @@ mod test {
     use super::ContextBuffer;
     use iter::iter::FilteredLine;
     use iter::iter::FilterPredicate;
-    use iter::line_buffer::LineBuffer;
+    use iter::line_buffer::{IterDirection, LineBuffer};
 
     #[test]
     fn test1() {
@@ -186,14 +375,11 @@ mod test {
         let iter = lines.iter().map(|i| i.to_owned());
         let line_buf = LineBuffer::new(iter);
 
-        let pred = FilterPredicate {
-            filter_string: filter_string,
-            context_lines: context_lines
-        };
+        let pred = FilterPredicate::new(filter_string, context_lines);
         let mut cb = ContextBuffer::new(Some(pred), line_buf);
 
         let e0 = cb.next();
-        assert!(e0 == Some(FilteredLine::Gap));
+        assert!(e0 == Some(FilteredLine::Gap { from: 1, to: 1 }));
         let e1 = cb.next();
         assert!(e1 == Some(FilteredLine::ContextLine((2, String::from("ctx")))));
         let e2 = cb.next();
@@ -205,7 +391,7 @@ mod test {
         let e5 = cb.next();
         assert!(e5 == Some(FilteredLine::ContextLine((6, String::from("ctx")))));
         let e6 = cb.next();
-        assert!(e6 == Some(FilteredLine::Gap));
+        assert!(e6 == Some(FilteredLine::Gap { from: 7, to: 8 }));
         let e7 = cb.next();
         assert!(e7 == Some(FilteredLine::ContextLine((9, String::from("ctx")))));
         let e8 = cb.next();
@@ -230,10 +416,7 @@ mod test {
         let iter = lines.iter().map(|i| i.to_owned());
         let line_buf = LineBuffer::new(iter);
 
-        let pred = FilterPredicate {
-            filter_string: filter_string,
-            context_lines: context_lines
-        };
+        let pred = FilterPredicate::new(filter_string, context_lines);
         let mut cb = ContextBuffer::new(Some(pred), line_buf);
 
         let e0 = cb.next();
@@ -242,11 +425,11 @@ mod test {
         let e1 = cb.next();
         assert!(e1 == Some(FilteredLine::MatchLine((2, String::from("match")))));
         let e2 = cb.next();
-        assert!(e2 == Some(FilteredLine::Gap));
+        assert!(e2 == Some(FilteredLine::Gap { from: 3, to: 3 }));
         let e3 = cb.next();
         assert!(e3 == Some(FilteredLine::MatchLine((4, String::from("match")))));
         let e4 = cb.next();
-        assert!(e4 == None);
+        assert!(e4.is_none());
     }
 
     #[test]
@@ -269,9 +452,9 @@ mod test {
         let e3 = cb.next();
         assert!(e3 == Some(FilteredLine::UnfilteredLine((3, String::from("three")))));
         let e4 = cb.next();
-        assert!(e4 == None);
+        assert!(e4.is_none());
         let e5 = cb.next();
-        assert!(e5 == None);
+        assert!(e5.is_none());
     }
 
     #[test]
@@ -295,4 +478,80 @@ mod test {
         let mb2 = cb.into_line_buffer();
         let _ = ContextBuffer::new(None, mb2);
     }
+
+    #[test]
+    fn test_starting_at() {
+        let lines: Vec<String> = vec![
+            "none".to_owned(),
+            "ctx".to_owned(),
+            "ctx".to_owned(),
+            "match".to_owned(),
+            "ctx".to_owned(),
+            "ctx".to_owned(),
+            "none".to_owned(),
+            "none".to_owned(),
+            "ctx".to_owned(),
+            "ctx".to_owned(),
+            "match".to_owned(),
+            "ctx".to_owned(),
+        ];
+        let pred = FilterPredicate::new("match".to_owned(), 2);
+
+        let line_buf = LineBuffer::new(lines.iter().map(|i| i.to_owned()));
+        let cb = ContextBuffer::starting_at(Some(pred.clone()), line_buf, 6, IterDirection::FORWARD);
+        assert_eq!(cb.collect::<Vec<_>>(), vec![
+            FilteredLine::ContextLine((6, String::from("ctx"))),
+            FilteredLine::Gap { from: 7, to: 8 },
+            FilteredLine::ContextLine((9, String::from("ctx"))),
+            FilteredLine::ContextLine((10, String::from("ctx"))),
+            FilteredLine::MatchLine((11, String::from("match"))),
+            FilteredLine::ContextLine((12, String::from("ctx"))),
+        ]);
+
+        let line_buf = LineBuffer::new(lines.iter().map(|i| i.to_owned()));
+        let cb = ContextBuffer::starting_at(Some(pred.clone()), line_buf, 12, IterDirection::BACKWARD);
+        assert_eq!(cb.collect::<Vec<_>>(), vec![
+            FilteredLine::ContextLine((12, String::from("ctx"))),
+            FilteredLine::MatchLine((11, String::from("match"))),
+            FilteredLine::ContextLine((10, String::from("ctx"))),
+            FilteredLine::ContextLine((9, String::from("ctx"))),
+            FilteredLine::Gap { from: 7, to: 8 },
+            FilteredLine::ContextLine((6, String::from("ctx"))),
+            FilteredLine::ContextLine((5, String::from("ctx"))),
+            FilteredLine::MatchLine((4, String::from("match"))),
+            FilteredLine::ContextLine((3, String::from("ctx"))),
+            FilteredLine::ContextLine((2, String::from("ctx"))),
+        ]);
+
+        let line_buf = LineBuffer::new(lines.iter().map(|i| i.to_owned()));
+        let mut cb = ContextBuffer::starting_at(Some(pred), line_buf, 8, IterDirection::BACKWARD);
+        assert_eq!(cb.next(), Some(FilteredLine::ContextLine((6, String::from("ctx")))));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let lines: Vec<String> = vec![
+            "tick", "tick", "tick", "work", "work", "work", "work", "work", "work", "tick",
+            "work", "work",
+        ].into_iter().map(|line| line.to_owned()).collect();
+        let pred = FilterPredicate::new("tick".to_owned(), 1).with_inverse(true);
+        let ctx = |line_num: usize| FilteredLine::ContextLine((line_num, "work".to_owned()));
+
+        let line_buf = LineBuffer::new(lines.iter().map(|i| i.to_owned()));
+        let cb = ContextBuffer::new(Some(pred.clone()), line_buf);
+        assert_eq!(cb.collect::<Vec<_>>(), vec![
+            FilteredLine::Gap { from: 1, to: 4 },
+            ctx(5), ctx(6), ctx(7), ctx(8),
+            FilteredLine::Gap { from: 9, to: 11 },
+            ctx(12),
+        ]);
+
+        let line_buf = LineBuffer::new(lines.iter().map(|i| i.to_owned()));
+        let cb = ContextBuffer::starting_at(Some(pred), line_buf, 12, IterDirection::BACKWARD);
+        assert_eq!(cb.collect::<Vec<_>>(), vec![
+            ctx(12),
+            FilteredLine::Gap { from: 9, to: 11 },
+            ctx(8), ctx(7), ctx(6), ctx(5),
+        ]);
+    }
 }
@@ -0,0 +1,896 @@
+//! Windows event logs, i.e. `.evtx` files, whose records are read as lines in
+//! the layout of the `evtx` format, e.g. `2024-05-01T09:30:00.000Z record=7
+//! provider="Service Control Manager" level=info event_id=7036 msg="..."`.
+//!
+//! A log is a header followed by chunks of 64 KiB, each holding records of
+//! events in binary XML. Names and templates of elements are defined once in
+//! a chunk and referred to by their offset within it thereafter, so chunks
+//! are decoded one at a time, as their records are read.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::rc::Rc;
+
+use timestamp;
+
+use super::line_source::LineSource;
+
+/// Magic bytes beginning the header of a log.
+const FILE_MAGIC: &[u8] = b"ElfFile\0";
+/// Magic bytes beginning a chunk which holds records.
+const CHUNK_MAGIC: &[u8] = b"ElfChnk\0";
+/// Magic bytes beginning a record.
+const RECORD_MAGIC: &[u8] = b"**\0\0";
+
+/// Length in bytes of a chunk.
+const CHUNK_LEN: usize = 0x10000;
+/// Offset within a chunk of its first record.
+const RECORDS_OFFSET: usize = 0x200;
+/// Length in bytes of the header of a record, before its binary XML.
+const RECORD_HEADER_LEN: usize = 24;
+/// Length in bytes of the header of a template definition, before its binary
+/// XML.
+const TEMPLATE_HEADER_LEN: usize = 24;
+/// Greatest depth to which instances of templates are nested, e.g. by binary
+/// XML substituted into a template, beyond which a record isn't decoded, so
+/// that a template instantiating itself in a corrupt log can't recurse without
+/// bound.
+const MAX_TEMPLATE_DEPTH: usize = 32;
+
+/// Names of the levels of events, indexed by their numbers; 0 is logged
+/// always, and is taken to be informational, as by the Event Viewer.
+const LEVELS: &[&str] = &["info", "critical", "error", "warning", "info", "debug"];
+
+/// Milliseconds from 1601-01-01, from which a `FILETIME` is counted, to the
+/// Unix epoch.
+const FILETIME_EPOCH_MILLIS: i64 = 11_644_473_600_000;
+
+
+/// Records of a Windows event log, which isn't seekable.
+///
+/// Each record is read as the time it was created, its record number, the
+/// name of the provider which logged it, its level, its event ID and, as its
+/// message, the data of the event, e.g. `TargetUserName: ann, LogonType: 3`;
+/// the messages formatted by Windows itself are kept in the resources of the
+/// providers rather than in the log. Records which can't be decoded are read
+/// as `error` fields, saying why.
+pub struct EvtxSource {
+    file: File,
+    /// lines of the records of the chunk last decoded not read yet
+    lines: VecDeque<String>,
+}
+
+impl EvtxSource {
+    /// Reads the records of a log from its beginning, returning an error if
+    /// it isn't a Windows event log.
+    pub fn new(mut file: File) -> io::Result<EvtxSource> {
+        let mut header = [0; 42];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        if !header.starts_with(FILE_MAGIC) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a Windows event log"));
+        }
+
+        // the chunks follow the header block, whose length is given by it
+        let header_len = u16::from_le_bytes([header[40], header[41]]);
+        file.seek(SeekFrom::Start(header_len as u64))?;
+        Ok(EvtxSource { file: file, lines: VecDeque::new() })
+    }
+
+    /// Opens a log to read its records.
+    pub fn open(path: &str) -> io::Result<EvtxSource> {
+        EvtxSource::new(File::open(path)?)
+    }
+
+    /// Returns `true` if the file at `path` is a Windows event log, as told
+    /// by its magic bytes.
+    pub fn is_evtx(path: &str) -> bool {
+        let mut magic = [0; 8];
+        File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok()
+            && magic == FILE_MAGIC
+    }
+}
+
+impl Iterator for EvtxSource {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        loop {
+            if let Some(line) = self.lines.pop_front() {
+                return Some(Ok(line));
+            }
+
+            let mut chunk = Vec::with_capacity(CHUNK_LEN);
+            match (&mut self.file).take(CHUNK_LEN as u64).read_to_end(&mut chunk) {
+                Ok(0) => return None,
+                Ok(_) => self.lines.extend(decode_chunk(&chunk)),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl LineSource for EvtxSource {}
+
+/// Returns the lines of the records of a chunk, or none if it isn't one, e.g.
+/// a chunk preallocated but not yet written.
+fn decode_chunk(chunk: &[u8]) -> Vec<String> {
+    let mut lines = Vec::new();
+    if !chunk.starts_with(CHUNK_MAGIC) || chunk.len() < RECORDS_OFFSET {
+        return lines;
+    }
+
+    // the records end where the chunk's free space begins
+    let free = Cursor { chunk: chunk, pos: 48 }.u32().unwrap_or(0) as usize;
+    let end = free.clamp(RECORDS_OFFSET, chunk.len());
+    let mut templates = HashMap::new();
+    let mut pos = RECORDS_OFFSET;
+
+    while pos + RECORD_HEADER_LEN <= end && chunk[pos..].starts_with(RECORD_MAGIC) {
+        let mut cursor = Cursor { chunk: chunk, pos: pos + 4 };
+        let (len, id, written) = match (cursor.u32(), cursor.u64(), cursor.u64()) {
+            (Ok(len), Ok(id), Ok(written)) => (len as usize, id, written),
+            _ => break,
+        };
+        if len < RECORD_HEADER_LEN || pos + len > end {
+            break;
+        }
+
+        let mut parser = Parser {
+            chunk: &chunk[..pos + len],
+            templates: &mut templates,
+            depth: 0,
+        };
+        let mut cursor = Cursor { chunk: parser.chunk, pos: pos + RECORD_HEADER_LEN };
+        let line = match parser.nodes(&mut cursor, true) {
+            Ok(nodes) => format_record(id, written, &nodes),
+            Err(err) => format!("{} record={} error={}", format_filetime(written), id,
+                                quote(&format!("can't decode record: {}", err))),
+        };
+        lines.push(line);
+        pos += len;
+    }
+    lines
+}
+
+/// Node of the XML of an event.
+#[derive(Clone, Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+    /// placeholder in a template for the value of the given index
+    Substitution(usize),
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, Vec<Node>)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    /// Returns the first child element with the given name.
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    fn elements(&self) -> impl Iterator<Item=&Element> {
+        self.children.iter().filter_map(|node| match *node {
+            Node::Element(ref element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Returns the text of the attribute with the given name, if it's set.
+    fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes.iter()
+            .find(|&&(ref attribute, _)| attribute == name)
+            .map(|&(_, ref value)| text(value))
+    }
+}
+
+/// Value substituted into a template.
+enum Value {
+    Null,
+    Text(String),
+    /// binary XML, e.g. the data of an event given as a template of its own
+    Xml(Vec<Node>),
+}
+
+/// Reader of little-endian values within a chunk.
+struct Cursor<'a> {
+    chunk: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.chunk.get(self.pos..self.pos + len)
+            .ok_or_else(|| format!("unexpected end of record at offset {}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.chunk.get(self.pos).cloned()
+    }
+
+    /// Reads a string of UTF-16 code units preceded by their number.
+    fn utf16(&mut self) -> Result<String, String> {
+        let len = self.u16()? as usize;
+        Ok(decode_utf16(self.bytes(len * 2)?))
+    }
+}
+
+/// Parser of the binary XML of the records of a chunk.
+struct Parser<'a, 'b> {
+    /// the chunk, up to the end of the record being parsed
+    chunk: &'a [u8],
+    /// templates defined in the chunk, by their offsets within it
+    templates: &'b mut HashMap<usize, Rc<Vec<Node>>>,
+    /// number of instances of templates being parsed within one another
+    depth: usize,
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+    /// Parses nodes up to the end of a fragment of binary XML.
+    ///
+    /// ### Parameters
+    /// * `cursor`: position of the fragment
+    /// * `dependencies`: whether elements carry dependency identifiers, as
+    ///   they do except within binary XML substituted into a template
+    fn nodes(&mut self, cursor: &mut Cursor<'a>, dependencies: bool)
+             -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        // elements whose start has been read but not their end
+        let mut open: Vec<Element> = Vec::new();
+
+        while let Some(token) = cursor.peek() {
+            let node = match token & !0x40 {
+                // end of the fragment
+                0x00 => {
+                    cursor.pos += 1;
+                    break;
+                },
+                // start of an element, closed by 0x02, or by 0x03 if empty
+                0x01 => {
+                    let element = self.element(cursor, dependencies)?;
+                    match cursor.u8()? {
+                        0x02 => {
+                            open.push(element);
+                            continue;
+                        },
+                        0x03 => Node::Element(element),
+                        token => return Err(format!("unexpected token {:#04x}", token)),
+                    }
+                },
+                // end of an element
+                0x04 => {
+                    cursor.pos += 1;
+                    match open.pop() {
+                        Some(element) => Node::Element(element),
+                        None => return Err("end of an element never started".to_owned()),
+                    }
+                },
+                0x0c => {
+                    cursor.pos += 1;
+                    for node in self.template_instance(cursor)? {
+                        push(&mut open, &mut nodes, node);
+                    }
+                    continue;
+                },
+                // header of a fragment
+                0x0f => {
+                    cursor.bytes(4)?;
+                    continue;
+                },
+                // processing instruction
+                0x0a => {
+                    cursor.pos += 1;
+                    self.name(cursor)?;
+                    continue;
+                },
+                0x0b => {
+                    cursor.pos += 1;
+                    cursor.utf16()?;
+                    continue;
+                },
+                _ => self.value(cursor)?,
+            };
+            push(&mut open, &mut nodes, node);
+        }
+
+        if open.is_empty() {
+            Ok(nodes)
+        } else {
+            Err("element never ended".to_owned())
+        }
+    }
+
+    /// Parses the start of an element, along with its attributes.
+    fn element(&mut self, cursor: &mut Cursor<'a>, dependencies: bool)
+               -> Result<Element, String> {
+        let has_attributes = cursor.u8()? & 0x40 != 0;
+        if dependencies {
+            cursor.u16()?;
+        }
+        // length of the element
+        cursor.u32()?;
+        let name = self.name(cursor)?;
+        if has_attributes {
+            cursor.u32()?;
+        }
+
+        let mut attributes = Vec::new();
+        while cursor.peek().is_some_and(|token| token & !0x40 == 0x06) {
+            cursor.pos += 1;
+            let name = self.name(cursor)?;
+            let mut value = Vec::new();
+            while cursor.peek().is_some_and(|token| [0x05, 0x07, 0x08, 0x09, 0x0d, 0x0e]
+                                                        .contains(&(token & !0x40))) {
+                value.push(self.value(cursor)?);
+            }
+            attributes.push((name, value));
+        }
+        Ok(Element { name: name, attributes: attributes, children: Vec::new() })
+    }
+
+    /// Parses text, a reference to a character or an entity, or a
+    /// substitution.
+    fn value(&mut self, cursor: &mut Cursor<'a>) -> Result<Node, String> {
+        let token = cursor.u8()?;
+        Ok(match token & !0x40 {
+            0x05 => {
+                // type of the value, which is always a string
+                cursor.u8()?;
+                Node::Text(cursor.utf16()?)
+            },
+            0x07 => Node::Text(cursor.utf16()?),
+            0x08 => {
+                let code = cursor.u16()? as u32;
+                Node::Text(char::from_u32(code).unwrap_or('\u{fffd}').to_string())
+            },
+            0x09 => {
+                let text = match self.name(cursor)?.as_str() {
+                    "lt" => "<",
+                    "gt" => ">",
+                    "amp" => "&",
+                    "quot" => "\"",
+                    "apos" => "'",
+                    _ => "",
+                };
+                Node::Text(text.to_owned())
+            },
+            // normal or optional substitution, followed by the type of value
+            0x0d | 0x0e => {
+                let idx = cursor.u16()? as usize;
+                cursor.u8()?;
+                Node::Substitution(idx)
+            },
+            _ => return Err(format!("unexpected token {:#04x}", token)),
+        })
+    }
+
+    /// Reads the offset of a name within the chunk, and the name there, which
+    /// follows the offset if it's the name's first use.
+    fn name(&mut self, cursor: &mut Cursor<'a>) -> Result<String, String> {
+        let offset = cursor.u32()? as usize;
+        let mut name = Cursor { chunk: self.chunk, pos: offset };
+        // offset of the next name, and hash
+        name.bytes(6)?;
+        let text = name.utf16()?;
+        if offset == cursor.pos {
+            // past the name and its terminating null
+            cursor.pos = name.pos + 2;
+        }
+        Ok(text)
+    }
+
+    /// Parses an instance of a template, returning its nodes with the values
+    /// given substituted into them, or an error if it's nested in more than
+    /// `MAX_TEMPLATE_DEPTH` others.
+    fn template_instance(&mut self, cursor: &mut Cursor<'a>) -> Result<Vec<Node>, String> {
+        if self.depth == MAX_TEMPLATE_DEPTH {
+            return Err("templates nested too deeply".to_owned());
+        }
+        self.depth += 1;
+        let nodes = self.instantiate(cursor);
+        self.depth -= 1;
+        nodes
+    }
+
+    /// Parses an instance of a template, however deeply it's nested.
+    fn instantiate(&mut self, cursor: &mut Cursor<'a>) -> Result<Vec<Node>, String> {
+        // unknown, and the identifier of the template
+        cursor.bytes(5)?;
+        let offset = cursor.u32()? as usize;
+        if offset == cursor.pos {
+            // the template is defined here, at its first use
+            cursor.bytes(TEMPLATE_HEADER_LEN - 4)?;
+            let len = cursor.u32()? as usize;
+            cursor.bytes(len)?;
+        }
+
+        let template = match self.templates.get(&offset) {
+            Some(template) => template.clone(),
+            None => {
+                let pos = offset + TEMPLATE_HEADER_LEN;
+                let mut definition = Cursor { chunk: self.chunk, pos: pos };
+                let template = Rc::new(self.nodes(&mut definition, true)?);
+                self.templates.insert(offset, template.clone());
+                template
+            },
+        };
+
+        let count = cursor.u32()? as usize;
+        let mut kinds = Vec::new();
+        for _ in 0..count {
+            let len = cursor.u16()? as usize;
+            let kind = cursor.u8()?;
+            cursor.u8()?;
+            kinds.push((len, kind));
+        }
+        let mut values = Vec::with_capacity(count);
+        for (len, kind) in kinds {
+            let start = cursor.pos;
+            let bytes = cursor.bytes(len)?;
+            values.push(if kind == 0x21 {
+                let chunk: &'a [u8] = self.chunk;
+                let mut xml = Cursor { chunk: &chunk[..start + len], pos: start };
+                Value::Xml(self.nodes(&mut xml, false)?)
+            } else {
+                format_value(kind, bytes).map_or(Value::Null, Value::Text)
+            });
+        }
+        Ok(substitute(&template, &values))
+    }
+}
+
+/// Adds a node to the element last started, or to the top level if none is.
+fn push(open: &mut [Element], nodes: &mut Vec<Node>, node: Node) {
+    match open.last_mut() {
+        Some(element) => element.children.push(node),
+        None => nodes.push(node),
+    }
+}
+
+/// Returns the nodes of a template with values substituted for the
+/// placeholders; null values are left out.
+fn substitute(nodes: &[Node], values: &[Value]) -> Vec<Node> {
+    let mut substituted = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        match *node {
+            Node::Substitution(idx) => match values.get(idx) {
+                Some(&Value::Text(ref text)) => substituted.push(Node::Text(text.clone())),
+                Some(&Value::Xml(ref nodes)) => substituted.extend(nodes.iter().cloned()),
+                Some(&Value::Null) | None => {},
+            },
+            Node::Element(ref element) => substituted.push(Node::Element(Element {
+                name: element.name.clone(),
+                attributes: element.attributes.iter()
+                    .map(|&(ref name, ref value)| (name.clone(), substitute(value, values)))
+                    .collect(),
+                children: substitute(&element.children, values),
+            })),
+            Node::Text(_) => substituted.push(node.clone()),
+        }
+    }
+    substituted
+}
+
+/// Formats a value substituted into a template, given its type, returning
+/// `None` if it's null.
+fn format_value(kind: u8, bytes: &[u8]) -> Option<String> {
+    // case: an array, of strings separated by nulls or of fixed-size values
+    if kind & 0x80 != 0 {
+        let kind = kind & !0x80;
+        let items: Vec<String> = match (kind, fixed_len(kind)) {
+            (0x01, _) => decode_utf16(bytes).split('\0').filter(|item| !item.is_empty())
+                .map(|item| item.to_owned()).collect(),
+            (_, Some(len)) => bytes.chunks(len).filter_map(|item| format_value(kind, item))
+                .collect(),
+            _ => return Some(hex(bytes)),
+        };
+        return Some(items.join(", "));
+    }
+
+    let int = |len: usize| {
+        let mut buf = [0; 8];
+        let len = len.min(bytes.len());
+        buf[..len].copy_from_slice(&bytes[..len]);
+        u64::from_le_bytes(buf)
+    };
+    Some(match kind {
+        0x00 => return None,
+        0x01 => decode_utf16(bytes).trim_end_matches('\0').to_owned(),
+        0x02 => String::from_utf8_lossy(bytes).trim_end_matches('\0').to_owned(),
+        0x03 => (int(1) as i8).to_string(),
+        0x04 => int(1).to_string(),
+        0x05 => (int(2) as i16).to_string(),
+        0x06 => int(2).to_string(),
+        0x07 => (int(4) as i32).to_string(),
+        0x08 => int(4).to_string(),
+        0x09 => (int(8) as i64).to_string(),
+        0x0a => int(8).to_string(),
+        0x0b => f32::from_bits(int(4) as u32).to_string(),
+        0x0c => f64::from_bits(int(8)).to_string(),
+        0x0d => (int(4) != 0).to_string(),
+        0x0f if bytes.len() == 16 => format_guid(bytes),
+        0x10 | 0x14 | 0x15 => format!("{:#x}", int(8)),
+        0x11 => format_filetime(int(8)),
+        0x12 if bytes.len() == 16 => format_systemtime(bytes),
+        0x13 => format_sid(bytes).unwrap_or_else(|| hex(bytes)),
+        _ => hex(bytes),
+    })
+}
+
+/// Returns the length of each value of a type which has a fixed length.
+fn fixed_len(kind: u8) -> Option<usize> {
+    match kind {
+        0x03 | 0x04 => Some(1),
+        0x05 | 0x06 => Some(2),
+        0x07 | 0x08 | 0x0b | 0x0d | 0x14 => Some(4),
+        0x09 | 0x0a | 0x0c | 0x11 | 0x15 => Some(8),
+        0x0f | 0x12 => Some(16),
+        _ => None,
+    }
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks(2)
+        .filter(|unit| unit.len() == 2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// Formats a `FILETIME`, in 100 nanoseconds since 1601-01-01 UTC, as an ISO
+/// timestamp in UTC.
+fn format_filetime(filetime: u64) -> String {
+    timestamp::format_iso((filetime / 10_000) as i64 - FILETIME_EPOCH_MILLIS, 0)
+}
+
+/// Formats a `SYSTEMTIME`, of eight 16-bit fields from the year down to the
+/// millisecond, the third being the day of the week, as an ISO timestamp.
+fn format_systemtime(bytes: &[u8]) -> String {
+    let field = |i: usize| u16::from_le_bytes([bytes[i * 2], bytes[i * 2 + 1]]) as i64;
+    let days = timestamp::days_from_civil(field(0), field(1), field(3));
+    let millis = ((days * 24 + field(4)) * 60 + field(5)) * 60_000 + field(6) * 1000 + field(7);
+    timestamp::format_iso(millis, 0)
+}
+
+fn format_guid(bytes: &[u8]) -> String {
+    format!("{{{:08X}-{:04X}-{:04X}-{}-{}}}",
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            u16::from_le_bytes([bytes[4], bytes[5]]),
+            u16::from_le_bytes([bytes[6], bytes[7]]),
+            hex(&bytes[8..10]), hex(&bytes[10..16]))
+}
+
+/// Formats a security identifier, e.g. `S-1-5-18`, returning `None` if it's
+/// malformed.
+fn format_sid(bytes: &[u8]) -> Option<String> {
+    let count = *bytes.get(1)? as usize;
+    if bytes.len() != 8 + count * 4 {
+        return None;
+    }
+    let authority = bytes[2..8].iter().fold(0u64, |authority, &byte| authority << 8 | byte as u64);
+    let mut sid = format!("S-{}-{}", bytes[0], authority);
+    for sub in bytes[8..].chunks(4) {
+        sid.push_str(&format!("-{}", u32::from_le_bytes([sub[0], sub[1], sub[2], sub[3]])));
+    }
+    Some(sid)
+}
+
+/// Returns the text within nodes, elements included.
+fn text(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match *node {
+            Node::Text(ref part) => text.push_str(part),
+            Node::Element(ref element) => text.push_str(&self::text(&element.children)),
+            Node::Substitution(_) => {},
+        }
+    }
+    text
+}
+
+/// Formats a record as a line: the time it was created, falling back on the
+/// time it was written, followed by its fields, of which the message is the
+/// data of the event.
+fn format_record(id: u64, written: u64, nodes: &[Node]) -> String {
+    let event = nodes.iter().filter_map(|node| match *node {
+        Node::Element(ref element) if element.name == "Event" => Some(element),
+        _ => None,
+    }).next();
+    let system = event.and_then(|event| event.child("System"));
+    let field = |name: &str| system.and_then(|system| system.child(name));
+
+    let time = field("TimeCreated").and_then(|time| time.attribute("SystemTime"))
+        .filter(|time| timestamp::parse_leading(time).is_some())
+        .unwrap_or_else(|| format_filetime(written));
+    let provider = field("Provider")
+        .and_then(|provider| provider.attribute("Name")
+                  .or_else(|| provider.attribute("EventSourceName")))
+        .unwrap_or_default();
+    let level = field("Level").map(|level| text(&level.children)).unwrap_or_default();
+    let level = level.parse::<usize>().ok()
+        .and_then(|level| LEVELS.get(level))
+        .map_or(level.clone(), |name| (*name).to_owned());
+    let event_id = field("EventID").map(|id| text(&id.children)).unwrap_or_default();
+
+    let mut data = Vec::new();
+    if let Some(event) = event {
+        for element in event.elements().filter(|element| element.name != "System") {
+            describe(element, &mut data);
+        }
+    }
+
+    format!("{} record={} provider={} level={} event_id={} msg={}", time, id, quote(&provider),
+            quote(&level), quote(&event_id), quote(&data.join(", ")))
+}
+
+/// Adds the values of the elements within `element` which hold text, each
+/// labeled by its `Name` attribute or else its name, e.g. `LogonType: 3`.
+fn describe(element: &Element, data: &mut Vec<String>) {
+    for child in element.elements() {
+        if child.elements().next().is_some() {
+            describe(child, data);
+            continue;
+        }
+        let value = text(&child.children);
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        match child.attribute("Name") {
+            Some(name) => data.push(format!("{}: {}", name, value)),
+            None if child.name == "Data" => data.push(value.to_owned()),
+            None => data.push(format!("{}: {}", child.name, value)),
+        }
+    }
+}
+
+/// Returns a logfmt value, quoted if it's empty or contains whitespace,
+/// quotes or equals signs.
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=' || c == '\\');
+    if plain {
+        return value.to_owned();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            },
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => {},
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process;
+
+    use super::{decode_chunk, EvtxSource, CHUNK_LEN, RECORDS_OFFSET};
+
+    /// Writer of binary XML into a chunk, at offsets within it.
+    struct Chunk(Vec<u8>);
+
+    impl Chunk {
+        fn u16(&mut self, value: u16) -> &mut Chunk {
+            self.0.extend_from_slice(&value.to_le_bytes());
+            self
+        }
+
+        fn u32(&mut self, value: u32) -> &mut Chunk {
+            self.0.extend_from_slice(&value.to_le_bytes());
+            self
+        }
+
+        fn utf16(&mut self, text: &str) -> &mut Chunk {
+            for unit in text.encode_utf16() {
+                self.u16(unit);
+            }
+            self
+        }
+
+        /// Writes the offset of a name, followed by the name itself.
+        fn name(&mut self, name: &str) -> &mut Chunk {
+            let offset = self.0.len() as u32 + 4;
+            self.u32(offset).u32(0).u16(0).u16(name.len() as u16).utf16(name).u16(0)
+        }
+
+        /// Writes the start of an element, with an attribute set to a
+        /// substitution if one is given.
+        fn start(&mut self, name: &str, attribute: Option<(&str, u16)>) -> &mut Chunk {
+            self.0.push(if attribute.is_some() { 0x41 } else { 0x01 });
+            self.u16(0).u32(0).name(name);
+            if let Some((attribute, idx)) = attribute {
+                self.u32(0);
+                self.0.push(0x06);
+                self.name(attribute).substitution(idx);
+            }
+            self.0.push(if attribute.is_some() { 0x03 } else { 0x02 });
+            self
+        }
+
+        fn substitution(&mut self, idx: u16) -> &mut Chunk {
+            self.0.push(0x0d);
+            self.u16(idx);
+            self.0.push(0x01);
+            self
+        }
+
+        fn end(&mut self) -> &mut Chunk {
+            self.0.push(0x04);
+            self
+        }
+    }
+
+    /// Returns a record of an event with the given substitution values, its
+    /// template defined inline by the first record of the chunk.
+    fn record(chunk: &mut Chunk, id: u64, template: Option<u32>, values: &[&str]) {
+        let start = chunk.0.len();
+        chunk.0.extend_from_slice(b"**\0\0");
+        chunk.u32(0);
+        chunk.0.extend_from_slice(&id.to_le_bytes());
+        chunk.0.extend_from_slice(&133_588_404_000_000_000u64.to_le_bytes());
+        chunk.0.extend_from_slice(&[0x0f, 1, 1, 0, 0x0c, 1]);
+        chunk.u32(0);
+
+        match template {
+            Some(offset) => {
+                chunk.u32(offset);
+            },
+            None => {
+                let offset = chunk.0.len() as u32 + 4;
+                chunk.u32(offset).u32(0).u32(0).u32(0).u32(0).u32(0);
+                let len_at = chunk.0.len();
+                chunk.u32(0);
+                chunk.0.extend_from_slice(&[0x0f, 1, 1, 0]);
+                chunk.start("Event", None).start("System", None);
+                chunk.0.push(0x41);
+                chunk.u16(0).u32(0).name("Provider").u32(0);
+                chunk.0.push(0x06);
+                chunk.name("Name").substitution(0);
+                chunk.0.push(0x03);
+                chunk.start("EventID", None).substitution(1).end();
+                chunk.start("Level", None).substitution(2).end();
+                chunk.end();
+                chunk.start("EventData", None);
+                chunk.start("Data", None);
+                chunk.0.push(0x05);
+                chunk.0.push(0x01);
+                chunk.u16(7).utf16("started").end();
+                chunk.start("Binary", None).substitution(3).end();
+                chunk.end().end();
+                chunk.0.push(0x00);
+                let len = (chunk.0.len() - len_at - 4) as u32;
+                chunk.0[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+            },
+        }
+
+        chunk.u32(values.len() as u32);
+        for value in values {
+            chunk.u16(value.len() as u16 * 2);
+            chunk.0.extend_from_slice(&[0x01, 0]);
+        }
+        for value in values {
+            chunk.utf16(value);
+        }
+        chunk.0.push(0x00);
+
+        chunk.u32(0);
+        let len = (chunk.0.len() - start) as u32;
+        chunk.0[start + 4..start + 8].copy_from_slice(&len.to_le_bytes());
+        let end = chunk.0.len();
+        chunk.0[end - 4..].copy_from_slice(&len.to_le_bytes());
+    }
+
+    #[test]
+    fn test_evtx_source() {
+        let mut chunk = Chunk(b"ElfChnk\0".to_vec());
+        chunk.0.resize(RECORDS_OFFSET, 0);
+        record(&mut chunk, 7, None, &["Service Control Manager", "7036", "4", ""]);
+        let template = RECORDS_OFFSET as u32 + 24 + 10 + 4;
+        record(&mut chunk, 8, Some(template), &["Disk", "11", "2", "x\"y"]);
+        let free = chunk.0.len() as u32;
+        chunk.0[48..52].copy_from_slice(&free.to_le_bytes());
+        chunk.0.resize(CHUNK_LEN, 0);
+
+        let mut header = b"ElfFile\0".to_vec();
+        header.resize(4096, 0);
+        header[40..42].copy_from_slice(&4096u16.to_le_bytes());
+        // a chunk preallocated but never written follows
+        let mut file = header;
+        file.extend_from_slice(&chunk.0);
+        file.resize(file.len() + CHUNK_LEN, 0);
+
+        let path = env::temp_dir().join(format!("filterless-{}-events.evtx", process::id()));
+        File::create(&path).unwrap().write_all(&file).unwrap();
+        let path = path.to_str().unwrap();
+        assert!(EvtxSource::is_evtx(path));
+        let lines: Vec<String> = EvtxSource::open(path).unwrap().map(Result::unwrap).collect();
+        assert_eq!(lines, vec![
+            concat!("2024-04-29T05:00:00.000Z record=7 provider=\"Service Control Manager\" ",
+                    "level=info event_id=7036 msg=started").to_owned(),
+            concat!("2024-04-29T05:00:00.000Z record=8 provider=Disk level=error event_id=11 ",
+                    "msg=\"started, Binary: x\\\"y\"").to_owned(),
+        ]);
+        fs::remove_file(path).unwrap();
+
+        assert!(!EvtxSource::is_evtx("Cargo.toml"));
+        assert!(EvtxSource::open("Cargo.toml").is_err());
+    }
+
+    #[test]
+    fn test_template_instancing_itself() {
+        let mut chunk = Chunk(b"ElfChnk\0".to_vec());
+        chunk.0.resize(RECORDS_OFFSET, 0);
+        chunk.0.extend_from_slice(b"**\0\0");
+        chunk.u32(0);
+        chunk.0.extend_from_slice(&9u64.to_le_bytes());
+        chunk.0.extend_from_slice(&133_588_404_000_000_000u64.to_le_bytes());
+        chunk.0.extend_from_slice(&[0x0f, 1, 1, 0, 0x0c, 1]);
+        chunk.u32(0);
+        // a template defined inline whose definition is an instance of itself
+        let offset = chunk.0.len() as u32 + 4;
+        chunk.u32(offset).u32(0).u32(0).u32(0).u32(0).u32(0).u32(19);
+        chunk.0.extend_from_slice(&[0x0f, 1, 1, 0, 0x0c, 1]);
+        chunk.u32(0).u32(offset).u32(0);
+        chunk.0.push(0x00);
+        chunk.u32(0);
+        chunk.0.push(0x00);
+        chunk.u32(0);
+        let len = (chunk.0.len() - RECORDS_OFFSET) as u32;
+        chunk.0[RECORDS_OFFSET + 4..RECORDS_OFFSET + 8].copy_from_slice(&len.to_le_bytes());
+        let free = chunk.0.len() as u32;
+        chunk.0[48..52].copy_from_slice(&free.to_le_bytes());
+
+        assert_eq!(decode_chunk(&chunk.0), vec![
+            concat!("2024-04-29T05:00:00.000Z record=9 ",
+                    "error=\"can't decode record: templates nested too deeply\"").to_owned(),
+        ]);
+    }
+}
@@ -1,23 +1,385 @@
 use std::fmt;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::matcher::{self, MatchOptions, Matcher, MatcherKind, SUBSTRING};
+
 /// Parameters used when creating a filtering iterator
 #[derive(Clone)]
 pub struct FilterPredicate {
-    /// Search string which must be included in a line to be considered a match
-    pub filter_string: String,
     /// Number of non-match lines above and below a match line to include in
     /// the lines returned by the iterator
     pub context_lines: usize ,
+    /// Means by which lines are tested for a match
+    pub matcher: Matcher,
+    /// Filter strings from which `matcher` was built
+    pub source: FilterSource,
+    /// Predicate which lines must match as well to be matched, e.g. a sticky
+    /// filter of log levels kept while others come and go
+    pub base: Option<Box<FilterPredicate>>,
+    /// Whether the lines returned are instead those with no match within
+    /// `context_lines` above or below, e.g. where a heartbeat stopped
+    pub inverse: bool,
+}
+
+/// Filter strings from which a predicate's matcher is built, by which the
+/// predicate can be described and built again.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FilterSource {
+    /// name of the kind of matcher, e.g. `regex`
+    pub kind: String,
+    /// the filter strings; lines matching any of them match
+    pub patterns: Vec<String>,
+    /// how the filter strings are compared with lines
+    pub options: MatchOptions,
+    /// range of lines to which matches are restricted, if any
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub lines: Option<LineRange>,
+}
+
+/// Range of line numbers, including both ends, e.g. as written `1000,2000`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LineRange {
+    /// number of the first line of the range
+    pub first: usize,
+    /// number of the last line of the range, or `usize::MAX` if the range
+    /// runs to the end of the input
+    pub last: usize,
+}
+
+impl LineRange {
+    /// Parses a range written `FIRST,LAST`, where either may be omitted to
+    /// leave the range open at that end, e.g. `1000,` for line 1000 onward.
+    pub fn parse(text: &str) -> Result<LineRange, String> {
+        let invalid = || format!("expected `FIRST,LAST`; got `{}`", text);
+        let mut bounds = text.splitn(2, ',');
+        let parse_bound = |bound: Option<&str>, default: usize| match bound.map(str::trim) {
+            Some("") => Ok(default),
+            Some(bound) => bound.parse::<usize>().ok().filter(|&n| n > 0).ok_or_else(invalid),
+            None => Err(invalid()),
+        };
+
+        let first = parse_bound(bounds.next(), 1)?;
+        let last = parse_bound(bounds.next(), usize::MAX)?;
+        if first > last {
+            return Err(format!("line {} comes after line {}", first, last));
+        }
+        Ok(LineRange { first: first, last: last })
+    }
+
+    /// Returns `true` if line `line_num` lies within the range.
+    pub fn contains(&self, line_num: usize) -> bool {
+        self.first <= line_num && line_num <= self.last
+    }
+}
+
+/// Writes the range as it's parsed, e.g. `1000,2000` or `1000,`.
+impl fmt::Display for LineRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.last {
+            usize::MAX => write!(f, "{},", self.first),
+            last => write!(f, "{},{}", self.first, last),
+        }
+    }
+}
+
+impl FilterPredicate {
+    /// Creates a predicate matching lines which contain `filter_string`.
+    pub fn new(filter_string: String, context_lines: usize) -> FilterPredicate {
+        FilterPredicate {
+            matcher: Matcher::substring(filter_string.clone()),
+            context_lines: context_lines,
+            source: FilterSource {
+                kind: SUBSTRING.name.to_owned(),
+                patterns: vec![filter_string],
+                options: MatchOptions::default(),
+                lines: None,
+            },
+            base: None,
+            inverse: false,
+        }
+    }
+
+    /// Creates a predicate matching every line within `lines`, e.g. to view
+    /// only part of the input.
+    pub fn within(lines: LineRange, context_lines: usize) -> FilterPredicate {
+        FilterPredicate {
+            matcher: Matcher::substring(String::new()),
+            context_lines: context_lines,
+            source: FilterSource {
+                kind: SUBSTRING.name.to_owned(),
+                patterns: Vec::new(),
+                options: MatchOptions::default(),
+                lines: Some(lines),
+            },
+            base: None,
+            inverse: false,
+        }
+    }
+
+    /// Restricts the matches of the predicate to the lines within `lines`,
+    /// or lifts the restriction if `lines` is `None`.
+    ///
+    /// Context lines are still shown around matches near either end of the
+    /// range, even though they lie outside it.
+    pub fn with_lines(mut self, lines: Option<LineRange>) -> FilterPredicate {
+        self.source.lines = lines;
+        self
+    }
+
+    /// Requires lines matched by the predicate to be matched by `base` as
+    /// well, replacing any base it had, or lifts the requirement if `base` is
+    /// `None`.
+    ///
+    /// Only the matches of the predicate itself are highlighted; the context
+    /// lines of `base` are ignored.
+    pub fn with_base(mut self, base: Option<FilterPredicate>) -> FilterPredicate {
+        self.base = base.map(Box::new);
+        self
+    }
+
+    /// Shows the lines which are more than `context_lines` away from any
+    /// match instead of the matches and their context, if `inverse` is
+    /// `true`, or the usual view if it's `false`.
+    pub fn with_inverse(mut self, inverse: bool) -> FilterPredicate {
+        self.inverse = inverse;
+        self
+    }
+
+    /// Returns `true` if line `line_num`, the text of which is `line`, is
+    /// matched by the predicate.
+    pub fn is_match(&self, line_num: usize, line: &str) -> bool {
+        self.source.lines.is_none_or(|lines| lines.contains(line_num))
+            && self.matcher.is_match(line)
+            && self.base.as_ref().is_none_or(|base| base.is_match(line_num, line))
+    }
+
+    /// Returns `true` if every line matched by the predicate is known to be
+    /// matched by `other` as well, e.g. when a filter of `ERROR` is refined
+    /// to `ERROR timeout`: both match substrings compared alike, each filter
+    /// string of the predicate contains the only one of `other`, and they
+    /// share their base and range of lines.
+    ///
+    /// Context lines aren't considered, as they don't bear on which lines
+    /// match.
+    pub fn narrows(&self, other: &FilterPredicate) -> bool {
+        let substrings = |source: &FilterSource| source.kind == SUBSTRING.name;
+        let pattern = match other.source.patterns.as_slice() {
+            [pattern] => pattern,
+            _ => return false,
+        };
+
+        substrings(&self.source) && substrings(&other.source)
+            && !self.inverse && !other.inverse
+            && self.source.options == other.source.options
+            && self.source.lines == other.source.lines
+            && self.base == other.base
+            && !self.source.patterns.is_empty()
+            && self.source.patterns.iter().all(|narrower| narrower.contains(pattern.as_str()))
+    }
+
+    /// Creates a predicate matching lines according to the given kind of
+    /// matcher.
+    ///
+    /// ### Parameters
+    /// * `kind`: how `pattern` is interpreted
+    /// * `pattern`: the filter string
+    /// * `options`: how `pattern` is compared with lines
+    /// * `context_lines`: number of context lines around each match
+    pub fn with_kind(kind: &MatcherKind, pattern: &str, options: &MatchOptions,
+                     context_lines: usize) -> Result<FilterPredicate, String> {
+        Ok(FilterPredicate {
+            context_lines: context_lines,
+            matcher: (kind.build)(pattern, options)?,
+            source: FilterSource {
+                kind: kind.name.to_owned(),
+                patterns: vec![pattern.to_owned()],
+                options: *options,
+                lines: None,
+            },
+            base: None,
+            inverse: false,
+        })
+    }
+
+    /// Creates a predicate matching lines which match any of several filter
+    /// strings, each interpreted by the given kind of matcher.
+    ///
+    /// ### Parameters
+    /// * `kind`: how `patterns` are interpreted
+    /// * `patterns`: the filter strings
+    /// * `options`: how `patterns` are compared with lines
+    /// * `context_lines`: number of context lines around each match
+    pub fn any_of(kind: &MatcherKind, patterns: &[String], options: &MatchOptions,
+                  context_lines: usize) -> Result<FilterPredicate, String> {
+        let matchers = patterns.iter()
+            .map(|pattern| {
+                (kind.build)(pattern, options).map_err(|e| format!("`{}`: {}", pattern, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(FilterPredicate {
+            context_lines: context_lines,
+            matcher: Matcher::any(matchers),
+            source: FilterSource {
+                kind: kind.name.to_owned(),
+                patterns: patterns.to_vec(),
+                options: *options,
+                lines: None,
+            },
+            base: None,
+            inverse: false,
+        })
+    }
+
+    /// Creates a predicate from the filter strings of another, e.g. one which
+    /// was serialized, failing if its kind of matcher isn't compiled in.
+    pub fn from_source(source: &FilterSource, context_lines: usize)
+        -> Result<FilterPredicate, String> {
+
+        let kind = matcher::kind(&source.kind)?;
+        let predicate = match (source.patterns.len(), source.lines) {
+            (0, Some(lines)) => Ok(FilterPredicate::within(lines, context_lines)),
+            (1, _) => FilterPredicate::with_kind(&kind, &source.patterns[0], &source.options,
+                                                 context_lines),
+            _ => FilterPredicate::any_of(&kind, &source.patterns, &source.options,
+                                         context_lines),
+        };
+        predicate.map(|predicate| predicate.with_lines(source.lines))
+    }
+}
+
+impl PartialEq for FilterPredicate {
+    fn eq(&self, other: &FilterPredicate) -> bool {
+        self.context_lines == other.context_lines && self.source == other.source
+            && self.base == other.base && self.inverse == other.inverse
+    }
+}
+
+impl fmt::Debug for FilterPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FilterPredicate")
+            .field("context_lines", &self.context_lines)
+            .field("source", &self.source)
+            .field("base", &self.base)
+            .field("inverse", &self.inverse)
+            .finish()
+    }
+}
+
+/// Writes the filter strings of `source`, e.g. `regex "err(or)?" | "warn"`,
+/// or `lines 1000,2000` if it has none but a range of lines.
+fn describe_source(f: &mut fmt::Formatter, source: &FilterSource) -> fmt::Result {
+    if source.patterns.is_empty() {
+        write!(f, "lines")?;
+    } else {
+        write!(f, "{}", source.kind)?;
+    }
+    for (i, pattern) in source.patterns.iter().enumerate() {
+        write!(f, "{}{:?}", if i == 0 { " " } else { " | " }, pattern)?;
+    }
+    if let Some(lines) = source.lines {
+        let separator = if source.patterns.is_empty() { " " } else { " in lines " };
+        write!(f, "{}{}", separator, lines)?;
+    }
+    Ok(())
+}
+
+/// Describes the predicate as e.g. `regex "err(or)?" | "warn" (3 context
+/// lines, ignore case)`, `lines 1000,2000 (0 context lines)` for one which
+/// matches a range of lines, `substring "db" and level "warn" (3 context
+/// lines)` for one with a base, or `no substring "tick" (30 context lines)`
+/// for an inverse one.
+impl fmt::Display for FilterPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.inverse {
+            write!(f, "no ")?;
+        }
+        describe_source(f, &self.source)?;
+        if let Some(ref base) = self.base {
+            write!(f, " and ")?;
+            describe_source(f, &base.source)?;
+        }
+
+        write!(f, " ({} context line{}", self.context_lines,
+               if self.context_lines == 1 { "" } else { "s" })?;
+        if self.source.options.ignore_case {
+            write!(f, ", ignore case")?;
+        }
+        if self.source.options.ignore_accents {
+            write!(f, ", ignore accents")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Predicates are serialized as their filter strings, from which the matcher
+/// is built again when deserialized.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SerializedPredicate {
+    context_lines: usize,
+    source: FilterSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<FilterSource>,
+    #[serde(default, skip_serializing_if = "::std::ops::Not::not")]
+    inverse: bool,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for FilterPredicate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedPredicate {
+            context_lines: self.context_lines,
+            source: self.source.clone(),
+            base: self.base.as_ref().map(|base| base.source.clone()),
+            inverse: self.inverse,
+        }.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for FilterPredicate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<FilterPredicate, D::Error> {
+        let serialized = SerializedPredicate::deserialize(deserializer)?;
+        let base = match serialized.base {
+            Some(ref base) => Some(FilterPredicate::from_source(base, serialized.context_lines)
+                .map_err(::serde::de::Error::custom)?),
+            None => None,
+        };
+        FilterPredicate::from_source(&serialized.source, serialized.context_lines)
+            .map(|predicate| predicate.with_base(base).with_inverse(serialized.inverse))
+            .map_err(::serde::de::Error::custom)
+    }
 }
 
 pub type NumberedLine = (usize, String);
 
+/// Displays a `NumberedLine` as its zero-padded line number followed by its
+/// text, e.g. `00042: text`.
+pub struct DisplayNumbered<'a>(pub &'a NumberedLine);
+
+impl<'a> fmt::Display for DisplayNumbered<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:05}: {}", (self.0).0, (self.0).1)
+    }
+}
+
 /// Representation of a line that might be returned from a filtering iterator.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FilteredLine {
     /// a gap between context groups (i.e., groups of context lines
-    /// corresponding to distinct match lines)
-    Gap,
+    /// corresponding to distinct match lines), standing for the lines skipped
+    Gap {
+        /// number of the first line skipped
+        from: usize,
+        /// number of the last line skipped
+        to: usize,
+    },
     /// a line which provides context before or after a matched line
     ContextLine(NumberedLine),
     /// a line matched by a filter string
@@ -27,12 +389,14 @@ pub enum FilteredLine {
 }
 
 #[derive(Clone, Debug)]
-/// Representation of a line returned from a ContextBuffer.
+/// Representation of a line held by a ContextBuffer: the line's number,
+/// which indexes its text in the `LineBuffer`, so that the text is only copied
+/// if the line is returned.
 pub enum ContextLine {
     /// the line matched a given filter string
-    Match(NumberedLine),
+    Match(usize),
     /// the line did not match the filter string
-    NoMatch(NumberedLine),
+    NoMatch(usize),
 }
 
 /// Representation of an iterator's encounter with a context gap.
@@ -46,25 +410,41 @@ pub enum Gap {
 }
 
 impl ContextLine {
-    /// Creates a `ContextLine` instance by consuming a `NumberedLine`.
-    pub fn from_numbered_line(numbered_line: NumberedLine, filter_string: &String) -> ContextLine {
-        if numbered_line.1.contains(filter_string) {
-            ContextLine::Match(numbered_line)
+    /// Creates a `ContextLine` instance classifying a line by whether it
+    /// matches a predicate.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed number of the line
+    /// * `line`: text of the line
+    /// * `predicate`: predicate of the lines which match
+    pub fn classify(line_num: usize, line: &str, predicate: &FilterPredicate) -> ContextLine {
+        if predicate.is_match(line_num, line) {
+            ContextLine::Match(line_num)
         } else {
-            ContextLine::NoMatch(numbered_line)
+            ContextLine::NoMatch(line_num)
         }
     }
 
-    /// Creates a `FilteredLine` by cloning the inner `NumberedLine`.
-    pub fn to_filtered_line(&self, pred: &Option<FilterPredicate>) -> FilteredLine {
+    /// Returns the number of the line.
+    pub fn line_num(&self) -> usize {
+        match *self {
+            ContextLine::Match(line_num) | ContextLine::NoMatch(line_num) => line_num,
+        }
+    }
+
+    /// Creates a `FilteredLine` of this line, taking its text.
+    ///
+    /// ### Parameters
+    /// * `line`: text of the line
+    /// * `pred`: filter predicate in use, if any
+    pub fn to_filtered_line(&self, line: String, pred: &Option<FilterPredicate>) -> FilteredLine {
+        let numbered_line = (self.line_num(), line);
         match self {
-            &ContextLine::Match(ref numbered_line) => {
-                FilteredLine::MatchLine(numbered_line.to_owned())
-            },
-            &ContextLine::NoMatch(ref numbered_line) => {
+            &ContextLine::Match(_) => FilteredLine::MatchLine(numbered_line),
+            &ContextLine::NoMatch(_) => {
                 match pred {
-                    &Some(_) => FilteredLine::ContextLine(numbered_line.to_owned()),
-                    &None => FilteredLine::UnfilteredLine(numbered_line.to_owned()),
+                    &Some(_) => FilteredLine::ContextLine(numbered_line),
+                    &None => FilteredLine::UnfilteredLine(numbered_line),
                 }
             },
         }
@@ -72,21 +452,193 @@ impl ContextLine {
 
 }
 
+impl FilteredLine {
+    /// Returns the number of the line within the underlying input, or `None`
+    /// for a gap.
+    pub fn line_num(&self) -> Option<usize> {
+        match *self {
+            FilteredLine::Gap { .. } => None,
+            FilteredLine::ContextLine((line_num, _)) |
+            FilteredLine::MatchLine((line_num, _)) |
+            FilteredLine::UnfilteredLine((line_num, _)) => Some(line_num),
+        }
+    }
+
+    /// Returns `true` if this line matched the filter string.
+    pub fn is_match(&self) -> bool {
+        matches!(*self, FilteredLine::MatchLine(_))
+    }
+
+    /// Returns `true` if this is a gap rather than a line.
+    pub fn is_gap(&self) -> bool {
+        matches!(*self, FilteredLine::Gap { .. })
+    }
+
+    /// Returns the number of lines a gap stands for, or `None` for a line.
+    pub fn skipped(&self) -> Option<usize> {
+        match *self {
+            FilteredLine::Gap { from, to } => Some((to + 1).saturating_sub(from)),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for FilteredLine {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            &FilteredLine::Gap => {
-                write!(f, "-----")
+            &FilteredLine::Gap { from, to } => {
+                write!(f, "G {:05}-{:05}", from, to)
             },
-            &FilteredLine::ContextLine((line_num, ref line)) => {
-                write!(f, "C {:05}: {}", line_num, line)
+            &FilteredLine::ContextLine(ref numbered_line) => {
+                write!(f, "C {}", DisplayNumbered(numbered_line))
             },
-            &FilteredLine::MatchLine((line_num, ref line)) => {
-                write!(f, "M {:05}: {}", line_num, line)
+            &FilteredLine::MatchLine(ref numbered_line) => {
+                write!(f, "M {}", DisplayNumbered(numbered_line))
             },
-            &FilteredLine::UnfilteredLine((line_num, ref line)) => {
-                write!(f, "U {:05}: {}", line_num, line)
+            &FilteredLine::UnfilteredLine(ref numbered_line) => {
+                write!(f, "U {}", DisplayNumbered(numbered_line))
             },
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::super::matcher::{self, MatchOptions};
+    use super::{FilteredLine, FilterPredicate, FilterSource, LineRange};
+
+    #[test]
+    fn test_display() {
+        let predicate = FilterPredicate::new("ERROR".to_owned(), 1);
+        assert_eq!(predicate.to_string(), "substring \"ERROR\" (1 context line)");
+
+        let options = MatchOptions { ignore_case: true, ignore_accents: false };
+        let predicate = FilterPredicate::any_of(
+            &matcher::SUBSTRING, &["a \"b\"".to_owned(), "c".to_owned()], &options, 3).unwrap();
+        assert_eq!(predicate.to_string(),
+                   "substring \"a \\\"b\\\"\" | \"c\" (3 context lines, ignore case)");
+
+        assert_eq!(FilteredLine::MatchLine((42, "text".to_owned())).to_string(), "M 00042: text");
+        assert_eq!(FilteredLine::Gap { from: 3, to: 8 }.to_string(), "G 00003-00008");
+        assert_eq!(FilteredLine::Gap { from: 3, to: 8 }.skipped(), Some(6));
+        assert_eq!(FilteredLine::MatchLine((42, "text".to_owned())).skipped(), None);
+
+        // predicates are built again from their filter strings
+        let rebuilt = FilterPredicate::from_source(&predicate.source, 3).unwrap();
+        assert_eq!(rebuilt, predicate);
+        assert!(rebuilt.matcher.is_match("C"));
+        assert!(!rebuilt.matcher.is_match("b"));
+
+        let unknown = FilterSource { kind: "telepathy".to_owned(), ..predicate.source };
+        assert!(FilterPredicate::from_source(&unknown, 3).is_err());
+
+        let inverse = FilterPredicate::new("tick".to_owned(), 30).with_inverse(true);
+        assert_eq!(inverse.to_string(), "no substring \"tick\" (30 context lines)");
+        assert_ne!(inverse, FilterPredicate::new("tick".to_owned(), 30));
+    }
+
+    #[test]
+    fn test_line_range() {
+        let range = LineRange::parse("1000,2000").unwrap();
+        assert_eq!(range, LineRange { first: 1000, last: 2000 });
+        assert!(range.contains(1000) && range.contains(2000) && !range.contains(2001));
+        assert_eq!(range.to_string(), "1000,2000");
+        assert_eq!(LineRange::parse(" 5 , ").unwrap().to_string(), "5,");
+        assert_eq!(LineRange::parse(",7"), Ok(LineRange { first: 1, last: 7 }));
+        assert!(LineRange::parse("7,5").is_err());
+        assert!(LineRange::parse("0,5").is_err());
+        assert!(LineRange::parse("5").is_err());
+        assert!(LineRange::parse("a,b").is_err());
+
+        let within = FilterPredicate::within(range, 0);
+        assert_eq!(within.to_string(), "lines 1000,2000 (0 context lines)");
+        assert!(within.is_match(1500, "anything") && !within.is_match(999, "anything"));
+
+        let predicate = FilterPredicate::new("err".to_owned(), 1).with_lines(Some(range));
+        assert_eq!(predicate.to_string(), "substring \"err\" in lines 1000,2000 (1 context line)");
+        assert!(predicate.is_match(1000, "err") && !predicate.is_match(1000, "ok"));
+        assert!(!predicate.is_match(3000, "err"));
+
+        // ranges are kept when predicates are built again
+        assert_eq!(FilterPredicate::from_source(&within.source, 0).unwrap(), within);
+        assert_eq!(FilterPredicate::from_source(&predicate.source, 1).unwrap(), predicate);
+    }
+
+    #[test]
+    fn test_base() {
+        let options = MatchOptions::default();
+        let base = FilterPredicate::with_kind(&matcher::LEVEL, "warn", &options, 0).unwrap();
+        let predicate = FilterPredicate::new("db".to_owned(), 3).with_base(Some(base.clone()));
+
+        assert!(predicate.is_match(1, "ERROR db: timeout"));
+        assert!(!predicate.is_match(1, "INFO db: connected"));
+        assert!(!predicate.is_match(1, "ERROR cache: miss"));
+        assert_eq!(predicate.to_string(), "substring \"db\" and level \"warn\" (3 context lines)");
+        assert_ne!(predicate, FilterPredicate::new("db".to_owned(), 3));
+        assert_eq!(predicate.clone().with_base(None), FilterPredicate::new("db".to_owned(), 3));
+
+        // a base's range of lines applies as well
+        let base = base.with_lines(LineRange::parse("5,").ok());
+        let predicate = predicate.with_base(Some(base));
+        assert!(!predicate.is_match(4, "ERROR db: timeout"));
+        assert!(predicate.is_match(5, "ERROR db: timeout"));
+    }
+
+    #[test]
+    fn test_narrows() {
+        let error = FilterPredicate::new("ERROR".to_owned(), 0);
+        let timeout = FilterPredicate::new("ERROR timeout".to_owned(), 3);
+        assert!(timeout.narrows(&error));
+        assert!(!error.narrows(&timeout));
+        assert!(error.narrows(&error));
+
+        let options = MatchOptions { ignore_case: true, ignore_accents: false };
+        let patterns = ["ERROR db".to_owned(), "ERROR cache".to_owned()];
+        let any = FilterPredicate::any_of(&matcher::SUBSTRING, &patterns, &options, 0).unwrap();
+        let folded = FilterPredicate::with_kind(&matcher::SUBSTRING, "ERROR", &options, 0)
+            .unwrap();
+        assert!(any.narrows(&folded));
+        assert!(!any.narrows(&error));
+        assert!(!folded.narrows(&any));
+
+        // nor are other kinds of matchers, inverted predicates, or ones with
+        // different bases or ranges of lines
+        let level = FilterPredicate::with_kind(&matcher::LEVEL, "warn", &options, 0).unwrap();
+        assert!(!level.narrows(&level));
+        assert!(!timeout.clone().with_inverse(true).narrows(&error));
+        assert!(!timeout.clone().with_base(Some(level)).narrows(&error));
+        assert!(!timeout.with_lines(LineRange::parse("5,").ok()).narrows(&error));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use serde_json;
+
+        let lines = vec![
+            FilteredLine::ContextLine((1, "one".to_owned())),
+            FilteredLine::MatchLine((2, "two".to_owned())),
+            FilteredLine::Gap { from: 3, to: 8 },
+            FilteredLine::UnfilteredLine((9, "nine".to_owned())),
+        ];
+        let json = serde_json::to_string(&lines).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<FilteredLine>>(&json).unwrap(), lines);
+
+        let predicate = FilterPredicate::new("two".to_owned(), 2);
+        let json = serde_json::to_string(&predicate).unwrap();
+        assert_eq!(json, "{\"context_lines\":2,\"source\":{\"kind\":\"substring\",\
+                          \"patterns\":[\"two\"],\
+                          \"options\":{\"ignore_case\":false,\"ignore_accents\":false}}}");
+        let deserialized: FilterPredicate = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, predicate);
+        assert!(deserialized.matcher.is_match("one two"));
+
+        let unknown = json.replace("substring", "telepathy");
+        assert!(serde_json::from_str::<FilterPredicate>(&unknown).is_err());
+
+        let inverse = predicate.with_inverse(true);
+        let json = serde_json::to_string(&inverse).unwrap();
+        assert!(json.ends_with(",\"inverse\":true}"));
+        assert_eq!(serde_json::from_str::<FilterPredicate>(&json).unwrap(), inverse);
+    }
+}
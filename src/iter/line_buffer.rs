@@ -1,27 +1,74 @@
 use super::iter::NumberedLine;
+use super::line_cache::LineCache;
+use super::line_source::LineSource;
+use super::mapped_file::MappedFile;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum IterDirection {
     BACKWARD,
     FORWARD,
 }
 
+/// Origin of the lines of a `LineBuffer`.
+pub enum Input<I: Iterator<Item=String>> {
+    /// lines produced by a linewise iterator, which are copied into a cache
+    /// as they're read
+    Lines(I),
+    /// lines of a memory-mapped regular file, which are read from the
+    /// mapping on request
+    Mapped(MappedFile),
+    /// lines read off a `LineSource`, which are copied into the cache as
+    /// they're read unless the source is seekable, when they're read afresh
+    /// on request
+    Source(Box<dyn LineSource>),
+}
+
+impl<I: Iterator<Item=String>> Input<I> {
+    /// Returns the input of the lines of a `LineSource`, e.g.
+    /// `Input::source(FileSource::open(path)?)`.
+    pub fn source<S: LineSource + 'static>(source: S) -> Input<I> {
+        Input::Source(Box::new(source))
+    }
+}
+
+impl<I: Iterator<Item=String>> From<I> for Input<I> {
+    fn from(iterator: I) -> Input<I> {
+        Input::Lines(iterator)
+    }
+}
+
 /// Thing which reads, caches, and makes filterable lines produced by linewise
 /// iterators.
 pub struct LineBuffer<I: Iterator<Item=String>> {
-    lines: I,
-    cached_lines: Vec<NumberedLine>,
+    input: Input<I>,
+    /// lines read off `input` if it's `Input::Lines` or a source which isn't
+    /// seekable
+    cached_lines: LineCache,
+    /// number of lines read off `input` if it's a seekable source
+    source_len: usize,
+    /// error which ended the lines last read off `input`, if it's a source
+    error: Option<String>,
+    /// number of lines in each block of lines read into the cache, the
+    /// lines up to the end of the block of a line requested being read ahead
+    /// of it
+    block_lines: usize,
+    /// whether the last attempt to read off `input` found no more lines
+    exhausted: bool,
     last_iter_line: usize,
     iter_direction: IterDirection,
 }
 
 
 impl<I: Iterator<Item=String>> LineBuffer<I> {
-    /// Creates a new `LineBuffer` from a linewise iterator.
-    pub fn new(iterator: I) -> LineBuffer<I> {
+    /// Creates a new `LineBuffer` from a linewise iterator or other `Input`.
+    pub fn new<S: Into<Input<I>>>(input: S) -> LineBuffer<I> {
         LineBuffer {
-            lines: iterator,
-            cached_lines: Vec::new(),
+            input: input.into(),
+            cached_lines: LineCache::new(),
+            source_len: 0,
+            error: None,
+            block_lines: 1,
+            exhausted: false,
             last_iter_line: 0,
             iter_direction: IterDirection::FORWARD,
         }
@@ -39,22 +86,227 @@ impl<I: Iterator<Item=String>> LineBuffer<I> {
         }
 
         let cache_idx = line_num - 1;
-        let last_line_num = self.cached_lines.len();
+        if let Input::Mapped(ref mut file) = self.input {
+            return file.get(cache_idx).map(|line| (line_num, line));
+        }
+        if self.is_seekable_source() {
+            return self.read_source(cache_idx).map(|line| (line_num, line));
+        }
 
-        if line_num > last_line_num {
+        self.cache_to(line_num);
+        self.cached_lines.get(cache_idx).map(|line| (line_num, line))
+    }
+
+    /// Applies `f` to the text of the `line_num`th line, which is borrowed
+    /// from the cache rather than copied as `get()` does, returning `f`'s
+    /// result or `None` if there's no such line.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed index of the line of the underlying buffer
+    /// * `f`: function of the line's text
+    pub fn with_line<R, F: FnOnce(&str) -> R>(&mut self, line_num: usize, f: F) -> Option<R> {
+        if line_num < 1 {
+            return None;
+        }
+
+        let cache_idx = line_num - 1;
+        if let Input::Mapped(ref mut file) = self.input {
+            return file.get(cache_idx).map(|line| f(&line));
+        }
+        if self.is_seekable_source() {
+            return self.read_source(cache_idx).map(|line| f(&line));
+        }
+
+        self.cache_to(line_num);
+        self.cached_lines.get_str(cache_idx).map(f)
+    }
+
+    /// Reads lines off the input lines into the cache until it holds
+    /// `line_num` lines, rounded up to a whole number of blocks, or the input
+    /// lines are exhausted.
+    fn cache_to(&mut self, line_num: usize) {
+        if self.cached_lines.len() >= line_num {
+            return;
+        }
+        let line_num = line_num.checked_next_multiple_of(self.block_lines).unwrap_or(line_num);
+        while self.cached_lines.len() < line_num {
             // case: not enough lines in cache; load more from line iter
+            let line = match self.input {
+                Input::Mapped(_) => return,
+                Input::Lines(ref mut lines) => lines.next(),
+                Input::Source(ref mut source) => read_next(source.as_mut(), &mut self.error),
+            };
+            match line {
+                Some(line) => {
+                    self.cached_lines.push(line);
+                    self.exhausted = false;
+                },
+                None => {
+                    self.exhausted = true;
+                    break;
+                },
+            }
+        }
+    }
+
+    /// Returns `true` if lines are read off a seekable `LineSource`.
+    fn is_seekable_source(&self) -> bool {
+        match self.input {
+            Input::Source(ref source) => source.is_seekable(),
+            _ => false,
+        }
+    }
+
+    /// Reads the `idx`th line, counted from 0, off a seekable source, reading
+    /// the lines before it in order if they haven't been yet.
+    fn read_source(&mut self, idx: usize) -> Option<String> {
+        let source = match self.input {
+            Input::Source(ref mut source) => source,
+            _ => return None,
+        };
+
+        while self.source_len <= idx {
+            match read_next(source.as_mut(), &mut self.error) {
+                Some(line) => {
+                    self.source_len += 1;
+                    self.exhausted = false;
+                    if self.source_len > idx {
+                        return Some(line);
+                    }
+                },
+                None => {
+                    self.exhausted = true;
+                    return None;
+                },
+            }
+        }
+
+        match source.read_line(idx)? {
+            Ok(line) => Some(line),
+            Err(err) => {
+                self.error = Some(err.to_string());
+                None
+            },
+        }
+    }
+
+    /// Returns the error which ended the lines last read off a `LineSource`,
+    /// if any.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Returns the number of the line of a `LineSource` which stands for
+    /// lines left out of it, if any, as told by `LineSource::omitted()`. The
+    /// iterator steps over it, so that it's shown as a gap.
+    pub fn omitted_line(&self) -> Option<usize> {
+        match self.input {
+            Input::Source(ref source) => source.omitted().map(|idx| idx + 1),
+            _ => None,
+        }
+    }
+
+    /// Returns the memory-mapped file lines are read from, if any.
+    pub fn mapped_file(&self) -> Option<&MappedFile> {
+        match self.input {
+            Input::Mapped(ref file) => Some(file),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of lines read off the input lines so far.
+    pub fn len(&self) -> usize {
+        match self.input {
+            Input::Mapped(ref file) => file.len(),
+            Input::Source(ref source) if source.is_seekable() => self.source_len,
+            _ => self.cached_lines.len(),
+        }
+    }
+
+    /// Returns `true` if no lines have been read off the input lines so far.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the 1-based index of the line lying `fraction` of the way
+    /// through the input, or 0 if the input is empty.
+    ///
+    /// For a memory-mapped file the line is found by its byte offset,
+    /// scanning the file only as far as that offset; otherwise all lines
+    /// currently available are read and counted.
+    ///
+    /// ### Parameters
+    /// * `fraction`: position within the input, from 0.0 for the first line
+    ///   to 1.0 for the last
+    pub fn line_at_fraction(&mut self, fraction: f64) -> usize {
+        let fraction = fraction.clamp(0.0, 1.0);
+
+        if let Input::Mapped(ref mut file) = self.input {
+            let offset = (fraction * file.byte_len() as f64) as usize;
+            let idx = file.line_at_byte(offset);
+            return if file.is_empty() { 0 } else { idx + 1 };
+        }
+
+        let total = self.read_to_end();
+        ((fraction * total as f64).ceil() as usize).max(1).min(total)
+    }
+
+    /// Returns `true` if the input lines were found to have no more lines
+    /// when last read, i.e. `len()` is the number of lines in the input, at
+    /// least until more are appended.
+    ///
+    /// Always `false` for a memory-mapped file, whose lines are found by
+    /// scanning ahead of those read.
+    pub fn is_exhausted(&self) -> bool {
+        match self.input {
+            Input::Mapped(_) => false,
+            _ => self.exhausted,
+        }
+    }
 
-            let num_lines = line_num - last_line_num;
-            let next_line_num = last_line_num + 1;
-            let new_lines = (next_line_num..)
-                .zip(self.lines.by_ref())
-                .take(num_lines);
+    /// Sets the limit on the bytes of line text held in memory, beyond which
+    /// lines are spilled to a temporary file, or removes the limit if
+    /// `max_bytes` is `None`.
+    ///
+    /// Lines of a memory-mapped file are never held in memory, so the limit
+    /// has no effect on them.
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.cached_lines.set_max_bytes(max_bytes);
+    }
 
-            self.cached_lines.extend(new_lines);
+    /// Sets the number of lines read into the cache at a time, rather than
+    /// one at a time as they're requested, so that a line requested is read
+    /// along with those which follow it up to the end of its block.
+    ///
+    /// Lines of a memory-mapped file or a seekable source aren't cached, so
+    /// the block size has no effect on them.
+    pub fn set_block_lines(&mut self, lines: usize) {
+        self.block_lines = lines.max(1);
+    }
 
+    /// Reads all lines currently available from the input lines, returning
+    /// the total number of lines read so far.
+    pub fn read_to_end(&mut self) -> usize {
+        match self.input {
+            Input::Mapped(ref mut file) => return file.read_to_end(),
+            Input::Lines(ref mut lines) => {
+                for line in lines {
+                    self.cached_lines.push(line);
+                }
+                self.exhausted = true;
+            },
+            Input::Source(_) if self.is_seekable_source() => {
+                while self.read_source(self.source_len).is_some() {}
+            },
+            Input::Source(_) => self.cache_to(usize::MAX),
         }
+        self.len()
+    }
 
-        self.cached_lines.get(cache_idx).map(|i| i.to_owned())
+    /// Returns the 1-based index of the line most recently returned by the
+    /// iterator, or 0 if none has been.
+    pub fn last_iter_line(&self) -> usize {
+        self.last_iter_line
     }
 
     /// Sets the position and direction of the iterator.
@@ -77,29 +329,72 @@ impl<I: Iterator<Item=String>> LineBuffer<I> {
             };
         }
     }
-}
 
-impl<I: Iterator<Item=String>> Iterator for LineBuffer<I> {
-    type Item = NumberedLine;
+    /// Returns the 1-based index of the line the iterator returns next, if
+    /// it's not at the beginning of the buffer going backward, stepping over
+    /// the line omitted, if any.
+    fn next_line_num(&self) -> Option<usize> {
+        let next_line = self.step(self.last_iter_line)?;
+        if Some(next_line) == self.omitted_line() {
+            self.step(next_line)
+        } else {
+            Some(next_line)
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let maybe_next_line = match self.iter_direction {
-            IterDirection::FORWARD => Some(self.last_iter_line + 1),
+    /// Returns the 1-based index of the line following line `line_num` in the
+    /// direction of the iterator, if it's not at the beginning of the buffer
+    /// going backward.
+    fn step(&self, line_num: usize) -> Option<usize> {
+        match self.iter_direction {
+            IterDirection::FORWARD => Some(line_num + 1),
             IterDirection::BACKWARD => {
-                if self.last_iter_line > 1 {
-                    Some(self.last_iter_line - 1)
+                if line_num > 1 {
+                    Some(line_num - 1)
                 } else {
-                    // case: last iter line was already at beginning of buffer
+                    // case: the line was already at beginning of buffer
                     None
                 }
             }
-        };
+        }
+    }
+
+    /// Advances the iterator as `next()` does, but applies `f` to the number
+    /// and text of the line reached rather than returning a copy of it, as
+    /// `with_line()` does.
+    pub fn next_with<R, F: FnOnce(usize, &str) -> R>(&mut self, f: F) -> Option<R> {
+        let next_line = self.next_line_num()?;
+        let result = self.with_line(next_line, |line| f(next_line, line));
+        if result.is_some() {
+            self.last_iter_line = next_line;
+        }
+        result
+    }
+}
+
+/// Reads the next line off a source, keeping the error which ends the lines,
+/// if any.
+fn read_next(source: &mut dyn LineSource, error: &mut Option<String>) -> Option<String> {
+    match source.next()? {
+        Ok(line) => {
+            *error = None;
+            Some(line)
+        },
+        Err(err) => {
+            *error = Some(err.to_string());
+            None
+        },
+    }
+}
+
+impl<I: Iterator<Item=String>> Iterator for LineBuffer<I> {
+    type Item = NumberedLine;
 
-        maybe_next_line.and_then(|next_line| {
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line_num().and_then(|next_line| {
             self.get(next_line)
-                .map(|line| {
+                .inspect(|_line| {
                     self.last_iter_line = next_line;
-                    line
                 })
         })
     }
@@ -107,7 +402,12 @@ impl<I: Iterator<Item=String>> Iterator for LineBuffer<I> {
 
 #[cfg(test)]
 mod test {
-    use super::{IterDirection, LineBuffer};
+    use std::io;
+    use std::iter;
+
+    use iter::line_source::{LineSource, VecSource};
+
+    use super::{Input, IterDirection, LineBuffer};
 
     #[test]
     fn test_iteration() {
@@ -193,4 +493,82 @@ mod test {
         let actual = line_buf.next();
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_next_with() {
+        let vec: Vec<String> = vec!("one".to_owned(), "two".to_owned(), "three".to_owned());
+        let mut line_buf = LineBuffer::new(vec.iter().cloned());
+
+        assert_eq!(line_buf.with_line(3, str::len), Some(5));
+        assert_eq!(line_buf.with_line(4, str::len), None);
+        assert_eq!(line_buf.with_line(0, str::len), None);
+
+        assert_eq!(line_buf.next_with(|line_num, line| (line_num, line.len())), Some((1, 3)));
+        assert_eq!(line_buf.next(), Some((2, "two".to_owned())));
+        assert_eq!(line_buf.next_with(|line_num, _| line_num), Some(3));
+        assert_eq!(line_buf.next_with(|line_num, _| line_num), None);
+        assert_eq!(line_buf.last_iter_line(), 3);
+
+        line_buf.seek(Some(2), Some(IterDirection::BACKWARD));
+        assert_eq!(line_buf.next_with(|_, line| line.to_owned()), Some("two".to_owned()));
+        assert_eq!(line_buf.next_with(|_, line| line.to_owned()), Some("one".to_owned()));
+        assert_eq!(line_buf.next_with(|_, line| line.to_owned()), None);
+    }
+
+    #[test]
+    fn test_block_lines() {
+        let vec: Vec<String> = (1..11).map(|n| n.to_string()).collect();
+        let mut line_buf = LineBuffer::new(vec.iter().cloned());
+        line_buf.set_block_lines(4);
+
+        assert_eq!(line_buf.get(1), Some((1, "1".to_owned())));
+        assert_eq!(line_buf.len(), 4);
+        assert_eq!(line_buf.get(4), Some((4, "4".to_owned())));
+        assert_eq!(line_buf.len(), 4);
+        assert_eq!(line_buf.get(5), Some((5, "5".to_owned())));
+        assert_eq!(line_buf.len(), 8);
+        assert!(!line_buf.is_exhausted());
+        assert_eq!(line_buf.get(9), Some((9, "9".to_owned())));
+        assert_eq!(line_buf.len(), 10);
+        assert!(line_buf.is_exhausted());
+        assert_eq!(line_buf.read_to_end(), 10);
+    }
+
+    /// Source which isn't seekable, failing after its lines.
+    struct Failing(Vec<&'static str>);
+
+    impl Iterator for Failing {
+        type Item = io::Result<String>;
+
+        fn next(&mut self) -> Option<io::Result<String>> {
+            if self.0.is_empty() {
+                return Some(Err(io::Error::other("connection reset")));
+            }
+            Some(Ok(self.0.remove(0).to_owned()))
+        }
+    }
+
+    impl LineSource for Failing {}
+
+    #[test]
+    fn test_source() {
+        let lines = vec!["one".to_owned(), "two".to_owned(), "three".to_owned()];
+        let input: Input<iter::Empty<String>> = Input::source(VecSource::from(lines));
+        let mut line_buf = LineBuffer::new(input);
+
+        assert_eq!(line_buf.get(2), Some((2, "two".to_owned())));
+        assert_eq!(line_buf.len(), 2);
+        assert_eq!(line_buf.with_line(1, str::len), Some(3));
+        assert_eq!(line_buf.get(4), None);
+        assert!(line_buf.is_exhausted());
+        assert_eq!(line_buf.read_to_end(), 3);
+        line_buf.seek(Some(3), Some(IterDirection::BACKWARD));
+        assert_eq!(line_buf.next(), Some((3, "three".to_owned())));
+
+        let input: Input<iter::Empty<String>> = Input::source(Failing(vec!["one", "two"]));
+        let mut line_buf = LineBuffer::new(input);
+        assert_eq!(line_buf.read_to_end(), 2);
+        assert_eq!(line_buf.error(), Some("connection reset"));
+        assert_eq!(line_buf.get(1), Some((1, "one".to_owned())));
+    }
 }
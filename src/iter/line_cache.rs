@@ -0,0 +1,290 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of lines in each block of the cache.
+const BLOCK_LINES: usize = 1024;
+
+/// Used to give each spill file of the process a distinct name.
+static SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Run of consecutive lines which is either held in memory, written to the
+/// spill file, or both.
+struct Block {
+    /// the lines, if held in memory
+    lines: Option<Vec<String>>,
+    /// byte offset and length of the lines within the spill file, if written
+    spilled: Option<(u64, usize)>,
+    /// bytes of line text held in memory
+    bytes: usize,
+    /// value of `LineCache::clock` when the block was last accessed
+    last_used: u64,
+}
+
+/// Append-only store of lines which holds at most roughly `max_bytes` of line
+/// text in memory.
+///
+/// Lines are grouped into blocks. When the limit is exceeded the least
+/// recently used blocks are written to a temporary spill file and dropped
+/// from memory, to be read back as needed.
+pub struct LineCache {
+    blocks: Vec<Block>,
+    /// number of lines in each block
+    block_lines: usize,
+    /// total number of lines stored
+    len: usize,
+    /// bytes of line text held in memory across all blocks
+    resident_bytes: usize,
+    /// limit on `resident_bytes`, or `None` to keep every line in memory
+    max_bytes: Option<usize>,
+    /// temporary file holding spilled blocks, created when first needed
+    spill_file: Option<File>,
+    /// incremented on every access, for finding least recently used blocks
+    clock: u64,
+}
+
+impl LineCache {
+    /// Creates a cache which keeps every line in memory until a limit is set
+    /// with `set_max_bytes()`.
+    pub fn new() -> LineCache {
+        LineCache::with_block_lines(BLOCK_LINES)
+    }
+
+    fn with_block_lines(block_lines: usize) -> LineCache {
+        LineCache {
+            blocks: Vec::new(),
+            block_lines: block_lines,
+            len: 0,
+            resident_bytes: 0,
+            max_bytes: None,
+            spill_file: None,
+            clock: 0,
+        }
+    }
+
+    /// Sets the limit on the bytes of line text held in memory, or removes
+    /// it if `max_bytes` is `None`.
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_bytes = max_bytes;
+        self.evict();
+    }
+
+    /// Returns the number of lines stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the bytes of line text currently held in memory.
+    #[cfg(test)]
+    pub fn resident_bytes(&self) -> usize {
+        self.resident_bytes
+    }
+
+    /// Appends a line.
+    pub fn push(&mut self, line: String) {
+        if self.len.is_multiple_of(self.block_lines) {
+            self.blocks.push(Block {
+                lines: Some(Vec::with_capacity(self.block_lines)),
+                spilled: None,
+                bytes: 0,
+                last_used: self.clock,
+            });
+        }
+
+        let bytes = line.len();
+        {
+            let block = self.blocks.last_mut().expect("a block was just pushed");
+            block.bytes += bytes;
+            block.lines.as_mut().expect("the last block is always resident").push(line);
+        }
+
+        self.resident_bytes += bytes;
+        self.len += 1;
+        self.evict();
+    }
+
+    /// Returns a copy of the line at 0-based index `idx`.
+    pub fn get(&mut self, idx: usize) -> Option<String> {
+        let line = self.get_str(idx).map(|line| line.to_owned());
+        self.evict();
+        line
+    }
+
+    /// Returns the line at 0-based index `idx` without copying it.
+    ///
+    /// A spilled block read back to return the line may take the lines held
+    /// in memory over `max_bytes` until the next call to `push()` or `get()`,
+    /// as the block can't be dropped while the line is borrowed.
+    pub fn get_str(&mut self, idx: usize) -> Option<&str> {
+        if idx >= self.len {
+            return None;
+        }
+
+        let block_idx = idx / self.block_lines;
+        self.clock += 1;
+        self.blocks[block_idx].last_used = self.clock;
+
+        if self.blocks[block_idx].lines.is_none() {
+            // makes room for the block before it's read back
+            self.evict();
+            self.load(block_idx);
+        }
+
+        self.blocks[block_idx].lines.as_ref()
+            .map(|lines| lines[idx % self.block_lines].as_str())
+    }
+
+    /// Reads a spilled block back into memory.
+    fn load(&mut self, block_idx: usize) {
+        let (offset, len) = self.blocks[block_idx].spilled.expect("block is neither resident nor spilled");
+        let mut buf = vec![0; len];
+
+        {
+            let file = self.spill_file.as_mut().expect("block was spilled without a spill file");
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.read_exact(&mut buf))
+                .expect("failed to read spilled lines");
+        }
+
+        let mut lines = Vec::with_capacity(self.block_lines);
+        let mut bytes = 0;
+        let mut pos = 0;
+        while pos < buf.len() {
+            let mut len_bytes = [0; 8];
+            len_bytes.copy_from_slice(&buf[pos..pos + 8]);
+            let line_len = u64::from_le_bytes(len_bytes) as usize;
+            pos += 8;
+            lines.push(String::from_utf8_lossy(&buf[pos..pos + line_len]).into_owned());
+            bytes += line_len;
+            pos += line_len;
+        }
+
+        let block = &mut self.blocks[block_idx];
+        block.lines = Some(lines);
+        block.bytes = bytes;
+        self.resident_bytes += bytes;
+    }
+
+    /// Drops least recently used blocks from memory, spilling them to disk
+    /// first if need be, until `resident_bytes` is within `max_bytes`.
+    ///
+    /// The block being appended to is never dropped. If the spill file can't
+    /// be written, the limit is removed and every line is kept in memory.
+    fn evict(&mut self) {
+        let max_bytes = match self.max_bytes {
+            Some(max_bytes) => max_bytes,
+            None => return,
+        };
+
+        while self.resident_bytes > max_bytes {
+            let num_closed = if self.len.is_multiple_of(self.block_lines) {
+                self.blocks.len()
+            } else {
+                self.blocks.len() - 1
+            };
+
+            let victim = self.blocks[..num_closed].iter()
+                .enumerate()
+                .filter(|&(_, block)| block.lines.is_some())
+                .min_by_key(|&(_, block)| block.last_used)
+                .map(|(idx, _)| idx);
+
+            let victim = match victim {
+                Some(victim) => victim,
+                None => return,
+            };
+
+            if self.blocks[victim].spilled.is_none()
+                && self.spill(victim).is_err() {
+                    self.max_bytes = None;
+                    return;
+                }
+
+            let block = &mut self.blocks[victim];
+            block.lines = None;
+            self.resident_bytes -= block.bytes;
+            block.bytes = 0;
+        }
+    }
+
+    /// Appends a block to the spill file.
+    fn spill(&mut self, block_idx: usize) -> ::std::io::Result<()> {
+        if self.spill_file.is_none() {
+            self.spill_file = Some(create_spill_file()?);
+        }
+
+        let mut buf = Vec::new();
+        for line in self.blocks[block_idx].lines.as_ref().expect("only resident blocks are spilled") {
+            buf.extend_from_slice(&(line.len() as u64).to_le_bytes());
+            buf.extend_from_slice(line.as_bytes());
+        }
+
+        let file = self.spill_file.as_mut().expect("spill file was just created");
+        let offset = file.seek(SeekFrom::End(0))?;
+        file.write_all(&buf)?;
+        self.blocks[block_idx].spilled = Some((offset, buf.len()));
+        Ok(())
+    }
+}
+
+/// Creates a spill file in the temporary directory.
+///
+/// The file is unlinked as soon as it's opened so that it's removed when the
+/// process exits, however it exits.
+fn create_spill_file() -> ::std::io::Result<File> {
+    let path = env::temp_dir().join(format!("filterless-{}-{}.spill", process::id(),
+                                            SPILL_FILES.fetch_add(1, Ordering::SeqCst)));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    let _ = fs::remove_file(&path);
+    Ok(file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::LineCache;
+
+    #[test]
+    fn test_unbounded() {
+        let mut cache = LineCache::with_block_lines(4);
+        for i in 0..10 {
+            cache.push(format!("line {}", i));
+        }
+
+        assert_eq!(cache.len(), 10);
+        assert_eq!(cache.get(0), Some("line 0".to_owned()));
+        assert_eq!(cache.get(9), Some("line 9".to_owned()));
+        assert_eq!(cache.get(10), None);
+        assert_eq!(cache.resident_bytes(), 60);
+    }
+
+    #[test]
+    fn test_spill() {
+        let mut cache = LineCache::with_block_lines(4);
+        cache.set_max_bytes(Some(20));
+
+        for i in 0..100 {
+            cache.push(format!("{:02}", i));
+            assert!(cache.resident_bytes() <= 20);
+        }
+
+        for &i in &[0, 57, 3, 99, 98, 4, 0, 42] {
+            assert_eq!(cache.get(i), Some(format!("{:02}", i)));
+            assert!(cache.resident_bytes() <= 20);
+        }
+
+        for &i in &[17, 61, 17] {
+            assert_eq!(cache.get_str(i), Some(format!("{:02}", i).as_str()));
+            // at most the block just read back, of 4 lines of 2 bytes, over
+            assert!(cache.resident_bytes() <= 28);
+        }
+
+        cache.set_max_bytes(None);
+        assert_eq!(cache.get(13), Some("13".to_owned()));
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Stdin};
+
+/// Source of the lines of a `LineBuffer`, such as a socket, a database or an
+/// in-memory store, given as `Input::Source`.
+///
+/// Lines are read off the source in order, as by any iterator; an error ends
+/// them. A source which can also read a line again by its index, e.g. a file,
+/// is seekable, and its lines are then read afresh on request rather than
+/// copied into memory as they're read.
+pub trait LineSource: Iterator<Item=io::Result<String>> {
+    /// Returns `true` if lines may be read again with `read_line()`.
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    /// Reads the `idx`th line again, counted from 0, returning `None` if the
+    /// source isn't seekable or no such line has been read yet.
+    fn read_line(&mut self, _idx: usize) -> Option<io::Result<String>> {
+        None
+    }
+
+    /// Returns the index, counted from 0, of a line of the source which
+    /// stands for lines left out of it, e.g. the middle of a sampled file,
+    /// rather than being one of its lines; it's shown as a gap, and never
+    /// matched.
+    fn omitted(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Lines of a file, which is seekable, the byte offset of each line being
+/// kept as it's read.
+///
+/// Lines which aren't valid UTF-8 are decoded lossily.
+pub struct FileSource {
+    reader: BufReader<File>,
+    /// byte offsets of the lines read so far
+    offsets: Vec<u64>,
+    /// byte offset of the next line to be read
+    pos: u64,
+}
+
+impl FileSource {
+    /// Reads the lines of a file from its current position.
+    pub fn new(mut file: File) -> io::Result<FileSource> {
+        let pos = file.stream_position()?;
+        Ok(FileSource { reader: BufReader::new(file), offsets: Vec::new(), pos: pos })
+    }
+
+    /// Opens a file to read its lines.
+    pub fn open(path: &str) -> io::Result<FileSource> {
+        FileSource::new(File::open(path)?)
+    }
+}
+
+impl Iterator for FileSource {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        let mut buf = Vec::new();
+        match self.reader.read_until(b'\n', &mut buf) {
+            Ok(0) => None,
+            Ok(len) => {
+                self.offsets.push(self.pos);
+                self.pos += len as u64;
+                Some(Ok(decode(buf)))
+            },
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl LineSource for FileSource {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn read_line(&mut self, idx: usize) -> Option<io::Result<String>> {
+        let offset = *self.offsets.get(idx)?;
+        let mut buf = Vec::new();
+        let read = self.reader.seek(SeekFrom::Start(offset))
+            .and_then(|_| self.reader.read_until(b'\n', &mut buf))
+            .and_then(|_| self.reader.seek(SeekFrom::Start(self.pos)));
+        Some(read.map(|_| decode(buf)))
+    }
+}
+
+/// Lines of standard input, which isn't seekable.
+///
+/// Lines which aren't valid UTF-8 are decoded lossily.
+pub struct StdinSource {
+    stdin: Stdin,
+}
+
+impl StdinSource {
+    /// Reads the lines of standard input.
+    pub fn new() -> StdinSource {
+        StdinSource { stdin: io::stdin() }
+    }
+}
+
+impl Default for StdinSource {
+    fn default() -> StdinSource {
+        StdinSource::new()
+    }
+}
+
+impl Iterator for StdinSource {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        let mut buf = Vec::new();
+        match self.stdin.lock().read_until(b'\n', &mut buf) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(decode(buf))),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl LineSource for StdinSource {}
+
+/// Lines held in memory, which are seekable.
+pub struct VecSource {
+    lines: Vec<String>,
+    /// index of the next line to be read
+    next: usize,
+}
+
+impl From<Vec<String>> for VecSource {
+    fn from(lines: Vec<String>) -> VecSource {
+        VecSource { lines: lines, next: 0 }
+    }
+}
+
+impl Iterator for VecSource {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        let line = self.lines.get(self.next)?.clone();
+        self.next += 1;
+        Some(Ok(line))
+    }
+}
+
+impl LineSource for VecSource {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn read_line(&mut self, idx: usize) -> Option<io::Result<String>> {
+        self.lines[..self.next].get(idx).cloned().map(Ok)
+    }
+}
+
+/// Decodes a line read with its line ending, which is removed.
+fn decode(mut buf: Vec<u8>) -> String {
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+    }
+    String::from_utf8(buf)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process;
+
+    use super::{FileSource, LineSource, VecSource};
+
+    #[test]
+    fn test_file_source() {
+        let path = env::temp_dir().join(format!("filterless-{}-source.txt", process::id()));
+        File::create(&path).unwrap().write_all(b"one\r\n\ntw\xffo\nthr").unwrap();
+
+        let mut source = FileSource::open(path.to_str().unwrap()).unwrap();
+        assert!(source.is_seekable());
+        assert!(source.read_line(0).is_none());
+        assert_eq!(source.next().unwrap().unwrap(), "one");
+        assert_eq!(source.next().unwrap().unwrap(), "");
+        assert_eq!(source.read_line(0).unwrap().unwrap(), "one");
+        assert_eq!(source.next().unwrap().unwrap(), "tw\u{fffd}o");
+        assert_eq!(source.next().unwrap().unwrap(), "thr");
+        assert!(source.next().is_none());
+        assert_eq!(source.read_line(2).unwrap().unwrap(), "tw\u{fffd}o");
+        assert!(source.read_line(4).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_vec_source() {
+        let mut source = VecSource::from(vec!["one".to_owned(), "two".to_owned()]);
+        assert!(source.read_line(0).is_none());
+        assert_eq!(source.next().unwrap().unwrap(), "one");
+        assert_eq!(source.read_line(0).unwrap().unwrap(), "one");
+        assert!(source.read_line(1).is_none());
+        assert_eq!(source.next().unwrap().unwrap(), "two");
+        assert!(source.next().is_none());
+    }
+}
@@ -0,0 +1,250 @@
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::slice;
+
+use libc;
+use memchr::memchr;
+
+/// Regular file whose lines are read directly out of a memory mapping.
+///
+/// Rather than copying lines into memory as they're read, the file is
+/// scanned for line endings only as far as the lines requested so far, and
+/// each line is decoded from the mapping on request. If the file grows, it's
+/// mapped afresh once the lines mapped so far are exhausted.
+///
+/// Like any memory-mapped file, the process is liable to be killed if the
+/// file is truncated while mapped.
+pub struct MappedFile {
+    file: File,
+    /// start of the mapping, or null if nothing is mapped
+    ptr: *mut libc::c_void,
+    /// length of the mapping in bytes
+    len: usize,
+    /// byte offsets of the line endings found so far
+    ends: Vec<usize>,
+    /// byte offset just past the last line ending found, where the scan for
+    /// the next resumes
+    scanned: usize,
+    /// whether the scan has reached the end of the file and found a final
+    /// line without a line ending
+    tail: bool,
+}
+
+impl MappedFile {
+    /// Maps a regular file.
+    pub fn new(file: File) -> io::Result<MappedFile> {
+        let mut mapped = MappedFile {
+            file: file,
+            ptr: ptr::null_mut(),
+            len: 0,
+            ends: Vec::new(),
+            scanned: 0,
+            tail: false,
+        };
+        mapped.remap()?;
+        Ok(mapped)
+    }
+
+    /// Maps the same file afresh, with an index of its own.
+    pub fn try_clone(&self) -> io::Result<MappedFile> {
+        MappedFile::new(self.file.try_clone()?)
+    }
+
+    /// Returns the number of bytes scanned for line endings so far.
+    pub fn bytes_indexed(&self) -> usize {
+        if self.tail { self.len } else { self.scanned }
+    }
+
+    /// Returns the length of the mapped part of the file in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of lines found so far.
+    ///
+    /// A final line without a line ending is counted.
+    pub fn len(&self) -> usize {
+        if self.tail { self.ends.len() + 1 } else { self.ends.len() }
+    }
+
+    /// Returns `true` if no lines have been found so far.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a copy of the line at 0-based index `idx`, scanning further
+    /// into the file as necessary.
+    pub fn get(&mut self, idx: usize) -> Option<String> {
+        if idx >= self.len() {
+            self.index(Some(idx));
+        }
+
+        let start = match idx {
+            0 => 0,
+            _ => self.ends.get(idx - 1).map(|end| end + 1)?,
+        };
+        let end = match self.ends.get(idx) {
+            Some(&end) => end,
+            None if idx == self.ends.len() && self.tail => self.len,
+            None => return None,
+        };
+
+        let mut line = &self.data()[start..end];
+        if line.last() == Some(&b'\r') {
+            line = &line[..line.len() - 1];
+        }
+        Some(String::from_utf8_lossy(line).into_owned())
+    }
+
+    /// Returns the 0-based index of the line containing the byte at `offset`,
+    /// scanning further into the file as necessary.
+    ///
+    /// An offset past the end of the file is taken to be in the last line.
+    pub fn line_at_byte(&mut self, offset: usize) -> usize {
+        while self.scanned <= offset && !self.tail {
+            let found = self.ends.len();
+            self.index(Some(found));
+            if self.ends.len() == found {
+                // case: the end of the file has been reached
+                break;
+            }
+        }
+
+        match self.ends.binary_search(&offset) {
+            Ok(idx) | Err(idx) => idx.min(self.len().saturating_sub(1)),
+        }
+    }
+
+    /// Scans the whole file for line endings, returning the number of lines.
+    pub fn read_to_end(&mut self) -> usize {
+        self.index(None);
+        self.len()
+    }
+
+    /// Scans for line endings until the line at 0-based index `idx` has
+    /// been found, or to the end of the file if `idx` is `None`.
+    fn index(&mut self, idx: Option<usize>) {
+        let mut remapped = false;
+
+        while idx.is_none_or(|idx| self.ends.len() <= idx) {
+            // not borrowed from `self`, so that `ends` may be updated; the
+            // mapping isn't replaced while `data` is in use
+            let data: &[u8] = if self.ptr.is_null() {
+                &[]
+            } else {
+                unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+            };
+
+            match memchr(b'\n', &data[self.scanned..]) {
+                Some(pos) => {
+                    self.ends.push(self.scanned + pos);
+                    self.scanned += pos + 1;
+                    self.tail = false;
+                },
+                None if !remapped => {
+                    // the file may have grown since it was mapped
+                    remapped = true;
+                    if self.remap().is_err() {
+                        break;
+                    }
+                },
+                None => {
+                    self.tail = self.scanned < self.len;
+                    break;
+                },
+            }
+        }
+    }
+
+    /// Returns the mapped bytes.
+    fn data(&self) -> &[u8] {
+        if self.ptr.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    /// Maps the file afresh if it has grown since it was last mapped.
+    fn remap(&mut self) -> io::Result<()> {
+        let len = self.file.metadata()?.len() as usize;
+        if len <= self.len {
+            return Ok(());
+        }
+
+        let ptr = unsafe {
+            libc::mmap(ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE,
+                       self.file.as_raw_fd(), 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        self.unmap();
+        self.ptr = ptr;
+        self.len = len;
+        Ok(())
+    }
+
+    fn unmap(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe { libc::munmap(self.ptr, self.len); }
+            self.ptr = ptr::null_mut();
+        }
+    }
+}
+
+// the mapping is read-only and owned by a single `MappedFile`, so it may be
+// moved to another thread along with it
+unsafe impl Send for MappedFile {}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        self.unmap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File, OpenOptions};
+    use std::io::Write;
+    use std::process;
+
+    use super::MappedFile;
+
+    #[test]
+    fn test_lines() {
+        let path = env::temp_dir().join(format!("filterless-{}-mapped.txt", process::id()));
+        File::create(&path).unwrap().write_all(b"one\r\n\ntwo\nthr").unwrap();
+
+        let mut mapped = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(mapped.len(), 0);
+        assert_eq!(mapped.get(1), Some("".to_owned()));
+        assert_eq!(mapped.len(), 2);
+        assert_eq!(mapped.get(0), Some("one".to_owned()));
+        assert_eq!(mapped.get(3), Some("thr".to_owned()));
+        assert_eq!(mapped.get(4), None);
+        assert_eq!(mapped.read_to_end(), 4);
+        assert_eq!(mapped.line_at_byte(0), 0);
+        assert_eq!(mapped.line_at_byte(4), 0);
+        assert_eq!(mapped.line_at_byte(5), 1);
+        assert_eq!(mapped.line_at_byte(11), 3);
+        assert_eq!(mapped.line_at_byte(99), 3);
+
+        // an appended line is picked up, completing the unterminated one
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"ee\nfour\n").unwrap();
+        assert_eq!(mapped.get(4), Some("four".to_owned()));
+        assert_eq!(mapped.get(3), Some("three".to_owned()));
+        assert_eq!(mapped.read_to_end(), 5);
+        drop(mapped);
+
+        File::create(&path).unwrap();
+        let mut empty = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(empty.get(0), None);
+        assert_eq!(empty.read_to_end(), 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
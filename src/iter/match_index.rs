@@ -0,0 +1,286 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use super::iter::FilterPredicate;
+use super::mapped_file::MappedFile;
+
+/// Number of lines scanned between publications of the scan's progress.
+const BATCH_LINES: usize = 4096;
+
+/// Progress of a scan, shared between the scanning thread and its handles.
+struct State {
+    /// numbers of the matching lines found so far, in ascending order
+    matches: Vec<usize>,
+    /// number of lines scanned so far
+    scanned: usize,
+    /// percentage of the file's bytes scanned so far
+    percent: usize,
+    /// whether the scan has reached the end of the file
+    done: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// signalled each time the scan publishes its progress, and when it's
+    /// done
+    progressed: Condvar,
+}
+
+/// Index of the lines of a file which match a filter, built by a background
+/// thread.
+///
+/// Consulted while filtering, the index lets runs of lines which are known
+/// not to match be skipped rather than read and tested one by one. The scan
+/// stops early once every handle to the index has been dropped.
+#[derive(Clone)]
+pub struct MatchIndex {
+    shared: Arc<Shared>,
+}
+
+impl MatchIndex {
+    /// Starts scanning `file` for lines matched by `predicate`.
+    pub fn spawn(file: MappedFile, predicate: FilterPredicate) -> MatchIndex {
+        let index = MatchIndex::empty();
+        let thread_shared = index.shared.clone();
+        thread::spawn(move || scan(file, predicate, thread_shared));
+        index
+    }
+
+    /// Starts scanning `file` for lines matched by `predicate` among only the
+    /// lines this index has found to match, as they're found, rather than
+    /// among every line of `file`.
+    ///
+    /// `predicate` must match no line which isn't matched by the predicate of
+    /// this index, e.g. because it narrows that predicate as told by
+    /// `FilterPredicate::narrows()`.
+    pub fn narrow(&self, file: MappedFile, predicate: FilterPredicate) -> MatchIndex {
+        let index = MatchIndex::empty();
+        let thread_shared = index.shared.clone();
+        let source = self.clone();
+        thread::spawn(move || rescan(file, predicate, source, thread_shared));
+        index
+    }
+
+    fn empty() -> MatchIndex {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                matches: Vec::new(),
+                scanned: 0,
+                percent: 0,
+                done: false,
+            }),
+            progressed: Condvar::new(),
+        });
+        MatchIndex { shared: shared }
+    }
+
+    /// Looks up the first match after line `line_num`.
+    ///
+    /// Returns the match, if it's been found, along with the number of lines
+    /// scanned so far and whether the scan is done.
+    pub fn next_after(&self, line_num: usize) -> (Option<usize>, usize, bool) {
+        let state = self.shared.state.lock().expect("match index lock poisoned");
+        let idx = match state.matches.binary_search(&line_num) {
+            Ok(idx) => idx + 1,
+            Err(idx) => idx,
+        };
+        (state.matches.get(idx).cloned(), state.scanned, state.done)
+    }
+
+    /// Looks up the last match before line `line_num`.
+    ///
+    /// Returns the match, if there is one, along with whether every line
+    /// before `line_num` has been scanned; if not, the match may not be the
+    /// last one.
+    pub fn prev_before(&self, line_num: usize) -> (Option<usize>, bool) {
+        let state = self.shared.state.lock().expect("match index lock poisoned");
+        let idx = match state.matches.binary_search(&line_num) {
+            Ok(idx) | Err(idx) => idx,
+        };
+        let prev = if idx > 0 { Some(state.matches[idx - 1]) } else { None };
+        (prev, state.done || state.scanned + 1 >= line_num)
+    }
+
+    /// Looks up the ordinal of line `line_num` among the matches, e.g. 7 for
+    /// the seventh, or `None` if it doesn't match or hasn't been scanned yet.
+    pub fn ordinal_of(&self, line_num: usize) -> Option<usize> {
+        let state = self.shared.state.lock().expect("match index lock poisoned");
+        state.matches.binary_search(&line_num).ok().map(|idx| idx + 1)
+    }
+
+    /// Looks up the match with the given 1-indexed ordinal, or `None` if
+    /// there are fewer matches or it hasn't been found yet.
+    pub fn nth_match(&self, ordinal: usize) -> Option<usize> {
+        let state = self.shared.state.lock().expect("match index lock poisoned");
+        ordinal.checked_sub(1).and_then(|idx| state.matches.get(idx)).cloned()
+    }
+
+    /// Looks up the last match found so far.
+    ///
+    /// Returns the match, if one has been found, along with whether the scan
+    /// is done; if not, the match may not be the last one.
+    pub fn last(&self) -> (Option<usize>, bool) {
+        let state = self.shared.state.lock().expect("match index lock poisoned");
+        (state.matches.last().cloned(), state.done)
+    }
+
+    /// Returns the percentage of the file scanned so far, or `None` if the
+    /// scan is done.
+    pub fn progress(&self) -> Option<usize> {
+        let state = self.shared.state.lock().expect("match index lock poisoned");
+        if state.done { None } else { Some(state.percent) }
+    }
+
+    /// Returns the number of lines in the file and the number of them which
+    /// match, or `None` if the scan isn't done.
+    pub fn totals(&self) -> Option<(usize, usize)> {
+        let state = self.shared.state.lock().expect("match index lock poisoned");
+        if state.done { Some((state.scanned, state.matches.len())) } else { None }
+    }
+
+    /// Blocks until the scan is done.
+    pub fn wait(&self) {
+        let mut state = self.shared.state.lock().expect("match index lock poisoned");
+        while !state.done {
+            state = self.shared.progressed.wait(state).expect("match index lock poisoned");
+        }
+    }
+}
+
+/// Body of the scanning thread.
+fn scan(mut file: MappedFile, predicate: FilterPredicate, shared: Arc<Shared>) {
+    let mut matches = Vec::new();
+    let mut line_num = 0;
+
+    while let Some(line) = file.get(line_num) {
+        line_num += 1;
+        if predicate.is_match(line_num, &line) {
+            matches.push(line_num);
+        }
+
+        if line_num % BATCH_LINES == 0 {
+            if Arc::strong_count(&shared) == 1 {
+                // case: nobody is left to consult the index
+                return;
+            }
+
+            let percent = file.bytes_indexed() * 100 / file.byte_len().max(1);
+            publish(&shared, &mut matches, line_num, percent, false);
+        }
+    }
+
+    publish(&shared, &mut matches, line_num, 100, true);
+}
+
+/// Body of the thread scanning the lines found to match by `source`.
+fn rescan(mut file: MappedFile, predicate: FilterPredicate, source: MatchIndex,
+          shared: Arc<Shared>) {
+    let mut checked = 0;
+    let mut scanned = 0;
+    loop {
+        let (candidates, source_scanned, percent, done) = {
+            let mut state = source.shared.state.lock().expect("match index lock poisoned");
+            while state.scanned == scanned && !state.done {
+                state = source.shared.progressed.wait(state)
+                    .expect("match index lock poisoned");
+            }
+            (state.matches[checked..].to_vec(), state.scanned, state.percent, state.done)
+        };
+        checked += candidates.len();
+
+        for (i, batch) in candidates.chunks(BATCH_LINES).enumerate() {
+            if Arc::strong_count(&shared) == 1 {
+                // case: nobody is left to consult the index
+                return;
+            }
+
+            let mut matches: Vec<usize> = batch.iter()
+                .cloned()
+                .filter(|&line_num| {
+                    file.get(line_num - 1).is_some_and(|line| predicate.is_match(line_num, &line))
+                })
+                .collect();
+            // every line before the next candidate is known not to match
+            let next = candidates.get((i + 1) * BATCH_LINES);
+            let is_last = next.is_none();
+            let known = next.map_or(source_scanned, |&next| next - 1);
+            publish(&shared, &mut matches, known, percent, done && is_last);
+        }
+
+        if candidates.is_empty() {
+            publish(&shared, &mut Vec::new(), source_scanned, percent, done);
+        }
+        if done {
+            return;
+        }
+        scanned = source_scanned;
+    }
+}
+
+/// Adds the matches found to the index, along with the progress of the scan,
+/// and wakes those waiting on it.
+fn publish(shared: &Shared, matches: &mut Vec<usize>, scanned: usize, percent: usize,
+           done: bool) {
+    let mut state = shared.state.lock().expect("match index lock poisoned");
+    state.matches.append(matches);
+    state.scanned = scanned;
+    state.percent = percent;
+    state.done = done;
+    shared.progressed.notify_all();
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process;
+
+    use iter::mapped_file::MappedFile;
+    use iter::FilterPredicate;
+
+    use super::MatchIndex;
+
+    #[test]
+    fn test_index() {
+        let path = env::temp_dir().join(format!("filterless-{}-index.txt", process::id()));
+        {
+            let mut file = File::create(&path).unwrap();
+            for i in 1..10001 {
+                writeln!(file, "{}", if i % 3000 == 0 { "match" } else { "line" }).unwrap();
+            }
+        }
+
+        let file = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        let index = MatchIndex::spawn(file, FilterPredicate::new("match".to_owned(), 0));
+        index.wait();
+
+        assert_eq!(index.progress(), None);
+        assert_eq!(index.totals(), Some((10000, 3)));
+        assert_eq!(index.next_after(0), (Some(3000), 10000, true));
+        assert_eq!(index.next_after(3000), (Some(6000), 10000, true));
+        assert_eq!(index.next_after(9000), (None, 10000, true));
+        assert_eq!(index.prev_before(3000), (None, true));
+        assert_eq!(index.prev_before(3001), (Some(3000), true));
+        assert_eq!(index.prev_before(10001), (Some(9000), true));
+        assert_eq!(index.ordinal_of(6000), Some(2));
+        assert_eq!(index.ordinal_of(6001), None);
+        assert_eq!(index.nth_match(3), Some(9000));
+        assert_eq!(index.nth_match(4), None);
+        assert_eq!(index.nth_match(0), None);
+
+        // a narrower predicate is tested only on the lines already matched
+        let file = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        let every = MatchIndex::spawn(file, FilterPredicate::new(String::new(), 0));
+        let file = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        let narrowed = every.narrow(file, FilterPredicate::new("match".to_owned(), 0));
+        narrowed.wait();
+
+        assert_eq!(narrowed.progress(), None);
+        assert_eq!(narrowed.totals(), Some((10000, 3)));
+        assert_eq!(narrowed.next_after(3000), (Some(6000), 10000, true));
+        assert_eq!(narrowed.prev_before(10001), (Some(9000), true));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
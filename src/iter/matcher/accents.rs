@@ -0,0 +1,275 @@
+//! Removal of accents and other diacritical marks from characters.
+//!
+//! Characters are decomposed as by Unicode's compatibility decomposition
+//! (NFKD) and any combining marks dropped, so that e.g. `é`, `e\u{301}` and
+//! `ｅ` all become `e`. Decompositions are tabulated only for the Latin,
+//! Greek and Cyrillic letters and the fullwidth forms and ligatures most
+//! likely to turn up in text.
+
+/// Inclusive ranges of the blocks of combining diacritical marks.
+const MARKS: &[(char, char)] = &[
+    ('\u{300}', '\u{36f}'),
+    ('\u{1ab0}', '\u{1aff}'),
+    ('\u{1dc0}', '\u{1dff}'),
+    ('\u{20d0}', '\u{20ff}'),
+    ('\u{fe20}', '\u{fe2f}'),
+];
+
+/// Returns `true` if `c` is a combining mark, e.g. a combining acute accent.
+pub fn is_mark(c: char) -> bool {
+    MARKS.iter().any(|&(first, last)| first <= c && c <= last)
+}
+
+/// Returns the characters of `c` less its marks, or `None` if `c` has no
+/// tabulated decomposition.
+pub fn strip(c: char) -> Option<&'static str> {
+    DECOMPOSITIONS.binary_search_by(|&(key, _)| key.cmp(&c))
+        .ok()
+        .map(|idx| DECOMPOSITIONS[idx].1)
+}
+
+/// Characters and their compatibility decompositions with marks removed,
+/// sorted by character; generated from version 14.0 of the Unicode
+/// Character Database.
+const DECOMPOSITIONS: &[(char, &str)] = &[
+    ('\u{aa}', "a"), ('\u{b2}', "2"), ('\u{b3}', "3"), ('\u{b5}', "\u{3bc}"), ('\u{b9}', "1"),
+    ('\u{ba}', "o"), ('\u{c0}', "A"), ('\u{c1}', "A"), ('\u{c2}', "A"), ('\u{c3}', "A"),
+    ('\u{c4}', "A"), ('\u{c5}', "A"), ('\u{c7}', "C"), ('\u{c8}', "E"), ('\u{c9}', "E"),
+    ('\u{ca}', "E"), ('\u{cb}', "E"), ('\u{cc}', "I"), ('\u{cd}', "I"), ('\u{ce}', "I"),
+    ('\u{cf}', "I"), ('\u{d1}', "N"), ('\u{d2}', "O"), ('\u{d3}', "O"), ('\u{d4}', "O"),
+    ('\u{d5}', "O"), ('\u{d6}', "O"), ('\u{d9}', "U"), ('\u{da}', "U"), ('\u{db}', "U"),
+    ('\u{dc}', "U"), ('\u{dd}', "Y"), ('\u{e0}', "a"), ('\u{e1}', "a"), ('\u{e2}', "a"),
+    ('\u{e3}', "a"), ('\u{e4}', "a"), ('\u{e5}', "a"), ('\u{e7}', "c"), ('\u{e8}', "e"),
+    ('\u{e9}', "e"), ('\u{ea}', "e"), ('\u{eb}', "e"), ('\u{ec}', "i"), ('\u{ed}', "i"),
+    ('\u{ee}', "i"), ('\u{ef}', "i"), ('\u{f1}', "n"), ('\u{f2}', "o"), ('\u{f3}', "o"),
+    ('\u{f4}', "o"), ('\u{f5}', "o"), ('\u{f6}', "o"), ('\u{f9}', "u"), ('\u{fa}', "u"),
+    ('\u{fb}', "u"), ('\u{fc}', "u"), ('\u{fd}', "y"), ('\u{ff}', "y"), ('\u{100}', "A"),
+    ('\u{101}', "a"), ('\u{102}', "A"), ('\u{103}', "a"), ('\u{104}', "A"), ('\u{105}', "a"),
+    ('\u{106}', "C"), ('\u{107}', "c"), ('\u{108}', "C"), ('\u{109}', "c"), ('\u{10a}', "C"),
+    ('\u{10b}', "c"), ('\u{10c}', "C"), ('\u{10d}', "c"), ('\u{10e}', "D"), ('\u{10f}', "d"),
+    ('\u{112}', "E"), ('\u{113}', "e"), ('\u{114}', "E"), ('\u{115}', "e"), ('\u{116}', "E"),
+    ('\u{117}', "e"), ('\u{118}', "E"), ('\u{119}', "e"), ('\u{11a}', "E"), ('\u{11b}', "e"),
+    ('\u{11c}', "G"), ('\u{11d}', "g"), ('\u{11e}', "G"), ('\u{11f}', "g"), ('\u{120}', "G"),
+    ('\u{121}', "g"), ('\u{122}', "G"), ('\u{123}', "g"), ('\u{124}', "H"), ('\u{125}', "h"),
+    ('\u{128}', "I"), ('\u{129}', "i"), ('\u{12a}', "I"), ('\u{12b}', "i"), ('\u{12c}', "I"),
+    ('\u{12d}', "i"), ('\u{12e}', "I"), ('\u{12f}', "i"), ('\u{130}', "I"), ('\u{132}', "IJ"),
+    ('\u{133}', "ij"), ('\u{134}', "J"), ('\u{135}', "j"), ('\u{136}', "K"), ('\u{137}', "k"),
+    ('\u{139}', "L"), ('\u{13a}', "l"), ('\u{13b}', "L"), ('\u{13c}', "l"), ('\u{13d}', "L"),
+    ('\u{13e}', "l"), ('\u{143}', "N"), ('\u{144}', "n"), ('\u{145}', "N"), ('\u{146}', "n"),
+    ('\u{147}', "N"), ('\u{148}', "n"), ('\u{149}', "\u{2bc}n"), ('\u{14c}', "O"),
+    ('\u{14d}', "o"), ('\u{14e}', "O"), ('\u{14f}', "o"), ('\u{150}', "O"), ('\u{151}', "o"),
+    ('\u{154}', "R"), ('\u{155}', "r"), ('\u{156}', "R"), ('\u{157}', "r"), ('\u{158}', "R"),
+    ('\u{159}', "r"), ('\u{15a}', "S"), ('\u{15b}', "s"), ('\u{15c}', "S"), ('\u{15d}', "s"),
+    ('\u{15e}', "S"), ('\u{15f}', "s"), ('\u{160}', "S"), ('\u{161}', "s"), ('\u{162}', "T"),
+    ('\u{163}', "t"), ('\u{164}', "T"), ('\u{165}', "t"), ('\u{168}', "U"), ('\u{169}', "u"),
+    ('\u{16a}', "U"), ('\u{16b}', "u"), ('\u{16c}', "U"), ('\u{16d}', "u"), ('\u{16e}', "U"),
+    ('\u{16f}', "u"), ('\u{170}', "U"), ('\u{171}', "u"), ('\u{172}', "U"), ('\u{173}', "u"),
+    ('\u{174}', "W"), ('\u{175}', "w"), ('\u{176}', "Y"), ('\u{177}', "y"), ('\u{178}', "Y"),
+    ('\u{179}', "Z"), ('\u{17a}', "z"), ('\u{17b}', "Z"), ('\u{17c}', "z"), ('\u{17d}', "Z"),
+    ('\u{17e}', "z"), ('\u{17f}', "s"), ('\u{1a0}', "O"), ('\u{1a1}', "o"), ('\u{1af}', "U"),
+    ('\u{1b0}', "u"), ('\u{1c4}', "DZ"), ('\u{1c5}', "Dz"), ('\u{1c6}', "dz"), ('\u{1c7}', "LJ"),
+    ('\u{1c8}', "Lj"), ('\u{1c9}', "lj"), ('\u{1ca}', "NJ"), ('\u{1cb}', "Nj"), ('\u{1cc}', "nj"),
+    ('\u{1cd}', "A"), ('\u{1ce}', "a"), ('\u{1cf}', "I"), ('\u{1d0}', "i"), ('\u{1d1}', "O"),
+    ('\u{1d2}', "o"), ('\u{1d3}', "U"), ('\u{1d4}', "u"), ('\u{1d5}', "U"), ('\u{1d6}', "u"),
+    ('\u{1d7}', "U"), ('\u{1d8}', "u"), ('\u{1d9}', "U"), ('\u{1da}', "u"), ('\u{1db}', "U"),
+    ('\u{1dc}', "u"), ('\u{1de}', "A"), ('\u{1df}', "a"), ('\u{1e0}', "A"), ('\u{1e1}', "a"),
+    ('\u{1e2}', "\u{c6}"), ('\u{1e3}', "\u{e6}"), ('\u{1e6}', "G"), ('\u{1e7}', "g"),
+    ('\u{1e8}', "K"), ('\u{1e9}', "k"), ('\u{1ea}', "O"), ('\u{1eb}', "o"), ('\u{1ec}', "O"),
+    ('\u{1ed}', "o"), ('\u{1ee}', "\u{1b7}"), ('\u{1ef}', "\u{292}"), ('\u{1f0}', "j"),
+    ('\u{1f1}', "DZ"), ('\u{1f2}', "Dz"), ('\u{1f3}', "dz"), ('\u{1f4}', "G"), ('\u{1f5}', "g"),
+    ('\u{1f8}', "N"), ('\u{1f9}', "n"), ('\u{1fa}', "A"), ('\u{1fb}', "a"), ('\u{1fc}', "\u{c6}"),
+    ('\u{1fd}', "\u{e6}"), ('\u{1fe}', "\u{d8}"), ('\u{1ff}', "\u{f8}"), ('\u{200}', "A"),
+    ('\u{201}', "a"), ('\u{202}', "A"), ('\u{203}', "a"), ('\u{204}', "E"), ('\u{205}', "e"),
+    ('\u{206}', "E"), ('\u{207}', "e"), ('\u{208}', "I"), ('\u{209}', "i"), ('\u{20a}', "I"),
+    ('\u{20b}', "i"), ('\u{20c}', "O"), ('\u{20d}', "o"), ('\u{20e}', "O"), ('\u{20f}', "o"),
+    ('\u{210}', "R"), ('\u{211}', "r"), ('\u{212}', "R"), ('\u{213}', "r"), ('\u{214}', "U"),
+    ('\u{215}', "u"), ('\u{216}', "U"), ('\u{217}', "u"), ('\u{218}', "S"), ('\u{219}', "s"),
+    ('\u{21a}', "T"), ('\u{21b}', "t"), ('\u{21e}', "H"), ('\u{21f}', "h"), ('\u{226}', "A"),
+    ('\u{227}', "a"), ('\u{228}', "E"), ('\u{229}', "e"), ('\u{22a}', "O"), ('\u{22b}', "o"),
+    ('\u{22c}', "O"), ('\u{22d}', "o"), ('\u{22e}', "O"), ('\u{22f}', "o"), ('\u{230}', "O"),
+    ('\u{231}', "o"), ('\u{232}', "Y"), ('\u{233}', "y"), ('\u{374}', "\u{2b9}"),
+    ('\u{386}', "\u{391}"), ('\u{388}', "\u{395}"), ('\u{389}', "\u{397}"), ('\u{38a}', "\u{399}"),
+    ('\u{38c}', "\u{39f}"), ('\u{38e}', "\u{3a5}"), ('\u{38f}', "\u{3a9}"), ('\u{390}', "\u{3b9}"),
+    ('\u{3aa}', "\u{399}"), ('\u{3ab}', "\u{3a5}"), ('\u{3ac}', "\u{3b1}"), ('\u{3ad}', "\u{3b5}"),
+    ('\u{3ae}', "\u{3b7}"), ('\u{3af}', "\u{3b9}"), ('\u{3b0}', "\u{3c5}"), ('\u{3ca}', "\u{3b9}"),
+    ('\u{3cb}', "\u{3c5}"), ('\u{3cc}', "\u{3bf}"), ('\u{3cd}', "\u{3c5}"), ('\u{3ce}', "\u{3c9}"),
+    ('\u{3d0}', "\u{3b2}"), ('\u{3d1}', "\u{3b8}"), ('\u{3d2}', "\u{3a5}"), ('\u{3d3}', "\u{3a5}"),
+    ('\u{3d4}', "\u{3a5}"), ('\u{3d5}', "\u{3c6}"), ('\u{3d6}', "\u{3c0}"), ('\u{3f0}', "\u{3ba}"),
+    ('\u{3f1}', "\u{3c1}"), ('\u{3f2}', "\u{3c2}"), ('\u{3f4}', "\u{398}"), ('\u{3f5}', "\u{3b5}"),
+    ('\u{3f9}', "\u{3a3}"), ('\u{400}', "\u{415}"), ('\u{401}', "\u{415}"), ('\u{403}', "\u{413}"),
+    ('\u{407}', "\u{406}"), ('\u{40c}', "\u{41a}"), ('\u{40d}', "\u{418}"), ('\u{40e}', "\u{423}"),
+    ('\u{419}', "\u{418}"), ('\u{439}', "\u{438}"), ('\u{450}', "\u{435}"), ('\u{451}', "\u{435}"),
+    ('\u{453}', "\u{433}"), ('\u{457}', "\u{456}"), ('\u{45c}', "\u{43a}"), ('\u{45d}', "\u{438}"),
+    ('\u{45e}', "\u{443}"), ('\u{476}', "\u{474}"), ('\u{477}', "\u{475}"), ('\u{4c1}', "\u{416}"),
+    ('\u{4c2}', "\u{436}"), ('\u{4d0}', "\u{410}"), ('\u{4d1}', "\u{430}"), ('\u{4d2}', "\u{410}"),
+    ('\u{4d3}', "\u{430}"), ('\u{4d6}', "\u{415}"), ('\u{4d7}', "\u{435}"), ('\u{4da}', "\u{4d8}"),
+    ('\u{4db}', "\u{4d9}"), ('\u{4dc}', "\u{416}"), ('\u{4dd}', "\u{436}"), ('\u{4de}', "\u{417}"),
+    ('\u{4df}', "\u{437}"), ('\u{4e2}', "\u{418}"), ('\u{4e3}', "\u{438}"), ('\u{4e4}', "\u{418}"),
+    ('\u{4e5}', "\u{438}"), ('\u{4e6}', "\u{41e}"), ('\u{4e7}', "\u{43e}"), ('\u{4ea}', "\u{4e8}"),
+    ('\u{4eb}', "\u{4e9}"), ('\u{4ec}', "\u{42d}"), ('\u{4ed}', "\u{44d}"), ('\u{4ee}', "\u{423}"),
+    ('\u{4ef}', "\u{443}"), ('\u{4f0}', "\u{423}"), ('\u{4f1}', "\u{443}"), ('\u{4f2}', "\u{423}"),
+    ('\u{4f3}', "\u{443}"), ('\u{4f4}', "\u{427}"), ('\u{4f5}', "\u{447}"), ('\u{4f8}', "\u{42b}"),
+    ('\u{4f9}', "\u{44b}"), ('\u{1e00}', "A"), ('\u{1e01}', "a"), ('\u{1e02}', "B"),
+    ('\u{1e03}', "b"), ('\u{1e04}', "B"), ('\u{1e05}', "b"), ('\u{1e06}', "B"), ('\u{1e07}', "b"),
+    ('\u{1e08}', "C"), ('\u{1e09}', "c"), ('\u{1e0a}', "D"), ('\u{1e0b}', "d"), ('\u{1e0c}', "D"),
+    ('\u{1e0d}', "d"), ('\u{1e0e}', "D"), ('\u{1e0f}', "d"), ('\u{1e10}', "D"), ('\u{1e11}', "d"),
+    ('\u{1e12}', "D"), ('\u{1e13}', "d"), ('\u{1e14}', "E"), ('\u{1e15}', "e"), ('\u{1e16}', "E"),
+    ('\u{1e17}', "e"), ('\u{1e18}', "E"), ('\u{1e19}', "e"), ('\u{1e1a}', "E"), ('\u{1e1b}', "e"),
+    ('\u{1e1c}', "E"), ('\u{1e1d}', "e"), ('\u{1e1e}', "F"), ('\u{1e1f}', "f"), ('\u{1e20}', "G"),
+    ('\u{1e21}', "g"), ('\u{1e22}', "H"), ('\u{1e23}', "h"), ('\u{1e24}', "H"), ('\u{1e25}', "h"),
+    ('\u{1e26}', "H"), ('\u{1e27}', "h"), ('\u{1e28}', "H"), ('\u{1e29}', "h"), ('\u{1e2a}', "H"),
+    ('\u{1e2b}', "h"), ('\u{1e2c}', "I"), ('\u{1e2d}', "i"), ('\u{1e2e}', "I"), ('\u{1e2f}', "i"),
+    ('\u{1e30}', "K"), ('\u{1e31}', "k"), ('\u{1e32}', "K"), ('\u{1e33}', "k"), ('\u{1e34}', "K"),
+    ('\u{1e35}', "k"), ('\u{1e36}', "L"), ('\u{1e37}', "l"), ('\u{1e38}', "L"), ('\u{1e39}', "l"),
+    ('\u{1e3a}', "L"), ('\u{1e3b}', "l"), ('\u{1e3c}', "L"), ('\u{1e3d}', "l"), ('\u{1e3e}', "M"),
+    ('\u{1e3f}', "m"), ('\u{1e40}', "M"), ('\u{1e41}', "m"), ('\u{1e42}', "M"), ('\u{1e43}', "m"),
+    ('\u{1e44}', "N"), ('\u{1e45}', "n"), ('\u{1e46}', "N"), ('\u{1e47}', "n"), ('\u{1e48}', "N"),
+    ('\u{1e49}', "n"), ('\u{1e4a}', "N"), ('\u{1e4b}', "n"), ('\u{1e4c}', "O"), ('\u{1e4d}', "o"),
+    ('\u{1e4e}', "O"), ('\u{1e4f}', "o"), ('\u{1e50}', "O"), ('\u{1e51}', "o"), ('\u{1e52}', "O"),
+    ('\u{1e53}', "o"), ('\u{1e54}', "P"), ('\u{1e55}', "p"), ('\u{1e56}', "P"), ('\u{1e57}', "p"),
+    ('\u{1e58}', "R"), ('\u{1e59}', "r"), ('\u{1e5a}', "R"), ('\u{1e5b}', "r"), ('\u{1e5c}', "R"),
+    ('\u{1e5d}', "r"), ('\u{1e5e}', "R"), ('\u{1e5f}', "r"), ('\u{1e60}', "S"), ('\u{1e61}', "s"),
+    ('\u{1e62}', "S"), ('\u{1e63}', "s"), ('\u{1e64}', "S"), ('\u{1e65}', "s"), ('\u{1e66}', "S"),
+    ('\u{1e67}', "s"), ('\u{1e68}', "S"), ('\u{1e69}', "s"), ('\u{1e6a}', "T"), ('\u{1e6b}', "t"),
+    ('\u{1e6c}', "T"), ('\u{1e6d}', "t"), ('\u{1e6e}', "T"), ('\u{1e6f}', "t"), ('\u{1e70}', "T"),
+    ('\u{1e71}', "t"), ('\u{1e72}', "U"), ('\u{1e73}', "u"), ('\u{1e74}', "U"), ('\u{1e75}', "u"),
+    ('\u{1e76}', "U"), ('\u{1e77}', "u"), ('\u{1e78}', "U"), ('\u{1e79}', "u"), ('\u{1e7a}', "U"),
+    ('\u{1e7b}', "u"), ('\u{1e7c}', "V"), ('\u{1e7d}', "v"), ('\u{1e7e}', "V"), ('\u{1e7f}', "v"),
+    ('\u{1e80}', "W"), ('\u{1e81}', "w"), ('\u{1e82}', "W"), ('\u{1e83}', "w"), ('\u{1e84}', "W"),
+    ('\u{1e85}', "w"), ('\u{1e86}', "W"), ('\u{1e87}', "w"), ('\u{1e88}', "W"), ('\u{1e89}', "w"),
+    ('\u{1e8a}', "X"), ('\u{1e8b}', "x"), ('\u{1e8c}', "X"), ('\u{1e8d}', "x"), ('\u{1e8e}', "Y"),
+    ('\u{1e8f}', "y"), ('\u{1e90}', "Z"), ('\u{1e91}', "z"), ('\u{1e92}', "Z"), ('\u{1e93}', "z"),
+    ('\u{1e94}', "Z"), ('\u{1e95}', "z"), ('\u{1e96}', "h"), ('\u{1e97}', "t"), ('\u{1e98}', "w"),
+    ('\u{1e99}', "y"), ('\u{1e9a}', "a\u{2be}"), ('\u{1e9b}', "s"), ('\u{1ea0}', "A"),
+    ('\u{1ea1}', "a"), ('\u{1ea2}', "A"), ('\u{1ea3}', "a"), ('\u{1ea4}', "A"), ('\u{1ea5}', "a"),
+    ('\u{1ea6}', "A"), ('\u{1ea7}', "a"), ('\u{1ea8}', "A"), ('\u{1ea9}', "a"), ('\u{1eaa}', "A"),
+    ('\u{1eab}', "a"), ('\u{1eac}', "A"), ('\u{1ead}', "a"), ('\u{1eae}', "A"), ('\u{1eaf}', "a"),
+    ('\u{1eb0}', "A"), ('\u{1eb1}', "a"), ('\u{1eb2}', "A"), ('\u{1eb3}', "a"), ('\u{1eb4}', "A"),
+    ('\u{1eb5}', "a"), ('\u{1eb6}', "A"), ('\u{1eb7}', "a"), ('\u{1eb8}', "E"), ('\u{1eb9}', "e"),
+    ('\u{1eba}', "E"), ('\u{1ebb}', "e"), ('\u{1ebc}', "E"), ('\u{1ebd}', "e"), ('\u{1ebe}', "E"),
+    ('\u{1ebf}', "e"), ('\u{1ec0}', "E"), ('\u{1ec1}', "e"), ('\u{1ec2}', "E"), ('\u{1ec3}', "e"),
+    ('\u{1ec4}', "E"), ('\u{1ec5}', "e"), ('\u{1ec6}', "E"), ('\u{1ec7}', "e"), ('\u{1ec8}', "I"),
+    ('\u{1ec9}', "i"), ('\u{1eca}', "I"), ('\u{1ecb}', "i"), ('\u{1ecc}', "O"), ('\u{1ecd}', "o"),
+    ('\u{1ece}', "O"), ('\u{1ecf}', "o"), ('\u{1ed0}', "O"), ('\u{1ed1}', "o"), ('\u{1ed2}', "O"),
+    ('\u{1ed3}', "o"), ('\u{1ed4}', "O"), ('\u{1ed5}', "o"), ('\u{1ed6}', "O"), ('\u{1ed7}', "o"),
+    ('\u{1ed8}', "O"), ('\u{1ed9}', "o"), ('\u{1eda}', "O"), ('\u{1edb}', "o"), ('\u{1edc}', "O"),
+    ('\u{1edd}', "o"), ('\u{1ede}', "O"), ('\u{1edf}', "o"), ('\u{1ee0}', "O"), ('\u{1ee1}', "o"),
+    ('\u{1ee2}', "O"), ('\u{1ee3}', "o"), ('\u{1ee4}', "U"), ('\u{1ee5}', "u"), ('\u{1ee6}', "U"),
+    ('\u{1ee7}', "u"), ('\u{1ee8}', "U"), ('\u{1ee9}', "u"), ('\u{1eea}', "U"), ('\u{1eeb}', "u"),
+    ('\u{1eec}', "U"), ('\u{1eed}', "u"), ('\u{1eee}', "U"), ('\u{1eef}', "u"), ('\u{1ef0}', "U"),
+    ('\u{1ef1}', "u"), ('\u{1ef2}', "Y"), ('\u{1ef3}', "y"), ('\u{1ef4}', "Y"), ('\u{1ef5}', "y"),
+    ('\u{1ef6}', "Y"), ('\u{1ef7}', "y"), ('\u{1ef8}', "Y"), ('\u{1ef9}', "y"),
+    ('\u{1f00}', "\u{3b1}"), ('\u{1f01}', "\u{3b1}"), ('\u{1f02}', "\u{3b1}"),
+    ('\u{1f03}', "\u{3b1}"), ('\u{1f04}', "\u{3b1}"), ('\u{1f05}', "\u{3b1}"),
+    ('\u{1f06}', "\u{3b1}"), ('\u{1f07}', "\u{3b1}"), ('\u{1f08}', "\u{391}"),
+    ('\u{1f09}', "\u{391}"), ('\u{1f0a}', "\u{391}"), ('\u{1f0b}', "\u{391}"),
+    ('\u{1f0c}', "\u{391}"), ('\u{1f0d}', "\u{391}"), ('\u{1f0e}', "\u{391}"),
+    ('\u{1f0f}', "\u{391}"), ('\u{1f10}', "\u{3b5}"), ('\u{1f11}', "\u{3b5}"),
+    ('\u{1f12}', "\u{3b5}"), ('\u{1f13}', "\u{3b5}"), ('\u{1f14}', "\u{3b5}"),
+    ('\u{1f15}', "\u{3b5}"), ('\u{1f18}', "\u{395}"), ('\u{1f19}', "\u{395}"),
+    ('\u{1f1a}', "\u{395}"), ('\u{1f1b}', "\u{395}"), ('\u{1f1c}', "\u{395}"),
+    ('\u{1f1d}', "\u{395}"), ('\u{1f20}', "\u{3b7}"), ('\u{1f21}', "\u{3b7}"),
+    ('\u{1f22}', "\u{3b7}"), ('\u{1f23}', "\u{3b7}"), ('\u{1f24}', "\u{3b7}"),
+    ('\u{1f25}', "\u{3b7}"), ('\u{1f26}', "\u{3b7}"), ('\u{1f27}', "\u{3b7}"),
+    ('\u{1f28}', "\u{397}"), ('\u{1f29}', "\u{397}"), ('\u{1f2a}', "\u{397}"),
+    ('\u{1f2b}', "\u{397}"), ('\u{1f2c}', "\u{397}"), ('\u{1f2d}', "\u{397}"),
+    ('\u{1f2e}', "\u{397}"), ('\u{1f2f}', "\u{397}"), ('\u{1f30}', "\u{3b9}"),
+    ('\u{1f31}', "\u{3b9}"), ('\u{1f32}', "\u{3b9}"), ('\u{1f33}', "\u{3b9}"),
+    ('\u{1f34}', "\u{3b9}"), ('\u{1f35}', "\u{3b9}"), ('\u{1f36}', "\u{3b9}"),
+    ('\u{1f37}', "\u{3b9}"), ('\u{1f38}', "\u{399}"), ('\u{1f39}', "\u{399}"),
+    ('\u{1f3a}', "\u{399}"), ('\u{1f3b}', "\u{399}"), ('\u{1f3c}', "\u{399}"),
+    ('\u{1f3d}', "\u{399}"), ('\u{1f3e}', "\u{399}"), ('\u{1f3f}', "\u{399}"),
+    ('\u{1f40}', "\u{3bf}"), ('\u{1f41}', "\u{3bf}"), ('\u{1f42}', "\u{3bf}"),
+    ('\u{1f43}', "\u{3bf}"), ('\u{1f44}', "\u{3bf}"), ('\u{1f45}', "\u{3bf}"),
+    ('\u{1f48}', "\u{39f}"), ('\u{1f49}', "\u{39f}"), ('\u{1f4a}', "\u{39f}"),
+    ('\u{1f4b}', "\u{39f}"), ('\u{1f4c}', "\u{39f}"), ('\u{1f4d}', "\u{39f}"),
+    ('\u{1f50}', "\u{3c5}"), ('\u{1f51}', "\u{3c5}"), ('\u{1f52}', "\u{3c5}"),
+    ('\u{1f53}', "\u{3c5}"), ('\u{1f54}', "\u{3c5}"), ('\u{1f55}', "\u{3c5}"),
+    ('\u{1f56}', "\u{3c5}"), ('\u{1f57}', "\u{3c5}"), ('\u{1f59}', "\u{3a5}"),
+    ('\u{1f5b}', "\u{3a5}"), ('\u{1f5d}', "\u{3a5}"), ('\u{1f5f}', "\u{3a5}"),
+    ('\u{1f60}', "\u{3c9}"), ('\u{1f61}', "\u{3c9}"), ('\u{1f62}', "\u{3c9}"),
+    ('\u{1f63}', "\u{3c9}"), ('\u{1f64}', "\u{3c9}"), ('\u{1f65}', "\u{3c9}"),
+    ('\u{1f66}', "\u{3c9}"), ('\u{1f67}', "\u{3c9}"), ('\u{1f68}', "\u{3a9}"),
+    ('\u{1f69}', "\u{3a9}"), ('\u{1f6a}', "\u{3a9}"), ('\u{1f6b}', "\u{3a9}"),
+    ('\u{1f6c}', "\u{3a9}"), ('\u{1f6d}', "\u{3a9}"), ('\u{1f6e}', "\u{3a9}"),
+    ('\u{1f6f}', "\u{3a9}"), ('\u{1f70}', "\u{3b1}"), ('\u{1f71}', "\u{3b1}"),
+    ('\u{1f72}', "\u{3b5}"), ('\u{1f73}', "\u{3b5}"), ('\u{1f74}', "\u{3b7}"),
+    ('\u{1f75}', "\u{3b7}"), ('\u{1f76}', "\u{3b9}"), ('\u{1f77}', "\u{3b9}"),
+    ('\u{1f78}', "\u{3bf}"), ('\u{1f79}', "\u{3bf}"), ('\u{1f7a}', "\u{3c5}"),
+    ('\u{1f7b}', "\u{3c5}"), ('\u{1f7c}', "\u{3c9}"), ('\u{1f7d}', "\u{3c9}"),
+    ('\u{1f80}', "\u{3b1}"), ('\u{1f81}', "\u{3b1}"), ('\u{1f82}', "\u{3b1}"),
+    ('\u{1f83}', "\u{3b1}"), ('\u{1f84}', "\u{3b1}"), ('\u{1f85}', "\u{3b1}"),
+    ('\u{1f86}', "\u{3b1}"), ('\u{1f87}', "\u{3b1}"), ('\u{1f88}', "\u{391}"),
+    ('\u{1f89}', "\u{391}"), ('\u{1f8a}', "\u{391}"), ('\u{1f8b}', "\u{391}"),
+    ('\u{1f8c}', "\u{391}"), ('\u{1f8d}', "\u{391}"), ('\u{1f8e}', "\u{391}"),
+    ('\u{1f8f}', "\u{391}"), ('\u{1f90}', "\u{3b7}"), ('\u{1f91}', "\u{3b7}"),
+    ('\u{1f92}', "\u{3b7}"), ('\u{1f93}', "\u{3b7}"), ('\u{1f94}', "\u{3b7}"),
+    ('\u{1f95}', "\u{3b7}"), ('\u{1f96}', "\u{3b7}"), ('\u{1f97}', "\u{3b7}"),
+    ('\u{1f98}', "\u{397}"), ('\u{1f99}', "\u{397}"), ('\u{1f9a}', "\u{397}"),
+    ('\u{1f9b}', "\u{397}"), ('\u{1f9c}', "\u{397}"), ('\u{1f9d}', "\u{397}"),
+    ('\u{1f9e}', "\u{397}"), ('\u{1f9f}', "\u{397}"), ('\u{1fa0}', "\u{3c9}"),
+    ('\u{1fa1}', "\u{3c9}"), ('\u{1fa2}', "\u{3c9}"), ('\u{1fa3}', "\u{3c9}"),
+    ('\u{1fa4}', "\u{3c9}"), ('\u{1fa5}', "\u{3c9}"), ('\u{1fa6}', "\u{3c9}"),
+    ('\u{1fa7}', "\u{3c9}"), ('\u{1fa8}', "\u{3a9}"), ('\u{1fa9}', "\u{3a9}"),
+    ('\u{1faa}', "\u{3a9}"), ('\u{1fab}', "\u{3a9}"), ('\u{1fac}', "\u{3a9}"),
+    ('\u{1fad}', "\u{3a9}"), ('\u{1fae}', "\u{3a9}"), ('\u{1faf}', "\u{3a9}"),
+    ('\u{1fb0}', "\u{3b1}"), ('\u{1fb1}', "\u{3b1}"), ('\u{1fb2}', "\u{3b1}"),
+    ('\u{1fb3}', "\u{3b1}"), ('\u{1fb4}', "\u{3b1}"), ('\u{1fb6}', "\u{3b1}"),
+    ('\u{1fb7}', "\u{3b1}"), ('\u{1fb8}', "\u{391}"), ('\u{1fb9}', "\u{391}"),
+    ('\u{1fba}', "\u{391}"), ('\u{1fbb}', "\u{391}"), ('\u{1fbc}', "\u{391}"),
+    ('\u{1fbe}', "\u{3b9}"), ('\u{1fc2}', "\u{3b7}"), ('\u{1fc3}', "\u{3b7}"),
+    ('\u{1fc4}', "\u{3b7}"), ('\u{1fc6}', "\u{3b7}"), ('\u{1fc7}', "\u{3b7}"),
+    ('\u{1fc8}', "\u{395}"), ('\u{1fc9}', "\u{395}"), ('\u{1fca}', "\u{397}"),
+    ('\u{1fcb}', "\u{397}"), ('\u{1fcc}', "\u{397}"), ('\u{1fd0}', "\u{3b9}"),
+    ('\u{1fd1}', "\u{3b9}"), ('\u{1fd2}', "\u{3b9}"), ('\u{1fd3}', "\u{3b9}"),
+    ('\u{1fd6}', "\u{3b9}"), ('\u{1fd7}', "\u{3b9}"), ('\u{1fd8}', "\u{399}"),
+    ('\u{1fd9}', "\u{399}"), ('\u{1fda}', "\u{399}"), ('\u{1fdb}', "\u{399}"),
+    ('\u{1fe0}', "\u{3c5}"), ('\u{1fe1}', "\u{3c5}"), ('\u{1fe2}', "\u{3c5}"),
+    ('\u{1fe3}', "\u{3c5}"), ('\u{1fe4}', "\u{3c1}"), ('\u{1fe5}', "\u{3c1}"),
+    ('\u{1fe6}', "\u{3c5}"), ('\u{1fe7}', "\u{3c5}"), ('\u{1fe8}', "\u{3a5}"),
+    ('\u{1fe9}', "\u{3a5}"), ('\u{1fea}', "\u{3a5}"), ('\u{1feb}', "\u{3a5}"),
+    ('\u{1fec}', "\u{3a1}"), ('\u{1ff2}', "\u{3c9}"), ('\u{1ff3}', "\u{3c9}"),
+    ('\u{1ff4}', "\u{3c9}"), ('\u{1ff6}', "\u{3c9}"), ('\u{1ff7}', "\u{3c9}"),
+    ('\u{1ff8}', "\u{39f}"), ('\u{1ff9}', "\u{39f}"), ('\u{1ffa}', "\u{3a9}"),
+    ('\u{1ffb}', "\u{3a9}"), ('\u{1ffc}', "\u{3a9}"), ('\u{fb00}', "ff"), ('\u{fb01}', "fi"),
+    ('\u{fb02}', "fl"), ('\u{fb03}', "ffi"), ('\u{fb04}', "ffl"), ('\u{fb05}', "st"),
+    ('\u{fb06}', "st"), ('\u{ff01}', "!"), ('\u{ff02}', "\""), ('\u{ff03}', "#"),
+    ('\u{ff04}', "$"), ('\u{ff05}', "%"), ('\u{ff06}', "&"), ('\u{ff07}', "'"), ('\u{ff08}', "("),
+    ('\u{ff09}', ")"), ('\u{ff0a}', "*"), ('\u{ff0b}', "+"), ('\u{ff0c}', ","), ('\u{ff0d}', "-"),
+    ('\u{ff0e}', "."), ('\u{ff0f}', "/"), ('\u{ff10}', "0"), ('\u{ff11}', "1"), ('\u{ff12}', "2"),
+    ('\u{ff13}', "3"), ('\u{ff14}', "4"), ('\u{ff15}', "5"), ('\u{ff16}', "6"), ('\u{ff17}', "7"),
+    ('\u{ff18}', "8"), ('\u{ff19}', "9"), ('\u{ff1a}', ":"), ('\u{ff1b}', ";"), ('\u{ff1c}', "<"),
+    ('\u{ff1d}', "="), ('\u{ff1e}', ">"), ('\u{ff1f}', "?"), ('\u{ff20}', "@"), ('\u{ff21}', "A"),
+    ('\u{ff22}', "B"), ('\u{ff23}', "C"), ('\u{ff24}', "D"), ('\u{ff25}', "E"), ('\u{ff26}', "F"),
+    ('\u{ff27}', "G"), ('\u{ff28}', "H"), ('\u{ff29}', "I"), ('\u{ff2a}', "J"), ('\u{ff2b}', "K"),
+    ('\u{ff2c}', "L"), ('\u{ff2d}', "M"), ('\u{ff2e}', "N"), ('\u{ff2f}', "O"), ('\u{ff30}', "P"),
+    ('\u{ff31}', "Q"), ('\u{ff32}', "R"), ('\u{ff33}', "S"), ('\u{ff34}', "T"), ('\u{ff35}', "U"),
+    ('\u{ff36}', "V"), ('\u{ff37}', "W"), ('\u{ff38}', "X"), ('\u{ff39}', "Y"), ('\u{ff3a}', "Z"),
+    ('\u{ff3b}', "["), ('\u{ff3c}', "\\"), ('\u{ff3d}', "]"), ('\u{ff3e}', "^"), ('\u{ff3f}', "_"),
+    ('\u{ff40}', "`"), ('\u{ff41}', "a"), ('\u{ff42}', "b"), ('\u{ff43}', "c"), ('\u{ff44}', "d"),
+    ('\u{ff45}', "e"), ('\u{ff46}', "f"), ('\u{ff47}', "g"), ('\u{ff48}', "h"), ('\u{ff49}', "i"),
+    ('\u{ff4a}', "j"), ('\u{ff4b}', "k"), ('\u{ff4c}', "l"), ('\u{ff4d}', "m"), ('\u{ff4e}', "n"),
+    ('\u{ff4f}', "o"), ('\u{ff50}', "p"), ('\u{ff51}', "q"), ('\u{ff52}', "r"), ('\u{ff53}', "s"),
+    ('\u{ff54}', "t"), ('\u{ff55}', "u"), ('\u{ff56}', "v"), ('\u{ff57}', "w"), ('\u{ff58}', "x"),
+    ('\u{ff59}', "y"), ('\u{ff5a}', "z"), ('\u{ff5b}', "{"), ('\u{ff5c}', "|"), ('\u{ff5d}', "}"),
+    ('\u{ff5e}', "~"),
+];
+
+#[cfg(test)]
+mod test {
+    use super::{DECOMPOSITIONS, is_mark, strip};
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(strip('\u{e9}'), Some("e"));
+        assert_eq!(strip('\u{c5}'), Some("A"));
+        assert_eq!(strip('\u{1ec7}'), Some("e"));
+        assert_eq!(strip('\u{fb01}'), Some("fi"));
+        assert_eq!(strip('\u{ff21}'), Some("A"));
+        assert_eq!(strip('e'), None);
+        assert!(is_mark('\u{301}'));
+        assert!(!is_mark('e'));
+
+        assert!(DECOMPOSITIONS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
@@ -0,0 +1,216 @@
+//! Field matcher, which tests the fields of lines in any format parsed by the
+//! `parser` module, e.g. `status=404` of access logs or `level=warn` of
+//! logfmt or JSON lines, and compares them, e.g. `severity<=err` of syslog.
+//! Values may stand for classes of numbers, e.g. `status=5xx`.
+
+use std::cmp::Ordering;
+
+use iter::parser;
+
+use super::{fold, LineMatcher, MatchOptions, Matcher, MatcherKind, Span};
+
+pub const KIND: MatcherKind = MatcherKind {
+    name: "field",
+    description: "field",
+    build: build,
+};
+
+/// Word joining queries which lines must all match, e.g.
+/// `severity<=err AND app=sshd`.
+const AND: &str = " AND ";
+
+/// Operators by which a field is tested, longest first, so that `<=` isn't
+/// taken for `<`.
+const OPERATORS: &[&str] = &["!=", "<=", ">=", "=", "~", "<", ">"];
+
+
+fn build(pattern: &str, options: &MatchOptions) -> Result<Matcher, String> {
+    let queries = pattern.split(AND)
+        .map(|query| fold::wrap(query, options, build_query))
+        .collect::<Result<Vec<Matcher>, String>>()?;
+    if queries.len() == 1 {
+        Ok(queries.into_iter().next().unwrap())
+    } else {
+        Ok(Matcher::all(queries))
+    }
+}
+
+fn build_query(pattern: &str) -> Result<Matcher, String> {
+    let start = pattern.find(['=', '~', '<', '>', '!']);
+    let (path, value) = match start {
+        Some(i) => {
+            let operator = OPERATORS.iter().find(|op| pattern[i..].starts_with(*op))
+                .ok_or_else(|| usage(pattern))?;
+            let value = pattern[i + operator.len()..].to_owned();
+            let value = match *operator {
+                "=" => Value::Equals(value),
+                "!=" => Value::NotEquals(value),
+                "~" => Value::Contains(value),
+                _ => {
+                    let bound = rank(value.trim()).ok_or_else(|| {
+                        format!("expected a number or a syslog severity after `{}`; got `{}`",
+                                operator, value.trim())
+                    })?;
+                    let wanted = if operator.starts_with('<') {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    };
+                    Value::Compare { wanted: wanted, or_equal: operator.ends_with('='),
+                                     bound: bound }
+                },
+            };
+            (pattern[..i].trim(), Some(value))
+        },
+        None => (pattern.trim(), None),
+    };
+
+    if path.is_empty() {
+        return Err(usage(pattern));
+    }
+
+    Ok(Matcher::new(Query {
+        path: path.to_owned(),
+        value: value,
+    }))
+}
+
+fn usage(pattern: &str) -> String {
+    format!("expected `FIELD`, `FIELD=VALUE`, `FIELD~TEXT` or a comparison such as \
+             `FIELD<=VALUE`; got `{}`", pattern)
+}
+
+/// Returns `true` if the value of a field is the given one or, if that has
+/// `x`s for digits, e.g. `5xx`, of the class of numbers it stands for.
+fn equals(value: &str, field: &str) -> bool {
+    let is_class = value.contains(['x', 'X']) && value.len() == field.len();
+    value == field || is_class && value.bytes().zip(field.bytes()).all(|(v, f)| {
+        if v == b'x' || v == b'X' { f.is_ascii_digit() } else { v == f }
+    })
+}
+
+/// Returns the number by which a value is compared: the value itself if it's
+/// a number, else the code of the syslog severity it names, if any, so that
+/// `severity<=err` selects errors and worse.
+fn rank(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok().filter(|rank| !rank.is_nan())
+        .or_else(|| parser::severity_code(value).map(f64::from))
+}
+
+/// Test of the value of a field.
+#[derive(Debug)]
+enum Value {
+    /// the value is the given one
+    Equals(String),
+    /// the value isn't the given one
+    NotEquals(String),
+    /// the value contains the given text
+    Contains(String),
+    /// the rank of the value compares with a bound as wanted, or equals it if
+    /// `or_equal`
+    Compare { wanted: Ordering, or_equal: bool, bound: f64 },
+}
+
+/// Matches lines having a given field, optionally with a given value.
+///
+/// Queries take the form `FIELD`, `FIELD=VALUE`, `FIELD!=VALUE`, `FIELD~TEXT`
+/// or a comparison `FIELD<VALUE`, `FIELD<=VALUE`, `FIELD>VALUE` or
+/// `FIELD>=VALUE`, where `FIELD` is the name of a field as parsed from the
+/// first format the line is in, e.g. `req.method` of a JSON line or `3` of
+/// comma-separated values, `VALUE` may have `x`s for digits, e.g. `5xx`,
+/// `FIELD~TEXT` matches values containing `TEXT`, and comparisons are of
+/// numbers or syslog severities. The span of a match is
+/// the field's value.
+#[derive(Debug)]
+struct Query {
+    path: String,
+    value: Option<Value>,
+}
+
+impl LineMatcher for Query {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        let structured = match parser::parse_any(line) {
+            Some(structured) => structured,
+            None => return Vec::new(),
+        };
+        let field = match structured.get(&self.path) {
+            Some(field) => field,
+            None => return Vec::new(),
+        };
+
+        let matched = match self.value {
+            None => true,
+            Some(Value::Equals(ref value)) => equals(value, &field.value),
+            Some(Value::NotEquals(ref value)) => !equals(value, &field.value),
+            Some(Value::Contains(ref value)) => field.value.contains(value.as_str()),
+            Some(Value::Compare { wanted, or_equal, bound }) => {
+                match rank(&field.value).and_then(|rank| rank.partial_cmp(&bound)) {
+                    Some(Ordering::Equal) => or_equal,
+                    Some(ordering) => ordering == wanted,
+                    None => false,
+                }
+            },
+        };
+        if matched && field.span.0 < field.span.1 { vec![field.span] } else { Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use iter::matcher::{MatchOptions, Matcher};
+
+    fn build(pattern: &str) -> Result<Matcher, String> {
+        super::build(pattern, &MatchOptions::default())
+    }
+
+    #[test]
+    fn test_field_spans() {
+        let logfmt = r#"level=warn msg="slow request" ms=950"#;
+        assert_eq!(build("level=warn").unwrap().spans(logfmt), vec![(6, 10)]);
+        assert_eq!(build("msg~slow").unwrap().spans(logfmt), vec![(16, 28)]);
+        assert!(build("ms").unwrap().is_match(logfmt));
+        assert!(!build("level=error").unwrap().is_match(logfmt));
+        assert!(!build("status").unwrap().is_match(logfmt));
+
+        assert!(build("req.method=GET").unwrap().is_match(r#"{"req": {"method": "GET"}}"#));
+        assert!(build("status=404").unwrap()
+            .is_match(r#"::1 - - [10/Oct/2000:13:55:36 -0700] "GET /x HTTP/1.0" 404 -"#));
+        assert!(build("2=ann").unwrap().is_match("7,ann,x"));
+        assert!(build("2=5xx").unwrap().is_match("7,503,x"));
+        assert!(build("2!=5xx").unwrap().is_match("7,404,x"));
+        assert!(!build("2=5xx").unwrap().is_match("7,5003,x"));
+        assert!(!build("2=5xx").unwrap().is_match("7,5a3,x"));
+        assert!(build("3=x").unwrap().is_match("7,5a3,x"));
+        assert!(!build("level").unwrap().is_match("level: warn"));
+
+        let options = MatchOptions { ignore_case: true, ..MatchOptions::default() };
+        assert!(super::build("level=WARN", &options).unwrap().is_match(logfmt));
+
+        assert!(build("").is_err());
+        assert!(build("=x").is_err());
+    }
+
+    #[test]
+    fn test_compare() {
+        let sshd = "<34>Oct 11 22:14:15 gateway sshd[42]: Failed password for ann";
+        let cron = "<78>Oct 11 22:14:16 gateway CRON[43]: (root) CMD (run-parts)";
+        let query = build("severity<=err AND app=sshd").unwrap();
+        assert_eq!(query.spans(sshd), vec![(1, 3), (28, 32)]);
+        assert!(!query.is_match(cron));
+        assert!(build("severity>warning").unwrap().is_match(cron));
+        assert!(build("facility=cron").unwrap().is_match(cron));
+        assert!(build("app!=sshd").unwrap().is_match(cron));
+        assert!(!build("app!=sshd").unwrap().is_match(sshd));
+
+        let logfmt = "level=warn msg=slow ms=950";
+        assert!(build("ms>900").unwrap().is_match(logfmt));
+        assert!(build("ms>=950").unwrap().is_match(logfmt));
+        assert!(!build("ms<950").unwrap().is_match(logfmt));
+        assert!(build("level<=warning").unwrap().is_match(logfmt));
+        assert!(!build("msg>1").unwrap().is_match(logfmt));
+
+        assert!(build("ms>slow").is_err());
+        assert!(build("ms!950").is_err());
+        assert!(build("ms>950 AND =x").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Folding of text so that strings which differ only in case or accents
+//! compare equal.
+//!
+//! By default only ASCII letters are folded to lower case. With the
+//! `unicode-case` feature all letters are, including those whose folded forms
+//! are longer than the originals, so that e.g. `straße` and `STRASSE` compare
+//! equal. Accents are removed as described in `accents`.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::accents;
+use super::{LineMatcher, Matcher, Span};
+
+/// Settings which affect how filter strings and lines are compared.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MatchOptions {
+    /// whether letters differing only in case are considered equal
+    pub ignore_case: bool,
+    /// whether letters differing only in their diacritical marks, e.g. `é`
+    /// and `e`, are considered equal
+    pub ignore_accents: bool,
+}
+
+impl MatchOptions {
+    /// Returns `true` if text is folded before being compared.
+    fn folds(&self) -> bool {
+        self.ignore_case || self.ignore_accents
+    }
+}
+
+/// Text folded for comparison, along with where each of its bytes came from.
+struct Folded {
+    text: String,
+    /// byte range of the original character each byte of `text` came from
+    origins: Vec<Span>,
+}
+
+impl Folded {
+    /// Returns the span of the original text corresponding to `span` of the
+    /// folded text.
+    fn original_span(&self, (start, end): Span) -> Span {
+        (self.origins[start].0, self.origins[end - 1].1)
+    }
+}
+
+/// Appends the folded form of `c` to `out`.
+fn fold_char(c: char, options: &MatchOptions, out: &mut String) {
+    if !options.ignore_accents {
+        fold_case(c, options, out);
+    } else if accents::is_mark(c) {
+        // case: dropped along with the marks of decomposed characters
+    } else if let Some(stripped) = accents::strip(c) {
+        for c in stripped.chars() {
+            fold_case(c, options, out);
+        }
+    } else {
+        fold_case(c, options, out);
+    }
+}
+
+/// Appends `c`, folded to lower case if case is ignored, to `out`.
+fn fold_case(c: char, options: &MatchOptions, out: &mut String) {
+    if !options.ignore_case {
+        out.push(c);
+    } else if cfg!(feature = "unicode-case") {
+        match full_folding(c) {
+            Some(folded) => out.push_str(folded),
+            None => out.extend(c.to_lowercase()),
+        }
+    } else {
+        out.push(c.to_ascii_lowercase());
+    }
+}
+
+/// Returns the full case folding of `c` where it differs from the lower case
+/// form of `c`.
+fn full_folding(c: char) -> Option<&'static str> {
+    let folded = match c {
+        '\u{b5}' => "\u{3bc}",
+        '\u{df}' | '\u{1e9e}' => "ss",
+        '\u{149}' => "\u{2bc}n",
+        '\u{17f}' => "s",
+        '\u{3c2}' => "\u{3c3}",
+        '\u{3d0}' => "\u{3b2}",
+        '\u{3d1}' => "\u{3b8}",
+        '\u{3d5}' => "\u{3c6}",
+        '\u{3d6}' => "\u{3c0}",
+        '\u{3f0}' => "\u{3ba}",
+        '\u{3f1}' => "\u{3c1}",
+        '\u{3f5}' => "\u{3b5}",
+        '\u{587}' => "\u{565}\u{582}",
+        '\u{1e9b}' => "\u{1e61}",
+        '\u{1fbe}' => "\u{3b9}",
+        '\u{fb00}' => "ff",
+        '\u{fb01}' => "fi",
+        '\u{fb02}' => "fl",
+        '\u{fb03}' => "ffi",
+        '\u{fb04}' => "ffl",
+        '\u{fb05}' | '\u{fb06}' => "st",
+        _ => return None,
+    };
+    Some(folded)
+}
+
+/// Folds `text`, keeping track of where each byte of the result came from.
+fn fold_mapped(text: &str, options: &MatchOptions) -> Folded {
+    let mut folded = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+
+    for (idx, c) in text.char_indices() {
+        let before = folded.len();
+        fold_char(c, options, &mut folded);
+        let origin = (idx, idx + c.len_utf8());
+        origins.extend((before..folded.len()).map(|_| origin));
+    }
+
+    Folded { text: folded, origins: origins }
+}
+
+/// Returns `text` folded according to `options`.
+pub fn fold(text: &str, options: &MatchOptions) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        fold_char(c, options, &mut folded);
+    }
+    folded
+}
+
+/// Tests lines with a matcher after folding them, reporting spans of the
+/// original lines.
+#[derive(Debug)]
+struct Folding {
+    inner: Matcher,
+    options: MatchOptions,
+}
+
+impl LineMatcher for Folding {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        let folded = fold_mapped(line, &self.options);
+        let mut spans: Vec<Span> = Vec::new();
+
+        for span in self.inner.spans(&folded.text) {
+            let (start, end) = folded.original_span(span);
+            match spans.last_mut() {
+                // case: matches within the expansion of a single character
+                Some(last) if start < last.1 => last.1 = end.max(last.1),
+                _ => spans.push((start, end)),
+            }
+        }
+
+        spans
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        self.inner.is_match(&fold(line, &self.options))
+    }
+}
+
+/// Builds a matcher which folds lines according to `options` before testing
+/// them with a matcher built from the folded `pattern`.
+///
+/// ### Parameters
+/// * `pattern`: the filter string
+/// * `options`: how `pattern` and lines are folded
+/// * `build`: builds the inner matcher from the folded pattern
+pub fn wrap<F>(pattern: &str, options: &MatchOptions, build: F) -> Result<Matcher, String>
+    where F: FnOnce(&str) -> Result<Matcher, String> {
+
+    if !options.folds() {
+        return build(pattern);
+    }
+
+    let inner = build(&fold(pattern, options))?;
+    Ok(Matcher::new(Folding { inner: inner, options: *options }))
+}
+
+#[cfg(test)]
+mod test {
+    use iter::matcher::Matcher;
+
+    use super::{MatchOptions, fold, wrap};
+
+    fn substring(pattern: &str, options: &MatchOptions) -> Matcher {
+        wrap(pattern, options, |p| Ok(Matcher::substring(p.to_owned()))).unwrap()
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let options = MatchOptions { ignore_case: true, ..MatchOptions::default() };
+        let matcher = substring("Error", &options);
+
+        assert_eq!(matcher.spans("ERROR: an error"), vec![(0, 5), (10, 15)]);
+        assert!(!substring("Error", &MatchOptions::default()).is_match("ERROR"));
+        assert_eq!(fold("MiXeD 123", &options), "mixed 123");
+    }
+
+    #[test]
+    fn test_ignore_accents() {
+        let options = MatchOptions { ignore_accents: true, ..MatchOptions::default() };
+        let matcher = substring("café", &options);
+
+        assert_eq!(matcher.spans("cafe, café, cafe\u{301}"), vec![(0, 4), (6, 11), (13, 17)]);
+        assert!(!matcher.is_match("CAFE"));
+        assert!(substring("cafe", &options).is_match("ｃａｆé"));
+
+        let options = MatchOptions { ignore_case: true, ignore_accents: true };
+        assert!(substring("ÉCOLE", &options).is_match("ecole"));
+        assert_eq!(fold("Ǆ æ", &options), "dz æ");
+    }
+
+    #[cfg(not(feature = "unicode-case"))]
+    #[test]
+    fn test_ascii_folding() {
+        let options = MatchOptions { ignore_case: true, ..MatchOptions::default() };
+        assert_eq!(fold("ÉCOLE", &options), "École");
+        assert!(!substring("straße", &options).is_match("STRASSE"));
+    }
+
+    #[cfg(feature = "unicode-case")]
+    #[test]
+    fn test_unicode_folding() {
+        let options = MatchOptions { ignore_case: true, ..MatchOptions::default() };
+        assert_eq!(fold("ÉCOLE", &options), "école");
+
+        let matcher = substring("straße", &options);
+        assert_eq!(matcher.spans("Die STRASSE, die Straße"), vec![(4, 11), (17, 24)]);
+
+        // part of an expanded character covers the whole character
+        assert_eq!(substring("s", &options).spans("aßb"), vec![(1, 3)]);
+    }
+}
@@ -0,0 +1,84 @@
+//! Fuzzy matcher, enabled by the `fuzzy` feature.
+
+use super::{fold, LineMatcher, MatchOptions, Matcher, MatcherKind, Span};
+
+pub const KIND: MatcherKind = MatcherKind {
+    name: "fuzzy",
+    description: "fuzzy",
+    build: build,
+};
+
+/// Builds a fuzzy matcher, which always ignores case; folding beforehand
+/// lets e.g. `ß` match `ss` when the `unicode-case` feature is enabled.
+fn build(pattern: &str, options: &MatchOptions) -> Result<Matcher, String> {
+    let mut options = *options;
+    options.ignore_case = true;
+
+    fold::wrap(pattern, &options, |pattern| {
+        Ok(Matcher::new(Fuzzy(pattern.chars().map(fold).collect())))
+    })
+}
+
+/// Returns `c` in lower case, for case-insensitive comparison.
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Matches lines which contain the characters of a pattern in order, though
+/// not necessarily adjacent, ignoring case; e.g. `cnref` matches
+/// `connection refused`.
+///
+/// Characters are matched at their earliest possible positions, and each
+/// run of adjacent matched characters forms a span.
+#[derive(Debug)]
+struct Fuzzy(Vec<char>);
+
+impl LineMatcher for Fuzzy {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        let mut spans: Vec<Span> = Vec::new();
+        let mut wanted = self.0.iter().peekable();
+
+        for (idx, c) in line.char_indices() {
+            match wanted.peek() {
+                Some(&&w) if w == fold(c) => (),
+                Some(_) => continue,
+                None => break,
+            }
+            wanted.next();
+
+            let end = idx + c.len_utf8();
+            match spans.last_mut() {
+                Some(span) if span.1 == idx => span.1 = end,
+                _ => spans.push((idx, end)),
+            }
+        }
+
+        if wanted.peek().is_some() {
+            return Vec::new();
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use iter::matcher::{MatchOptions, Matcher};
+
+    fn build(pattern: &str) -> Result<Matcher, String> {
+        super::build(pattern, &MatchOptions::default())
+    }
+
+    #[test]
+    fn test_fuzzy_spans() {
+        let matcher = build("cnref").unwrap();
+
+        assert_eq!(matcher.spans("Connection refused"), vec![(0, 1), (2, 3), (11, 14)]);
+        assert!(matcher.is_match("c n r e f"));
+        assert!(!matcher.is_match("refused connection"));
+
+        let matcher = build("ÉT").unwrap();
+        assert_eq!(matcher.spans("été"), vec![(0, 3)]);
+
+        assert!(!build("").unwrap().is_match("anything"));
+    }
+}
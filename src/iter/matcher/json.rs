@@ -0,0 +1,154 @@
+//! JSON field matcher, enabled by the `json-query` feature.
+
+use iter::structured::Scanner;
+
+use super::{fold, LineMatcher, MatchOptions, Matcher, MatcherKind, Span};
+
+pub const KIND: MatcherKind = MatcherKind {
+    name: "json",
+    description: "JSON field",
+    build: build,
+};
+
+fn build(pattern: &str, options: &MatchOptions) -> Result<Matcher, String> {
+    fold::wrap(pattern, options, build_query)
+}
+
+fn build_query(pattern: &str) -> Result<Matcher, String> {
+    let (key, value) = match pattern.find(['=', '~']) {
+        Some(i) => {
+            let value = pattern[i + 1..].to_owned();
+            let value = match pattern.as_bytes()[i] {
+                b'=' => Value::Equals(value),
+                _ => Value::Contains(value),
+            };
+            (&pattern[..i], Some(value))
+        },
+        None => (pattern, None),
+    };
+    let path: Vec<String> = key.split('.')
+        .map(|key| key.trim().to_owned())
+        .collect();
+
+    if path.iter().any(|key| key.is_empty()) {
+        return Err(format!("expected `KEY`, `KEY=VALUE` or `KEY~TEXT`; got `{}`", pattern));
+    }
+
+    Ok(Matcher::new(Query {
+        path: path,
+        value: value,
+    }))
+}
+
+/// Test of the value of a field.
+#[derive(Debug)]
+enum Value {
+    /// the value is the given one
+    Equals(String),
+    /// the value contains the given text
+    Contains(String),
+}
+
+/// Matches lines which are JSON objects having a given field, optionally
+/// with a given value.
+///
+/// Queries take the form `KEY`, `KEY=VALUE` or `KEY~TEXT`, where `KEY` may
+/// be a dotted path into nested objects, e.g. `request.method=GET`, and
+/// `KEY~TEXT` matches values containing `TEXT`, e.g. `msg~timeout`. String
+/// values are compared after unescaping; other values are compared as
+/// written. The span of a match is the field's value.
+#[derive(Debug)]
+struct Query {
+    path: Vec<String>,
+    value: Option<Value>,
+}
+
+impl LineMatcher for Query {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        let (span, string) = match lookup(line, &self.path) {
+            Some(found) => found,
+            None => return Vec::new(),
+        };
+
+        let text = string.as_ref().map_or(&line[span.0..span.1], |string| string.as_str());
+        let matched = match self.value {
+            None => true,
+            Some(Value::Equals(ref value)) => value == text,
+            Some(Value::Contains(ref value)) => text.contains(value.as_str()),
+        };
+
+        if matched && span.0 < span.1 { vec![span] } else { Vec::new() }
+    }
+}
+
+/// Returns the span of the value found at `path` within the JSON object
+/// `line`, along with its unescaped contents if it's a string.
+///
+/// The span of a string excludes its quotes.
+fn lookup(line: &str, path: &[String]) -> Option<(Span, Option<String>)> {
+    let mut scanner = Scanner::new(line);
+
+    for key in path {
+        if !scanner.byte(b'{') {
+            return None;
+        }
+
+        loop {
+            let name = scanner.string()?;
+            if !scanner.byte(b':') {
+                return None;
+            }
+            if name == *key {
+                break;
+            }
+            scanner.value()?;
+            if !scanner.byte(b',') {
+                return None;
+            }
+        }
+    }
+
+    scanner.skip_whitespace();
+    let start = scanner.pos;
+    if scanner.peek() == Some(b'"') {
+        let string = scanner.string()?;
+        Some(((start + 1, scanner.pos - 1), Some(string)))
+    } else {
+        scanner.value().map(|span| (span, None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use iter::matcher::{MatchOptions, Matcher};
+
+    const LINE: &str =
+        r#"{"level": "warn", "code": 503, "req": {"tags": ["a", "}"], "method": "GET"}}"#;
+
+    fn build(pattern: &str) -> Result<Matcher, String> {
+        super::build(pattern, &MatchOptions::default())
+    }
+
+    #[test]
+    fn test_json_spans() {
+        assert_eq!(build("level=warn").unwrap().spans(LINE), vec![(11, 15)]);
+        assert_eq!(build("code=503").unwrap().spans(LINE), vec![(26, 29)]);
+        assert_eq!(build("req.method=GET").unwrap().spans(LINE), vec![(70, 73)]);
+        assert!(build("req.tags").unwrap().is_match(LINE));
+        assert!(build("level").unwrap().is_match(LINE));
+
+        assert!(!build("level=error").unwrap().is_match(LINE));
+        assert!(!build("method").unwrap().is_match(LINE));
+        assert!(!build("level").unwrap().is_match("level: warn"));
+        assert!(build("msg=a\"b").unwrap().is_match(r#"{"msg": "a\"b"}"#));
+
+        assert_eq!(build("req.method~E").unwrap().spans(LINE), vec![(70, 73)]);
+        assert!(build("code~50").unwrap().is_match(LINE));
+        assert!(build("level~").unwrap().is_match(LINE));
+        assert!(!build("level~error").unwrap().is_match(LINE));
+        assert!(build("url=a~b").unwrap().is_match(r#"{"url": "a~b"}"#));
+
+        assert!(build("").is_err());
+        assert!(build("a..b=c").is_err());
+    }
+}
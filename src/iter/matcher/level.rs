@@ -0,0 +1,121 @@
+//! Log level matcher, which selects lines logged at a level at least as
+//! severe as a given one, e.g. `warn` for warnings, errors and worse.
+
+use super::{LineMatcher, MatchOptions, Matcher, MatcherKind, Span};
+
+pub const KIND: MatcherKind = MatcherKind {
+    name: "level",
+    description: "log level",
+    build: build,
+};
+
+/// Words naming log levels, in lower case, and their severities.
+const LEVELS: &[(&str, u8)] = &[
+    ("trace", 0),
+    ("debug", 1),
+    ("info", 2),
+    ("notice", 2),
+    ("warn", 3),
+    ("warning", 3),
+    ("err", 4),
+    ("error", 4),
+    ("crit", 5),
+    ("critical", 5),
+    ("fatal", 5),
+    ("panic", 5),
+    ("alert", 5),
+    ("emerg", 5),
+];
+
+/// Keys whose values name log levels, e.g. in `level=info`.
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity"];
+
+
+/// Builds a matcher of lines logged at the level named by `pattern` or a
+/// more severe one; levels are named without regard to case, so `options`
+/// don't apply.
+fn build(pattern: &str, _options: &MatchOptions) -> Result<Matcher, String> {
+    let severity = severity(pattern.trim()).ok_or_else(|| {
+        format!("unknown log level `{}`; expected one of: trace, debug, info, warn, error, fatal",
+                pattern.trim())
+    })?;
+    Ok(Matcher::new(MinLevel(severity)))
+}
+
+/// Returns the severity of the log level named by `word`, if it names one.
+fn severity(word: &str) -> Option<u8> {
+    LEVELS.iter()
+        .find(|&&(level, _)| word.eq_ignore_ascii_case(level))
+        .map(|&(_, severity)| severity)
+}
+
+/// Returns `true` if the text before a word ends with a key naming log
+/// levels and a separator, e.g. `level=` or `{"level": "`.
+fn after_level_key(before: &str) -> bool {
+    let before = before.trim_end_matches('"').trim_end();
+    before.strip_suffix('=').or_else(|| before.strip_suffix(':'))
+        .map(|key| key.trim_end().trim_end_matches('"'))
+        .is_some_and(|key| LEVEL_KEYS.iter().any(|k| key.ends_with(k)))
+}
+
+/// Returns the byte range and severity of the level at which `line` was
+/// logged: the first word naming a log level which is written in capitals,
+/// e.g. `ERROR`, or is the value of a key such as `level`.
+fn line_level(line: &str) -> Option<(Span, u8)> {
+    let bytes = line.as_bytes();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        if !bytes[pos].is_ascii_alphabetic() {
+            pos += 1;
+            continue;
+        }
+
+        let start = pos;
+        while pos < bytes.len() && bytes[pos].is_ascii_alphanumeric() {
+            pos += 1;
+        }
+        let word = &line[start..pos];
+        if word.bytes().all(|b| b.is_ascii_uppercase()) || after_level_key(&line[..start]) {
+            if let Some(severity) = severity(word) {
+                return Some(((start, pos), severity));
+            }
+        }
+    }
+    None
+}
+
+/// Matches lines logged at a level of at least the given severity, the
+/// span of a match being the word naming the level.
+#[derive(Debug)]
+struct MinLevel(u8);
+
+impl LineMatcher for MinLevel {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        match line_level(line) {
+            Some((span, severity)) if severity >= self.0 => vec![span],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::MatchOptions;
+    use super::build;
+
+    #[test]
+    fn test_min_level() {
+        let matcher = build("Warn", &MatchOptions::default()).unwrap();
+        assert_eq!(matcher.spans("2017-10-05 14:00:01 ERROR db: timeout"), vec![(20, 25)]);
+        assert!(matcher.is_match("[WARNING] disk 91% full"));
+        assert!(!matcher.is_match("INFO retrying after ERROR"));
+        assert!(!matcher.is_match("an error, in lower case"));
+        assert!(matcher.is_match("GET /health level=fatal"));
+        assert!(matcher.is_match(r#"{"level": "warn", "msg": "slow"}"#));
+        assert!(!matcher.is_match(r#"{"level": "debug", "msg": "ERROR"}"#));
+        assert!(!matcher.is_match("level info"));
+
+        assert!(build("loud", &MatchOptions::default()).is_err());
+    }
+}
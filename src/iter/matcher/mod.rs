@@ -0,0 +1,390 @@
+//! Means by which lines are tested against a filter string.
+//!
+//! Each kind of matcher is described by a `MatcherKind`, which knows how to
+//! build a `Matcher` from a filter string. Besides plain substrings, fields of
+//! lines in any format of the `parser` module, e.g. `status=404`, and log
+//! levels, e.g. `warn` for warnings and worse, are always available; other
+//! kinds are compiled in by cargo features and registered in `kinds()`:
+//!
+//! * `regex` (default): regular expressions
+//! * `fuzzy`: case-insensitive subsequences
+//! * `json-query`: fields of JSON objects
+//! * `time-range`: ranges of leading timestamps
+//!
+//! Case and accents are ignored, when asked, by folding filter strings and
+//! lines alike before they're compared; only ASCII letters are folded to
+//! lower case unless the `unicode-case` feature is enabled. The `full` feature enables all of the
+//! above.
+
+use std::fmt;
+use std::sync::Arc;
+
+mod accents;
+mod field;
+mod fold;
+mod level;
+#[cfg(feature = "fuzzy")]
+mod fuzzy;
+#[cfg(feature = "json-query")]
+mod json;
+#[cfg(feature = "regex")]
+mod pattern;
+#[cfg(feature = "time-range")]
+mod time_range;
+
+pub use self::fold::MatchOptions;
+pub use self::field::KIND as FIELD;
+pub use self::level::KIND as LEVEL;
+
+/// Byte range `(start, end)` of a match within a line.
+pub type Span = (usize, usize);
+
+/// Test applied to lines by a `Matcher`.
+pub trait LineMatcher: fmt::Debug + Send + Sync {
+    /// Returns the byte ranges of all non-overlapping, non-empty matches
+    /// within `line`, in ascending order.
+    fn spans(&self, line: &str) -> Vec<Span>;
+
+    /// Returns `true` if `line` contains at least one match.
+    fn is_match(&self, line: &str) -> bool {
+        !self.spans(line).is_empty()
+    }
+}
+
+/// Shareable handle to a `LineMatcher`.
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    inner: Arc<dyn LineMatcher>,
+}
+
+impl Matcher {
+    /// Wraps a `LineMatcher`.
+    pub fn new<M: LineMatcher + 'static>(matcher: M) -> Matcher {
+        Matcher { inner: Arc::new(matcher) }
+    }
+
+    /// Returns a matcher of lines which contain `needle` verbatim.
+    pub fn substring(needle: String) -> Matcher {
+        Matcher::new(Substring(needle))
+    }
+
+    /// Returns a matcher of lines which match any of `matchers`.
+    pub fn any(matchers: Vec<Matcher>) -> Matcher {
+        Matcher::new(AnyOf(matchers))
+    }
+
+    /// Returns a matcher of lines which match all of `matchers`.
+    pub fn all(matchers: Vec<Matcher>) -> Matcher {
+        Matcher::new(AllOf(matchers))
+    }
+
+    /// Returns a matcher of words, i.e. runs of alphanumeric characters and
+    /// underscores.
+    pub fn word() -> Matcher {
+        Matcher::new(Word)
+    }
+
+    /// Builds a matcher of the named kind from a filter string.
+    ///
+    /// ### Parameters
+    /// * `kind`: name of a registered kind, e.g. `regex`
+    /// * `pattern`: filter string, interpreted according to `kind`
+    /// * `options`: how `pattern` is compared with lines
+    pub fn build(kind: &str, pattern: &str, options: &MatchOptions) -> Result<Matcher, String> {
+        (self::kind(kind)?.build)(pattern, options)
+    }
+
+    /// Returns `true` if `line` contains at least one match.
+    pub fn is_match(&self, line: &str) -> bool {
+        self.inner.is_match(line)
+    }
+
+    /// Returns the byte ranges of all non-overlapping, non-empty matches
+    /// within `line`, in ascending order.
+    pub fn spans(&self, line: &str) -> Vec<Span> {
+        self.inner.spans(line)
+    }
+}
+
+/// Named way of interpreting filter strings.
+#[derive(Clone, Copy, Debug)]
+pub struct MatcherKind {
+    /// name by which the kind is selected, e.g. with `--matcher`
+    pub name: &'static str,
+    /// how filter strings are interpreted, for display to the user
+    pub description: &'static str,
+    /// builds a matcher from a filter string
+    pub build: fn(&str, &MatchOptions) -> Result<Matcher, String>,
+}
+
+/// Kind which matches filter strings verbatim; always available.
+pub const SUBSTRING: MatcherKind = MatcherKind {
+    name: "substring",
+    description: "substring",
+    build: build_substring,
+};
+
+fn build_substring(pattern: &str, options: &MatchOptions) -> Result<Matcher, String> {
+    fold::wrap(pattern, options, |pattern| Ok(Matcher::substring(pattern.to_owned())))
+}
+
+/// Returns the kinds of matcher compiled into this build, beginning with
+/// `SUBSTRING`.
+pub fn kinds() -> Vec<MatcherKind> {
+    vec![
+        SUBSTRING,
+        #[cfg(feature = "regex")]
+        pattern::KIND,
+        #[cfg(feature = "fuzzy")]
+        fuzzy::KIND,
+        #[cfg(feature = "json-query")]
+        json::KIND,
+        #[cfg(feature = "time-range")]
+        time_range::KIND,
+        field::KIND,
+        LEVEL,
+    ]
+}
+
+/// Returns the kind of matcher with the given name.
+pub fn kind(name: &str) -> Result<MatcherKind, String> {
+    let kinds = kinds();
+    kinds.iter()
+        .find(|kind| kind.name == name)
+        .cloned()
+        .ok_or_else(|| {
+            let names: Vec<_> = kinds.iter().map(|kind| kind.name).collect();
+            format!("unknown matcher `{}`; expected one of: {}", name, names.join(", "))
+        })
+}
+
+/// Returns the kind registered after `current`, wrapping around to the
+/// first.
+pub fn next_kind(current: &MatcherKind) -> MatcherKind {
+    let kinds = kinds();
+    let idx = kinds.iter().position(|kind| kind.name == current.name).unwrap_or(0);
+    kinds[(idx + 1) % kinds.len()]
+}
+
+/// Matches lines which contain a string verbatim.
+#[derive(Debug)]
+struct Substring(String);
+
+impl LineMatcher for Substring {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        if self.0.is_empty() {
+            return Vec::new();
+        }
+
+        line.match_indices(self.0.as_str())
+            .map(|(start, matched)| (start, start + matched.len()))
+            .collect()
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        line.contains(self.0.as_str())
+    }
+}
+
+/// Matches lines which match any of several matchers.
+#[derive(Debug)]
+struct AnyOf(Vec<Matcher>);
+
+impl LineMatcher for AnyOf {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        merge(self.0.iter().flat_map(|matcher| matcher.spans(line)).collect())
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        self.0.iter().any(|matcher| matcher.is_match(line))
+    }
+}
+
+/// Matches lines which match all of several matchers, the spans of a match
+/// being those of every matcher.
+#[derive(Debug)]
+struct AllOf(Vec<Matcher>);
+
+impl LineMatcher for AllOf {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        let mut all = Vec::new();
+        for matcher in &self.0 {
+            let spans = matcher.spans(line);
+            if spans.is_empty() {
+                return Vec::new();
+            }
+            all.extend(spans);
+        }
+        merge(all)
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        self.0.iter().all(|matcher| matcher.is_match(line))
+    }
+}
+
+/// Sorts the matches of different matchers, merging those which overlap.
+fn merge(mut all: Vec<Span>) -> Vec<Span> {
+    all.sort();
+
+    let mut spans: Vec<Span> = Vec::new();
+    for (start, end) in all {
+        match spans.last_mut() {
+            Some(last) if start < last.1 => last.1 = end.max(last.1),
+            _ => spans.push((start, end)),
+        }
+    }
+    spans
+}
+
+/// Matches runs of alphanumeric characters and underscores.
+#[derive(Debug)]
+struct Word;
+
+impl LineMatcher for Word {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        let mut spans: Vec<Span> = Vec::new();
+        let mut start = None;
+
+        for (idx, c) in line.char_indices() {
+            match (c.is_alphanumeric() || c == '_', start) {
+                (true, None) => start = Some(idx),
+                (false, Some(s)) => {
+                    spans.push((s, idx));
+                    start = None;
+                },
+                _ => (),
+            }
+        }
+
+        if let Some(s) = start {
+            spans.push((s, line.len()));
+        }
+
+        spans
+    }
+}
+
+/// Splits `line` into consecutive fragments, each paired with `true` if it
+/// lies within one of `spans`.
+///
+/// ### Parameters
+/// * `line`: the text to split
+/// * `spans`: ascending, non-overlapping byte ranges within `line`; ranges
+///   extending past the end of `line` are clipped
+pub fn split_spans<'a>(line: &'a str, spans: &[Span]) -> Vec<(&'a str, bool)> {
+    let mut frags = Vec::new();
+    let mut pos = 0;
+
+    for &(start, end) in spans {
+        let end = if end > line.len() { line.len() } else { end };
+        if start < pos || start >= end {
+            continue;
+        }
+
+        if start > pos {
+            frags.push((&line[pos..start], false));
+        }
+        frags.push((&line[start..end], true));
+        pos = end;
+    }
+
+    if pos < line.len() {
+        frags.push((&line[pos..], false));
+    }
+
+    frags
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MatchOptions, Matcher, LEVEL, SUBSTRING, kind, kinds, next_kind, split_spans};
+
+    #[test]
+    fn test_substring_spans() {
+        let matcher = Matcher::substring("ab".to_owned());
+
+        assert_eq!(matcher.spans("ab"), vec![(0, 2)]);
+        assert_eq!(matcher.spans("abab"), vec![(0, 2), (2, 4)]);
+        assert_eq!(matcher.spans("xabyab"), vec![(1, 3), (4, 6)]);
+        assert_eq!(matcher.spans("aaa"), vec![]);
+        assert!(matcher.is_match("xaby"));
+        assert!(!matcher.is_match("xy"));
+
+        let matcher = Matcher::substring("aa".to_owned());
+        assert_eq!(matcher.spans("aaa"), vec![(0, 2)]);
+
+        let matcher = Matcher::substring("".to_owned());
+        assert_eq!(matcher.spans("abc"), vec![]);
+    }
+
+    #[test]
+    fn test_any_spans() {
+        let matcher = Matcher::any(vec![
+            Matcher::substring("cd".to_owned()),
+            Matcher::substring("ab".to_owned()),
+            Matcher::substring("bc".to_owned()),
+        ]);
+
+        assert_eq!(matcher.spans("abcd xab"), vec![(0, 4), (6, 8)]);
+        assert!(matcher.is_match("xcd"));
+        assert!(!matcher.is_match("ac"));
+        assert!(!Matcher::any(Vec::new()).is_match("abc"));
+    }
+
+    #[test]
+    fn test_all_spans() {
+        let matcher = Matcher::all(vec![
+            Matcher::substring("ab".to_owned()),
+            Matcher::substring("bc".to_owned()),
+        ]);
+
+        assert_eq!(matcher.spans("abc ab"), vec![(0, 3), (4, 6)]);
+        assert!(matcher.is_match("ab bc"));
+        assert!(!matcher.is_match("ab"));
+        assert_eq!(matcher.spans("ab"), vec![]);
+    }
+
+    #[test]
+    fn test_word_spans() {
+        let matcher = Matcher::word();
+
+        assert_eq!(matcher.spans("héllo wörld, id_1"), vec![(0, 6), (7, 13), (15, 19)]);
+        assert_eq!(matcher.spans(" -- "), vec![]);
+        assert!(matcher.is_match("a"));
+    }
+
+    #[test]
+    fn test_registry() {
+        assert_eq!(kinds()[0].name, SUBSTRING.name);
+        assert_eq!(kind("substring").unwrap().name, "substring");
+        assert!(kind("bogus").is_err());
+        assert_eq!(kind("level").unwrap().name, LEVEL.name);
+        let options = MatchOptions::default();
+        assert!(Matcher::build("bogus", "x", &options).is_err());
+        assert!(Matcher::build("substring", "x", &options).unwrap().is_match("xyz"));
+        assert!(!Matcher::build("substring", "X", &options).unwrap().is_match("xyz"));
+
+        let options = MatchOptions { ignore_case: true, ..MatchOptions::default() };
+        assert!(Matcher::build("substring", "X", &options).unwrap().is_match("xyz"));
+
+        // cycling through the kinds visits each once before wrapping around
+        let mut current = SUBSTRING;
+        for expected in kinds().iter().skip(1).chain(Some(&SUBSTRING)) {
+            current = next_kind(&current);
+            assert_eq!(current.name, expected.name);
+        }
+    }
+
+    #[test]
+    fn test_split_spans() {
+        assert_eq!(split_spans("abcdef", &[]), vec![("abcdef", false)]);
+        assert_eq!(split_spans("abcdef", &[(0, 2), (4, 6)]),
+                   vec![("ab", true), ("cd", false), ("ef", true)]);
+        assert_eq!(split_spans("abcdef", &[(1, 2)]),
+                   vec![("a", false), ("b", true), ("cdef", false)]);
+        assert_eq!(split_spans("abc", &[(1, 9)]), vec![("a", false), ("bc", true)]);
+        assert_eq!(split_spans("abc", &[(5, 9)]), vec![("abc", false)]);
+        assert_eq!(split_spans("", &[]), vec![]);
+    }
+}
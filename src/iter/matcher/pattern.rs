@@ -0,0 +1,82 @@
+//! Regular expression matcher, enabled by the `regex` feature.
+
+use regex::Regex;
+
+use super::{fold, LineMatcher, MatchOptions, Matcher, MatcherKind, Span};
+
+pub const KIND: MatcherKind = MatcherKind {
+    name: "regex",
+    description: "regular expression",
+    build: build,
+};
+
+/// Compiles `pattern`, ignoring case and accents if asked.
+///
+/// Case is ignored by the regex engine itself, which folds each character to
+/// a single character; unlike other kinds, e.g. `ß` doesn't match `SS`. So
+/// that escapes such as `\W` keep their meaning, only accents are folded out
+/// of the pattern.
+fn build(pattern: &str, options: &MatchOptions) -> Result<Matcher, String> {
+    let mut accents = *options;
+    accents.ignore_case = false;
+
+    fold::wrap(pattern, &accents, |pattern| {
+        let pattern = if options.ignore_case {
+            format!("(?i){}", pattern)
+        } else {
+            pattern.to_owned()
+        };
+
+        Regex::new(&pattern)
+            .map(|regex| Matcher::new(Pattern(regex)))
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Matches lines against a regular expression.
+#[derive(Debug)]
+struct Pattern(Regex);
+
+impl LineMatcher for Pattern {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        self.0.find_iter(line)
+            .map(|m| (m.start(), m.end()))
+            .filter(|&(start, end)| start < end)
+            .collect()
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        self.0.is_match(line)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use iter::matcher::MatchOptions;
+
+    use super::build;
+
+    #[test]
+    fn test_regex_spans() {
+        let build = |pattern| build(pattern, &MatchOptions::default());
+        let matcher = build("a+b?").unwrap();
+
+        assert_eq!(matcher.spans("aab xa ab"), vec![(0, 3), (5, 6), (7, 9)]);
+        assert!(matcher.is_match("xa"));
+        assert!(!matcher.is_match("xyz"));
+
+        let matcher = build("x*").unwrap();
+        assert_eq!(matcher.spans("axxb"), vec![(1, 3)]);
+        assert!(matcher.is_match("ab"));
+
+        assert!(build("(").is_err());
+
+        let options = MatchOptions { ignore_case: true, ..MatchOptions::default() };
+        let matcher = super::build("A+", &options).unwrap();
+        assert_eq!(matcher.spans("xaAa"), vec![(1, 4)]);
+
+        let options = MatchOptions { ignore_case: true, ignore_accents: true };
+        let matcher = super::build(r"\WÉt[eé]\W", &options).unwrap();
+        assert_eq!(matcher.spans("un été "), vec![(2, 9)]);
+    }
+}
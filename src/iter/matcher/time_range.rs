@@ -0,0 +1,92 @@
+//! Time range matcher, enabled by the `time-range` feature.
+
+use timestamp::{self, Timestamp};
+
+use super::{LineMatcher, MatchOptions, Matcher, MatcherKind, Span};
+
+pub const KIND: MatcherKind = MatcherKind {
+    name: "time",
+    description: "time range",
+    build: build,
+};
+
+/// Parses a range; timestamps have no case, so `_options` doesn't apply.
+fn build(pattern: &str, _options: &MatchOptions) -> Result<Matcher, String> {
+    let mut bounds = pattern.splitn(2, "..");
+    let from = bounds.next().unwrap_or("");
+    let to = bounds.next()
+        .ok_or(format!("expected `FROM..TO`; got `{}`", pattern))?;
+
+    Ok(Matcher::new(TimeRange {
+        from: parse_bound(from)?,
+        to: parse_bound(to)?,
+    }))
+}
+
+/// Parses one end of a range, which may be omitted.
+fn parse_bound(bound: &str) -> Result<Option<i64>, String> {
+    let bound = bound.trim();
+    if bound.is_empty() {
+        return Ok(None);
+    }
+
+    match timestamp::parse_leading(bound) {
+        Some(Timestamp { millis, end, .. }) if end == bound.len() => Ok(Some(millis)),
+        _ => Err(format!("invalid timestamp `{}`", bound)),
+    }
+}
+
+/// Matches lines whose leading timestamp lies within a range.
+///
+/// Ranges take the form `FROM..TO` and include `FROM` but not `TO`; either
+/// may be omitted to leave the range open at that end, e.g.
+/// `2017-10-05 14:00:00..`. The span of a match is the timestamp.
+#[derive(Debug)]
+struct TimeRange {
+    /// earliest matching time, in milliseconds since the Unix epoch
+    from: Option<i64>,
+    /// time just past the latest matching time
+    to: Option<i64>,
+}
+
+impl LineMatcher for TimeRange {
+    fn spans(&self, line: &str) -> Vec<Span> {
+        let ts = match timestamp::parse_leading(line) {
+            Some(ts) => ts,
+            None => return Vec::new(),
+        };
+
+        let after_from = self.from.is_none_or(|from| ts.millis >= from);
+        let before_to = self.to.is_none_or(|to| ts.millis < to);
+
+        if after_from && before_to { vec![(ts.start, ts.end)] } else { Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use iter::matcher::{MatchOptions, Matcher};
+
+    fn build(pattern: &str) -> Result<Matcher, String> {
+        super::build(pattern, &MatchOptions::default())
+    }
+
+    #[test]
+    fn test_time_range_spans() {
+        let matcher = build("2017-10-05 14:00:00..2017-10-05 15:00:00").unwrap();
+
+        assert_eq!(matcher.spans("2017-10-05 14:00:00 start"), vec![(0, 19)]);
+        assert_eq!(matcher.spans("[2017-10-05T14:59:59Z] late"), vec![(1, 21)]);
+        assert!(!matcher.is_match("2017-10-05 15:00:00 end"));
+        assert!(!matcher.is_match("2017-10-05 13:59:59 early"));
+        assert!(!matcher.is_match("no timestamp"));
+
+        let matcher = build("Oct  5 14:00:00..").unwrap();
+        assert!(matcher.is_match("Oct  6 00:00:00 host sshd[1]: hello"));
+        assert!(!matcher.is_match("Oct  4 23:59:59 host sshd[1]: hello"));
+
+        assert!(build("..").unwrap().is_match("2017-10-05 14:00:00"));
+        assert!(build("2017-10-05 14:00:00").is_err());
+        assert!(build("yesterday..").is_err());
+    }
+}
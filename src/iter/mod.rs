@@ -1,7 +1,36 @@
+mod between;
+#[cfg(feature = "evtx")]
+mod evtx_source;
 mod line_buffer;
+mod line_cache;
+mod line_source;
+mod mapped_file;
+mod match_index;
+pub mod parser;
+mod position;
+#[cfg(test)]
+mod schedule;
+mod sections;
+mod structured;
+mod time_index;
 mod iter;
+pub mod matcher;
 mod context_buffer;
 mod window_buffer;
 
-pub use self::iter::{ContextLine, FilteredLine, FilterPredicate, NumberedLine};
-pub use self::window_buffer::WindowBuffer;
+pub use self::between::Between;
+pub use self::context_buffer::ContextBuffer;
+#[cfg(feature = "evtx")]
+pub use self::evtx_source::EvtxSource;
+pub use self::iter::{ContextLine, DisplayNumbered, FilteredLine, FilterPredicate, FilterSource,
+                     LineRange, NumberedLine};
+pub use self::line_buffer::{Input, IterDirection, LineBuffer};
+pub use self::line_source::{FileSource, LineSource, StdinSource, VecSource};
+pub use self::mapped_file::MappedFile;
+pub use self::match_index::MatchIndex;
+pub use self::matcher::{MatchOptions, Matcher, MatcherKind, split_spans};
+pub use self::parser::Format;
+pub use self::sections::{Marker, Sections};
+pub use self::structured::{Field, StructuredLine};
+pub use self::time_index::TimeIndex;
+pub use self::window_buffer::{Search, Totals, WindowBuffer};
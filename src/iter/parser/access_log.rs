@@ -0,0 +1,169 @@
+//! Lines of web server access logs in the common or combined log format, as
+//! written by Apache and nginx, e.g.
+//! `10.0.0.1 - ann [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 200 2326`,
+//! followed in the combined format by the referer and user agent, quoted, and
+//! optionally by the latency of the request, e.g. `0.532` as logged by nginx's
+//! `$request_time`.
+
+use iter::structured::{Field, StructuredLine};
+
+use super::Format;
+
+pub const FORMAT: Format = Format {
+    name: "access",
+    description: "access log",
+    parse: parse,
+    columns: false,
+};
+
+/// Names of the fields, in the order they're written; the referer and agent
+/// are written only in the combined format, and the latency only after them.
+const FIELDS: &[&str] = &["host", "ident", "user", "time", "request", "status", "bytes",
+                          "referer", "agent", "latency"];
+
+/// Names of the fields into which the request is split, e.g. `GET`, `/` and
+/// `HTTP/1.1`.
+const REQUEST_FIELDS: &[&str] = &["method", "path", "protocol"];
+
+
+/// Parses a line of an access log in the common or combined log format.
+///
+/// The spans of the time and of quoted fields exclude their brackets and
+/// quotes. A request of three words is also split into the fields in
+/// `REQUEST_FIELDS`, which follow it.
+fn parse(line: &str) -> Option<StructuredLine> {
+    let mut spans = Vec::with_capacity(FIELDS.len());
+    let mut pos = 0;
+
+    while pos < line.len() && spans.len() < FIELDS.len() {
+        let (span, end) = match line.as_bytes()[pos] {
+            b'[' => {
+                let len = line[pos..].find(']')?;
+                ((pos + 1, pos + len), pos + len + 1)
+            },
+            b'"' => {
+                let len = quoted_len(&line[pos + 1..])?;
+                ((pos + 1, pos + 1 + len), pos + len + 2)
+            },
+            _ => {
+                let len = line[pos..].find(' ').unwrap_or(line.len() - pos);
+                ((pos, pos + len), pos + len)
+            },
+        };
+        spans.push(span);
+        pos = end;
+        if pos < line.len() {
+            if !line[pos..].starts_with(' ') {
+                return None;
+            }
+            pos += 1;
+        }
+    }
+
+    let text = |i: usize| &line[spans[i].0..spans[i].1];
+    let valid = [7, 9, 10].contains(&spans.len()) && pos == line.len()
+        && line[..spans[3].0].ends_with('[') && line[spans[4].1..].starts_with('"')
+        && is_number(text(5)) && (is_number(text(6)) || text(6) == "-")
+        && (spans.len() < 10 || is_decimal(text(9)));
+    if !valid {
+        return None;
+    }
+
+    let mut fields: Vec<Field> = FIELDS.iter().zip(spans.iter())
+        .map(|(path, &span)| field(line, path, span))
+        .collect();
+
+    let (start, end) = spans[4];
+    let words: Vec<&str> = line[start..end].split(' ').collect();
+    if words.len() == REQUEST_FIELDS.len() && words.iter().all(|word| !word.is_empty()) {
+        let mut pos = start;
+        for (i, (path, word)) in REQUEST_FIELDS.iter().zip(words).enumerate() {
+            fields.insert(5 + i, field(line, path, (pos, pos + word.len())));
+            pos += word.len() + 1;
+        }
+    }
+    Some(StructuredLine::new(fields))
+}
+
+fn field(line: &str, path: &str, span: (usize, usize)) -> Field {
+    Field {
+        path: path.to_owned(),
+        span: span,
+        value: line[span.0..span.1].replace("\\\"", "\""),
+    }
+}
+
+/// Returns the length of the text before the closing quote of a string
+/// beginning `text`, within which quotes are escaped by backslashes.
+fn quoted_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => return Some(pos),
+            b'\\' => pos += 2,
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+fn is_number(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Returns `true` if `text` is a number which may have a fractional part,
+/// e.g. `0.532`.
+fn is_decimal(text: &str) -> bool {
+    let mut parts = text.splitn(2, '.');
+    parts.next().is_some_and(is_number) && parts.next().is_none_or(is_number)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let line = concat!(r#"10.0.0.1 - ann [10/Oct/2000:13:55:36 -0700] "#,
+                           r#""GET /a?q=\"x\" HTTP/1.1" 200 2326 "#,
+                           r#""http://example.com/" "Mozilla/5.0 (X11)""#);
+        let structured = parse(line).unwrap();
+        let fields: Vec<(&str, &str)> = structured.fields().iter()
+            .map(|field| (field.path.as_str(), field.value.as_str()))
+            .collect();
+        assert_eq!(fields, vec![
+            ("host", "10.0.0.1"),
+            ("ident", "-"),
+            ("user", "ann"),
+            ("time", "10/Oct/2000:13:55:36 -0700"),
+            ("request", "GET /a?q=\"x\" HTTP/1.1"),
+            ("method", "GET"),
+            ("path", "/a?q=\"x\""),
+            ("protocol", "HTTP/1.1"),
+            ("status", "200"),
+            ("bytes", "2326"),
+            ("referer", "http://example.com/"),
+            ("agent", "Mozilla/5.0 (X11)"),
+        ]);
+        assert_eq!(structured.get("time").map(|field| field.span), Some((16, 42)));
+        assert_eq!(structured.get("status").map(|field| field.span), Some((70, 73)));
+        assert_eq!(structured.get("path").map(|field| field.span), Some((49, 59)));
+
+        let common = r#"::1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 304 -"#;
+        assert_eq!(parse(common).map(|s| s.fields().len()), Some(10));
+        let timed = concat!(r#"::1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 5 "#,
+                            r#""-" "curl" 0.532"#);
+        assert_eq!(parse(timed).unwrap().get("latency").map(|field| field.value.as_str()),
+                   Some("0.532"));
+        let invalid = r#"::1 - - [10/Oct/2000:13:55:36 -0700] "-" 400 0 "-" "-" 1.2.3"#;
+        assert_eq!(parse(invalid), None);
+        let unsplit = r#"::1 - - [10/Oct/2000:13:55:36 -0700] "-" 400 0 "-" "-" 12"#;
+        assert_eq!(parse(unsplit).map(|s| s.fields().len()), Some(10));
+
+        assert_eq!(parse(r#"::1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" OK -"#), None);
+        assert_eq!(parse(r#"::1 - - 10/Oct/2000 "GET / HTTP/1.0" 200 5"#), None);
+        assert_eq!(parse(r#"::1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0 200 5"#), None);
+        assert_eq!(parse("level=warn"), None);
+    }
+}
@@ -0,0 +1,82 @@
+//! Comma-separated values, whose fields are named by the number of their
+//! column, counted from 1, as by the `sort` command of the pager.
+
+use iter::structured::{Field, StructuredLine};
+
+use super::Format;
+
+pub const FORMAT: Format = Format {
+    name: "csv",
+    description: "comma-separated values",
+    parse: parse,
+    columns: true,
+};
+
+
+/// Parses a line of at least two comma-separated values, which may be
+/// quoted, with quotes within them doubled.
+///
+/// The span of a quoted value excludes its quotes; values aren't trimmed.
+fn parse(line: &str) -> Option<StructuredLine> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let (span, value) = if line[pos..].starts_with('"') {
+            let start = pos + 1;
+            let mut value = String::new();
+            pos = start;
+            loop {
+                let c = line[pos..].chars().next()?;
+                pos += c.len_utf8();
+                if c != '"' {
+                    value.push(c);
+                } else if line[pos..].starts_with('"') {
+                    value.push('"');
+                    pos += 1;
+                } else {
+                    break;
+                }
+            }
+            if !(pos == line.len() || line[pos..].starts_with(',')) {
+                return None;
+            }
+            ((start, pos - 1), value)
+        } else {
+            let end = line[pos..].find(',').map_or(line.len(), |len| pos + len);
+            let span = (pos, end);
+            pos = end;
+            (span, line[span.0..span.1].to_owned())
+        };
+        fields.push(Field { path: (fields.len() + 1).to_string(), span: span, value: value });
+
+        if pos == line.len() {
+            break;
+        }
+        pos += 1;
+    }
+
+    if fields.len() < 2 { None } else { Some(StructuredLine::new(fields)) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let line = r#"7,"Smith, ""Al""",,x"#;
+        let structured = parse(line).unwrap();
+        let fields: Vec<(&str, &str)> = structured.fields().iter()
+            .map(|field| (field.path.as_str(), field.value.as_str()))
+            .collect();
+        assert_eq!(fields, vec![("1", "7"), ("2", "Smith, \"Al\""), ("3", ""), ("4", "x")]);
+        assert_eq!(structured.get("2").map(|field| field.span), Some((3, 16)));
+        assert_eq!(structured.get("4").map(|field| field.span), Some((19, 20)));
+
+        assert_eq!(parse("a,").map(|s| s.fields().len()), Some(2));
+        assert_eq!(parse("no commas"), None);
+        assert_eq!(parse(r#""unterminated,x"#), None);
+        assert_eq!(parse(r#""a"b,c"#), None);
+    }
+}
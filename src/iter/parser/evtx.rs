@@ -0,0 +1,70 @@
+//! Records of Windows event logs as read by `EvtxSource`, each a timestamp
+//! followed by logfmt fields, e.g. `2024-05-01T09:30:00.000Z record=7
+//! provider="Service Control Manager" level=info event_id=7036 msg="..."`.
+
+use iter::structured::{Field, StructuredLine};
+use timestamp;
+
+use super::{Format, LOGFMT};
+
+pub const FORMAT: Format = Format {
+    name: "evtx",
+    description: "Windows event log",
+    parse: parse,
+    columns: false,
+};
+
+
+/// Parses a record into the field `time`, spanning its timestamp, followed
+/// by the logfmt fields after it, of which `event_id` must be one.
+fn parse(line: &str) -> Option<StructuredLine> {
+    let time = timestamp::parse_leading(line).filter(|time| time.start == 0)?;
+    if !line[time.end..].starts_with(' ') {
+        return None;
+    }
+
+    let rest = time.end + 1;
+    let pairs = (LOGFMT.parse)(&line[rest..])?;
+    pairs.get("event_id")?;
+
+    let mut fields = vec![Field {
+        path: "time".to_owned(),
+        span: (0, time.end),
+        value: line[..time.end].to_owned(),
+    }];
+    fields.extend(pairs.fields().iter().map(|field| Field {
+        path: field.path.clone(),
+        span: (field.span.0 + rest, field.span.1 + rest),
+        value: field.value.clone(),
+    }));
+    Some(StructuredLine::new(fields))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let line = concat!("2024-05-01T09:30:00.000Z record=7 provider=\"Service Control ",
+                           "Manager\" level=info event_id=7036 msg=\"param1: Print Spooler\"");
+        let structured = parse(line).unwrap();
+        let fields: Vec<(&str, &str)> = structured.fields().iter()
+            .map(|field| (field.path.as_str(), field.value.as_str()))
+            .collect();
+        assert_eq!(fields, vec![
+            ("time", "2024-05-01T09:30:00.000Z"),
+            ("record", "7"),
+            ("provider", "Service Control Manager"),
+            ("level", "info"),
+            ("event_id", "7036"),
+            ("msg", "param1: Print Spooler"),
+        ]);
+        assert_eq!(structured.get("level").map(|field| field.span), Some((75, 79)));
+
+        assert_eq!(parse("2024-05-01T09:30:00Z level=info msg=started"), None);
+        assert_eq!(parse("level=info event_id=1"), None);
+        assert_eq!(parse("[2024-05-01T09:30:00Z] event_id=1"), None);
+        assert_eq!(parse("2024-05-01T09:30:00Z"), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! logfmt lines, e.g. `level=warn msg="slow request" ms=950`.
+
+use iter::structured::{Field, StructuredLine};
+
+use super::Format;
+
+pub const FORMAT: Format = Format {
+    name: "logfmt",
+    description: "logfmt",
+    parse: parse,
+    columns: false,
+};
+
+
+/// Parses a line of whitespace-separated `KEY=VALUE` pairs, whose values may
+/// be quoted, returning `None` unless every word of the line is a pair.
+///
+/// The span of a quoted value excludes its quotes.
+fn parse(line: &str) -> Option<StructuredLine> {
+    let bytes = line.as_bytes();
+    let mut fields = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos == bytes.len() {
+            break;
+        }
+
+        let key_start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace()
+            && bytes[pos] != b'=' && bytes[pos] != b'"' {
+            pos += 1;
+        }
+        if pos == key_start || bytes.get(pos) != Some(&b'=') {
+            return None;
+        }
+        let path = line[key_start..pos].to_owned();
+        pos += 1;
+
+        let (span, value) = if bytes.get(pos) == Some(&b'"') {
+            let start = pos + 1;
+            let mut value = String::new();
+            pos = start;
+            loop {
+                let c = line[pos..].chars().next()?;
+                match c {
+                    '"' => break,
+                    '\\' => {
+                        let escaped = line[pos + 1..].chars().next()?;
+                        value.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            other => other,
+                        });
+                        pos += 1 + escaped.len_utf8();
+                    },
+                    other => {
+                        value.push(other);
+                        pos += other.len_utf8();
+                    },
+                }
+            }
+            pos += 1;
+            ((start, pos - 1), value)
+        } else {
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            ((start, pos), line[start..pos].to_owned())
+        };
+        if pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+            return None;
+        }
+        fields.push(Field { path: path, span: span, value: value });
+    }
+
+    if fields.is_empty() { None } else { Some(StructuredLine::new(fields)) }
+}
+
+#[cfg(test)]
+mod test {
+    use iter::structured::Field;
+
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let line = r#"level=warn msg="slow \"GET\"" ms=950 empty="#;
+        let structured = parse(line).unwrap();
+        let fields: Vec<(&str, &str)> = structured.fields().iter()
+            .map(|field| (field.path.as_str(), field.value.as_str()))
+            .collect();
+        assert_eq!(fields, vec![("level", "warn"), ("msg", "slow \"GET\""), ("ms", "950"),
+                                ("empty", "")]);
+        assert_eq!(structured.get("msg"), Some(&Field {
+            path: "msg".to_owned(),
+            span: (16, 28),
+            value: "slow \"GET\"".to_owned(),
+        }));
+        assert_eq!(structured.get("ms").map(|field| field.span), Some((33, 36)));
+
+        assert_eq!(parse("level=warn slow request"), None);
+        assert_eq!(parse(r#"msg="unterminated"#), None);
+        assert_eq!(parse(r#"msg="a"b"#), None);
+        assert_eq!(parse("=x"), None);
+        assert_eq!(parse("  "), None);
+    }
+}
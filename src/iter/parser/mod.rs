@@ -0,0 +1,172 @@
+//! Formats of lines which are parsed into fields, e.g. logfmt or access logs,
+//! so that the fields can be filtered by, highlighted and sorted by whatever
+//! the format.
+//!
+//! Each format is described by a `Format`, which knows how to parse a line in
+//! that format into a `StructuredLine`. The formats built in are registered in
+//! `formats()`, and the format of an input is told by `detect()` from its
+//! first lines; other formats may be described by users of this crate.
+
+use super::structured::StructuredLine;
+
+mod access_log;
+mod csv;
+mod evtx;
+mod logfmt;
+mod syslog;
+
+pub use self::access_log::FORMAT as ACCESS_LOG;
+pub use self::csv::FORMAT as CSV;
+pub use self::evtx::FORMAT as EVTX;
+pub use self::logfmt::FORMAT as LOGFMT;
+pub use self::syslog::{FORMAT as SYSLOG, severity_code};
+
+/// Least share of the lines looked at by `detect()` which must be in a format
+/// for the input to be taken to be in it.
+const MIN_SHARE: f64 = 0.8;
+
+
+/// Named format of lines.
+#[derive(Clone, Copy, Debug)]
+pub struct Format {
+    /// name by which the format is selected, e.g. with `--format`
+    pub name: &'static str,
+    /// what the format is, for display to the user
+    pub description: &'static str,
+    /// parses a line into its fields, returning `None` if it isn't in the
+    /// format
+    pub parse: fn(&str) -> Option<StructuredLine>,
+    /// whether the fields are columns, every line in the format having as
+    /// many
+    pub columns: bool,
+}
+
+/// Formats are told apart by name.
+impl PartialEq for Format {
+    fn eq(&self, other: &Format) -> bool {
+        self.name == other.name
+    }
+}
+
+/// Format of lines which are plain text, having no fields; always available.
+pub const PLAIN: Format = Format {
+    name: "plain",
+    description: "plain text",
+    parse: parse_plain,
+    columns: false,
+};
+
+/// Format of lines which are JSON objects, as parsed by
+/// `StructuredLine::parse()`.
+pub const JSON: Format = Format {
+    name: "json",
+    description: "JSON",
+    parse: StructuredLine::parse,
+    columns: false,
+};
+
+fn parse_plain(_line: &str) -> Option<StructuredLine> {
+    None
+}
+
+/// Returns the formats built in, beginning with `PLAIN`, then the others
+/// from the most particular to the least, in which order `detect()` and
+/// `parse_any()` try them.
+pub fn formats() -> Vec<Format> {
+    vec![PLAIN, JSON, ACCESS_LOG, SYSLOG, EVTX, LOGFMT, CSV]
+}
+
+/// Returns the format built in with the given name.
+pub fn format(name: &str) -> Result<Format, String> {
+    let formats = formats();
+    formats.iter()
+        .find(|format| format.name == name)
+        .cloned()
+        .ok_or_else(|| {
+            let names: Vec<_> = formats.iter().map(|format| format.name).collect();
+            format!("unknown format `{}`; expected one of: {}", name, names.join(", "))
+        })
+}
+
+/// Returns the format most of `lines`, e.g. the first lines of the input, are
+/// in, or `None` if they're plain text or empty.
+///
+/// Blank lines aren't considered, and lines in a format whose fields are
+/// columns must all have as many.
+pub fn detect(lines: &[&str]) -> Option<Format> {
+    let lines: Vec<&str> = lines.iter().cloned().filter(|line| !line.trim().is_empty()).collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    formats().into_iter().skip(1).find(|format| {
+        let parsed: Vec<StructuredLine> = lines.iter().filter_map(|line| (format.parse)(line))
+            .collect();
+        let uniform = !format.columns || parsed.windows(2)
+            .all(|pair| pair[0].fields().len() == pair[1].fields().len());
+        uniform && parsed.len() as f64 >= lines.len() as f64 * MIN_SHARE
+    })
+}
+
+/// Parses `line` in the first format built in which it's in, if any.
+pub fn parse_any(line: &str) -> Option<StructuredLine> {
+    formats().iter().skip(1).filter_map(|format| (format.parse)(line)).next()
+}
+
+/// Parses `line` in the given format, or in the first it's in if none is
+/// given.
+pub fn parse(format: Option<Format>, line: &str) -> Option<StructuredLine> {
+    match format {
+        Some(format) => (format.parse)(line),
+        None => parse_any(line),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{detect, format, parse, parse_any, ACCESS_LOG, CSV, EVTX, JSON, LOGFMT, PLAIN,
+                SYSLOG};
+
+    #[test]
+    fn test_detect() {
+        let lines = [
+            r#"{"level": "info", "msg": "started"}"#,
+            "",
+            r#"{"level": "warn", "msg": "slow", "ms": 950}"#,
+        ];
+        assert_eq!(detect(&lines), Some(JSON));
+
+        let lines = ["level=info msg=started", "level=warn msg=\"slow request\" ms=950"];
+        assert_eq!(detect(&lines), Some(LOGFMT));
+
+        let lines = [
+            r#"10.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 2326"#,
+            r#"10.0.0.2 - bob [10/Oct/2000:13:55:37 -0700] "GET /a HTTP/1.0" 404 12"#,
+        ];
+        assert_eq!(detect(&lines), Some(ACCESS_LOG));
+
+        let lines = ["Oct 11 22:14:15 host sshd[42]: accepted", "Oct 11 22:14:16 host cron: ran"];
+        assert_eq!(detect(&lines), Some(SYSLOG));
+        let lines = ["<38>1 2023-10-11T22:14:15Z host sshd 42 - - accepted"];
+        assert_eq!(detect(&lines), Some(SYSLOG));
+        let lines = ["2024-05-01T09:30:00Z record=7 provider=EventLog level=info event_id=6005"];
+        assert_eq!(detect(&lines), Some(EVTX));
+
+        assert_eq!(detect(&["id,name", "1,ann", "2,bob"]), Some(CSV));
+        // case: a few lines of prose happen to contain commas
+        assert_eq!(detect(&["Hello, world", "one, two, three", "done"]), None);
+        assert_eq!(detect(&["plain text", "more text"]), None);
+        assert_eq!(detect(&[]), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_any("level=warn").unwrap().get("level").map(|f| f.span), Some((6, 10)));
+        assert_eq!(parse_any(r#"{"level": "warn"}"#).unwrap().fields().len(), 1);
+        assert_eq!(parse_any("no fields"), None);
+        assert_eq!(parse(Some(PLAIN), "level=warn"), None);
+        assert_eq!(parse(Some(CSV), "level=warn,x").unwrap().fields().len(), 2);
+        assert_eq!(format("syslog"), Ok(SYSLOG));
+        assert!(format("xml").is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! Lines of syslog in the BSD format of RFC 3164, e.g.
+//! `Oct 11 22:14:15 gateway sshd[4242]: Accepted publickey for ann`, or the
+//! format of RFC 5424, e.g.
+//! `<38>1 2023-10-11T22:14:15.003Z gateway sshd 4242 - - Accepted publickey`.
+
+use iter::structured::{Field, StructuredLine};
+use iter::matcher::Span;
+
+use super::Format;
+
+pub const FORMAT: Format = Format {
+    name: "syslog",
+    description: "syslog (RFC 3164 or 5424)",
+    parse: parse,
+    columns: false,
+};
+
+/// Abbreviated names of the months, with which timestamps begin.
+const MONTHS: &[&str] = &["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+                          "Nov", "Dec"];
+
+/// Names of the facilities, indexed by their codes.
+const FACILITIES: &[&str] = &["kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news",
+                              "uucp", "cron", "authpriv", "ftp", "ntp", "security", "console",
+                              "solaris-cron", "local0", "local1", "local2", "local3", "local4",
+                              "local5", "local6", "local7"];
+
+/// Names of the severities, indexed by their codes, from the most severe.
+const SEVERITIES: &[&str] = &["emerg", "alert", "crit", "err", "warning", "notice", "info",
+                              "debug"];
+
+/// Other names by which severities go, e.g. in the configuration of rsyslog.
+const SEVERITY_ALIASES: &[(&str, u8)] = &[("panic", 0), ("critical", 2), ("error", 3),
+                                          ("warn", 4)];
+
+
+/// Returns the code of the severity named by `name`, e.g. 3 for `err` or
+/// `error`, the most severe having the least.
+pub fn severity_code(name: &str) -> Option<u8> {
+    SEVERITIES.iter().position(|severity| name.eq_ignore_ascii_case(severity))
+        .map(|code| code as u8)
+        .or_else(|| {
+            SEVERITY_ALIASES.iter()
+                .find(|&&(alias, _)| name.eq_ignore_ascii_case(alias))
+                .map(|&(_, code)| code)
+        })
+}
+
+/// Parses a line of syslog in either format into the fields `time`, `host`,
+/// `app`, `pid`, if the app gave it, and `msg`; RFC 5424 also gives `msgid`
+/// and `data`, its structured data, unless they're nil.
+///
+/// The priority which may begin the line, e.g. `<34>`, gives the fields
+/// `facility` and `severity`, named as in `FACILITIES` and `SEVERITIES` and
+/// spanning its number.
+fn parse(line: &str) -> Option<StructuredLine> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    if line.starts_with('<') {
+        let len = line.find('>')?;
+        let code = line[1..len].parse::<usize>().ok()
+            .filter(|_| len >= 2 && line[1..len].bytes().all(|b| b.is_ascii_digit()))?;
+        let span = (1, len);
+        fields.push(Field {
+            path: "facility".to_owned(),
+            span: span,
+            value: FACILITIES.get(code / 8)?.to_string(),
+        });
+        fields.push(Field {
+            path: "severity".to_owned(),
+            span: span,
+            value: SEVERITIES[code % 8].to_owned(),
+        });
+        pos = len + 1;
+
+        if line[pos..].starts_with("1 ") {
+            return parse_rfc5424(line, pos + 2, fields);
+        }
+    }
+
+    // e.g. `Oct 11 22:14:15` or `Oct  1 22:14:15`
+    let time = line.get(pos..pos + 15)?;
+    let bytes = time.as_bytes();
+    let digits = |range: &[usize]| range.iter().all(|&i| bytes[i].is_ascii_digit());
+    let valid = time.is_ascii() && MONTHS.contains(&&time[..3]) && bytes[3] == b' '
+        && (bytes[4] == b' ' || digits(&[4])) && digits(&[5, 7, 8, 10, 11, 13, 14])
+        && bytes[6] == b' ' && bytes[9] == b':' && bytes[12] == b':';
+    if !valid {
+        return None;
+    }
+    fields.push(field(line, "time", (pos, pos + 15)));
+    pos += 15;
+
+    let host = word(line, pos + 1)?;
+    if !line[pos..].starts_with(' ') {
+        return None;
+    }
+    fields.push(field(line, "host", host));
+
+    // the tag, e.g. `sshd[4242]:` or `cron:`, ends at the first colon
+    let tag_start = host.1 + 1;
+    let tag_len = line.get(tag_start..)?.find(':')?;
+    let tag = &line[tag_start..tag_start + tag_len];
+    if tag.is_empty() || tag.contains(' ') {
+        return None;
+    }
+    match tag.find('[') {
+        Some(open) if tag.ends_with(']') => {
+            fields.push(field(line, "app", (tag_start, tag_start + open)));
+            fields.push(field(line, "pid", (tag_start + open + 1, tag_start + tag_len - 1)));
+        },
+        Some(_) => return None,
+        None => fields.push(field(line, "app", (tag_start, tag_start + tag_len))),
+    }
+
+    let msg_start = tag_start + tag_len + 1;
+    let msg_start = if line[msg_start..].starts_with(' ') { msg_start + 1 } else { msg_start };
+    fields.push(field(line, "msg", (msg_start, line.len())));
+    Some(StructuredLine::new(fields))
+}
+
+/// Parses the rest of a line of syslog in the format of RFC 5424, following
+/// its priority and version.
+///
+/// ### Parameters
+/// * `line`: the line
+/// * `pos`: where the timestamp begins
+/// * `fields`: the fields given by the priority
+fn parse_rfc5424(line: &str, mut pos: usize, mut fields: Vec<Field>)
+                 -> Option<StructuredLine> {
+    // the header's fields, each a word which is `-` if nil
+    for path in &["time", "host", "app", "pid", "msgid"] {
+        let span = word(line, pos)?;
+        if &line[span.0..span.1] != "-" {
+            fields.push(field(line, path, span));
+        }
+        pos = span.1;
+        if !line[pos..].starts_with(' ') {
+            return None;
+        }
+        pos += 1;
+    }
+
+    // the structured data, e.g. `[origin ip="10.0.0.1"][meta seq="7"]`,
+    // within whose quoted values `"`, `\` and `]` are escaped by backslashes
+    let data_start = pos;
+    if line[pos..].starts_with('-') {
+        pos += 1;
+    } else {
+        while line[pos..].starts_with('[') {
+            let mut quoted = false;
+            let mut escaped = false;
+            let len = line[pos..].bytes().position(|b| {
+                let closed = b == b']' && !quoted;
+                match b {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => quoted = !quoted,
+                    _ => {},
+                }
+                closed
+            })?;
+            pos += len + 1;
+        }
+        if pos == data_start {
+            return None;
+        }
+        fields.push(field(line, "data", (data_start, pos)));
+    }
+
+    let msg_start = match line.get(pos..) {
+        Some("") => pos,
+        Some(rest) if rest.starts_with(' ') => pos + 1,
+        _ => return None,
+    };
+    // a message in UTF-8 may begin with a byte order mark
+    let msg_start = if line[msg_start..].starts_with('\u{feff}') {
+        msg_start + '\u{feff}'.len_utf8()
+    } else {
+        msg_start
+    };
+    fields.push(field(line, "msg", (msg_start, line.len())));
+    Some(StructuredLine::new(fields))
+}
+
+/// Returns the span of the word of `line` beginning at `start`, if there's
+/// one, ending before the next space.
+fn word(line: &str, start: usize) -> Option<Span> {
+    let rest = line.get(start..)?;
+    let len = rest.find(' ').unwrap_or(rest.len());
+    if len == 0 { None } else { Some((start, start + len)) }
+}
+
+fn field(line: &str, path: &str, span: Span) -> Field {
+    Field { path: path.to_owned(), span: span, value: line[span.0..span.1].to_owned() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, severity_code};
+
+    fn fields(line: &str) -> Vec<(String, String)> {
+        parse(line).unwrap().fields().iter()
+            .map(|field| (field.path.clone(), field.value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let line = "<34>Oct  1 22:14:15 gateway sshd[4242]: Accepted publickey for ann";
+        assert_eq!(fields(line), vec![
+            ("facility".to_owned(), "auth".to_owned()),
+            ("severity".to_owned(), "crit".to_owned()),
+            ("time".to_owned(), "Oct  1 22:14:15".to_owned()),
+            ("host".to_owned(), "gateway".to_owned()),
+            ("app".to_owned(), "sshd".to_owned()),
+            ("pid".to_owned(), "4242".to_owned()),
+            ("msg".to_owned(), "Accepted publickey for ann".to_owned()),
+        ]);
+        let structured = parse(line).unwrap();
+        assert_eq!(structured.get("app").map(|field| field.span), Some((28, 32)));
+        assert_eq!(structured.get("severity").map(|field| field.span), Some((1, 3)));
+
+        let structured = parse("Oct 11 22:14:15 host CRON:").unwrap();
+        assert_eq!(structured.get("app").map(|field| field.value.as_str()), Some("CRON"));
+        assert_eq!(structured.get("pid"), None);
+        assert_eq!(structured.get("severity"), None);
+        assert_eq!(structured.get("msg").map(|field| field.value.as_str()), Some(""));
+
+        assert_eq!(parse("Oct 11 22:14:15 host no tag here"), None);
+        assert_eq!(parse("Foo 11 22:14:15 host app: msg"), None);
+        assert_eq!(parse("<x>Oct 11 22:14:15 host app: msg"), None);
+        assert_eq!(parse("<192>Oct 11 22:14:15 host app: msg"), None);
+        assert_eq!(parse("2017-10-11 22:14:15 ERROR db: down"), None);
+        assert_eq!(parse("Oct 11"), None);
+    }
+
+    #[test]
+    fn test_parse_rfc5424() {
+        let line = concat!(r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - "#,
+                           r#"ID47 [exampleSDID@32473 iut="3" eventID="a\]b"][x y="1"] "#,
+                           "\u{feff}An application event log entry");
+        let structured = parse(line).unwrap();
+        let fields: Vec<(&str, &str)> = structured.fields().iter()
+            .map(|field| (field.path.as_str(), field.value.as_str()))
+            .collect();
+        assert_eq!(fields, vec![
+            ("facility", "local4"),
+            ("severity", "notice"),
+            ("time", "2003-10-11T22:14:15.003Z"),
+            ("host", "mymachine.example.com"),
+            ("app", "evntslog"),
+            ("msgid", "ID47"),
+            ("data", r#"[exampleSDID@32473 iut="3" eventID="a\]b"][x y="1"]"#),
+            ("msg", "An application event log entry"),
+        ]);
+
+        let structured = parse("<13>1 - - - - - -").unwrap();
+        assert_eq!(structured.fields().len(), 3);
+        assert_eq!(structured.get("msg").map(|field| field.value.as_str()), Some(""));
+
+        assert_eq!(parse("<13>1 2003-10-11T22:14:15Z host app - -"), None);
+        assert_eq!(parse("<13>1 - - - - - [unterminated x"), None);
+        assert_eq!(parse("<13>1 - - - - -msg"), None);
+    }
+
+    #[test]
+    fn test_severity_code() {
+        assert_eq!(severity_code("err"), Some(3));
+        assert_eq!(severity_code("Error"), Some(3));
+        assert_eq!(severity_code("debug"), Some(7));
+        assert_eq!(severity_code("warn"), Some(4));
+        assert_eq!(severity_code("loud"), None);
+    }
+}
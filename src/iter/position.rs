@@ -0,0 +1,87 @@
+//! Positions of lines and the distances between them, with which
+//! `WindowBuffer` moves its window without stepping before the first line.
+
+/// 1-based position of a line within the underlying input or the cache of a
+/// `WindowBuffer`.
+///
+/// `Position::BEFORE_FIRST` stands for the position preceding the first
+/// line, e.g. the bottom of a window showing no lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position(usize);
+
+/// Number of lines by which a `Position` is moved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Offset(usize);
+
+impl Position {
+    pub const BEFORE_FIRST: Position = Position(0);
+    pub const FIRST: Position = Position(1);
+
+    /// Creates the position of a line.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed number of the line, or 0 for
+    ///   `Position::BEFORE_FIRST`
+    pub fn new(line_num: usize) -> Position {
+        Position(line_num)
+    }
+
+    /// Returns the position `offset` lines later.
+    pub fn forward(self, offset: Offset) -> Position {
+        Position(self.0.saturating_add(offset.0))
+    }
+
+    /// Returns the position `offset` lines earlier, or `None` if it would
+    /// precede the first line.
+    pub fn checked_back(self, offset: Offset) -> Option<Position> {
+        self.0.checked_sub(offset.0).filter(|&line_num| line_num >= 1).map(Position)
+    }
+
+    /// Returns the position `offset` lines earlier, or the first line's if
+    /// it would precede the first line.
+    pub fn back(self, offset: Offset) -> Position {
+        self.checked_back(offset).unwrap_or(Position::FIRST)
+    }
+
+    /// Returns the number of lines by which this position follows `earlier`,
+    /// or zero if it doesn't.
+    pub fn since(self, earlier: Position) -> Offset {
+        Offset(self.0.saturating_sub(earlier.0))
+    }
+}
+
+impl Offset {
+    pub const ONE: Offset = Offset(1);
+
+    pub fn new(lines: usize) -> Offset {
+        Offset(lines)
+    }
+
+    /// Returns the number of lines.
+    pub fn get(self) -> usize {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Offset, Position};
+
+    #[test]
+    fn test_arithmetic() {
+        let third = Position::new(3);
+
+        assert_eq!(third.forward(Offset::new(2)), Position::new(5));
+        assert_eq!(Position::new(usize::MAX).forward(Offset::ONE), Position::new(usize::MAX));
+
+        assert_eq!(third.checked_back(Offset::new(2)), Some(Position::FIRST));
+        assert_eq!(third.checked_back(Offset::new(3)), None);
+        assert_eq!(Position::BEFORE_FIRST.checked_back(Offset::ONE), None);
+        assert_eq!(third.back(Offset::new(10)), Position::FIRST);
+        assert_eq!(Position::BEFORE_FIRST.back(Offset::new(0)), Position::FIRST);
+
+        assert_eq!(third.since(Position::FIRST), Offset::new(2));
+        assert_eq!(Position::FIRST.since(third), Offset::new(0));
+        assert!(Position::BEFORE_FIRST < Position::FIRST);
+    }
+}
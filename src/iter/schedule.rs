@@ -0,0 +1,120 @@
+//! Input whose lines arrive on a schedule kept by a virtual clock, so that
+//! following a growing input can be tested without waiting in real time.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Clock which only moves when advanced, shared between a test and the
+/// `ScheduledLines` it drives.
+#[derive(Clone, Debug, Default)]
+pub struct VirtualClock {
+    /// time elapsed since the clock was created
+    now: Rc<Cell<Duration>>,
+}
+
+impl VirtualClock {
+    /// Creates a clock at time zero.
+    pub fn new() -> VirtualClock {
+        VirtualClock::default()
+    }
+
+    /// Returns the time elapsed since the clock was created.
+    pub fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    /// Moves the clock forward by `millis` milliseconds.
+    pub fn advance(&self, millis: u64) {
+        self.now.set(self.now.get() + Duration::from_millis(millis));
+    }
+}
+
+/// Iterator over lines which arrive at scheduled times, like those appended
+/// to a file being followed.
+///
+/// Once the lines which have arrived by the clock's current time are read,
+/// `None` is returned until the clock is advanced past the arrival of
+/// another.
+pub struct ScheduledLines {
+    clock: VirtualClock,
+    /// lines yet to be read, with their times of arrival, in order of arrival
+    pending: VecDeque<(Duration, String)>,
+}
+
+impl ScheduledLines {
+    /// Creates an input with no lines scheduled, driven by `clock`.
+    pub fn new(clock: &VirtualClock) -> ScheduledLines {
+        ScheduledLines { clock: clock.clone(), pending: VecDeque::new() }
+    }
+
+    /// Schedules `line` to arrive `millis` milliseconds after time zero, after
+    /// any lines already scheduled to arrive then.
+    pub fn at(mut self, millis: u64, line: &str) -> ScheduledLines {
+        let arrival = Duration::from_millis(millis);
+        let idx = self.pending.iter()
+            .position(|&(other, _)| other > arrival)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(idx, (arrival, line.to_owned()));
+        self
+    }
+
+    /// Schedules `lines` to arrive one every `millis` milliseconds, the first
+    /// of them `millis` after the last line already scheduled.
+    pub fn every(mut self, millis: u64, lines: &[&str]) -> ScheduledLines {
+        let mut arrival = self.pending.back().map(|&(arrival, _)| arrival).unwrap_or_default();
+        for line in lines {
+            arrival += Duration::from_millis(millis);
+            self.pending.push_back((arrival, (*line).to_owned()));
+        }
+        self
+    }
+}
+
+impl Iterator for ScheduledLines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let now = self.clock.now();
+        if self.pending.front().is_some_and(|&(arrival, _)| arrival <= now) {
+            self.pending.pop_front().map(|(_, line)| line)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{ScheduledLines, VirtualClock};
+
+    #[test]
+    fn test_schedule() {
+        let clock = VirtualClock::new();
+        let mut lines = ScheduledLines::new(&clock)
+            .at(0, "boot")
+            .at(100, "late")
+            .at(50, "early")
+            .every(10, &["a", "b"]);
+
+        assert_eq!(lines.next(), Some("boot".to_owned()));
+        assert_eq!(lines.next(), None);
+
+        clock.advance(99);
+        assert_eq!(lines.next(), Some("early".to_owned()));
+        assert_eq!(lines.next(), None);
+
+        // lines which arrived while nobody was reading are all available
+        clock.advance(20);
+        assert_eq!(clock.now(), Duration::from_millis(119));
+        assert_eq!(lines.by_ref().collect::<Vec<_>>(), vec!["late", "a"]);
+
+        clock.advance(1);
+        assert_eq!(lines.next(), Some("b".to_owned()));
+        clock.advance(1000);
+        assert_eq!(lines.next(), None);
+    }
+}
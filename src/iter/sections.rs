@@ -0,0 +1,246 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Form of the lines which separate the sections of an input, e.g. the
+/// `==> file.log <==` headers written by `tail` and `head` given several
+/// files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    /// text preceding the section's name
+    prefix: String,
+    /// text following the section's name
+    suffix: String,
+}
+
+impl Marker {
+    /// Parses a marker in which `{}` stands for the section's name, e.g.
+    /// `==> {} <==`.
+    pub fn parse(template: &str) -> Result<Marker, String> {
+        let mut parts = template.splitn(2, "{}");
+        let prefix = parts.next().unwrap_or("");
+        let suffix = parts.next()
+            .ok_or(format!("expected `{{}}` in place of the name in `{}`", template))?;
+
+        if prefix.is_empty() && suffix.is_empty() {
+            return Err("marker must contain more than `{}`".to_owned());
+        }
+
+        Ok(Marker { prefix: prefix.to_owned(), suffix: suffix.to_owned() })
+    }
+
+    /// Returns the name of the section begun by `line`, or `None` if `line`
+    /// isn't a marker.
+    pub fn name<'a>(&self, line: &'a str) -> Option<&'a str> {
+        if line.len() < self.prefix.len() + self.suffix.len() ||
+            !line.starts_with(self.prefix.as_str()) ||
+            !line.ends_with(self.suffix.as_str()) {
+            return None;
+        }
+
+        Some(&line[self.prefix.len()..line.len() - self.suffix.len()])
+    }
+}
+
+/// State shared between `Sections` and each of its `Section`s.
+struct Demux<I: Iterator<Item=String>> {
+    lines: I,
+    marker: Marker,
+    /// names of the sections found so far
+    names: Vec<String>,
+    /// lines read off `lines` but not yet taken, for each section
+    queues: Vec<VecDeque<String>>,
+    /// whether the last section has been given any lines
+    started: bool,
+    /// whether the last line read was blank; it's held back, as the blank
+    /// line before each marker belongs to neither section
+    held_blank: bool,
+    /// number of sections handed out by `Sections::take_new()`
+    taken: usize,
+}
+
+impl<I: Iterator<Item=String>> Demux<I> {
+    /// Reads a line, adding it to the last section or beginning a new one.
+    ///
+    /// Returns `false` if no lines are left.
+    fn read_one(&mut self) -> bool {
+        let line = match self.lines.next() {
+            Some(line) => line,
+            None => {
+                self.release_blank();
+                return false;
+            },
+        };
+
+        if let Some(name) = self.marker.name(&line).map(|name| name.to_owned()) {
+            self.held_blank = false;
+            if !self.started && self.names.len() == 1 {
+                // case: the input begins with a marker; name the first
+                // section rather than leaving it empty
+                self.names[0] = name;
+            } else {
+                self.names.push(name);
+                self.queues.push(VecDeque::new());
+                self.started = false;
+            }
+            return true;
+        }
+
+        self.release_blank();
+        if line.is_empty() {
+            self.held_blank = true;
+        } else {
+            self.push(line);
+        }
+        true
+    }
+
+    /// Adds the held back blank line, if any, to the last section.
+    fn release_blank(&mut self) {
+        if self.held_blank {
+            self.held_blank = false;
+            self.push(String::new());
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        self.started = true;
+        self.queues.last_mut().expect("there is always a section").push_back(line);
+    }
+}
+
+/// Splits the lines of an input into sections at marker lines, e.g. the
+/// output of `tail -n +1 one.log two.log` into the lines of each file.
+///
+/// Each section is read through its own `Section` iterator, which ends where
+/// the next section begins; lines are read off the input only as they're
+/// needed, and those belonging to sections other than the one being read are
+/// held until they're taken. Lines preceding the first marker form a section
+/// with an empty name.
+pub struct Sections<I: Iterator<Item=String>> {
+    shared: Rc<RefCell<Demux<I>>>,
+}
+
+impl<I: Iterator<Item=String>> Sections<I> {
+    /// Creates a new `Sections`, reading the first line of `lines` to find
+    /// the name of the first section.
+    pub fn new(lines: I, marker: Marker) -> Sections<I> {
+        let mut demux = Demux {
+            lines: lines,
+            marker: marker,
+            names: vec![String::new()],
+            queues: vec![VecDeque::new()],
+            started: false,
+            held_blank: false,
+            taken: 0,
+        };
+        demux.read_one();
+
+        Sections { shared: Rc::new(RefCell::new(demux)) }
+    }
+
+    /// Reads ahead until another section begins, returning `false` if the
+    /// input ends first.
+    pub fn discover(&self) -> bool {
+        let mut demux = self.shared.borrow_mut();
+        let found = demux.names.len();
+
+        while demux.names.len() == found {
+            if !demux.read_one() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns the names and iterators of the sections found since the last
+    /// call, beginning with the first section.
+    pub fn take_new(&self) -> Vec<(String, Section<I>)> {
+        let mut demux = self.shared.borrow_mut();
+        let found = demux.names.len();
+        let new = (demux.taken..found)
+            .map(|idx| (demux.names[idx].clone(), Section { shared: self.shared.clone(), idx: idx }))
+            .collect();
+        demux.taken = found;
+        new
+    }
+}
+
+/// Iterator over the lines of one section of a `Sections`.
+pub struct Section<I: Iterator<Item=String>> {
+    shared: Rc<RefCell<Demux<I>>>,
+    /// 0-based index of the section
+    idx: usize,
+}
+
+impl<I: Iterator<Item=String>> Iterator for Section<I> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let mut demux = self.shared.borrow_mut();
+
+        loop {
+            if let Some(line) = demux.queues[self.idx].pop_front() {
+                return Some(line);
+            }
+
+            if demux.names.len() > self.idx + 1 {
+                // case: a later section has begun
+                return None;
+            }
+
+            if !demux.read_one() {
+                // case: the input has ended, perhaps releasing a blank line
+                return demux.queues[self.idx].pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Marker, Sections};
+
+    #[test]
+    fn test_sections() {
+        let marker = Marker::parse("==> {} <==").unwrap();
+        assert_eq!(marker.name("==> a.log <=="), Some("a.log"));
+        assert_eq!(marker.name("==>  <=="), Some(""));
+        assert_eq!(marker.name("==> <=="), None);
+        assert_eq!(marker.name("a.log"), None);
+        assert!(Marker::parse("==>").is_err());
+        assert!(Marker::parse("{}").is_err());
+
+        let input: Vec<String> = vec!["==> a <==", "a1", "", "a2", "", "==> b <==", "b1", "",
+                                      "==> c <==", "c1", ""]
+            .into_iter().map(|line| line.to_owned()).collect();
+        let sections = Sections::new(input.into_iter(), marker.clone());
+
+        let mut found = sections.take_new();
+        assert_eq!(found.len(), 1);
+        let (name, mut a) = found.remove(0);
+        assert_eq!(name, "a");
+        assert_eq!(a.next(), Some("a1".to_owned()));
+
+        // reading ahead holds the lines of the sections read past
+        assert!(sections.discover());
+        let (name, b) = sections.take_new().remove(0);
+        assert_eq!(name, "b");
+        assert_eq!(a.collect::<Vec<_>>(), vec!["", "a2"]);
+
+        assert_eq!(b.collect::<Vec<_>>(), vec!["b1"]);
+        let (name, c) = sections.take_new().remove(0);
+        assert_eq!(name, "c");
+        assert_eq!(c.collect::<Vec<_>>(), vec!["c1", ""]);
+        assert!(!sections.discover());
+        assert!(sections.take_new().is_empty());
+
+        // lines preceding the first marker form an unnamed section
+        let input = vec!["x".to_owned(), "==> a <==".to_owned(), "a1".to_owned()];
+        let sections = Sections::new(input.into_iter(), marker);
+        let found = sections.take_new();
+        assert_eq!(found[0].0, "");
+        assert!(sections.discover());
+        assert_eq!(sections.take_new()[0].0, "a");
+    }
+}
@@ -0,0 +1,282 @@
+//! Lines of the input viewed as their fields, e.g. for showing a few chosen
+//! fields of each line of a JSON-lines log; lines in other formats are parsed
+//! into fields by the `parser` module.
+
+use super::matcher::Span;
+
+
+/// Field of a JSON object, which may be nested within other objects.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    /// keys leading to the field from the outermost object, joined by dots,
+    /// e.g. `request.method`
+    pub path: String,
+    /// byte range of the value within the line, excluding the quotes of a
+    /// string
+    pub span: Span,
+    /// the value: the unescaped contents of a string, else as written
+    pub value: String,
+}
+
+/// Line of the input viewed as its fields.
+///
+/// Where the line is a JSON object, the fields of nested objects are
+/// flattened into those of the outermost object, so that
+/// `{"req": {"method": "GET"}}` has the field `req.method`; arrays are kept
+/// whole.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructuredLine {
+    fields: Vec<Field>,
+}
+
+impl StructuredLine {
+    /// Creates a line having the given fields, e.g. as parsed from a line in
+    /// a format other than JSON.
+    pub fn new(fields: Vec<Field>) -> StructuredLine {
+        StructuredLine { fields: fields }
+    }
+
+    /// Parses `line` as a JSON object, returning `None` if it isn't one.
+    pub fn parse(line: &str) -> Option<StructuredLine> {
+        let mut scanner = Scanner::new(line);
+        let mut fields = Vec::new();
+        parse_object(&mut scanner, line, "", &mut fields)?;
+
+        scanner.skip_whitespace();
+        if scanner.peek().is_some() {
+            return None;
+        }
+        Some(StructuredLine { fields: fields })
+    }
+
+    /// Returns the fields of the line, in the order they're written.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns the field at the dotted `path`, if the line has one.
+    pub fn get(&self, path: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.path == path)
+    }
+
+    /// Renders the fields at the given paths on one line as `KEY=VALUE`
+    /// pairs separated by spaces, leaving out those which the line lacks.
+    ///
+    /// Values which are empty or contain spaces, quotes or `=` are quoted.
+    ///
+    /// ### Parameters
+    /// * `paths`: dotted paths of the fields to render, in order
+    pub fn render(&self, paths: &[String]) -> String {
+        let pairs: Vec<String> = paths.iter()
+            .filter_map(|path| self.get(path))
+            .map(|field| format!("{}={}", field.path, quote(&field.value)))
+            .collect();
+        pairs.join(" ")
+    }
+}
+
+/// Returns `value`, quoted and escaped if it would otherwise be ambiguous
+/// within `KEY=VALUE` pairs.
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty() && !value.chars()
+        .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '=');
+    if plain { value.to_owned() } else { format!("{:?}", value) }
+}
+
+/// Consumes an object, after any whitespace, adding its fields to `fields`.
+///
+/// ### Parameters
+/// * `scanner`: scanner over `line`
+/// * `line`: the line being parsed
+/// * `prefix`: path of the object followed by a dot, or empty for the
+///   outermost object
+/// * `fields`: fields found so far
+fn parse_object(scanner: &mut Scanner, line: &str, prefix: &str, fields: &mut Vec<Field>)
+                -> Option<()> {
+    if !scanner.byte(b'{') {
+        return None;
+    }
+    if scanner.byte(b'}') {
+        return Some(());
+    }
+
+    loop {
+        let key = scanner.string()?;
+        if !scanner.byte(b':') {
+            return None;
+        }
+        let path = format!("{}{}", prefix, key);
+
+        scanner.skip_whitespace();
+        let start = scanner.pos;
+        match scanner.peek()? {
+            b'{' => parse_object(scanner, line, &format!("{}.", path), fields)?,
+            b'"' => {
+                let value = scanner.string()?;
+                fields.push(Field { path: path, span: (start + 1, scanner.pos - 1), value: value });
+            },
+            _ => {
+                let span = scanner.value()?;
+                let value = line[span.0..span.1].to_owned();
+                fields.push(Field { path: path, span: span, value: value });
+            },
+        }
+
+        if scanner.byte(b'}') {
+            return Some(());
+        }
+        if !scanner.byte(b',') {
+            return None;
+        }
+    }
+}
+
+/// Cursor over the bytes of a JSON document.
+pub(crate) struct Scanner<'a> {
+    bytes: &'a [u8],
+    /// offset of the next byte to read
+    pub(crate) pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    pub(crate) fn new(text: &'a str) -> Scanner<'a> {
+        Scanner { bytes: text.as_bytes(), pos: 0 }
+    }
+
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    pub(crate) fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes `expected`, after any whitespace, if it's next.
+    pub(crate) fn byte(&mut self, expected: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes a string, after any whitespace, returning its unescaped
+    /// contents.
+    pub(crate) fn string(&mut self) -> Option<String> {
+        if !self.byte(b'"') {
+            return None;
+        }
+
+        let mut unescaped = Vec::new();
+        loop {
+            let b = self.peek()?;
+            self.pos += 1;
+            match b {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => unescaped.push(b'\n'),
+                        b't' => unescaped.push(b'\t'),
+                        b'r' => unescaped.push(b'\r'),
+                        b'b' => unescaped.push(0x08),
+                        b'f' => unescaped.push(0x0c),
+                        b'u' => {
+                            let hex = self.bytes.get(self.pos..self.pos + 4)?;
+                            let hex = ::std::str::from_utf8(hex).ok()?;
+                            let c = u32::from_str_radix(hex, 16).ok()
+                                .and_then(::std::char::from_u32)
+                                .unwrap_or('\u{fffd}');
+                            let mut buf = [0; 4];
+                            unescaped.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                            self.pos += 4;
+                        },
+                        other => unescaped.push(other),
+                    }
+                },
+                other => unescaped.push(other),
+            }
+        }
+
+        Some(String::from_utf8_lossy(&unescaped).into_owned())
+    }
+
+    /// Consumes a value of any type, after any whitespace, returning its
+    /// span.
+    pub(crate) fn value(&mut self) -> Option<Span> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        match self.peek()? {
+            b'"' => {
+                self.string()?;
+            },
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.string()?;
+                            continue;
+                        },
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => (),
+                    }
+                    self.pos += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            },
+            _ => {
+                while let Some(b) = self.peek() {
+                    match b {
+                        b',' | b'}' | b']' | b' ' | b'\t' | b'\r' | b'\n' => break,
+                        _ => self.pos += 1,
+                    }
+                }
+            },
+        }
+
+        if self.pos > start { Some((start, self.pos)) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Field, StructuredLine};
+
+    #[test]
+    fn test_structured_line() {
+        let line = concat!(r#"{"level": "warn", "msg": "timed out", "#,
+                           r#""req": {"tags": ["a", "}"], "n": 3}, "e": {}}"#);
+        let structured = StructuredLine::parse(line).unwrap();
+        let paths: Vec<&str> = structured.fields().iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["level", "msg", "req.tags", "req.n"]);
+        assert_eq!(structured.get("req.n"), Some(&Field {
+            path: "req.n".to_owned(),
+            span: (71, 72),
+            value: "3".to_owned(),
+        }));
+        assert_eq!(structured.get("msg").map(|f| f.span), Some((26, 35)));
+        assert_eq!(structured.get("req"), None);
+
+        assert_eq!(structured.get("req.tags").map(|f| f.value.as_str()), Some(r#"["a", "}"]"#));
+
+        let paths = ["msg".to_owned(), "absent".to_owned(), "level".to_owned(), "req.n".to_owned()];
+        assert_eq!(structured.render(&paths), r#"msg="timed out" level=warn req.n=3"#);
+
+        assert_eq!(StructuredLine::parse(r#"{"a": "x\"\n"}"#).unwrap().render(&["a".to_owned()]),
+                   r#"a="x\"\n""#);
+        assert_eq!(StructuredLine::parse("{}").map(|s| s.fields().len()), Some(0));
+        assert_eq!(StructuredLine::parse("level=warn"), None);
+        assert_eq!(StructuredLine::parse(r#"{"a": 1} trailing"#), None);
+        assert_eq!(StructuredLine::parse(r#"{"a": 1"#), None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use timestamp::{self, Parser};
+
+
+/// Index of the leading timestamps of the lines of an input, by which the
+/// first line at or after a point in time is found by binary search.
+///
+/// Timestamps are parsed only from the lines a search probes, and remembered
+/// for later searches, so that a search reads few lines of even a large
+/// input. Timestamps are assumed to ascend through the input, as in a log;
+/// lines without one, e.g. those of a stack trace, are skipped.
+pub struct TimeIndex {
+    /// parser of the timestamps at the beginning of lines
+    parser: Parser,
+    /// times in milliseconds since the Unix epoch of the lines parsed so
+    /// far, by line number, or `None` for lines without a timestamp
+    times: HashMap<usize, Option<i64>>,
+}
+
+impl TimeIndex {
+    /// Creates an empty `TimeIndex`.
+    ///
+    /// ### Parameters
+    /// * `parser`: parser of the timestamps at the beginning of lines, e.g.
+    ///   `timestamp::parse_leading`
+    pub fn new(parser: Parser) -> TimeIndex {
+        TimeIndex {
+            parser: parser,
+            times: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of the first line having a timestamp at or after
+    /// `millis`, or `None` if there's no such line.
+    ///
+    /// ### Parameters
+    /// * `millis`: milliseconds since the Unix epoch
+    /// * `num_lines`: number of lines in the input
+    /// * `get_line`: returns the text of a line given its 1-indexed number
+    pub fn first_at<F>(&mut self, millis: i64, num_lines: usize, mut get_line: F)
+                       -> Option<usize>
+            where F: FnMut(usize) -> Option<String> {
+        let mut found = None;
+        let mut low = 1;
+        let mut high = num_lines + 1;
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.next_time(mid, high, &mut get_line) {
+                Some((line_num, time)) if time < millis => low = line_num + 1,
+                Some((line_num, _)) => {
+                    found = Some(line_num);
+                    high = mid;
+                },
+                None => high = mid,
+            }
+        }
+        found
+    }
+
+    /// Returns the number and time of the first line having a timestamp from
+    /// line `from` up to but excluding line `to`, if any.
+    fn next_time<F>(&mut self, from: usize, to: usize, get_line: &mut F) -> Option<(usize, i64)>
+            where F: FnMut(usize) -> Option<String> {
+        for line_num in from..to {
+            let parser = self.parser;
+            let time = *self.times.entry(line_num).or_insert_with(|| {
+                get_line(line_num).and_then(|line| parser(&line)).map(|ts| ts.millis)
+            });
+            if let Some(time) = time {
+                return Some((line_num, time));
+            }
+        }
+        None
+    }
+}
+
+impl Default for TimeIndex {
+    fn default() -> TimeIndex {
+        TimeIndex::new(timestamp::parse_leading)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use timestamp::{self, Timestamp};
+
+    use super::TimeIndex;
+
+    const LINES: &[&str] = &[
+        "2017-10-05 14:00:00 started",
+        "2017-10-05 14:00:05 failed",
+        "    at main.rs:1",
+        "    at main.rs:2",
+        "2017-10-05 14:01:00 retried",
+        "2017-10-05 14:01:00 again",
+        "2017-10-05 14:02:00 stopped",
+        "done",
+    ];
+
+    fn time(text: &str) -> i64 {
+        timestamp::parse_time(text).unwrap()
+    }
+
+    #[test]
+    fn test_first_at() {
+        let mut index = TimeIndex::default();
+        let reads = Cell::new(0);
+        let mut first_at = |text| {
+            index.first_at(time(text), LINES.len(), |line_num| {
+                reads.set(reads.get() + 1);
+                LINES.get(line_num - 1).map(|line| line.to_string())
+            })
+        };
+
+        assert_eq!(first_at("2017-10-05 13:00"), Some(1));
+        assert_eq!(first_at("2017-10-05 14:00:00"), Some(1));
+        assert_eq!(first_at("2017-10-05 14:00:01"), Some(2));
+        assert_eq!(first_at("2017-10-05 14:00:30"), Some(5));
+        assert_eq!(first_at("2017-10-05 14:01"), Some(5));
+        assert_eq!(first_at("2017-10-05 14:01:30"), Some(7));
+        assert_eq!(first_at("2017-10-05 15:00"), None);
+
+        // lines already parsed aren't read again
+        let num_reads = reads.get();
+        assert_eq!(first_at("2017-10-05 14:00:30"), Some(5));
+        assert_eq!(reads.get(), num_reads);
+    }
+
+    #[test]
+    fn test_parser() {
+        fn parse_seconds(line: &str) -> Option<Timestamp> {
+            let end = line.find(' ').unwrap_or(line.len());
+            line[..end].parse::<i64>().ok().map(|secs| Timestamp {
+                millis: secs * 1000,
+                utc_offset: None,
+                start: 0,
+                end: end,
+            })
+        }
+
+        let lines = ["10 a", "20 b", "30 c"];
+        let mut index = TimeIndex::new(parse_seconds);
+        let first_at = index.first_at(15_000, lines.len(), |n| Some(lines[n - 1].to_owned()));
+        assert_eq!(first_at, Some(2));
+        assert_eq!(index.first_at(0, 0, |_| None), None);
+    }
+}
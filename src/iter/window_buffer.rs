@@ -1,109 +1,280 @@
-use super::line_buffer::LineBuffer;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use super::line_buffer::{Input, IterDirection, LineBuffer};
 use super::context_buffer::ContextBuffer;
 use super::iter;
+use super::match_index::MatchIndex;
+use super::position::{Offset, Position};
+use super::time_index::TimeIndex;
+use timestamp::Parser;
+
+/// Number of lines tested for matches per call to `WindowBuffer::totals()`.
+const TALLY_BATCH_LINES: usize = 16384;
+
+/// Number of lines restored at most by `WindowBuffer::expand_gap()`.
+const MAX_GAP_EXPANSION: usize = 1000;
+
+/// Numbers of lines and matches in the whole input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Totals {
+    /// number of lines in the input
+    pub lines: usize,
+    /// number of lines matched by the filter predicate, if there is one
+    pub matches: Option<usize>,
+}
+
+/// Outcome of a search for a match which may have to wait on the background
+/// scan for matches.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Search {
+    /// the number of the line found, or `None` if no line matches
+    Found(Option<usize>),
+    /// the scan hasn't got far enough to tell yet
+    Pending,
+}
+
+/// Progress toward the totals reported by `WindowBuffer::totals()`, other
+/// than those of a match index.
+enum Tally {
+    /// lines of a memory-mapped file being counted in the background
+    Counting(Receiver<usize>),
+    /// number of lines of a memory-mapped file
+    Counted(usize),
+    /// lines read off an iterator being tested a batch at a time, once the
+    /// iterator has been exhausted
+    Testing {
+        /// number of lines tested so far
+        lines: usize,
+        /// number of the lines tested so far which match
+        matches: usize,
+    },
+}
 
 /// Thing which filters, describes, and categorizes lines from an iterator
 /// according to some specific filtering criteria.
 pub struct WindowBuffer<T: Iterator<Item=String>> {
     /// line source and filtering apparatus
     context_buffer: Option<ContextBuffer<T>>,
-    /// cache of lines that have been read off of `context_buffer`
-    buffered_lines: Vec<iter::FilteredLine>,
+    /// direction in which `context_buffer` reads the underlying input
+    direction: IterDirection,
+    /// cache of lines that have been read off of `context_buffer`; a
+    /// contiguous run of the filtered lines which needn't begin at the start
+    /// of the input
+    buffered_lines: VecDeque<iter::FilteredLine>,
+    /// whether `buffered_lines` begins at the start of the input
+    at_start: bool,
+    /// whether `buffered_lines` ended at the end of the input when it was
+    /// last read forward, rather than where a scan for matches had reached
+    at_end: bool,
+    /// number of the line of the underlying input at which the cache was
+    /// last positioned
+    origin: usize,
     /// criteria on which lines are filtered by `context_buffer`
     predicate: Option<iter::FilterPredicate>,
+    /// index of the lines matching `predicate`, built in the background when
+    /// the input is a memory-mapped file
+    index: Option<MatchIndex>,
+    /// progress toward the totals of the input
+    tally: Tally,
     /// width of window in columns
     width: usize,
     /// height of window in lines
     height: usize,
-    /// 1-indexed offset of line at top of window
-    start_line: usize,
-    /// 1-offset index of line at bottom of window
-    end_line: usize,
+    /// position within `buffered_lines` of line at top of window
+    start_line: Position,
+    /// position within `buffered_lines` of line at bottom of window
+    end_line: Position,
+    /// ranges of line numbers, sorted and disjoint, which have been revealed
+    /// within gaps and are shown as context lines rather than skipped
+    revealed: Vec<(usize, usize)>,
+    /// leading timestamps of the lines of the underlying input parsed so far
+    time_index: TimeIndex,
+    /// line numbers of the matches among the first `ordinals_tested` lines of
+    /// the underlying input, by which matches are numbered in order
+    ordinals: Vec<usize>,
+    /// number of lines of the underlying input tested for `ordinals`
+    ordinals_tested: usize,
 }
 
 impl<T: Iterator<Item=String>> WindowBuffer<T> {
     /// Creates a new `WindowBuffer`.
     ///
     /// ### Parameters
+    /// * `input`: underlying line source; a linewise iterator or other
+    ///   `Input`
     /// * `predicate`: optinal filtering criteria applied to underlying line
     ///   source
     /// * `width`: width of the terminal window in columns
     /// * `height`: height of the terminal window in rows
-    pub fn new(
-           iter: T,
+    pub fn new<S: Into<Input<T>>>(
+           input: S,
            predicate: Option<iter::FilterPredicate>,
            width: usize,
            height: usize) -> Self {
 
-        let line_buffer = LineBuffer::new(iter);
+        let line_buffer = LineBuffer::new(input);
+        let tally = match line_buffer.mapped_file().map(|file| file.try_clone()) {
+            Some(Ok(mut file)) => {
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || sender.send(file.read_to_end()));
+                Tally::Counting(receiver)
+            },
+            _ => Tally::Testing { lines: 0, matches: 0 },
+        };
         let context_buffer = Some(ContextBuffer::new(predicate.clone(), line_buffer));
 
-        let ret = WindowBuffer {
+        WindowBuffer {
             context_buffer: context_buffer,
-            buffered_lines: Vec::new(),
+            direction: IterDirection::FORWARD,
+            buffered_lines: VecDeque::new(),
+            at_start: true,
+            at_end: false,
+            origin: 1,
             predicate: predicate,
+            index: None,
+            tally: tally,
             width: width,
             height: height,
-            start_line: 0,
-            end_line: 0,
-        };
-
-        ret
+            start_line: Position::BEFORE_FIRST,
+            end_line: Position::BEFORE_FIRST,
+            revealed: Vec::new(),
+            time_index: TimeIndex::default(),
+            ordinals: Vec::new(),
+            ordinals_tested: 0,
+        }
     }
 
     /// Sets the filter predicate.
     ///
     /// This also has the effect of purging the buffer and setting the current
-    /// position to zero.
+    /// position to zero, and of hiding the lines revealed within gaps.
     pub fn set_predicate(&mut self, predicate: Option<iter::FilterPredicate>) {
-        let context_buffer = self.context_buffer
-            .take()
-            .expect("context_buffer must always be Some");
-
-        let mut line_buffer = context_buffer.into_line_buffer();
-        line_buffer.seek(Some(1), None);
-        self.context_buffer = Some(ContextBuffer::new(predicate.clone(), line_buffer));
+        // case: the lines matched are among those already matched, so only
+        // those need be scanned again
+        let narrowed = match (&predicate, &self.predicate, self.index.take()) {
+            (&Some(ref new), &Some(ref old), Some(index)) if new.narrows(old) => Some(index),
+            _ => None,
+        };
+        self.index = match (&predicate, self.line_buffer_mut().mapped_file()) {
+            (&Some(ref predicate), Some(file)) => file.try_clone().ok()
+                .map(|file| match narrowed {
+                    Some(index) => index.narrow(file, predicate.clone()),
+                    None => MatchIndex::spawn(file, predicate.clone()),
+                }),
+            _ => None,
+        };
         self.predicate = predicate;
-        self.buffered_lines.clear();
+        self.revealed.clear();
+        self.ordinals.clear();
+        self.ordinals_tested = 0;
+        if let Tally::Testing { .. } = self.tally {
+            self.tally = Tally::Testing { lines: 0, matches: 0 };
+        }
 
         // XXX it's probably not desireable to reset the line number to zero
         // when the filter predicate is changed
-        self.end_line = 0;
+        self.reposition(1);
     }
 
     /// Gets the next line after the line currently displayed at the bottom of
     /// the window.
     pub fn next_line(&mut self) -> Option<iter::FilteredLine> {
-        let next_line = self.end_line + 1;
+        let next_line = self.end_line.forward(Offset::ONE);
+
+        let prev_start_line = self.start_line;
+        let prev_end_line = self.end_line;
 
         let lines = self.get_lines(next_line, 1);
-        self.end_line = if lines.len() > 0 { next_line } else { self.end_line };
+        if !lines.is_empty() {
+            self.end_line = next_line;
+            self.start_line = self.page_start(next_line);
+        } else {
+            self.start_line = prev_start_line;
+            self.end_line = prev_end_line;
+        }
 
         lines.first().map(|line| line.to_owned())
     }
 
     /// Gets the line before the line at the top of the window.
     pub fn prev_line(&mut self) -> Option<iter::FilteredLine> {
-        if self.end_line as i64 - self.height as i64 <= 0 {
-            // case already at the beginning; can't go back farther
-            return None
+        let height = Position::new(self.height);
+        if self.end_line <= height {
+            // case: the line above the window hasn't been read yet
+            let missing = height.since(self.end_line).get() + 1;
+            self.fill_front(missing);
         }
 
-        let next_line = if self.end_line - self.height > 0 { self.end_line - self.height } else { 0 };
-        let new_end_line = if self.end_line > 0 { self.end_line - 1 } else { 0 };
+        // case already at the beginning; can't go back farther
+        let next_line = self.end_line.checked_back(Offset::new(self.height))?;
+        let new_end_line = self.end_line.back(Offset::ONE);
+
+        let prev_start_line = self.start_line;
+        let prev_end_line = self.end_line;
 
         let lines = self.get_lines(next_line, 1);
-        self.end_line = if lines.len() > 0 { new_end_line } else { self.end_line };
+        if !lines.is_empty() {
+            self.end_line = new_end_line;
+        } else {
+            self.start_line = prev_start_line;
+            self.end_line = prev_end_line;
+        }
 
         lines.first().map(|line| line.to_owned())
     }
 
+    /// Gets up to `count` lines following the line at the bottom of the
+    /// window, moving the window down past them, e.g. for `5j`.
+    ///
+    /// Fewer are returned if the end of the filtered lines is reached.
+    pub fn next_lines(&mut self, count: usize) -> Vec<iter::FilteredLine> {
+        (0..count).map_while(|_| self.next_line()).collect()
+    }
+
+    /// Gets up to `count` lines preceding the line at the top of the window,
+    /// in order from top to bottom, moving the window up past them.
+    ///
+    /// Fewer are returned if the start of the filtered lines is reached.
+    pub fn prev_lines(&mut self, count: usize) -> Vec<iter::FilteredLine> {
+        let mut lines: Vec<iter::FilteredLine> = (0..count)
+            .map_while(|_| self.prev_line())
+            .collect();
+        lines.reverse();
+        lines
+    }
+
+    /// Moves the window down `lines` lines, or up if `lines` is negative,
+    /// stopping at either end of the filtered lines.
+    ///
+    /// Returns the lines brought into view, in order from top to bottom.
+    pub fn scroll(&mut self, lines: i64) -> Vec<iter::FilteredLine> {
+        let count = lines.unsigned_abs() as usize;
+        if lines >= 0 {
+            self.next_lines(count)
+        } else {
+            self.prev_lines(count)
+        }
+    }
+
     /// Gets a page full of lines beginning after the line currently displayed
     /// at the bottom of the window.
+    ///
+    /// If no lines follow the window, it stays where it is and no lines are
+    /// returned.
     pub fn next_page(&mut self) -> Vec<iter::FilteredLine> {
-        let start_line = self.end_line + 1;
+        let start_line = self.end_line.forward(Offset::ONE);
         let num_lines = self.height;
+
+        let prev_start_line = self.start_line;
+        let prev_end_line = self.end_line;
+
         let lines = self.get_lines(start_line, num_lines);
+        if lines.is_empty() {
+            self.start_line = prev_start_line;
+            self.end_line = prev_end_line;
+        }
 
         lines
     }
@@ -111,27 +282,978 @@ impl<T: Iterator<Item=String>> WindowBuffer<T> {
     /// Gets a page full of lines ending before the line currently displayed
     /// at the top of the window.
     pub fn prev_page(&mut self) -> Vec<iter::FilteredLine> {
-        let start_line = if self.start_line as i64 - self.height as i64 >= 1 {
-            self.start_line - self.height
-        } else {
-            1
-        };
+        let height = Position::new(self.height);
+        if self.start_line <= height {
+            // case: lines above the window haven't been read yet
+            let missing = height.since(self.start_line).get() + 1;
+            self.fill_front(missing);
+        }
+
+        let start_line = self.start_line.back(Offset::new(self.height));
 
+        let num_lines = self.height;
+
+        self.get_lines(start_line, num_lines)
+    }
+
+    /// Gets a page full of lines beginning with the line currently displayed
+    /// at the top of the window.
+    pub fn current_page(&mut self) -> Vec<iter::FilteredLine> {
+        let start_line = self.start_line.max(Position::FIRST);
         let num_lines = self.height;
         let lines = self.get_lines(start_line, num_lines);
 
+        if lines.len() < num_lines && self.scan_progress().is_none() {
+            // case: a page was left short by `goto_line()` while matches were
+            // being scanned for, and the end of the input has since been found
+            let missing = num_lines - lines.len();
+            if self.fill_front(missing) > 0 {
+                let start_line = self.page_start(self.end_line);
+                return self.get_lines(start_line, num_lines);
+            }
+        }
+
+        lines
+    }
+
+    /// Gets the page full of lines at the end of the input.
+    ///
+    /// All lines currently available from the underlying iterator are read.
+    /// Unless only a few lines remain to be filtered, the lines of the last
+    /// page are filtered backward from the end of the input rather than
+    /// filtering every line in between.
+    pub fn last_page(&mut self) -> Vec<iter::FilteredLine> {
+        let total = self.line_buffer_mut().read_to_end();
+        let remaining = match self.direction {
+            // the iterator may lie past the last line, e.g. after going to
+            // a line beyond the end of the input
+            IterDirection::FORWARD => {
+                Position::new(total).since(Position::new(self.line_buffer_mut().last_iter_line()))
+            },
+            IterDirection::BACKWARD => Position::new(total).since(Position::BEFORE_FIRST),
+        };
+
+        if remaining > Offset::new(self.height) {
+            self.reposition(total + 1);
+            let num_lines = self.height;
+            self.fill_front(num_lines);
+        }
+
+        self.read_to_end();
+
+        let num_lines = self.height;
+        let start_line = self.page_start(Position::new(self.buffered_lines.len()));
+
+        self.get_lines(start_line, num_lines)
+    }
+
+    /// Gets a page full of lines beginning at the line lying `fraction` of
+    /// the way through the underlying input, or the last page if fewer lines
+    /// than fill a page follow it.
+    ///
+    /// For a memory-mapped file the line is found from the file's size;
+    /// otherwise every line currently available is read to count them.
+    ///
+    /// ### Parameters
+    /// * `fraction`: position within the input, from 0.0 for the first line
+    ///   to 1.0 for the last
+    pub fn goto_fraction(&mut self, fraction: f64) -> Vec<iter::FilteredLine> {
+        let line_num = self.line_buffer_mut().line_at_fraction(fraction);
+        self.goto_line(line_num.max(1))
+    }
+
+    /// Sets the parser of the timestamps at the beginning of lines by which
+    /// `line_at_time()` finds lines, in place of `timestamp::parse_leading`.
+    pub fn set_time_parser(&mut self, parser: Parser) {
+        self.time_index = TimeIndex::new(parser);
+    }
+
+    /// Returns the number of the first line of the underlying input whose
+    /// leading timestamp is at or after `millis`, irrespective of the filter
+    /// predicate, or `None` if there's no such line.
+    ///
+    /// Every line currently available is read, as for `goto_fraction()`, but
+    /// only those probed by a binary search are parsed.
+    ///
+    /// ### Parameters
+    /// * `millis`: milliseconds since the Unix epoch
+    pub fn line_at_time(&mut self, millis: i64) -> Option<usize> {
+        let line_buffer = self.context_buffer
+            .as_mut()
+            .expect("context_buffer must always be Some")
+            .line_buffer_mut();
+        let num_lines = line_buffer.read_to_end();
+
+        self.time_index.first_at(millis, num_lines, |line_num| {
+            line_buffer.get(line_num).map(|(_, line)| line)
+        })
+    }
+
+    /// Returns the number of the line of the underlying input nearest line
+    /// `line_num` whose text is `text`, irrespective of the filter predicate,
+    /// or `None` if there's no such line within `radius` lines of it.
+    ///
+    /// This finds where a line has moved to when the input is read afresh,
+    /// e.g. after lines were inserted or removed before it.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number at which the line was last seen
+    /// * `text`: text of the line
+    /// * `radius`: most lines away from `line_num` the line may be found
+    pub fn find_line_near(&mut self, line_num: usize, text: &str, radius: usize)
+                          -> Option<usize> {
+        let line_buffer = self.line_buffer_mut();
+        let mut has_text = |line_num: usize| {
+            line_num > 0 && line_buffer.get(line_num).is_some_and(|(_, line)| line == text)
+        };
+
+        (0..radius + 1).flat_map(|distance| {
+            let after = line_num.checked_add(distance);
+            let before = line_num.checked_sub(distance).filter(|_| distance > 0);
+            after.into_iter().chain(before)
+        }).find(|&line_num| has_text(line_num))
+    }
+
+    /// Gets a page full of lines beginning at line `line_num` of the
+    /// underlying input, or at the first line after it selected by the
+    /// filter predicate; or the last page if fewer lines than fill a page
+    /// follow it.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number within the underlying input
+    pub fn goto_line(&mut self, line_num: usize) -> Vec<iter::FilteredLine> {
+        self.reposition(line_num);
+
+        let num_lines = self.height;
+        self.fill_buffer(num_lines);
+
+        // while matches are being scanned for, a short page may only mean the
+        // scan hasn't reached the following matches yet, whereas earlier ones
+        // may already have been found; `current_page()` fills the page once
+        // the scan is done
+        if self.scan_progress().is_none() {
+            let missing = num_lines.saturating_sub(self.buffered_lines.len());
+            self.fill_front(missing);
+        }
+
+        self.get_lines(Position::FIRST, num_lines)
+    }
+
+    /// Returns the line number within the underlying input of the first line
+    /// shown in the window, or `None` if the window shows no lines.
+    pub fn top_line_num(&self) -> Option<usize> {
+        self.page()
+            .filter_map(|line| line.line_num())
+            .next()
+    }
+
+    /// Returns the line shown in row `row` of the window, e.g. the row of a
+    /// cursor, or `None` if the row is empty.
+    ///
+    /// ### Parameters
+    /// * `row`: 0-indexed row of the window
+    pub fn line_at_row(&mut self, row: usize) -> Option<iter::FilteredLine> {
+        self.current_page().into_iter().nth(row)
+    }
+
+    /// Returns the lines read so far, leaving out gaps, whose line numbers
+    /// within the underlying input lie between `first` and `last` inclusive,
+    /// e.g. those of a selection made by moving through them.
+    ///
+    /// ### Parameters
+    /// * `first`: 1-indexed line number of the first line
+    /// * `last`: 1-indexed line number of the last line
+    pub fn lines_between(&self, first: usize, last: usize) -> Vec<iter::FilteredLine> {
+        self.buffered_lines.iter()
+            .filter(|line| line.line_num().is_some_and(|n| n >= first && n <= last))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the line number within the underlying input of the line shown
+    /// in row `row` of the window, or `None` if the row shows a gap or is
+    /// empty.
+    ///
+    /// ### Parameters
+    /// * `row`: 0-indexed row of the window
+    pub fn line_num_at_row(&self, row: usize) -> Option<usize> {
+        self.page().nth(row).and_then(|line| line.line_num())
+    }
+
+    /// Returns the row of the window in which line `line_num` of the
+    /// underlying input is shown, or `None` if it isn't shown.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number within the underlying input
+    pub fn row_of_line_num(&self, line_num: usize) -> Option<usize> {
+        self.page().position(|line| line.line_num() == Some(line_num))
+    }
+
+    /// Returns the ordinal of line `line_num` of the underlying input among
+    /// the lines matching the filter predicate, e.g. 7 for the seventh match,
+    /// or `None` if it doesn't match or there's no such line. Without a
+    /// predicate every line is counted, and so is its own ordinal.
+    ///
+    /// The ordinal is looked up in the index of matches if one is being
+    /// built, and is `None` until the scan has reached the line; otherwise
+    /// the lines before it are tested for matches if they haven't been yet.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number within the underlying input
+    pub fn ordinal_of_line(&mut self, line_num: usize) -> Option<usize> {
+        if self.predicate.is_none() {
+            return self.get_raw_line(line_num).map(|(line_num, _)| line_num);
+        }
+        if let Some(ref index) = self.index {
+            return index.ordinal_of(line_num);
+        }
+        self.number_matches(|_, tested| tested >= line_num);
+        self.ordinals.binary_search(&line_num).ok().map(|idx| idx + 1)
+    }
+
+    /// Returns the line number within the underlying input of the match with
+    /// the given ordinal, as told by `ordinal_of_line()`, or `None` if there
+    /// are fewer matches, or the scan of an index of matches hasn't found it
+    /// yet.
+    ///
+    /// ### Parameters
+    /// * `ordinal`: 1-indexed ordinal of the match
+    pub fn line_of_ordinal(&mut self, ordinal: usize) -> Option<usize> {
+        if ordinal == 0 {
+            return None;
+        }
+        if self.predicate.is_none() {
+            return self.get_raw_line(ordinal).map(|(line_num, _)| line_num);
+        }
+        if let Some(ref index) = self.index {
+            return index.nth_match(ordinal);
+        }
+        self.number_matches(|ordinals, _| ordinals.len() >= ordinal);
+        self.ordinals.get(ordinal - 1).cloned()
+    }
+
+    /// Returns the ordinal among the matches of the line shown in row `row`
+    /// of the window, or `None` if the row doesn't show a match.
+    ///
+    /// ### Parameters
+    /// * `row`: 0-indexed row of the window
+    pub fn ordinal_at_row(&mut self, row: usize) -> Option<usize> {
+        let line_num = self.page().nth(row)
+            .filter(|line| line.is_match() || self.predicate.is_none())
+            .and_then(|line| line.line_num())?;
+        self.ordinal_of_line(line_num)
+    }
+
+    /// Returns the row of the window in which the match with the given
+    /// ordinal is shown, or `None` if it isn't shown.
+    ///
+    /// ### Parameters
+    /// * `ordinal`: 1-indexed ordinal of the match
+    pub fn row_of_ordinal(&mut self, ordinal: usize) -> Option<usize> {
+        let line_num = self.line_of_ordinal(ordinal)?;
+        self.row_of_line_num(line_num)
+    }
+
+    /// Tests the lines of the underlying input after those tested so far for
+    /// matches of the filter predicate, in order, until `enough` is true of
+    /// the matches found and the number of lines tested, or the lines run out.
+    fn number_matches<F: Fn(&[usize], usize) -> bool>(&mut self, enough: F) {
+        let predicate = match self.predicate {
+            Some(ref predicate) => predicate,
+            None => return,
+        };
+        let line_buffer = self.context_buffer
+            .as_mut()
+            .expect("context_buffer must always be Some")
+            .line_buffer_mut();
+
+        let omitted = line_buffer.omitted_line();
+        while !enough(&self.ordinals, self.ordinals_tested) {
+            let line_num = self.ordinals_tested + 1;
+            let matched = match line_buffer.get(line_num) {
+                Some((line_num, line)) => {
+                    Some(line_num) != omitted && predicate.is_match(line_num, &line)
+                },
+                None => return,
+            };
+            if matched {
+                self.ordinals.push(line_num);
+            }
+            self.ordinals_tested = line_num;
+        }
+    }
+
+    /// Returns the line number within the underlying input of the match
+    /// shown nearest to row `row` of the window, preferring the earlier of
+    /// two equally near, or `None` if no matches are shown.
+    ///
+    /// ### Parameters
+    /// * `row`: 0-indexed row of the window
+    pub fn nearest_match(&self, row: usize) -> Option<usize> {
+        self.page()
+            .enumerate()
+            .filter(|&(_, line)| line.is_match())
+            .min_by_key(|&(match_row, _)| (match_row as i64 - row as i64).abs())
+            .and_then(|(_, line)| line.line_num())
+    }
+
+    /// Returns row `row` of the window if it shows a gap, or else the first
+    /// row which does, or `None` if the window shows no gaps.
+    ///
+    /// ### Parameters
+    /// * `row`: 0-indexed row of the window
+    pub fn gap_row(&mut self, row: usize) -> Option<usize> {
+        let page = self.current_page();
+        if page.get(row).is_some_and(|line| line.is_gap()) {
+            return Some(row);
+        }
+        page.iter().position(|line| line.is_gap())
+    }
+
+    /// Replaces the gap shown in row `row` of the window with the lines it
+    /// stands for, as context lines, returning the number of lines restored
+    /// or `None` if the row doesn't show a gap.
+    ///
+    /// At most `MAX_GAP_EXPANSION` lines are restored at once, followed by a
+    /// gap standing for the rest. The window keeps its top line; the next
+    /// call to `current_page()` returns the page with the lines restored.
+    ///
+    /// ### Parameters
+    /// * `row`: 0-indexed row of the window
+    pub fn expand_gap(&mut self, row: usize) -> Option<usize> {
+        self.reveal_in_gap(row, |from, to| {
+            vec![(from, to.min(from.saturating_add(MAX_GAP_EXPANSION - 1)))]
+        })
+    }
+
+    /// Reveals up to `num_lines` lines at either end of the gap shown in row
+    /// `row` of the window, like the expanders between the hunks of a diff,
+    /// returning the number of lines revealed or `None` if the row doesn't
+    /// show a gap.
+    ///
+    /// The lines revealed stay shown as context lines until the filter
+    /// predicate is changed, even once the window has moved away from them.
+    ///
+    /// ### Parameters
+    /// * `row`: 0-indexed row of the window
+    /// * `num_lines`: number of lines to reveal below the lines above the gap,
+    ///   and again above the lines below it
+    pub fn reveal_context(&mut self, row: usize, num_lines: usize) -> Option<usize> {
+        let num_lines = num_lines.clamp(1, MAX_GAP_EXPANSION);
+        self.reveal_in_gap(row, |from, to| {
+            vec![(from, to.min(from + num_lines - 1)),
+                 (from.max(to.saturating_sub(num_lines - 1)), to)]
+        })
+    }
+
+    /// Reveals the ranges of line numbers returned by `ranges`, given the
+    /// first and last lines skipped by the gap shown in row `row`, and
+    /// replaces the gap with the lines it now stands for.
+    fn reveal_in_gap<F>(&mut self, row: usize, ranges: F) -> Option<usize>
+        where F: Fn(usize, usize) -> Vec<(usize, usize)> {
+
+        let (from, to) = match self.page().nth(row) {
+            Some(&iter::FilteredLine::Gap { from, to }) => (from, to),
+            _ => return None,
+        };
+
+        for (first, last) in ranges(from, to) {
+            self.add_revealed(first, last);
+        }
+        let lines = self.reveal(from, to);
+        let hidden = lines.iter().filter_map(|line| line.skipped()).sum::<usize>();
+
+        let idx = self.start_line.since(Position::FIRST).get() + row;
+        let mut rest = self.buffered_lines.split_off(idx + 1);
+        self.buffered_lines.pop_back();
+        self.buffered_lines.extend(lines);
+        self.buffered_lines.append(&mut rest);
+
+        Some(to + 1 - from - hidden)
+    }
+
+    /// Adds the lines from `first` to `last` to those revealed, merging
+    /// ranges which overlap or adjoin.
+    fn add_revealed(&mut self, first: usize, last: usize) {
+        let (mut first, mut last) = (first, last);
+        self.revealed.retain(|&(from, to)| {
+            let separate = to + 1 < first || last + 1 < from;
+            if !separate {
+                first = first.min(from);
+                last = last.max(to);
+            }
+            separate
+        });
+
+        let idx = self.revealed.iter().position(|&(from, _)| from > last)
+            .unwrap_or(self.revealed.len());
+        self.revealed.insert(idx, (first, last));
+    }
+
+    /// Returns what stands for the lines from `from` to `to`, all skipped by
+    /// the filter: context lines for those revealed, and gaps for the rest.
+    fn reveal(&mut self, from: usize, to: usize) -> Vec<iter::FilteredLine> {
+        let revealed: Vec<(usize, usize)> = self.revealed.iter()
+            .filter(|&&(first, last)| first <= to && last >= from)
+            .map(|&(first, last)| (first.max(from), last.min(to)))
+            .collect();
+
+        let mut lines = Vec::new();
+        let mut next = from;
+        for (first, last) in revealed {
+            if first > next {
+                lines.push(iter::FilteredLine::Gap { from: next, to: first - 1 });
+            }
+            for line_num in first..=last {
+                match self.get_raw_line(line_num) {
+                    Some(line) => lines.push(iter::FilteredLine::ContextLine(line)),
+                    None => return vec![iter::FilteredLine::Gap { from: from, to: to }],
+                }
+            }
+            next = last + 1;
+        }
+        if next <= to {
+            lines.push(iter::FilteredLine::Gap { from: next, to: to });
+        }
         lines
     }
 
+    /// Returns the lines shown in the window, from top to bottom.
+    fn page<'a>(&'a self) -> Box<dyn Iterator<Item=&'a iter::FilteredLine> + 'a> {
+        let above = self.start_line.since(Position::FIRST).get();
+        Box::new(self.buffered_lines.iter()
+            .skip(above)
+            .take(self.end_line.since(Position::new(above)).get()))
+    }
+
+    /// Returns the position of the line at the top of a window whose bottom
+    /// line is at `end_line`, or of the first line if too few lines precede
+    /// it to fill the window.
+    fn page_start(&self, end_line: Position) -> Position {
+        end_line.back(Offset::new(self.height.saturating_sub(1)))
+    }
+
+    /// Reads all lines currently available from the underlying iterator,
+    /// returning the ones which hadn't been read before.
+    ///
+    /// When following a growing input this can be called repeatedly to pick
+    /// up lines appended since the last call.
+    pub fn read_to_end(&mut self) -> Vec<iter::FilteredLine> {
+        let num_lines = self.buffered_lines.len();
+
+        while let Some(line) = self.next_forward() {
+            self.push_back(line);
+        }
+
+        self.buffered_lines.iter().skip(num_lines).cloned().collect()
+    }
+
+    /// Returns every line of the input, as classified by the filter
+    /// predicate.
+    ///
+    /// All lines currently available from the underlying iterator are read,
+    /// waiting for any scan for matches in the background to finish.
+    pub fn all_lines(&mut self) -> &VecDeque<iter::FilteredLine> {
+        if let Some(ref index) = self.index {
+            index.wait();
+        }
+        self.fill_front(usize::MAX);
+        self.read_to_end();
+        &self.buffered_lines
+    }
+
+    /// Returns the first and last lines of each stretch of the input skipped
+    /// by the filter predicate, in order, including that after the last line
+    /// selected; or none if there's no filter predicate.
+    ///
+    /// All lines currently available are read, as by `all_lines()`.
+    pub fn gaps(&mut self) -> Vec<(usize, usize)> {
+        if self.predicate.is_none() {
+            return Vec::new();
+        }
+
+        let mut gaps = Vec::new();
+        let mut last = 0;
+        for line in self.all_lines() {
+            match *line {
+                iter::FilteredLine::Gap { from, to } => {
+                    gaps.push((from, to));
+                    last = to;
+                },
+                _ => last = line.line_num().unwrap_or(last),
+            }
+        }
+
+        let total = self.lines_read();
+        if last < total {
+            gaps.push((last + 1, total));
+        }
+        gaps
+    }
+
+    /// Looks up the first line matched by the filter predicate, leaving the
+    /// window where it is.
+    ///
+    /// The lines of an iterator are read until one matches; those of a
+    /// memory-mapped file are looked up in the background scan for matches,
+    /// which may not have found one yet.
+    pub fn first_match(&mut self) -> Search {
+        let predicate = match self.predicate {
+            Some(ref predicate) => predicate.clone(),
+            None => return Search::Found(None),
+        };
+        if let Some(ref index) = self.index {
+            return match index.next_after(0) {
+                (Some(line_num), _, _) => Search::Found(Some(line_num)),
+                (None, _, true) => Search::Found(None),
+                (None, _, false) => Search::Pending,
+            };
+        }
+
+        let line_buffer = self.line_buffer_mut();
+        let mut line_num = 1;
+        while let Some((n, line)) = line_buffer.get(line_num) {
+            if predicate.is_match(n, &line) {
+                return Search::Found(Some(n));
+            }
+            line_num += 1;
+        }
+        Search::Found(None)
+    }
+
+    /// Looks up the last line matched by the filter predicate, leaving the
+    /// window where it is.
+    ///
+    /// All lines currently available from an iterator are read, and tested
+    /// from the last one back; those of a memory-mapped file are looked up
+    /// once the background scan for matches has reached the end of the file.
+    pub fn last_match(&mut self) -> Search {
+        let predicate = match self.predicate {
+            Some(ref predicate) => predicate.clone(),
+            None => return Search::Found(None),
+        };
+        if let Some(ref index) = self.index {
+            return match index.last() {
+                (line_num, true) => Search::Found(line_num),
+                (_, false) => Search::Pending,
+            };
+        }
+
+        let line_buffer = self.line_buffer_mut();
+        let total = line_buffer.read_to_end();
+        let found = (1..=total).rev().find(|&line_num| {
+            line_buffer.get(line_num).is_some_and(|(n, line)| predicate.is_match(n, &line))
+        });
+        Search::Found(found)
+    }
+
+    /// Returns the percentage of the input scanned for matches in the
+    /// background, or `None` if no scan is in progress.
+    ///
+    /// Until the scan is done, reading forward stops at the lines it hasn't
+    /// reached yet.
+    pub fn scan_progress(&self) -> Option<usize> {
+        self.index.as_ref().and_then(|index| index.progress())
+    }
+
+    /// Returns the numbers of lines and matches in the whole input, or `None`
+    /// if they aren't known yet.
+    ///
+    /// The lines of a memory-mapped file are counted, and tested for matches,
+    /// in the background. Those of an iterator are counted once it's been
+    /// exhausted, e.g. by `last_page()`, after which each call tests another
+    /// batch of them for matches; if more lines are read later, e.g. while
+    /// following, they're tested in turn.
+    pub fn totals(&mut self) -> Option<Totals> {
+        if let Some(ref index) = self.index {
+            return index.totals().map(|(lines, matches)| {
+                Totals { lines: lines, matches: Some(matches) }
+            });
+        }
+
+        let counted = match self.tally {
+            Tally::Counting(ref receiver) => receiver.try_recv().ok(),
+            Tally::Counted(lines) => return Some(Totals { lines: lines, matches: None }),
+            Tally::Testing { .. } => None,
+        };
+        if let Some(lines) = counted {
+            self.tally = Tally::Counted(lines);
+            return Some(Totals { lines: lines, matches: None });
+        }
+
+        let predicate = self.predicate.clone();
+        let line_buffer = self.context_buffer
+            .as_mut()
+            .expect("context_buffer must always be Some")
+            .line_buffer_mut();
+        let (tested, matched) = match self.tally {
+            Tally::Testing { ref mut lines, ref mut matches } => (lines, matches),
+            _ => return None,
+        };
+
+        let total = line_buffer.len();
+        if !line_buffer.is_exhausted() {
+            return None;
+        }
+
+        let predicate = match predicate {
+            Some(predicate) => predicate,
+            None => return Some(Totals { lines: total, matches: None }),
+        };
+
+        let end = total.min(*tested + TALLY_BATCH_LINES);
+        while *tested < end {
+            *tested += 1;
+            if line_buffer.get(*tested).is_some_and(|(n, line)| predicate.is_match(n, &line)) {
+                *matched += 1;
+            }
+        }
+
+        if *tested == total {
+            Some(Totals { lines: total, matches: Some(*matched) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns an estimate of the number of lines in a memory-mapped file,
+    /// or `None` if the input isn't one or none of it has been read.
+    ///
+    /// The estimate assumes the lines not yet read are the same length on
+    /// average as those read so far, and so is refined as more are read; it's
+    /// exact once the whole file has been read.
+    pub fn estimated_lines(&self) -> Option<usize> {
+        let file = self.context_buffer
+            .as_ref()
+            .expect("context_buffer must always be Some")
+            .line_buffer()
+            .mapped_file()?;
+
+        match file.bytes_indexed() {
+            0 => None,
+            indexed => Some((file.len() as f64 * file.byte_len() as f64 / indexed as f64) as usize),
+        }
+    }
+
+    /// Returns `true` while the totals of the input are being computed, i.e.
+    /// `totals()` is worth calling again soon.
+    pub fn is_tallying(&self) -> bool {
+        if self.index.is_some() {
+            return self.scan_progress().is_some();
+        }
+
+        match self.tally {
+            Tally::Counting(_) => true,
+            Tally::Counted(_) => false,
+            Tally::Testing { lines, .. } => {
+                let line_buffer = self.context_buffer
+                    .as_ref()
+                    .expect("context_buffer must always be Some")
+                    .line_buffer();
+                self.predicate.is_some() && line_buffer.is_exhausted() && lines < line_buffer.len()
+            },
+        }
+    }
+
+    /// Returns the number of lines read from the underlying input so far,
+    /// irrespective of the filter predicate.
+    pub fn lines_read(&self) -> usize {
+        self.context_buffer
+            .as_ref()
+            .expect("context_buffer must always be Some")
+            .line_buffer()
+            .len()
+    }
+
+    /// Returns the number of lines in the underlying input, irrespective of
+    /// the filter predicate, or `None` if it isn't known yet.
+    ///
+    /// It's known once an iterator has been exhausted or a memory-mapped file
+    /// has been scanned to its end, and stays so until more lines are read,
+    /// e.g. while following.
+    pub fn total_lines(&self) -> Option<usize> {
+        if let Some((lines, _)) = self.index.as_ref().and_then(|index| index.totals()) {
+            return Some(lines);
+        }
+        if let Tally::Counted(lines) = self.tally {
+            return Some(lines);
+        }
+
+        let line_buffer = self.context_buffer
+            .as_ref()
+            .expect("context_buffer must always be Some")
+            .line_buffer();
+        match line_buffer.mapped_file() {
+            Some(file) if file.bytes_indexed() == file.byte_len() => Some(file.len()),
+            Some(_) => None,
+            None if line_buffer.is_exhausted() => Some(line_buffer.len()),
+            None => None,
+        }
+    }
+
+    /// Returns `true` if the window shows the last of the lines selected by
+    /// the filter predicate, i.e. no more follow it unless the input grows.
+    ///
+    /// While matches are scanned for in the background, the lines the scan
+    /// hasn't reached yet aren't shown, so a window at the bottom of the
+    /// lines found so far isn't counted as exhausted.
+    pub fn is_exhausted(&self) -> bool {
+        self.at_end && self.end_line == Position::new(self.buffered_lines.len())
+    }
+
+    /// Sets the limit on the bytes of line text held in memory, beyond which
+    /// lines are spilled to a temporary file, or removes the limit if
+    /// `max_bytes` is `None`.
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.line_buffer_mut().set_max_bytes(max_bytes);
+    }
+
+    /// Sets the number of lines read off the input at a time, as by
+    /// `LineBuffer::set_block_lines()`.
+    pub fn set_block_lines(&mut self, lines: usize) {
+        self.line_buffer_mut().set_block_lines(lines);
+    }
+
+    /// Gets a line of the underlying input irrespective of the filter
+    /// predicate, or `None` for the line standing for lines omitted from the
+    /// input, if any, which isn't one of its lines.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number within the underlying input
+    pub fn get_raw_line(&mut self, line_num: usize) -> Option<iter::NumberedLine> {
+        if self.line_buffer_mut().omitted_line() == Some(line_num) {
+            return None;
+        }
+        self.line_buffer_mut().get(line_num)
+    }
+
+    /// Returns the text of the line standing for lines omitted from the
+    /// input, e.g. `510 bytes skipped` between the head and tail of a sample,
+    /// if the gap from line `from` to line `to` stands for it alone.
+    pub fn omission(&mut self, from: usize, to: usize) -> Option<String> {
+        let line_buffer = self.line_buffer_mut();
+        line_buffer.omitted_line()
+            .filter(|&line_num| line_num == from && line_num == to)
+            .and_then(|line_num| line_buffer.get(line_num))
+            .map(|(_, line)| line)
+    }
+
+    /// Sets the dimensions of the window.
+    ///
+    /// The line at the top of the window stays put; the next call to
+    /// `current_page()` returns a page sized to the new dimensions.
+    ///
+    /// ### Parameters
+    /// * `width`: width of the terminal window in columns
+    /// * `height`: height of the terminal window in rows
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Discards all cached lines and positions the window just above line
+    /// `line_num` of the underlying input.
+    ///
+    /// Lines following the position are then read forward and lines
+    /// preceding it backward, as needed.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number within the underlying input
+    fn reposition(&mut self, line_num: usize) {
+        let line_buffer = self.context_buffer
+            .take()
+            .expect("context_buffer must always be Some")
+            .into_line_buffer();
+
+        self.context_buffer = Some(self.context_buffer_at(
+                line_buffer, line_num, IterDirection::FORWARD));
+        self.direction = IterDirection::FORWARD;
+        self.buffered_lines.clear();
+        self.at_start = line_num <= 1;
+        self.at_end = false;
+        self.origin = line_num;
+        self.start_line = Position::FIRST;
+        self.end_line = Position::BEFORE_FIRST;
+    }
+
+    /// Returns the inner `LineBuffer`.
+    fn line_buffer_mut(&mut self) -> &mut LineBuffer<T> {
+        self.context_buffer
+            .as_mut()
+            .expect("context_buffer must always be Some")
+            .line_buffer_mut()
+    }
+
+    /// Points `context_buffer` in `direction`, resuming from the
+    /// corresponding end of the cache.
+    fn turn(&mut self, direction: IterDirection) {
+        if self.direction == direction {
+            return;
+        }
+
+        let line_num = match direction {
+            IterDirection::FORWARD => self.buffered_lines.iter().rev()
+                .filter_map(|line| line.line_num())
+                .next()
+                .map(|line_num| line_num + 1)
+                .unwrap_or(self.origin),
+            IterDirection::BACKWARD => self.buffered_lines.iter()
+                .filter_map(|line| line.line_num())
+                .next()
+                .unwrap_or(self.origin) - 1,
+        };
+
+        let line_buffer = self.context_buffer
+            .take()
+            .expect("context_buffer must always be Some")
+            .into_line_buffer();
+
+        self.context_buffer = Some(self.context_buffer_at(
+                line_buffer, line_num, direction.clone()));
+        self.direction = direction;
+    }
+
+    /// Returns a `ContextBuffer` over `line_buffer` which applies the
+    /// predicate and index, beginning at `line_num` and proceeding in
+    /// `direction`.
+    fn context_buffer_at(&self, line_buffer: LineBuffer<T>, line_num: usize,
+                         direction: IterDirection) -> ContextBuffer<T> {
+        let mut context_buffer = ContextBuffer::starting_at(
+            self.predicate.clone(), line_buffer, line_num, direction);
+        context_buffer.set_index(self.index.clone());
+        context_buffer
+    }
+
+    /// Returns the line following the end of the cache.
+    fn next_forward(&mut self) -> Option<iter::FilteredLine> {
+        self.turn(IterDirection::FORWARD);
+
+        // the scan must be checked for first, lest it finish after stopping
+        // short of the end
+        let scanning = self.scan_progress().is_some();
+        let line = self.context_buffer
+            .as_mut()
+            .expect("context_buffer must always be Some")
+            .next();
+
+        self.at_end = line.is_none() && !scanning;
+        line
+    }
+
+    /// Returns the line preceding the start of the cache.
+    fn next_backward(&mut self) -> Option<iter::FilteredLine> {
+        self.turn(IterDirection::BACKWARD);
+        self.context_buffer
+            .as_mut()
+            .expect("context_buffer must always be Some")
+            .next()
+    }
+
+    /// Appends a line to the cache, preceded by a gap if lines of the input
+    /// were skipped since the last cached line.
+    ///
+    /// Lines revealed within a gap are appended in its place.
+    fn push_back(&mut self, line: iter::FilteredLine) {
+        let prev = self.buffered_lines.back();
+        let after_gap = prev.is_some_and(|prev| prev.is_gap());
+        let gap = match (prev, line.line_num()) {
+            _ if after_gap => None,
+            (_, None) => None,
+            (Some(prev), Some(line_num)) => prev.line_num()
+                .filter(|&n| line_num > n + 1)
+                .map(|n| (n + 1, line_num - 1)),
+            (None, Some(line_num)) => Some((1, line_num - 1))
+                .filter(|_| self.at_start && line_num > 1),
+        };
+
+        let mut lines = match gap {
+            Some((from, to)) => self.reveal(from, to),
+            None => Vec::new(),
+        };
+        match line {
+            iter::FilteredLine::Gap { from, to } => {
+                if gap.is_none() && !after_gap {
+                    lines.extend(self.reveal(from, to));
+                }
+            },
+            line => lines.push(line),
+        }
+
+        self.buffered_lines.extend(lines);
+    }
+
+    /// Prepends a line to the cache, followed by a gap if lines of the input
+    /// were skipped between it and the first cached line.
+    ///
+    /// Lines revealed within a gap are prepended in its place.
+    fn push_front(&mut self, line: iter::FilteredLine) {
+        let next = self.buffered_lines.front();
+        let before_gap = next.is_some_and(|next| next.is_gap());
+        let gap = match (next, line.line_num()) {
+            _ if before_gap => None,
+            (_, None) | (None, _) => None,
+            (Some(next), Some(line_num)) => next.line_num()
+                .filter(|&n| n > line_num + 1)
+                .map(|n| (line_num + 1, n - 1)),
+        };
+
+        let mut lines = match gap {
+            Some((from, to)) => self.reveal(from, to),
+            None => Vec::new(),
+        };
+        match line {
+            iter::FilteredLine::Gap { from, to } => {
+                if gap.is_none() && !before_gap {
+                    lines = self.reveal(from, to);
+                }
+            },
+            line => lines.insert(0, line),
+        }
+
+        for line in lines.into_iter().rev() {
+            self.buffered_lines.push_front(line);
+        }
+    }
+
+    /// Reads up to `num_lines` lines preceding the start of the cache,
+    /// shifting the window so that it continues to show the same lines.
+    ///
+    /// Returns the number of lines added to the cache.
+    fn fill_front(&mut self, num_lines: usize) -> usize {
+        let num_cached = self.buffered_lines.len();
+        let limit = num_cached.saturating_add(num_lines);
+
+        while !self.at_start && self.buffered_lines.len() < limit {
+            match self.next_backward() {
+                Some(line) => self.push_front(line),
+                None => {
+                    self.at_start = true;
+
+                    let first_line_num = self.buffered_lines.front().and_then(|line| line.line_num());
+                    if let Some(line_num) = first_line_num.filter(|&line_num| line_num > 1) {
+                        for line in self.reveal(1, line_num - 1).into_iter().rev() {
+                            self.buffered_lines.push_front(line);
+                        }
+                    }
+                },
+            }
+        }
+
+        let added = self.buffered_lines.len() - num_cached;
+        self.start_line = self.start_line.forward(Offset::new(added));
+        self.end_line = self.end_line.forward(Offset::new(added));
+        added
+    }
+
     /// Gets lines in range.
     ///
     /// ### Parameters
-    /// * `start`: 1-based index of the first line to return
+    /// * `start`: position within `buffered_lines` of the first line to return
     /// * `num_lines`: number of lines to return
-    fn get_lines(&mut self, start: usize, num_lines: usize) -> Vec<iter::FilteredLine> {
-        assert!(start >= 1, format!("first line number must be at least 1; got {}", start));
-        let start = start - 1;
-        let end_desired = start + num_lines;
+    fn get_lines(&mut self, start: Position, num_lines: usize) -> Vec<iter::FilteredLine> {
+        assert!(start >= Position::FIRST, "first line number must be at least 1; got {:?}", start);
+        let above = start.since(Position::FIRST).get();
+        let end_desired = above + num_lines;
         self.fill_buffer(end_desired);
 
         let end = if end_desired <= self.buffered_lines.len() {
@@ -141,34 +1263,56 @@ impl<T: Iterator<Item=String>> WindowBuffer<T> {
         };
 
 
-        self.start_line = start + 1;
-        self.end_line = end;
+        self.start_line = start;
+        self.end_line = Position::new(end);
 
-        if let Some(lines) = self.buffered_lines.get(start..end) {
-            lines.to_owned()
-        } else {
-            Vec::new()
-        }
+        self.buffered_lines.iter()
+            .skip(above)
+            .take(end.saturating_sub(above))
+            .cloned()
+            .collect()
     }
 
     fn fill_buffer(&mut self, limit: usize) {
-        let num_new_lines = limit as i64 - self.buffered_lines.len() as i64;
-
-        let context_buffer = self.context_buffer
-            .as_mut()
-            .expect("context_buffer must always be Some");
-
-        if num_new_lines > 0 {
-            let new_lines = context_buffer.take(num_new_lines as usize);
-            self.buffered_lines.extend(new_lines);
+        while self.buffered_lines.len() < limit {
+            match self.next_forward() {
+                Some(line) => self.push_back(line),
+                None => break,
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{WindowBuffer};
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process;
+    use std::rc::Rc;
+    use std::thread;
+    use std::time::Duration;
+    use std::vec;
+
+    use super::{Search, Totals, WindowBuffer};
     use iter::iter::{FilteredLine, FilterPredicate};
+    use iter::line_buffer::Input;
+    use iter::mapped_file::MappedFile;
+    use iter::schedule::{ScheduledLines, VirtualClock};
+
+    /// Iterator over a shared queue which may have lines appended after it
+    /// has been exhausted, like a file being followed.
+    struct GrowingIter(Rc<RefCell<VecDeque<String>>>);
+
+    impl Iterator for GrowingIter {
+        type Item = String;
+
+        fn next(&mut self) -> Option<String> {
+            self.0.borrow_mut().pop_front()
+        }
+    }
 
     #[test]
     fn test_prev_next() {
@@ -228,6 +1372,23 @@ mod test {
         assert_eq!(obj_ut.prev_line()
                , Some(FilteredLine::UnfilteredLine((1, "one".to_owned()))));
         assert_eq!(obj_ut.prev_line(), None);
+
+        let lines = |range: ::std::ops::Range<usize>| -> Vec<FilteredLine> {
+            range.map(|i| FilteredLine::UnfilteredLine((i, vec[i - 1].to_owned()))).collect()
+        };
+        assert_eq!(obj_ut.next_lines(5), lines(4..9));
+        assert_eq!(obj_ut.top_line_num(), Some(6));
+        assert_eq!(obj_ut.next_lines(5), lines(9..11));
+        assert_eq!(obj_ut.prev_lines(2), lines(6..8));
+        assert_eq!(obj_ut.top_line_num(), Some(6));
+        assert_eq!(obj_ut.prev_lines(100), lines(1..6));
+        assert_eq!(obj_ut.prev_lines(1), vec![]);
+
+        assert_eq!(obj_ut.scroll(2), lines(4..6));
+        assert_eq!(obj_ut.top_line_num(), Some(3));
+        assert_eq!(obj_ut.scroll(-1), lines(2..3));
+        assert_eq!(obj_ut.scroll(0), vec![]);
+        assert_eq!(obj_ut.scroll(-5), lines(1..2));
     }
 
     #[test]
@@ -268,23 +1429,25 @@ mod test {
         ]);
         assert_eq!(obj_ut.next_page(), Vec::new());
         assert_eq!(obj_ut.next_page(), Vec::new());
+        assert!(obj_ut.is_exhausted());
+        assert_eq!(obj_ut.top_line_num(), Some(10));
 
         assert_eq!(obj_ut.prev_page(), vec![
+                   FilteredLine::UnfilteredLine((7, "seven".to_owned())),
                    FilteredLine::UnfilteredLine((8, "eight".to_owned())),
                    FilteredLine::UnfilteredLine((9, "nine".to_owned())),
-                   FilteredLine::UnfilteredLine((10, "ten".to_owned())),
         ]);
 
         assert_eq!(obj_ut.prev_page(), vec![
+                   FilteredLine::UnfilteredLine((4, "four".to_owned())),
                    FilteredLine::UnfilteredLine((5, "five".to_owned())),
                    FilteredLine::UnfilteredLine((6, "six".to_owned())),
-                   FilteredLine::UnfilteredLine((7, "seven".to_owned())),
         ]);
 
         assert_eq!(obj_ut.prev_page(), vec![
+                   FilteredLine::UnfilteredLine((1, "one".to_owned())),
                    FilteredLine::UnfilteredLine((2, "two".to_owned())),
                    FilteredLine::UnfilteredLine((3, "three".to_owned())),
-                   FilteredLine::UnfilteredLine((4, "four".to_owned())),
         ]);
 
         assert_eq!(obj_ut.prev_page(), vec![
@@ -293,19 +1456,125 @@ mod test {
                    FilteredLine::UnfilteredLine((3, "three".to_owned())),
         ]);
 
-        assert_eq!(obj_ut.prev_page(), vec![
+        assert_eq!(obj_ut.next_page(), vec![
+                   FilteredLine::UnfilteredLine((4, "four".to_owned())),
+                   FilteredLine::UnfilteredLine((5, "five".to_owned())),
+                   FilteredLine::UnfilteredLine((6, "six".to_owned())),
+        ]);
+    }
+
+    #[test]
+    fn test_resize() {
+        let vec: Vec<String> = vec!(
+            "one".to_owned(),
+            "two".to_owned(),
+            "three".to_owned(),
+            "four".to_owned(),
+            "five".to_owned(),
+            "six".to_owned(),
+        );
+        let iter = vec.iter().map(|i| i.to_owned());
+
+        let mut obj_ut = WindowBuffer::new(iter, None, 80, 2);
+
+        assert_eq!(obj_ut.next_page(), vec![
                    FilteredLine::UnfilteredLine((1, "one".to_owned())),
                    FilteredLine::UnfilteredLine((2, "two".to_owned())),
-                   FilteredLine::UnfilteredLine((3, "three".to_owned())),
         ]);
+        assert_eq!(obj_ut.next_line()
+               , Some(FilteredLine::UnfilteredLine((3, "three".to_owned()))));
 
-        assert_eq!(obj_ut.next_page(), vec![
+        obj_ut.resize(80, 3);
+
+        assert_eq!(obj_ut.current_page(), vec![
+                   FilteredLine::UnfilteredLine((2, "two".to_owned())),
+                   FilteredLine::UnfilteredLine((3, "three".to_owned())),
                    FilteredLine::UnfilteredLine((4, "four".to_owned())),
+        ]);
+        assert_eq!(obj_ut.next_page(), vec![
                    FilteredLine::UnfilteredLine((5, "five".to_owned())),
                    FilteredLine::UnfilteredLine((6, "six".to_owned())),
         ]);
     }
 
+    #[test]
+    fn test_read_to_end() {
+        let source = Rc::new(RefCell::new(VecDeque::new()));
+        let iter = GrowingIter(source.clone());
+
+        let predicate = Some(FilterPredicate::new("t".to_owned(), 1));
+        let mut obj_ut = WindowBuffer::new(iter, predicate, 80, 2);
+
+        source.borrow_mut().extend(vec!["one".to_owned(), "two".to_owned()]);
+        assert_eq!(obj_ut.read_to_end(), vec![
+                   FilteredLine::ContextLine((1, "one".to_owned())),
+                   FilteredLine::MatchLine((2, "two".to_owned())),
+        ]);
+        assert_eq!(obj_ut.read_to_end(), Vec::new());
+
+        source.borrow_mut().extend(vec![
+            "four".to_owned(), "five".to_owned(), "six".to_owned(), "eight".to_owned()]);
+        assert_eq!(obj_ut.read_to_end(), vec![
+                   FilteredLine::Gap { from: 3, to: 4 },
+                   FilteredLine::ContextLine((5, "six".to_owned())),
+                   FilteredLine::MatchLine((6, "eight".to_owned())),
+        ]);
+        assert_eq!(obj_ut.last_page(), vec![
+                   FilteredLine::ContextLine((5, "six".to_owned())),
+                   FilteredLine::MatchLine((6, "eight".to_owned())),
+        ]);
+    }
+
+    #[test]
+    fn test_lines_between() {
+        let vec: Vec<String> = (1..21).map(|i| format!("line {}", i)).collect();
+        let predicate = Some(FilterPredicate::new("line 1".to_owned(), 0));
+        let mut obj_ut = WindowBuffer::new(vec.into_iter(), predicate, 80, 4);
+
+        obj_ut.next_page();
+        assert_eq!(obj_ut.lines_between(1, 11), vec![
+                   FilteredLine::MatchLine((1, "line 1".to_owned())),
+                   FilteredLine::MatchLine((10, "line 10".to_owned())),
+                   FilteredLine::MatchLine((11, "line 11".to_owned())),
+        ]);
+        assert_eq!(obj_ut.lines_between(2, 9), Vec::new());
+    }
+
+    #[test]
+    fn test_follow() {
+        let clock = VirtualClock::new();
+        let lines = ScheduledLines::new(&clock)
+            .at(0, "starting")
+            .at(100, "error: disk")
+            .every(10, &["ok", "ok", "error: net", "ok"]);
+
+        let predicate = Some(FilterPredicate::new("error".to_owned(), 0));
+        let mut obj_ut = WindowBuffer::new(lines, predicate, 80, 2);
+
+        // each tick picks up the lines which arrived since the last
+        assert_eq!(obj_ut.read_to_end(), Vec::new());
+        clock.advance(100);
+        assert_eq!(obj_ut.read_to_end(), vec![
+                   FilteredLine::Gap { from: 1, to: 1 },
+                   FilteredLine::MatchLine((2, "error: disk".to_owned())),
+        ]);
+        clock.advance(20);
+        assert_eq!(obj_ut.read_to_end(), Vec::new());
+
+        // lines which arrive while paused are read in a single batch
+        clock.advance(1000);
+        let new_lines = obj_ut.read_to_end();
+        assert_eq!(new_lines, vec![
+                   FilteredLine::Gap { from: 3, to: 4 },
+                   FilteredLine::MatchLine((5, "error: net".to_owned())),
+        ]);
+        assert!(new_lines.iter().any(|line| line.is_match()));
+        assert_eq!(obj_ut.last_page(), vec![
+                   FilteredLine::Gap { from: 3, to: 4 },
+                   FilteredLine::MatchLine((5, "error: net".to_owned())),
+        ]);
+    }
+
     #[test]
     fn test_predicate() {
         let vec: Vec<String> = vec!(
@@ -322,36 +1591,450 @@ mod test {
         );
         let iter = vec.iter().map(|i| i.to_owned());
 
-        let mut predicate = Some(FilterPredicate{
-            filter_string: "t".to_owned(),
-            context_lines: 0,
-        });
+        let mut predicate = Some(FilterPredicate::new("t".to_owned(), 0));
         let mut obj_ut = WindowBuffer::new(iter, predicate, 80, 3);
 
-        assert_eq!(obj_ut.next_line(), Some(FilteredLine::Gap));
+        assert_eq!(obj_ut.next_line(), Some(FilteredLine::Gap { from: 1, to: 1 }));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::MatchLine((2, "two".to_owned()))));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::MatchLine((3, "three".to_owned()))));
-        assert_eq!(obj_ut.next_line(), Some(FilteredLine::Gap));
+        assert_eq!(obj_ut.next_line(), Some(FilteredLine::Gap { from: 4, to: 7 }));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::MatchLine((8, "eight".to_owned()))));
-        assert_eq!(obj_ut.next_line(), Some(FilteredLine::Gap));
+        assert_eq!(obj_ut.next_line(), Some(FilteredLine::Gap { from: 9, to: 9 }));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::MatchLine((10, "ten".to_owned()))));
         assert_eq!(obj_ut.next_line(), None);
 
-        predicate = Some(FilterPredicate{
-            filter_string: "t".to_owned(),
-            context_lines: 1,
-        });
+        predicate = Some(FilterPredicate::new("t".to_owned(), 1));
         obj_ut.set_predicate(predicate);
 
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::ContextLine((1, "one".to_owned()))));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::MatchLine((2, "two".to_owned()))));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::MatchLine((3, "three".to_owned()))));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::ContextLine((4, "four".to_owned()))));
-        assert_eq!(obj_ut.next_line(), Some(FilteredLine::Gap));
+        assert_eq!(obj_ut.next_line(), Some(FilteredLine::Gap { from: 5, to: 6 }));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::ContextLine((7, "seven".to_owned()))));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::MatchLine((8, "eight".to_owned()))));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::ContextLine((9, "nine".to_owned()))));
         assert_eq!(obj_ut.next_line(), Some(FilteredLine::MatchLine((10, "ten".to_owned()))));
         assert_eq!(obj_ut.next_line(), None);
     }
+
+    #[test]
+    fn test_ordinals() {
+        let words = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+        let iter = words.iter().map(|word| word.to_string());
+        let predicate = Some(FilterPredicate::new("t".to_owned(), 0));
+        let mut obj_ut = WindowBuffer::new(iter, predicate, 80, 3);
+
+        assert_eq!(obj_ut.ordinal_of_line(8), Some(3));
+        assert_eq!(obj_ut.ordinal_of_line(2), Some(1));
+        assert_eq!(obj_ut.ordinal_of_line(4), None);
+        assert_eq!(obj_ut.ordinal_of_line(11), None);
+        assert_eq!(obj_ut.line_of_ordinal(4), Some(10));
+        assert_eq!(obj_ut.line_of_ordinal(5), None);
+        assert_eq!(obj_ut.line_of_ordinal(0), None);
+
+        // the page shows a gap, then lines 2 and 3
+        obj_ut.current_page();
+        assert_eq!(obj_ut.ordinal_at_row(0), None);
+        assert_eq!(obj_ut.ordinal_at_row(2), Some(2));
+        assert_eq!(obj_ut.row_of_ordinal(1), Some(1));
+        assert_eq!(obj_ut.row_of_ordinal(3), None);
+
+        obj_ut.set_predicate(Some(FilterPredicate::new("e".to_owned(), 1)));
+        assert_eq!(obj_ut.line_of_ordinal(2), Some(3));
+        obj_ut.set_predicate(None);
+        assert_eq!(obj_ut.ordinal_of_line(4), Some(4));
+        assert_eq!(obj_ut.line_of_ordinal(11), None);
+    }
+
+    #[test]
+    fn test_expand_gap() {
+        let vec: Vec<String> = (1..1201)
+            .map(|i| if i == 2 || i == 1200 { format!("match {}", i) } else { format!("line {}", i) })
+            .collect();
+        let predicate = Some(FilterPredicate::new("match".to_owned(), 0));
+        let mut obj_ut = WindowBuffer::new(vec.into_iter(), predicate, 80, 4);
+
+        assert_eq!(obj_ut.next_page(), vec![
+            FilteredLine::Gap { from: 1, to: 1 },
+            FilteredLine::MatchLine((2, "match 2".to_owned())),
+            FilteredLine::Gap { from: 3, to: 1199 },
+            FilteredLine::MatchLine((1200, "match 1200".to_owned())),
+        ]);
+        assert_eq!(obj_ut.gap_row(1), Some(0));
+        assert_eq!(obj_ut.gap_row(2), Some(2));
+        assert_eq!(obj_ut.expand_gap(1), None);
+
+        // a long gap is expanded a thousand lines at a time
+        assert_eq!(obj_ut.expand_gap(2), Some(1000));
+        assert_eq!(obj_ut.current_page(), vec![
+            FilteredLine::Gap { from: 1, to: 1 },
+            FilteredLine::MatchLine((2, "match 2".to_owned())),
+            FilteredLine::ContextLine((3, "line 3".to_owned())),
+            FilteredLine::ContextLine((4, "line 4".to_owned())),
+        ]);
+        assert_eq!(obj_ut.expand_gap(0), Some(1));
+        assert_eq!(obj_ut.last_page(), vec![
+            FilteredLine::ContextLine((1001, "line 1001".to_owned())),
+            FilteredLine::ContextLine((1002, "line 1002".to_owned())),
+            FilteredLine::Gap { from: 1003, to: 1199 },
+            FilteredLine::MatchLine((1200, "match 1200".to_owned())),
+        ]);
+        assert_eq!(obj_ut.expand_gap(2), Some(197));
+        assert_eq!(obj_ut.gap_row(0), None);
+    }
+
+    #[test]
+    fn test_gaps() {
+        let vec: Vec<String> = (1..101)
+            .map(|i| if i == 10 || i == 20 { format!("match {}", i) } else { format!("line {}", i) })
+            .collect();
+        let predicate = Some(FilterPredicate::new("match".to_owned(), 1));
+        let mut obj_ut = WindowBuffer::new(vec.clone().into_iter(), predicate, 80, 3);
+        assert_eq!(obj_ut.gaps(), vec![(1, 8), (12, 18), (22, 100)]);
+
+        let mut obj_ut = WindowBuffer::new(vec.into_iter(), None, 80, 3);
+        assert_eq!(obj_ut.gaps(), vec![]);
+    }
+
+    #[test]
+    fn test_reveal_context() {
+        let vec: Vec<String> = (1..101)
+            .map(|i| if i == 10 || i == 90 { format!("match {}", i) } else { format!("line {}", i) })
+            .collect();
+        let predicate = Some(FilterPredicate::new("match".to_owned(), 0));
+        let mut obj_ut = WindowBuffer::new(vec.into_iter(), predicate.clone(), 80, 3);
+
+        assert_eq!(obj_ut.next_page(), vec![
+            FilteredLine::Gap { from: 1, to: 9 },
+            FilteredLine::MatchLine((10, "match 10".to_owned())),
+            FilteredLine::Gap { from: 11, to: 89 },
+        ]);
+        assert_eq!(obj_ut.reveal_context(1, 2), None);
+
+        // lines are revealed at both ends of a gap
+        assert_eq!(obj_ut.reveal_context(2, 2), Some(4));
+        assert_eq!(obj_ut.current_page(), vec![
+            FilteredLine::Gap { from: 1, to: 9 },
+            FilteredLine::MatchLine((10, "match 10".to_owned())),
+            FilteredLine::ContextLine((11, "line 11".to_owned())),
+        ]);
+        assert_eq!(obj_ut.next_page(), vec![
+            FilteredLine::ContextLine((12, "line 12".to_owned())),
+            FilteredLine::Gap { from: 13, to: 87 },
+            FilteredLine::ContextLine((88, "line 88".to_owned())),
+        ]);
+
+        // and stay revealed when the cache is read afresh
+        obj_ut.goto_line(100);
+        assert_eq!(obj_ut.goto_line(10), vec![
+            FilteredLine::MatchLine((10, "match 10".to_owned())),
+            FilteredLine::ContextLine((11, "line 11".to_owned())),
+            FilteredLine::ContextLine((12, "line 12".to_owned())),
+        ]);
+        assert_eq!(obj_ut.last_page(), vec![
+            FilteredLine::ContextLine((88, "line 88".to_owned())),
+            FilteredLine::ContextLine((89, "line 89".to_owned())),
+            FilteredLine::MatchLine((90, "match 90".to_owned())),
+        ]);
+
+        // a gap is revealed in full once its ends meet
+        assert_eq!(obj_ut.prev_line(), Some(FilteredLine::Gap { from: 13, to: 87 }));
+        assert_eq!(obj_ut.reveal_context(0, 40), Some(75));
+        assert_eq!(obj_ut.gap_row(0), None);
+        let all_lines: Vec<FilteredLine> = obj_ut.all_lines().iter().cloned().collect();
+        assert_eq!(all_lines.iter().filter(|line| line.is_gap()).count(), 1);
+
+        obj_ut.set_predicate(predicate);
+        assert_eq!(obj_ut.next_page()[2], FilteredLine::Gap { from: 11, to: 89 });
+    }
+
+    #[test]
+    fn test_last_page_backward() {
+        let vec: Vec<String> = (1..41)
+            .map(|i| if i % 7 == 0 { format!("match {}", i) } else { format!("line {}", i) })
+            .collect();
+        let predicate = Some(FilterPredicate::new("match".to_owned(), 1));
+
+        let mut forward = WindowBuffer::new(vec.clone().into_iter(), predicate.clone(), 80, 4);
+        let expected: Vec<FilteredLine> = forward.all_lines().iter().cloned().collect();
+
+        let mut obj_ut = WindowBuffer::new(vec.into_iter(), predicate, 80, 4);
+        let last_page = obj_ut.last_page();
+        assert_eq!(last_page, expected[expected.len() - 4..].to_vec());
+        assert_eq!(obj_ut.prev_page(), expected[expected.len() - 8..expected.len() - 4].to_vec());
+        assert_eq!(obj_ut.next_page(), last_page);
+
+        let mut actual = Vec::new();
+        while let Some(line) = obj_ut.prev_line() {
+            actual.insert(0, line);
+        }
+        actual.extend(last_page);
+        assert_eq!(actual, expected);
+
+        assert_eq!(obj_ut.all_lines().iter().cloned().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_match_index() {
+        let vec: Vec<String> = (1..20001)
+            .map(|i| if i == 2 || i % 2500 == 0 { format!("match {}", i) } else { format!("line {}", i) })
+            .collect();
+        let predicate = Some(FilterPredicate::new("match".to_owned(), 2));
+
+        let mut forward = WindowBuffer::new(vec.clone().into_iter(), predicate.clone(), 80, 4);
+        let expected: Vec<FilteredLine> = forward.all_lines().iter().cloned().collect();
+
+        let path = env::temp_dir().join(format!("filterless-{}-window.txt", process::id()));
+        File::create(&path).unwrap().write_all((vec.join("\n") + "\n").as_bytes()).unwrap();
+        let mapped = || -> WindowBuffer<vec::IntoIter<String>> {
+            let file = MappedFile::new(File::open(&path).unwrap()).unwrap();
+            let mut window_buffer = WindowBuffer::new(Input::Mapped(file), None, 80, 4);
+            window_buffer.set_predicate(predicate.clone());
+            window_buffer
+        };
+
+        let mut obj_ut = mapped();
+        assert_eq!(obj_ut.all_lines().iter().cloned().collect::<Vec<_>>(), expected);
+
+        let mut obj_ut = mapped();
+        while obj_ut.scan_progress().is_some() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        // ordinals are looked up in the index, as they're found by testing
+        // lines without one
+        assert_eq!(obj_ut.ordinal_of_line(20000), Some(9));
+        assert_eq!(obj_ut.ordinal_of_line(19999), None);
+        assert_eq!(obj_ut.line_of_ordinal(2), Some(2500));
+        assert_eq!(obj_ut.line_of_ordinal(10), None);
+        assert_eq!(forward.ordinal_of_line(20000), Some(9));
+        assert_eq!(forward.line_of_ordinal(2), Some(2500));
+        let last_page = obj_ut.last_page();
+        assert_eq!(last_page, expected[expected.len() - 4..].to_vec());
+        assert_eq!(obj_ut.totals(), Some(Totals { lines: 20000, matches: Some(9) }));
+        assert_eq!(obj_ut.first_match(), Search::Found(Some(2)));
+        assert_eq!(obj_ut.last_match(), Search::Found(Some(20000)));
+        assert_eq!(forward.first_match(), Search::Found(Some(2)));
+        assert_eq!(forward.last_match(), Search::Found(Some(20000)));
+
+        let mut actual = Vec::new();
+        while let Some(line) = obj_ut.prev_line() {
+            actual.insert(0, line);
+        }
+        actual.extend(last_page);
+        assert_eq!(actual, expected);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_exhausted() {
+        let queue = Rc::new(RefCell::new(VecDeque::new()));
+        queue.borrow_mut().extend((1..11).map(|i| format!("line {}", i)));
+        let mut obj_ut = WindowBuffer::new(GrowingIter(queue.clone()), None, 80, 4);
+
+        obj_ut.next_page();
+        assert!(!obj_ut.is_exhausted());
+        assert_eq!((obj_ut.lines_read(), obj_ut.total_lines()), (4, None));
+
+        obj_ut.last_page();
+        assert!(obj_ut.is_exhausted());
+        assert_eq!(obj_ut.total_lines(), Some(10));
+        obj_ut.prev_line();
+        assert!(!obj_ut.is_exhausted());
+        obj_ut.next_line();
+        assert!(obj_ut.is_exhausted());
+
+        // appended lines follow the window until they're read
+        queue.borrow_mut().push_back("line 11".to_owned());
+        assert_eq!(obj_ut.next_line(),
+                   Some(FilteredLine::UnfilteredLine((11, "line 11".to_owned()))));
+        assert_eq!(obj_ut.total_lines(), None);
+        obj_ut.read_to_end();
+        assert_eq!(obj_ut.total_lines(), Some(11));
+        assert!(obj_ut.is_exhausted());
+
+        // no more matches below the last one
+        obj_ut.set_predicate(Some(FilterPredicate::new("line 2".to_owned(), 0)));
+        assert_eq!(obj_ut.next_page(), vec![
+            FilteredLine::Gap { from: 1, to: 1 },
+            FilteredLine::MatchLine((2, "line 2".to_owned())),
+        ]);
+        assert!(obj_ut.is_exhausted());
+
+        let path = env::temp_dir().join(format!("filterless-{}-exhausted.txt", process::id()));
+        File::create(&path).unwrap().write_all(b"a\nb\nc\n").unwrap();
+        let file = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        let mut obj_ut: WindowBuffer<vec::IntoIter<String>> =
+            WindowBuffer::new(Input::Mapped(file), None, 80, 4);
+        assert_eq!(obj_ut.total_lines(), None);
+        assert_eq!(obj_ut.next_page().len(), 3);
+        assert!(obj_ut.is_exhausted());
+        assert_eq!(obj_ut.total_lines(), Some(3));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_goto_fraction() {
+        let vec: Vec<String> = (1..101).map(|i| format!("{:03}", i)).collect();
+        let lines = |range: ::std::ops::Range<usize>| -> Vec<FilteredLine> {
+            range.map(|i| FilteredLine::UnfilteredLine((i, format!("{:03}", i)))).collect()
+        };
+
+        let mut obj_ut = WindowBuffer::new(vec.clone().into_iter(), None, 80, 4);
+        assert_eq!(obj_ut.goto_fraction(0.5), lines(50..54));
+        assert_eq!(obj_ut.prev_line(), Some(FilteredLine::UnfilteredLine((49, "049".to_owned()))));
+        assert_eq!(obj_ut.goto_fraction(0.0), lines(1..5));
+        assert_eq!(obj_ut.goto_fraction(0.99), lines(97..101));
+        assert_eq!(obj_ut.next_page(), vec![]);
+
+        // lines of a mapped file are found by their byte offsets
+        let path = env::temp_dir().join(format!("filterless-{}-fraction.txt", process::id()));
+        File::create(&path).unwrap().write_all((vec.join("\n") + "\n").as_bytes()).unwrap();
+        let file = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        let mut obj_ut: WindowBuffer<vec::IntoIter<String>> =
+            WindowBuffer::new(Input::Mapped(file), None, 80, 4);
+        assert_eq!(obj_ut.estimated_lines(), None);
+        assert_eq!(obj_ut.goto_fraction(0.5), lines(51..55));
+        assert_eq!(obj_ut.estimated_lines(), Some(100));
+        assert_eq!(obj_ut.goto_fraction(1.0), lines(97..101));
+        assert_eq!(obj_ut.estimated_lines(), Some(100));
+
+        // the estimate assumes unread lines are as long as those read
+        File::create(&path).unwrap().write_all(b"a\nb\nccccccccccccccccc\n").unwrap();
+        let file = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        let mut obj_ut: WindowBuffer<vec::IntoIter<String>> =
+            WindowBuffer::new(Input::Mapped(file), None, 80, 1);
+        obj_ut.next_page();
+        assert_eq!(obj_ut.estimated_lines(), Some(11));
+        obj_ut.last_page();
+        assert_eq!(obj_ut.estimated_lines(), Some(3));
+
+        let predicate = Some(FilterPredicate::new("5".to_owned(), 0));
+        let mut obj_ut = WindowBuffer::new(vec.into_iter(), predicate, 80, 4);
+        assert_eq!(obj_ut.goto_fraction(0.5), vec![
+            FilteredLine::MatchLine((50, "050".to_owned())),
+            FilteredLine::MatchLine((51, "051".to_owned())),
+            FilteredLine::MatchLine((52, "052".to_owned())),
+            FilteredLine::MatchLine((53, "053".to_owned())),
+        ]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_goto_line() {
+        let vec: Vec<String> = (1..31)
+            .map(|i| if i % 5 == 0 { format!("match {}", i) } else { format!("line {}", i) })
+            .collect();
+        let mut obj_ut = WindowBuffer::new(vec.into_iter(), None, 80, 4);
+        assert_eq!(obj_ut.top_line_num(), None);
+
+        obj_ut.next_page();
+        obj_ut.next_line();
+        assert_eq!(obj_ut.top_line_num(), Some(2));
+
+        // a line number survives a change of filter
+        obj_ut.goto_line(12);
+        let mark = obj_ut.top_line_num().unwrap();
+        obj_ut.set_predicate(Some(FilterPredicate::new("match".to_owned(), 1)));
+        assert_eq!(obj_ut.goto_line(mark), vec![
+            FilteredLine::ContextLine((14, "line 14".to_owned())),
+            FilteredLine::MatchLine((15, "match 15".to_owned())),
+            FilteredLine::ContextLine((16, "line 16".to_owned())),
+            FilteredLine::Gap { from: 17, to: 18 },
+        ]);
+        assert_eq!(obj_ut.top_line_num(), Some(14));
+
+        // rows of the window map to line numbers and back
+        assert_eq!(obj_ut.line_num_at_row(1), Some(15));
+        assert_eq!(obj_ut.line_num_at_row(3), None);
+        assert_eq!(obj_ut.row_of_line_num(16), Some(2));
+        assert_eq!(obj_ut.row_of_line_num(17), None);
+        assert_eq!(obj_ut.nearest_match(3), Some(15));
+        obj_ut.next_line();
+        assert_eq!(obj_ut.line_num_at_row(3), Some(19));
+        assert_eq!(obj_ut.nearest_match(2), Some(15));
+
+        obj_ut.set_predicate(None);
+        assert_eq!(obj_ut.goto_line(14)[0], FilteredLine::UnfilteredLine((14, "line 14".to_owned())));
+        assert_eq!(obj_ut.goto_line(29).len(), 4);
+        assert_eq!(obj_ut.top_line_num(), Some(27));
+
+        // going past the end of filtered input leaves nothing to read
+        // forward before the last page
+        obj_ut.set_predicate(Some(FilterPredicate::new("match".to_owned(), 0)));
+        assert_eq!(obj_ut.goto_line(100).len(), 4);
+        assert_eq!(obj_ut.last_page().len(), 4);
+        assert_eq!(obj_ut.top_line_num(), Some(25));
+        obj_ut.set_predicate(Some(FilterPredicate::new("nowhere".to_owned(), 3)));
+        assert_eq!(obj_ut.goto_line(100), vec![]);
+        assert_eq!(obj_ut.last_page(), vec![]);
+        assert_eq!(obj_ut.prev_line(), None);
+        assert_eq!(obj_ut.prev_page(), vec![]);
+    }
+
+    #[test]
+    fn test_find_line_near() {
+        let lines = |texts: &[&str]| -> Vec<String> {
+            texts.iter().map(|text| text.to_string()).collect()
+        };
+        let mut obj_ut = WindowBuffer::new(
+            lines(&["new", "a", "b", "dup", "c", "dup"]).into_iter(), None, 80, 4);
+
+        assert_eq!(obj_ut.find_line_near(2, "b", 5), Some(3));
+        assert_eq!(obj_ut.find_line_near(4, "new", 5), Some(1));
+        assert_eq!(obj_ut.find_line_near(4, "new", 2), None);
+        // the nearest of several lines is found, preferring those after
+        assert_eq!(obj_ut.find_line_near(5, "dup", 5), Some(6));
+        assert_eq!(obj_ut.find_line_near(3, "dup", 5), Some(4));
+        assert_eq!(obj_ut.find_line_near(20, "c", 100), Some(5));
+        assert_eq!(obj_ut.find_line_near(1, "gone", 100), None);
+    }
+
+    #[test]
+    fn test_totals() {
+        let queue = Rc::new(RefCell::new(VecDeque::new()));
+        queue.borrow_mut().extend((1..40001).map(|i| format!("line {}", i)));
+        let predicate = Some(FilterPredicate::new("0".to_owned(), 0));
+        let mut obj_ut = WindowBuffer::new(GrowingIter(queue.clone()), predicate, 80, 4);
+
+        // case: the iterator hasn't been exhausted
+        obj_ut.next_page();
+        assert_eq!(obj_ut.totals(), None);
+        assert!(!obj_ut.is_tallying());
+
+        // matches are tested a batch at a time
+        obj_ut.last_page();
+        assert!(obj_ut.is_tallying());
+        let mut totals = obj_ut.totals();
+        while totals.is_none() {
+            totals = obj_ut.totals();
+        }
+        assert_eq!(totals, Some(Totals { lines: 40000, matches: Some(12937) }));
+        assert!(!obj_ut.is_tallying());
+
+        // appended lines are tested in turn
+        queue.borrow_mut().extend(vec!["10".to_owned(), "11".to_owned()]);
+        obj_ut.read_to_end();
+        assert_eq!(obj_ut.totals(), Some(Totals { lines: 40002, matches: Some(12938) }));
+
+        obj_ut.set_predicate(None);
+        assert_eq!(obj_ut.totals(), Some(Totals { lines: 40002, matches: None }));
+
+        let path = env::temp_dir().join(format!("filterless-{}-totals.txt", process::id()));
+        File::create(&path).unwrap().write_all(b"a\nb\nc").unwrap();
+        let file = MappedFile::new(File::open(&path).unwrap()).unwrap();
+        let mut obj_ut: WindowBuffer<vec::IntoIter<String>> =
+            WindowBuffer::new(Input::Mapped(file), None, 80, 4);
+        let mut totals = obj_ut.totals();
+        while totals.is_none() {
+            thread::sleep(Duration::from_millis(1));
+            totals = obj_ut.totals();
+        }
+        assert_eq!(totals, Some(Totals { lines: 3, matches: None }));
+
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,389 @@
+//! Bindings of keys to the commands of the pager, which may be changed in the
+//! `[keys]` section of a configuration file.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use ncurses::{KEY_BTAB, KEY_DOWN, KEY_END, KEY_HOME, KEY_LEFT, KEY_NPAGE, KEY_PPAGE, KEY_RIGHT,
+              KEY_UP};
+
+use config;
+
+const TAB: i32 = 9;
+const ENTER: i32 = 10;
+const ESCAPE: i32 = 27;
+const SPACE: i32 = 0x20;
+const DEL: i32 = 0x7f;
+const DIGIT_0: i32 = 0x30;
+const DIGIT_9: i32 = 0x39;
+
+/// Commands which may be bound to keys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// go to the percentage of the input typed ahead, e.g. `50%`
+    GotoPercent,
+    /// go to the line typed ahead, e.g. `20G`, or else to the end
+    GotoLine,
+    NextLine,
+    PrevLine,
+    NextPage,
+    PrevPage,
+    /// scroll down by the scroll step, half a page unless set otherwise
+    ScrollDown,
+    /// scroll up by the scroll step
+    ScrollUp,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    /// filter by the token under the cursor
+    FilterToken,
+    /// count the occurrences of the token under the cursor
+    CountToken,
+    SetMark,
+    GotoMark,
+    /// go to the first line matched by the filter
+    FirstMatch,
+    /// go to the last line matched by the filter, once it's been found
+    LastMatch,
+    ClearFilter,
+    /// show every line, or only those selected by the filter
+    Zoom,
+    /// show the lines skipped by the gap under the cursor
+    ExpandGap,
+    /// show more lines at either end of the gap under the cursor
+    RevealContext,
+    /// start or stop selecting lines from the cursor line
+    Select,
+    /// copy the selected lines, or else the cursor line, to the clipboard
+    Yank,
+    TimeDeltas,
+    LineNumbers,
+    /// show or hide the number of matches within each line
+    MatchCounts,
+    /// highlight the words in which each line differs from the one before
+    WordDiff,
+    /// switch to the next kind of matcher
+    NextMatcher,
+    IgnoreAccents,
+    IgnoreCase,
+    /// switch to the next time zone in which timestamps are shown
+    TimeZone,
+    Follow,
+    /// edit the filter at a prompt
+    Filter,
+    Save,
+    /// run a command typed at the `:` prompt
+    Command,
+    NextTab,
+    PrevTab,
+    /// list the keys bound to each action
+    Help,
+    Quit,
+}
+
+/// Names of the actions as used in configuration files, in the order in which
+/// they're written, along with what each does, as listed by the help screen.
+const ACTIONS: &[(&str, Action, &str)] = &[
+    ("goto_percent", Action::GotoPercent, "go to the percentage typed, e.g. 50%"),
+    ("goto_line", Action::GotoLine, "go to the line typed, or the end"),
+    ("next_line", Action::NextLine, "scroll down a line"),
+    ("prev_line", Action::PrevLine, "scroll up a line"),
+    ("next_page", Action::NextPage, "next page"),
+    ("prev_page", Action::PrevPage, "previous page"),
+    ("scroll_down", Action::ScrollDown, "scroll down by the scroll step"),
+    ("scroll_up", Action::ScrollUp, "scroll up by the scroll step"),
+    ("cursor_up", Action::CursorUp, "move the cursor up"),
+    ("cursor_down", Action::CursorDown, "move the cursor down"),
+    ("cursor_left", Action::CursorLeft, "move the cursor left"),
+    ("cursor_right", Action::CursorRight, "move the cursor right"),
+    ("filter_token", Action::FilterToken, "filter by the token at the cursor"),
+    ("count_token", Action::CountToken, "count the token at the cursor"),
+    ("set_mark", Action::SetMark, "set a mark"),
+    ("goto_mark", Action::GotoMark, "go to a mark"),
+    ("first_match", Action::FirstMatch, "go to the first match"),
+    ("last_match", Action::LastMatch, "go to the last match"),
+    ("clear_filter", Action::ClearFilter, "clear the filter or selection"),
+    ("zoom", Action::Zoom, "show every line, or the matches"),
+    ("expand_gap", Action::ExpandGap, "show the lines of a gap"),
+    ("reveal_context", Action::RevealContext, "show more lines around a gap"),
+    ("select", Action::Select, "start or stop selecting lines"),
+    ("yank", Action::Yank, "copy lines to the clipboard"),
+    ("time_deltas", Action::TimeDeltas, "show or hide time deltas"),
+    ("line_numbers", Action::LineNumbers, "show or hide line numbers"),
+    ("match_counts", Action::MatchCounts, "show or hide match counts"),
+    ("word_diff", Action::WordDiff, "highlight changed words"),
+    ("next_matcher", Action::NextMatcher, "next kind of matcher"),
+    ("ignore_accents", Action::IgnoreAccents, "ignore accents or not"),
+    ("ignore_case", Action::IgnoreCase, "ignore case or not"),
+    ("time_zone", Action::TimeZone, "next time zone"),
+    ("follow", Action::Follow, "follow the input or stop"),
+    ("filter", Action::Filter, "edit the filter"),
+    ("save", Action::Save, "save the lines shown to a file"),
+    ("command", Action::Command, "run a command"),
+    ("next_tab", Action::NextTab, "next tab"),
+    ("prev_tab", Action::PrevTab, "previous tab"),
+    ("help", Action::Help, "show this help"),
+    ("quit", Action::Quit, "quit"),
+];
+
+/// Names of keys which aren't printable characters.
+const KEY_NAMES: &[(&str, i32)] = &[
+    ("space", SPACE),
+    ("tab", TAB),
+    ("backtab", KEY_BTAB),
+    ("enter", ENTER),
+    ("esc", ESCAPE),
+    ("up", KEY_UP),
+    ("down", KEY_DOWN),
+    ("left", KEY_LEFT),
+    ("right", KEY_RIGHT),
+    ("pgup", KEY_PPAGE),
+    ("pgdn", KEY_NPAGE),
+    ("home", KEY_HOME),
+    ("end", KEY_END),
+];
+
+impl Action {
+    /// Returns the action having a name as used in configuration files.
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|&&(n, _, _)| n == name).map(|&(_, action, _)| action)
+    }
+}
+
+/// Mapping of keys, as returned by `getch()`, to the actions they run.
+///
+/// Digits aren't bound, being reserved for the count typed ahead of an action.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<i32, Action>,
+}
+
+impl Default for Keymap {
+    /// The stock bindings, which are `less`-like where `less` has an
+    /// equivalent.
+    fn default() -> Keymap {
+        let mut keymap = Keymap { bindings: HashMap::new() };
+        for &(action, keys) in [
+            (Action::GotoPercent, "% p"),
+            (Action::GotoLine, "G"),
+            (Action::NextLine, "j"),
+            (Action::PrevLine, "k"),
+            (Action::NextPage, "pgdn"),
+            (Action::PrevPage, "pgup"),
+            (Action::ScrollDown, "ctrl-d"),
+            (Action::ScrollUp, "ctrl-u"),
+            (Action::CursorUp, "up"),
+            (Action::CursorDown, "down"),
+            (Action::CursorLeft, "left"),
+            (Action::CursorRight, "right"),
+            (Action::FilterToken, "*"),
+            (Action::CountToken, "#"),
+            (Action::SetMark, "m"),
+            (Action::GotoMark, "'"),
+            (Action::FirstMatch, "<"),
+            (Action::LastMatch, ">"),
+            (Action::ClearFilter, "esc"),
+            (Action::Zoom, "z"),
+            (Action::ExpandGap, "e"),
+            (Action::RevealContext, "enter +"),
+            (Action::Select, "v"),
+            (Action::Yank, "y"),
+            (Action::TimeDeltas, "D"),
+            (Action::LineNumbers, "N"),
+            (Action::MatchCounts, "C"),
+            (Action::WordDiff, "W"),
+            (Action::NextMatcher, "R"),
+            (Action::IgnoreAccents, "A"),
+            (Action::IgnoreCase, "I"),
+            (Action::TimeZone, "Z"),
+            (Action::Follow, "F"),
+            (Action::Filter, "/ &"),
+            (Action::Save, "s"),
+            (Action::Command, ":"),
+            (Action::NextTab, "tab"),
+            (Action::PrevTab, "backtab"),
+            (Action::Help, "? h"),
+            (Action::Quit, "q"),
+        ].iter() {
+            keymap.bind(action, &parse_keys(keys).unwrap());
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Returns the action bound to a key, if any.
+    pub fn action(&self, key: i32) -> Option<Action> {
+        self.bindings.get(&key).cloned()
+    }
+
+    /// Returns the keys bound to an action, in order of their codes.
+    pub fn keys(&self, action: Action) -> Vec<i32> {
+        let mut keys: Vec<i32> = self.bindings.iter()
+            .filter(|&(_, &a)| a == action)
+            .map(|(&key, _)| key)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Returns the names of the keys bound to each action which has any, along
+    /// with what the action does, in the order the actions are written in
+    /// configuration files.
+    pub fn help(&self) -> Vec<(Vec<String>, &'static str)> {
+        ACTIONS.iter()
+            .map(|&(_, action, description)| {
+                (self.keys(action).into_iter().map(key_name).collect::<Vec<_>>(), description)
+            })
+            .filter(|&(ref keys, _)| !keys.is_empty())
+            .collect()
+    }
+
+    /// Binds keys to an action in place of the keys bound to it before.
+    ///
+    /// Keys bound to other actions are taken from them.
+    ///
+    /// ### Parameters
+    /// * `action`: the action to bind
+    /// * `keys`: the keys which are to run it; none unbinds it
+    pub fn bind(&mut self, action: Action, keys: &[i32]) {
+        self.bindings.retain(|_, &mut a| a != action);
+        for &key in keys {
+            self.bindings.insert(key, action);
+        }
+    }
+
+    /// Applies a single line of the `[keys]` section of a configuration file.
+    ///
+    /// Lines take the form `action = 'key ...'`, where each key is a
+    /// printable character, `ctrl-` followed by a letter, or one of `space`,
+    /// `tab`, `backtab`, `enter`, `esc`, `up`, `down`, `left`, `right`,
+    /// `pgup`, `pgdn`, `home` and `end`, e.g. `next_page = 'pgdn space'`.
+    pub fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().ok_or(format!("expected `action = 'key ...'`; got `{}`", line))?;
+        let value = config::unquote(value.trim());
+
+        let action = Action::from_name(name).ok_or(format!("unknown action `{}`", name))?;
+        let keys = parse_keys(&value)?;
+        self.bind(action, &keys);
+        Ok(())
+    }
+}
+
+impl fmt::Display for Keymap {
+    /// Formats the keymap as lines of the `[keys]` section of a configuration
+    /// file.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &(name, action, _) in ACTIONS {
+            let keys: Vec<String> = self.keys(action).into_iter().map(key_name).collect();
+            writeln!(f, "{} = {}", name, config::quote(&keys.join(" ")))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a list of key names separated by whitespace, as written by
+/// `key_name()`.
+fn parse_keys(names: &str) -> Result<Vec<i32>, String> {
+    names.split_whitespace().map(parse_key).collect()
+}
+
+/// Parses the name of a key, returning its code as returned by `getch()`.
+fn parse_key(name: &str) -> Result<i32, String> {
+    if let Some(&(_, key)) = KEY_NAMES.iter().find(|&&(n, _)| n == name) {
+        return Ok(key);
+    }
+
+    let mut chars = name.chars();
+    let key = match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_graphic() => c as i32,
+        _ if name.starts_with("ctrl-") && name.len() == 6 => {
+            let c = name.as_bytes()[5];
+            if !c.is_ascii_alphabetic() {
+                return Err(format!("unknown key `{}`", name));
+            }
+            (c.to_ascii_lowercase() - b'a' + 1) as i32
+        },
+        _ => return Err(format!("unknown key `{}`", name)),
+    };
+
+    if (DIGIT_0..=DIGIT_9).contains(&key) {
+        return Err(format!("`{}` is reserved for counts", name));
+    }
+    Ok(key)
+}
+
+/// Returns the name of a key as parsed by `parse_key()`.
+pub fn key_name(key: i32) -> String {
+    if let Some(&(name, _)) = KEY_NAMES.iter().find(|&&(_, k)| k == key) {
+        name.to_owned()
+    } else if (1..=26).contains(&key) {
+        format!("ctrl-{}", (b'a' + key as u8 - 1) as char)
+    } else if (SPACE + 1..DEL).contains(&key) {
+        (key as u8 as char).to_string()
+    } else {
+        key.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ncurses::{KEY_NPAGE, KEY_PPAGE};
+
+    use super::{parse_key, Action, Keymap};
+
+    #[test]
+    fn test_apply_line() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.action('j' as i32), Some(Action::NextLine));
+        assert_eq!(keymap.keys(Action::NextPage), vec![KEY_NPAGE]);
+        assert_eq!(keymap.action(4), Some(Action::ScrollDown));
+
+        keymap.apply_line("# comment").unwrap();
+        keymap.apply_line("next_page = 'space ctrl-F pgdn'").unwrap();
+        keymap.apply_line("prev_page=b").unwrap();
+        keymap.apply_line("quit = \"Q '\"").unwrap();
+        keymap.apply_line("filter = ''").unwrap();
+
+        assert_eq!(keymap.keys(Action::NextPage), vec![6, 0x20, KEY_NPAGE]);
+        assert_eq!(keymap.keys(Action::PrevPage), vec!['b' as i32]);
+        assert_eq!(keymap.action(KEY_PPAGE), None);
+        assert_eq!(keymap.action('\'' as i32), Some(Action::Quit));
+        assert!(keymap.keys(Action::GotoMark).is_empty());
+        assert_eq!(keymap.action('/' as i32), None);
+
+        let help = keymap.help();
+        assert_eq!(help[0].0, vec!["%", "p"]);
+        assert_eq!(help[0].1, "go to the percentage typed, e.g. 50%");
+        assert!(help.iter().all(|&(_, description)| description != "edit the filter"));
+
+        assert!(keymap.apply_line("next_line").is_err());
+        assert!(keymap.apply_line("jump = j").is_err());
+        assert!(keymap.apply_line("next_line = 'j shift-j'").is_err());
+        assert!(keymap.apply_line("next_line = 5").is_err());
+        assert!(parse_key("ctrl-1").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut keymap = Keymap::default();
+        keymap.apply_line("quit = 'q ctrl-c \"'").unwrap();
+
+        let mut imported = Keymap::default();
+        imported.apply_line("quit = x").unwrap();
+        for line in keymap.to_string().lines() {
+            imported.apply_line(line).unwrap();
+        }
+
+        assert_eq!(imported.to_string(), keymap.to_string());
+        assert_eq!(imported.keys(Action::Quit), vec![3, '"' as i32, 'q' as i32]);
+    }
+}
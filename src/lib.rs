@@ -0,0 +1,42 @@
+//! Filtering of lines of text, along with the lines around each match, as
+//! done by the `filterless` pager but without its terminal interface.
+//!
+//! A `LineBuffer` reads numbered lines from an `Input`, which may be any
+//! iterator of lines, a memory-mapped file or a `LineSource`, through which
+//! lines may be fed from sockets, databases and the like. A `ContextBuffer` iterates over
+//! the lines of a `LineBuffer` which match a `FilterPredicate`, along with
+//! their context lines, as `FilteredLine`s. A `WindowBuffer` keeps a window's
+//! worth of those lines, which may be moved through the input in either
+//! direction. A `StructuredLine` views a line as its fields, as parsed by
+//! the `Format` of the line, e.g. JSON or logfmt, which the `parser` module
+//! can tell from the first lines of the input.
+//!
+//! The terminal interface is built by the `pager` feature, which is enabled by
+//! default; depend on this crate with `default-features = false` to do
+//! without it and its dependency on ncurses. The `evtx` feature adds
+//! `EvtxSource`, which reads the records of Windows event logs as lines.
+
+// the `field: field` initializers, `&Enum::Variant(ref x)` patterns and
+// `iter::iter` module of the original code are kept as they are
+#![allow(clippy::match_ref_pats, clippy::module_inception, clippy::needless_borrowed_reference,
+         clippy::redundant_field_names)]
+
+extern crate libc;
+extern crate memchr;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+mod iter;
+pub mod timestamp;
+
+pub use iter::{matcher, parser, split_spans, Between, ContextBuffer, ContextLine,
+               DisplayNumbered, Field, FileSource, FilteredLine, FilterPredicate, FilterSource,
+               Format, Input, IterDirection, LineBuffer, LineRange, LineSource, MappedFile, Marker,
+               MatchIndex, MatchOptions, Matcher, MatcherKind, NumberedLine, Search, Sections,
+               StdinSource, StructuredLine, TimeIndex, Totals, VecSource, WindowBuffer};
+#[cfg(feature = "evtx")]
+pub use iter::EvtxSource;
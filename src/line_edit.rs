@@ -0,0 +1,182 @@
+//! Editing of a single line of text typed at a prompt.
+
+use std::str;
+
+use ncurses::{KEY_BACKSPACE, KEY_DC, KEY_END, KEY_ENTER, KEY_HOME, KEY_LEFT, KEY_RIGHT};
+
+const CTRL_A: i32 = 1;
+const CTRL_B: i32 = 2;
+const CTRL_D: i32 = 4;
+const CTRL_E: i32 = 5;
+const CTRL_F: i32 = 6;
+const CTRL_H: i32 = 8;
+const CTRL_U: i32 = 21;
+const ENTER: i32 = 10;
+const RETURN: i32 = 13;
+const ESCAPE: i32 = 27;
+const BACKSPACE: i32 = 127;
+
+/// Maximum number of characters of a line, beyond which characters typed or
+/// pasted are dropped, so that e.g. a whole log pasted by accident isn't
+/// compiled into a filter.
+pub const MAX_CHARS: usize = 1024;
+
+/// Outcome of a keypress handled by a `LineEditor`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edit {
+    /// the text was changed
+    Changed,
+    /// the text was left as it was, though the cursor may have moved
+    Unchanged,
+    /// the text was submitted, e.g. by pressing Enter
+    Submitted,
+    /// editing was abandoned, e.g. by pressing Esc
+    Cancelled,
+}
+
+/// Line of text being typed, along with the position of the cursor within it.
+///
+/// Keys are handled as by a shell: the arrow keys, Home and End (or Ctrl-B,
+/// Ctrl-F, Ctrl-A and Ctrl-E) move the cursor, Backspace and Delete (or
+/// Ctrl-D) remove the character before or under the cursor, and Ctrl-U clears
+/// the line. Lines are at most `MAX_CHARS` characters long.
+pub struct LineEditor {
+    chars: Vec<char>,
+    /// index within `chars` of the character under the cursor
+    cursor: usize,
+    /// bytes of a multi-byte character which are yet to be completed
+    pending: Vec<u8>,
+    /// whether characters were dropped for the line being too long
+    truncated: bool,
+}
+
+impl LineEditor {
+    /// Creates an editor of an empty line.
+    pub fn new() -> LineEditor {
+        LineEditor { chars: Vec::new(), cursor: 0, pending: Vec::new(), truncated: false }
+    }
+
+    /// Returns the text typed.
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Returns the number of characters preceding the cursor.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Returns `true` if any characters typed were dropped for the line being
+    /// `MAX_CHARS` characters long.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Updates the line for a keypress, as returned by `getch()`.
+    pub fn handle(&mut self, key: i32) -> Edit {
+        match key {
+            ENTER | RETURN | KEY_ENTER => return Edit::Submitted,
+            ESCAPE => return Edit::Cancelled,
+            KEY_LEFT | CTRL_B => self.cursor = self.cursor.saturating_sub(1),
+            KEY_RIGHT | CTRL_F => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            KEY_HOME | CTRL_A => self.cursor = 0,
+            KEY_END | CTRL_E => self.cursor = self.chars.len(),
+            BACKSPACE | KEY_BACKSPACE | CTRL_H if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+                return Edit::Changed;
+            },
+            KEY_DC | CTRL_D if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+                return Edit::Changed;
+            },
+            CTRL_U if !self.chars.is_empty() => {
+                self.chars.clear();
+                self.cursor = 0;
+                return Edit::Changed;
+            },
+            _ if (0x20..0x7f).contains(&key) => return self.insert(key as u8 as char),
+            _ if (0x80..=0xff).contains(&key) => {
+                // case: part of a UTF-8 encoded character
+                self.pending.push(key as u8);
+                let decoded = str::from_utf8(&self.pending).ok().and_then(|s| s.chars().next());
+                if let Some(c) = decoded {
+                    self.pending.clear();
+                    return self.insert(c);
+                } else if self.pending.len() >= 4 {
+                    self.pending.clear();
+                }
+            },
+            _ => {},
+        }
+
+        Edit::Unchanged
+    }
+
+    fn insert(&mut self, c: char) -> Edit {
+        if self.chars.len() >= MAX_CHARS {
+            self.truncated = true;
+            return Edit::Unchanged;
+        }
+
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+        Edit::Changed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ncurses::{KEY_DC, KEY_END, KEY_HOME, KEY_LEFT};
+
+    use super::{Edit, LineEditor, MAX_CHARS};
+
+    fn type_text(editor: &mut LineEditor, text: &str) {
+        for byte in text.bytes() {
+            editor.handle(byte as i32);
+        }
+    }
+
+    #[test]
+    fn test_line_editor() {
+        let mut editor = LineEditor::new();
+        type_text(&mut editor, "cafe");
+        assert_eq!(editor.handle(KEY_LEFT), Edit::Unchanged);
+        assert_eq!(editor.handle(127), Edit::Changed);
+        type_text(&mut editor, "é");
+        assert_eq!((editor.text(), editor.cursor()), ("caée".to_owned(), 3));
+
+        editor.handle(KEY_HOME);
+        assert_eq!(editor.handle(KEY_DC), Edit::Changed);
+        assert_eq!(editor.handle(127), Edit::Unchanged);
+        editor.handle(KEY_END);
+        type_text(&mut editor, "s");
+        assert_eq!((editor.text(), editor.cursor()), ("aées".to_owned(), 4));
+
+        // keys which aren't characters aren't inserted
+        assert_eq!(editor.handle(0x1b5), Edit::Unchanged);
+        assert_eq!(editor.handle(21), Edit::Changed);
+        assert_eq!((editor.text(), editor.cursor()), ("".to_owned(), 0));
+
+        assert_eq!(editor.handle(10), Edit::Submitted);
+        assert_eq!(editor.handle(27), Edit::Cancelled);
+    }
+
+    #[test]
+    fn test_max_chars() {
+        let mut editor = LineEditor::new();
+        type_text(&mut editor, &"x".repeat(MAX_CHARS - 1));
+        assert!(!editor.is_truncated());
+
+        type_text(&mut editor, "éa");
+        assert_eq!(editor.handle('b' as i32), Edit::Unchanged);
+        assert!(editor.is_truncated());
+        assert_eq!(editor.text().chars().count(), MAX_CHARS);
+        assert!(editor.text().ends_with("xé"));
+
+        // room made by deleting is filled again
+        assert_eq!(editor.handle(127), Edit::Changed);
+        assert_eq!(editor.handle('c' as i32), Edit::Changed);
+        assert!(editor.text().ends_with("xc"));
+    }
+}
@@ -1,73 +1,187 @@
-#![feature(btree_range)]
-#![feature(collections_bound)]
-#![feature(type_ascription)]
+// the `field: field` initializers, `&Enum::Variant(ref x)` patterns and
+// `iter::iter` module of the original code are kept as they are
+#![allow(clippy::match_ref_pats, clippy::module_inception, clippy::needless_borrowed_reference,
+         clippy::redundant_field_names)]
 
+#[cfg(feature = "compressed")]
+extern crate bzip2;
 extern crate clap;
+extern crate filterless;
+#[cfg(feature = "compressed")]
+extern crate flate2;
 extern crate libc;
+extern crate memchr;
 extern crate ncurses;
+extern crate unicode_width;
+#[cfg(feature = "compressed")]
+extern crate xz2;
 
-mod iter;
+mod clipboard;
+mod command;
+mod compressed;
+mod config;
+mod control;
+mod diff;
+mod error;
+mod exec;
+mod export;
+mod help;
+mod highlight;
+mod integrity;
+mod keymap;
+mod line_edit;
+mod metrics;
 mod pager;
+mod patterns;
+mod plain;
+mod preset;
+mod read_only;
+mod render;
+mod sample;
+mod session;
+mod signals;
+mod sort;
+mod sparkline;
+mod status;
+mod stream;
+mod syntax;
+mod tee;
+mod terminal;
+mod text;
+mod theme;
+mod transcript;
+mod watchdog;
 
+use std::borrow::Cow;
 use std::char;
-use std::ffi::CString;
+use std::cmp::{min, Reverse};
+use std::env;
 use std::fs::File;
-use std::io::BufRead;
+use std::io::{self, BufRead, Read};
 use std::io::BufReader;
+use std::io::BufWriter;
 use std::io::stdin;
+use std::io::stdout;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use clap::{Arg, App};
-use libc::{fopen};
+use clap::{Arg, ArgMatches, App};
 use ncurses::*;
 
-use pager::Pager;
+use command::Command;
+use compressed::Compression;
+use config::Config;
+use control::ControlSocket;
+use exec::{Exec, ExecLines};
+use export::ExportOptions;
+use integrity::Integrity;
+use keymap::{Action, Keymap};
+use line_edit::{Edit, LineEditor};
+#[cfg(feature = "evtx")]
+use filterless::EvtxSource;
+use filterless::{matcher, parser, Between, FilteredLine, FilterPredicate, Format, Input, LineRange,
+                 LineSource, MappedFile, Marker, MatchOptions, Matcher, MatcherKind, Search,
+                 Sections, WindowBuffer};
+use metrics::Metrics;
+use pager::{Bell, CONTEXT_LINES, DisplayOptions, MatchEnd, Pager, REVEAL_LINES};
+use patterns::PatternsFile;
+use preset::Presets;
+use render::Scheduler;
+use sample::{Sample, SampleSource};
+use sort::SortKey;
+use sparkline::Sparkline;
+use session::Session;
+use status::StatusBar;
+use stream::{Stream, StreamLines};
+use syntax::Syntax;
+use tee::Tee;
+use terminal::Screen;
+use theme::{Role, Theme};
+use watchdog::Watchdog;
+use filterless::timestamp::{self, Zone};
 
 
-const LOWER_J: i32 = 0x6a;
-const LOWER_K: i32 = 0x6b;
-const LOWER_Q: i32 = 0x71;
-const FWD_SLASH: i32 = 0x2f;
-const CTRL_D: i32 = 4;
-const CTRL_U: i32 = 21;
-const ENTER: i32 = 10;
-const BACKSPACE: i32 = 127;
+const DIGIT_0: i32 = 0x30;
+const DIGIT_9: i32 = 0x39;
+const ESCAPE: i32 = 27;
 
 const MARGIN: i32 = 0;
 
+/// Milliseconds to wait for a keypress before refreshing the status bar and,
+/// when following, checking the input for new lines
+const TICK_MS: i32 = 1000;
+/// Milliseconds to wait for a keypress while matches are scanned for, or the
+/// input's totals computed, in the background
+const SCAN_TICK_MS: i32 = 100;
+/// Longest time to wait at startup for a page of the lines of input read on
+/// a thread, from which its format is detected
+const STARTUP_WAIT: Duration = Duration::from_millis(500);
+/// Time without a keypress after which the session is saved, if it changed
+const AUTOSAVE_IDLE: Duration = Duration::from_secs(5);
+/// Number of gap sizes listed by the `gaps` command
+const GAP_SIZES_LISTED: usize = 5;
 
-/// Returns a C-style string from a `&str`.
-fn get_cstring(string: &str) -> CString {
-    CString::new(string).unwrap()
-}
 
-/// Creates and returns an ncurses window which reads user input from tty
-/// (to avoid taking commands from a piped file) and writes to stdout.
-fn setup_term() -> SCREEN {
-    let tty;
-    let stdout;
+/// Waits for a keypress, returning the letter pressed or `None` if the key
+/// isn't a letter, e.g. to name a mark.
+fn read_letter() -> Option<char> {
+    // shows what was drawn for the key before, which may not have been shown
+    // yet if a frame wasn't due
+    doupdate();
+    transcript::timeout(-1);
+    char::from_u32(transcript::getch() as u32).filter(|c| c.is_ascii_alphabetic())
+}
 
-    unsafe {
-        tty = fopen(get_cstring("/dev/tty").as_ptr(),
-                        get_cstring("r").as_ptr());
-        stdout = fopen(get_cstring("/dev/stdout").as_ptr(),
-                           get_cstring("w").as_ptr());
+/// Creates and returns an ncurses screen which reads user input from the
+/// terminal (to avoid taking commands from a piped file) and writes to
+/// stdout, or returns an error telling why it can't be.
+///
+/// The terminal is restored when the screen returned is dropped, e.g. when
+/// the pager quits on Ctrl-C, and while it's suspended with Ctrl-Z.
+fn setup_term() -> Result<Screen, String> {
+    // Esc cancels prompts, so shouldn't wait the default second to be told
+    // apart from the start of an escape sequence
+    if env::var_os("ESCDELAY").is_none() {
+        env::set_var("ESCDELAY", "25");
     }
 
+    let tty = terminal::open_tty()
+        .and_then(|tty| terminal::stream(tty, "r").map_err(|err| err.to_string()))?;
+    let stdout = terminal::stdout()
+        .and_then(|stdout| terminal::stream(stdout, "w"))
+        .map_err(|err| format!("can't write to stdout: {}", err))?;
+
+    signals::install();
+    terminal::use_locale();
     let term = newterm(None, stdout, tty);
+    if term.is_null() {
+        let name = env::var("TERM").unwrap_or_default();
+        return Err(format!("can't set up the terminal; is TERM (`{}`) right?", name));
+    }
     set_term(term);
     noecho();
-    term
+    keypad(stdscr(), true);
+    Ok(Screen::new(term))
 }
 
-/// Presents a CLI and returns a boxed `std::io::BufRead` which enables
-/// line-wise reading of input from a file via the CLI or failing that from
-/// stdin.
-///
-/// ### Parameters
-/// * `_stdin`: standard input from which to read if user doesn't provide a file
-///   name
-fn get_input<'a>(_stdin: &'a std::io::Stdin) -> Box<BufRead + 'a> {
-      let matches = App::new("Filterless")
+/// Returns the `(width, height)` of the terminal, less `MARGIN`.
+fn get_term_size() -> (i32, i32) {
+    let mut max_x = 0;
+    let mut max_y = 0;
+    getmaxyx(stdscr(), &mut max_y, &mut max_x);
+    (max_x - MARGIN, max_y - MARGIN)
+}
+
+/// Presents a CLI and returns the parsed command line arguments.
+fn get_args<'a>() -> ArgMatches<'a> {
+      let matcher_kinds: Vec<&str> = matcher::kinds().iter().map(|kind| kind.name).collect();
+      let formats: Vec<&str> = Some("auto").into_iter()
+          .chain(parser::formats().iter().map(|format| format.name))
+          .collect();
+
+      App::new("Filterless")
           .version(env!("CARGO_PKG_VERSION"))
           .author("Michael Wilson")
           .about("Less, but with filtering")
@@ -75,110 +189,2368 @@ fn get_input<'a>(_stdin: &'a std::io::Stdin) -> Box<BufRead + 'a> {
                .help("Sets the input file to use")
                .required(false)
                .index(1))
-          .get_matches();
+          .arg(Arg::with_name("follow")
+               .short("f")
+               .long("follow")
+               .help("Starts in follow mode, showing lines as they are appended to the input"))
+          .arg(Arg::with_name("watchdog")
+               .long("watchdog")
+               .value_name("PERIOD")
+               .help("Warns in the status bar when no lines have been appended to a followed input \
+                      for a period, e.g. 90s, 5m or 2h")
+               .takes_value(true))
+          .arg(Arg::with_name("watchdog-command")
+               .long("watchdog-command")
+               .value_name("COMMAND")
+               .help("Runs a shell command each time --watchdog warns, with the seconds since the \
+                      last line in FILTERLESS_IDLE_SECONDS")
+               .requires("watchdog")
+               .takes_value(true))
+          .arg(Arg::with_name("sparkline")
+               .long("sparkline")
+               .value_name("REGEX")
+               .help("Shows a sparkline in the status bar of the recent values of the number \
+                      matched by a regex in the lines appended while following, e.g. \
+                      'latency=\\d+'")
+               .takes_value(true))
+          .arg(Arg::with_name("filter")
+               .long("filter")
+               .value_name("PATTERN")
+               .help("Sets the initial filter")
+               .takes_value(true))
+          .arg(Arg::with_name("patterns-file")
+               .long("patterns-file")
+               .value_name("FILE")
+               .help("Filters by the patterns listed in a file, one per line, showing lines which \
+                      match any of them; the file is read again whenever it changes")
+               .conflicts_with("filter")
+               .takes_value(true))
+          .arg(Arg::with_name("output")
+               .long("output")
+               .value_name("FILE")
+               .help("Writes the lines selected by --filter to a file, or to stdout if FILE is -, \
+                      instead of starting the pager")
+               .takes_value(true))
+          .arg(Arg::with_name("tee")
+               .long("tee")
+               .value_name("FILE")
+               .help("Mirrors the filtered lines shown to a file or named pipe as they're shown, \
+                      once each, for another program to consume; --with-context and \
+                      --with-line-numbers apply")
+               .conflicts_with_all(&["output", "plain"])
+               .takes_value(true))
+          .arg(Arg::with_name("markdown")
+               .long("markdown")
+               .help("Writes --output as a fenced Markdown code block per context group, headed \
+                      by FILE:LINES, for pasting into issues and reviews")
+               .requires("output"))
+          .arg(Arg::with_name("with-context")
+               .long("with-context")
+               .help("Includes context lines when saving filtered lines"))
+          .arg(Arg::with_name("with-line-numbers")
+               .long("with-line-numbers")
+               .help("Prefixes saved lines with their line numbers"))
+          .arg(Arg::with_name("max-memory")
+               .long("max-memory")
+               .value_name("MEGABYTES")
+               .help("Spills lines to a temporary file once they take up this much memory \
+                      [default: unlimited]")
+               .takes_value(true))
+          .arg(Arg::with_name("block-size")
+               .long("block-size")
+               .value_name("LINES")
+               .help("Reads input which is read line by line, e.g. from a pipe or a compressed \
+                      file, in blocks of this many lines ahead of those shown, making paging \
+                      through lines not yet read smoother; reading a pipe may then wait for a \
+                      block to fill [default: 1]")
+               .takes_value(true))
+          .arg(Arg::with_name("matcher")
+               .long("matcher")
+               .value_name("KIND")
+               .help("Sets how filter strings are interpreted [default: substring]")
+               .possible_values(&matcher_kinds)
+               .takes_value(true))
+          .arg(Arg::with_name("regex")
+               .short("E")
+               .long("regex")
+               .help("Interprets filter strings as regular expressions; same as --matcher regex")
+               .conflicts_with("matcher"))
+          .arg(Arg::with_name("json")
+               .long("json")
+               .help("Interprets filter strings as queries of the fields of JSON lines, e.g. \
+                      `level=ERROR` or `msg~timeout`; same as --matcher json")
+               .conflicts_with_all(&["matcher", "regex"]))
+          .arg(Arg::with_name("fields")
+               .long("fields")
+               .value_name("FIELD,...")
+               .help("Shows only the given fields of lines parsed into fields, as `KEY=VALUE` \
+                      pairs, e.g. `time,level,req.id,msg`; set with `:fields`")
+               .takes_value(true))
+          .arg(Arg::with_name("format")
+               .long("format")
+               .value_name("NAME")
+               .help("Parses lines in the given format into the fields shown by --fields, \
+                      sorted by with :sort and highlighted by --syntax fields; set with \
+                      `:format` [default: auto, told from the first lines]")
+               .possible_values(&formats)
+               .takes_value(true))
+          .arg(Arg::with_name("highlight")
+               .long("highlight")
+               .value_name("PATTERN")
+               .help("Highlights occurrences of PATTERN in a color of its own, whatever the \
+                      filter; may be given more than once; set with `:highlight`")
+               .takes_value(true)
+               .multiple(true)
+               .number_of_values(1))
+          .arg(Arg::with_name("min-level")
+               .long("min-level")
+               .value_name("LEVEL")
+               .help("Shows only lines logged at LEVEL or a more severe one, e.g. `warn`, \
+                      whatever other filter is applied; set with `:level`")
+               .takes_value(true))
+          .arg(Arg::with_name("status")
+               .long("status")
+               .value_name("CLASS")
+               .help("Shows only requests of access logs answered with a status of CLASS, \
+                      e.g. `5xx` or `404`, whatever other filter is applied; set with `:status`")
+               .takes_value(true)
+               .conflicts_with("min-level"))
+          .arg(Arg::with_name("slow")
+               .long("slow")
+               .value_name("LATENCY")
+               .help("Highlights requests of access logs whose latency, logged after the user \
+                      agent, exceeds LATENCY, in the unit logged, e.g. `0.5` for nginx's \
+                      $request_time")
+               .takes_value(true))
+          .arg(Arg::with_name("ignore-case")
+               .short("i")
+               .long("ignore-case")
+               .help("Ignores case when matching filter strings"))
+          .arg(Arg::with_name("ignore-accents")
+               .long("ignore-accents")
+               .help("Ignores accents and other diacritical marks when matching filter strings, \
+                      so that e.g. `cafe` matches `café`"))
+          .arg(Arg::with_name("token")
+               .long("token")
+               .value_name("REGEX")
+               .help("Sets the pattern of tokens picked up by `*` [default: words]")
+               .takes_value(true))
+          .arg(Arg::with_name("no-line-numbers")
+               .short("n")
+               .long("no-line-numbers")
+               .help("Hides the line number gutter"))
+          .arg(Arg::with_name("tab-width")
+               .long("tab-width")
+               .value_name("COLUMNS")
+               .help("Sets the number of columns between the tab stops to which tabs are \
+                      expanded [default: 8]")
+               .takes_value(true))
+          .arg(Arg::with_name("show-trailing-space")
+               .long("show-trailing-space")
+               .help("Draws the spaces and tabs ending each line as dots"))
+          .arg(Arg::with_name("word-diff")
+               .long("word-diff")
+               .help("Highlights the words in which each line differs from the similar line \
+                      before it, toggled with `W`"))
+          .arg(Arg::with_name("scroll-step")
+               .long("scroll-step")
+               .value_name("LINES")
+               .help("Sets the number of lines scrolled by Ctrl-D and Ctrl-U, which a count typed \
+                      ahead of either also sets [default: half the window]")
+               .takes_value(true))
+          .arg(Arg::with_name("clipboard-command")
+               .long("clipboard-command")
+               .value_name("COMMAND")
+               .help("Copies lines yanked with `y` by piping them to a shell command, e.g. \
+                      'xclip -selection clipboard', rather than by the terminal")
+               .takes_value(true))
+          .arg(Arg::with_name("bell")
+               .long("bell")
+               .value_name("KIND")
+               .help("Beeps or flashes the screen when the window can't move, e.g. when paging \
+                      down at the end of the input [default: off]")
+               .possible_values(&["off", "beep", "flash"])
+               .takes_value(true))
+          .arg(Arg::with_name("match-counts")
+               .long("match-counts")
+               .help("Shows the number of matches within each line in a gutter, toggled with `C`"))
+          .arg(Arg::with_name("match-ordinals")
+               .long("match-ordinals")
+               .help("Shows the ordinal of each match among the matches after its line number \
+                      while filtering, e.g. `42 (#7)` for the seventh match; set with \
+                      `:set ordinals`"))
+          .arg(Arg::with_name("time-deltas")
+               .long("time-deltas")
+               .help("Shows the time elapsed since the previous line's timestamp in a gutter"))
+          .arg(Arg::with_name("stall-threshold")
+               .long("stall-threshold")
+               .value_name("SECONDS")
+               .help("Highlights time deltas of at least this many seconds [default: 1]")
+               .takes_value(true))
+          .arg(Arg::with_name("time-zone")
+               .long("time-zone")
+               .value_name("ZONE")
+               .help("Displays leading timestamps in their original form, UTC, or local time")
+               .possible_values(&["original", "utc", "local"])
+               .takes_value(true))
+          .arg(Arg::with_name("theme")
+               .long("theme")
+               .value_name("NAME")
+               .help("Sets the color theme; high-contrast and deuteranopia don't rely on telling \
+                      red from green")
+               .possible_values(theme::NAMES)
+               .takes_value(true))
+          .arg(Arg::with_name("syntax")
+               .long("syntax")
+               .value_name("FORMAT")
+               .help("Highlights the syntax of lines: timestamps and levels of logs, keys of \
+                      JSON, or levels and timestamps among the fields of lines, beneath matches \
+                      [default: none]")
+               .possible_values(syntax::NAMES)
+               .takes_value(true))
+          .arg(Arg::with_name("theme-file")
+               .long("theme-file")
+               .value_name("FILE")
+               .help("Reads the color theme from a file")
+               .conflicts_with("theme")
+               .takes_value(true))
+          .arg(Arg::with_name("split-on")
+               .long("split-on")
+               .value_name("MARKER")
+               .help("Splits the input into sections, shown in tabs switched with Tab and \
+                      Shift-Tab, at lines of the form MARKER with `{}` in place of the section's \
+                      name, e.g. '==> {} <==' as written by `tail` given several files")
+               .conflicts_with("output")
+               .takes_value(true))
+          .arg(Arg::with_name("sample")
+               .long("sample")
+               .value_name("SPEC")
+               .help("Shows only lines at the start and end of the input file, e.g. \
+                      head:100,tail:100, numbered as lines of the sample")
+               .requires("INPUT")
+               .conflicts_with("follow")
+               .takes_value(true))
+          .arg(Arg::with_name("between")
+               .long("between")
+               .value_names(&["START", "END"])
+               .help("Shows only the blocks of lines from each line matching the regex START to \
+                      the next line matching the regex END, numbered as lines of the blocks")
+               .number_of_values(2)
+               .conflicts_with("sample")
+               .takes_value(true))
+          .arg(Arg::with_name("between-exclusive")
+               .long("between-exclusive")
+               .help("Leaves out the lines beginning and ending the blocks shown with --between")
+               .requires("between"))
+          .arg(Arg::with_name("exec")
+               .long("exec")
+               .value_name("COMMAND")
+               .help("Pages the output of a shell command, e.g. 'journalctl -f', following it as \
+                      it's written; the command is stopped on quitting")
+               .conflicts_with_all(&["INPUT", "output", "plain"])
+               .takes_value(true))
+          .arg(Arg::with_name("exec-stderr")
+               .long("exec-stderr")
+               .help("Pages what the command run with --exec writes to stderr as well, each line \
+                      tagged [stderr]")
+               .requires("exec"))
+          .arg(Arg::with_name("metrics-port")
+               .long("metrics-port")
+               .value_name("PORT")
+               .help("Serves metrics, e.g. the numbers of lines read and matched, on localhost:PORT \
+                      in the Prometheus text format")
+               .conflicts_with("output")
+               .takes_value(true))
+          .arg(Arg::with_name("control")
+               .long("control")
+               .value_name("SOCKET")
+               .help("Listens for commands, e.g. `filter PATTERN`, `goto LINE` and `reload`, on a \
+                      Unix socket, one per line, so that other programs may drive the pager")
+               .conflicts_with_all(&["output", "plain"])
+               .takes_value(true))
+          .arg(Arg::with_name("presets")
+               .long("presets")
+               .value_name("FILE")
+               .help("Reads filter presets, invoked with `:preset NAME [ARG ...]`, from a file")
+               .takes_value(true))
+          .arg(Arg::with_name("config")
+               .long("config")
+               .value_name("FILE")
+               .help("Reads the theme, presets and key bindings from a file [default: \
+                      ~/.config/filterless/config, if it exists]")
+               .takes_value(true))
+          .arg(Arg::with_name("plain")
+               .long("plain")
+               .help("Writes pages one after another to stdout, with matches and gaps marked in \
+                      words, instead of drawing them in place, e.g. for screen readers")
+               .conflicts_with_all(&["output", "follow", "split-on"]))
+          .arg(Arg::with_name("restore")
+               .long("restore")
+               .help("Restores the position, filter and marks of the last session paging the \
+                      input file")
+               .requires("INPUT")
+               .conflicts_with("no-session"))
+          .arg(Arg::with_name("save-session")
+               .long("save-session")
+               .value_name("FILE")
+               .help("Saves the position, filter and marks of the session to a file on quitting")
+               .takes_value(true))
+          .arg(Arg::with_name("restore-session")
+               .long("restore-session")
+               .value_name("FILE")
+               .help("Restores the position, filter and marks of a session saved with \
+                      --save-session")
+               .conflicts_with("restore")
+               .takes_value(true))
+          .arg(Arg::with_name("no-session")
+               .long("no-session")
+               .help("Doesn't save the position, filter and marks of the session, which \
+                      otherwise are saved when idle and on quitting"))
+          .arg(Arg::with_name("record")
+               .long("record")
+               .value_name("FILE")
+               .help("Records the keys pressed to a transcript file, along with the view each \
+                      led to, to be replayed with --replay")
+               .conflicts_with_all(&["output", "plain", "replay"])
+               .takes_value(true))
+          .arg(Arg::with_name("replay")
+               .long("replay")
+               .value_name("FILE")
+               .help("Replays the keys recorded in a transcript file, at the pace they were \
+                      pressed, before reading keys from the keyboard")
+               .conflicts_with_all(&["output", "plain"])
+               .takes_value(true))
+          .arg(Arg::with_name("read-only")
+               .long("read-only")
+               .help("Guarantees that nothing is written to the file system: no session is \
+                      saved, and writing a file, e.g. with --output or `s`, fails")
+               .conflicts_with_all(&["restore", "max-memory"]))
+          .arg(Arg::with_name("profile-render")
+               .long("profile-render")
+               .help("Shows, in the top right corner, the frames drawn per second and the time \
+                      each spent on average classifying lines, drawing them and updating the \
+                      terminal, along with the number slower than a frame's budget"))
+          .get_matches()
+}
 
+/// Returns a boxed `std::io::BufRead` which enables line-wise reading of input
+/// from a file named on the CLI or failing that from stdin.
+///
+/// ### Parameters
+/// * `_stdin`: standard input from which to read if user doesn't provide a file
+///   name
+/// * `matches`: parsed command line arguments
+/// * `integrity`: set to the checker of what's read from the file, if it's
+///   a regular file
+fn get_input<'a>(_stdin: &'a std::io::Stdin, matches: &ArgMatches,
+                 integrity: &mut Option<Integrity>) -> Result<Box<dyn BufRead + 'a>, String> {
       match matches.value_of("INPUT") {
-          Some(fname) => {
-              let file = File::open(fname).unwrap();
-              let reader: BufReader<File> = BufReader::new(file);
-              Box::new(reader)
-          },
-          None => Box::new(_stdin.lock())
+          Some(fname) => open_input_file(fname, integrity)
+              .map_err(|err| format!("{}: {}", fname, err)),
+          None => compressed::decompress(_stdin.lock(), None)
+              .map_err(|err| format!("stdin: {}", err)),
       }
 }
 
-/// Event handler for when a user chooses to begin filtering text.
+/// Opens the input file `fname` to be read line by line, decompressed if it's
+/// compressed, keeping track of what's read if it's a regular file, which may
+/// change while it's followed.
 ///
+/// ### Parameters
+/// * `fname`: path of the file
+/// * `integrity`: set to the checker of what's read from the file, if it's
+///   a regular file, else to `None`
+fn open_input_file(fname: &str, integrity: &mut Option<Integrity>)
+                   -> io::Result<Box<dyn BufRead>> {
+    let reader = open_tracked(fname, integrity)?;
+    compressed::decompress(reader, Some(Path::new(fname)))
+}
+
+/// Opens the input file `fname`, still compressed if it is, keeping track of
+/// what's read if it's a regular file, as `open_input_file()` does.
+fn open_tracked(fname: &str, integrity: &mut Option<Integrity>)
+                -> io::Result<BufReader<Box<dyn Read + Send>>> {
+    let file = File::open(fname)?;
+    *integrity = None;
+
+    if file.metadata()?.is_file() {
+        let (tracked, checker) = Integrity::track(file, Path::new(fname))?;
+        *integrity = Some(checker);
+        return Ok(BufReader::new(Box::new(tracked)));
+    }
+    Ok(BufReader::new(Box::new(file)))
+}
+
+/// Reads the input file named on the CLI or failing that stdin, as
+/// `get_input()` does, but on a thread of its own, so that a slow input
+/// doesn't keep the pager waiting.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+/// * `integrity`: set to the checker of what's read from the file, if it's
+///   a regular file
+fn get_stream(matches: &ArgMatches, integrity: &mut Option<Integrity>)
+              -> Result<(Stream, StreamLines), String> {
+    let open: Box<dyn FnOnce() -> io::Result<Box<dyn BufRead>> + Send> =
+        match matches.value_of("INPUT") {
+            Some(fname) => {
+                let reader = open_tracked(fname, integrity)
+                    .map_err(|err| format!("{}: {}", fname, err))?;
+                let path = PathBuf::from(fname);
+                Box::new(move || compressed::decompress(reader, Some(&path)))
+            },
+            None => Box::new(|| compressed::decompress(stdin().lock(), None)),
+        };
+    Ok(stream::spawn(open))
+}
+
+/// Returns the name by which the input is referred to in exported Markdown:
+/// the file named on the CLI, or `stdin`.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_source_name(matches: &ArgMatches) -> String {
+    if matches.is_present("sample") {
+        return "sample".to_owned();
+    }
+    if let Some(command) = matches.value_of("exec") {
+        return command.to_owned();
+    }
+    matches.value_of("INPUT").unwrap_or("stdin").to_owned()
+}
+
+/// Returns the file named on the CLI mapped into memory, or `None` if no file
+/// was named or it isn't a regular file which can be mapped.
+///
+/// Files aren't mapped in follow mode, where they're more likely to be
+/// truncated, e.g. by log rotation, while being viewed, nor if they're
+/// compressed, which are read through a decompressing reader instead, nor if
+/// they're Windows event logs, whose records are decoded as they're read.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_mapped_file(matches: &ArgMatches) -> Option<MappedFile> {
+    if matches.is_present("follow") || matches.is_present("split-on") ||
+        matches.is_present("sample") || matches.is_present("between") {
+        return None;
+    }
+
+    let fname = matches.value_of("INPUT")?;
+    #[cfg(feature = "evtx")]
+    {
+        if EvtxSource::is_evtx(fname) {
+            return None;
+        }
+    }
+    let file = File::open(fname).ok()?;
+    match file.metadata() {
+        Ok(ref metadata) if metadata.is_file() => {
+            match Compression::of_file(&file, Path::new(fname)) {
+                Ok(None) => MappedFile::new(file).ok(),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Returns the lines of the blocks delimited by the patterns given on the
+/// CLI with `--between`, if any, or else `lines` as they are, or an error if
+/// a pattern is invalid.
+///
+/// ### Parameters
+/// * `lines`: the lines of the input
+/// * `matches`: parsed command line arguments
+fn get_blocks<'a>(lines: Box<dyn Iterator<Item=String> + 'a>, matches: &ArgMatches)
+                  -> Result<Box<dyn Iterator<Item=String> + 'a>, String> {
+    let patterns: Vec<&str> = match matches.values_of("between") {
+        Some(patterns) => patterns.collect(),
+        None => return Ok(lines),
+    };
+
+    let build = |pattern: &str| {
+        Matcher::build("regex", pattern, &MatchOptions::default())
+            .map_err(|err| format!("invalid block delimiter `{}`: {}", pattern, err))
+    };
+    let (start, end) = (build(patterns[0])?, build(patterns[1])?);
+    Ok(Box::new(Between::new(lines, start, end, !matches.is_present("between-exclusive"))))
+}
+
+/// Returns the records of the Windows event log named on the CLI, read as
+/// lines, or `None` if the input isn't one.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+#[cfg(feature = "evtx")]
+fn get_evtx(matches: &ArgMatches) -> Result<Option<Box<dyn LineSource>>, String> {
+    match matches.value_of("INPUT") {
+        Some(fname) if EvtxSource::is_evtx(fname) => EvtxSource::open(fname)
+            .map(|source| Some(Box::new(source) as Box<dyn LineSource>))
+            .map_err(|err| format!("{}: {}", fname, err)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(not(feature = "evtx"))]
+fn get_evtx(_matches: &ArgMatches) -> Result<Option<Box<dyn LineSource>>, String> {
+    Ok(None)
+}
+
+/// Returns `true` if the input named on the CLI is a dataset of JSON lines,
+/// compressed or not, as told by its name, e.g. `train.jsonl.gz`, and no
+/// matcher, format or syntax is given, in which case it's paged in JSON mode.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+#[cfg(feature = "dataset")]
+fn is_dataset(matches: &ArgMatches) -> bool {
+    let chosen = ["matcher", "regex", "format", "syntax"].iter()
+        .any(|&name| matches.is_present(name));
+    !chosen && matches.value_of("INPUT")
+        .is_some_and(|fname| compressed::is_dataset(Path::new(fname)))
+}
+
+#[cfg(not(feature = "dataset"))]
+fn is_dataset(_matches: &ArgMatches) -> bool {
+    false
+}
+
+/// Runs the command given on the CLI to produce the input, if any, returning
+/// it along with the lines it writes, or an error if it can't be run.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_exec(matches: &ArgMatches) -> Result<Option<(Exec, ExecLines)>, String> {
+    let command = match matches.value_of("exec") {
+        Some(command) => command,
+        None => return Ok(None),
+    };
+    Exec::spawn(command, matches.is_present("exec-stderr"))
+        .map(Some)
+        .map_err(|err| format!("{}: {}", command, err))
+}
+
+/// Returns the lines sampled from the input file per the CLI, if sampling
+/// was asked for, or an error if they can't be read.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_sample(matches: &ArgMatches) -> Result<Option<SampleSource>, String> {
+    let (spec, fname) = match (matches.value_of("sample"), matches.value_of("INPUT")) {
+        (Some(spec), Some(fname)) => (spec, fname),
+        _ => return Ok(None),
+    };
+
+    Sample::parse(spec)
+        .map_err(|err| format!("invalid sample `{}`: {}", spec, err))
+        .and_then(|sample| {
+            File::open(fname)
+                .and_then(|file| match Compression::of_file(&file, Path::new(fname))? {
+                    Some(compression) => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                        format!("can't sample input compressed with {:?}", compression))),
+                    None => sample.read(file),
+                })
+                .map_err(|err| format!("{}: {}", fname, err))
+        })
+        .map(Some)
+}
+
+/// Returns the absolute path of the input file whose session is to be saved,
+/// or `None` if the input isn't a file named on the CLI, is sampled, or
+/// sessions aren't to be saved.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_session_input(matches: &ArgMatches) -> Option<PathBuf> {
+    if matches.is_present("no-session") || matches.is_present("sample") || read_only::is_enabled() {
+        return None;
+    }
+
+    matches.value_of("INPUT").and_then(|fname| Path::new(fname).canonicalize().ok())
+}
+
+/// Returns the state of the session paging `input`.
+///
+/// ### Parameters
+/// * `pager`: the pager whose state is returned
+/// * `input`: absolute path of the input file
+fn get_session<T: Iterator<Item=String>>(pager: &Pager<T>, input: &Path) -> Session {
+    let mut session = Session::new(input.to_owned());
+    session.top_line = pager.top_line_num();
+    session.filter = pager.filter_source();
+    session.marks = pager.marks();
+    session
+}
+
+/// Restores the state of an earlier session, keeping the filter given on the
+/// CLI if there is one.
+///
+/// ### Parameters
+/// * `pager`: the pager to restore the session in
+/// * `session`: the state of the earlier session
+/// * `matches`: parsed command line arguments
+fn restore_session<T: Iterator<Item=String>>(pager: &mut Pager<T>, session: &Session,
+                                             matches: &ArgMatches) -> Result<(), String> {
+    let keep_filter = matches.is_present("filter") || matches.is_present("patterns-file");
+    match session.filter {
+        Some(ref source) if !keep_filter => {
+            let predicate = FilterPredicate::from_source(source, CONTEXT_LINES)
+                .map_err(|err| format!("can't restore filter: {}", err))?;
+            pager.set_matcher_kind(matcher::kind(&source.kind)?);
+            pager.set_match_options(source.options);
+            pager.apply_predicate(Some(predicate));
+        },
+        _ => {},
+    }
+
+    for &(mark, tab, line_num) in &session.marks {
+        pager.restore_mark(mark, tab, line_num);
+    }
+    if let Some(line_num) = session.top_line {
+        pager.goto_line(line_num);
+    }
+    Ok(())
+}
+
+/// Returns the form of the lines splitting the input into sections given on
+/// the CLI, if any, or an error if it's invalid.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_marker(matches: &ArgMatches) -> Result<Option<Marker>, String> {
+    matches.value_of("split-on").map(|template| {
+        Marker::parse(template).map_err(|err| format!("invalid marker `{}`: {}", template, err))
+    }).transpose()
+}
+
+/// Adds a tab to `pager` for each section of the input found since the last
+/// call.
+///
+/// ### Parameters
+/// * `pager`: the pager showing the input
+/// * `sections`: the sections of the input
+fn add_sections<'a, I>(pager: &mut Pager<Box<dyn Iterator<Item=String> + 'a>>,
+                       sections: &Sections<I>)
+    where I: Iterator<Item=String> + 'a {
+
+    for (name, section) in sections.take_new() {
+        pager.add_tab(name, Input::Lines(Box::new(section)));
+    }
+}
+
+/// Starts serving metrics on the port given on the CLI, if any, or returns an
+/// error if it can't be listened on.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_metrics(matches: &ArgMatches) -> Result<Option<Arc<Metrics>>, String> {
+    let port = match matches.value_of("metrics-port") {
+        Some(port) => port,
+        None => return Ok(None),
+    };
+    let metrics = Arc::new(Metrics::new());
+
+    let port = port.parse().map_err(|_| format!("invalid metrics port `{}`", port))?;
+    metrics::serve(port, metrics.clone())
+        .map_err(|err| format!("can't serve metrics on port {}: {}", port, err))?;
+    Ok(Some(metrics))
+}
+
+/// Starts listening for commands on the control socket given on the CLI, if
+/// any, or returns an error if it can't be listened on.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_control_socket(matches: &ArgMatches) -> Result<Option<ControlSocket>, String> {
+    matches.value_of("control").map(|path| {
+        ControlSocket::bind(Path::new(path))
+            .map_err(|err| format!("can't listen on {}: {}", path, err))
+    }).transpose()
+}
+
+/// Opens the input file named on the CLI afresh, so that the pager may
+/// reload it.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+/// * `integrity`: set to the checker of what's read from the file, if it's
+///   read line by line
+fn reopen_input<'a>(matches: &ArgMatches, integrity: &mut Option<Integrity>)
+                    -> Result<Input<Box<dyn Iterator<Item=String> + 'a>>, String> {
+    if matches.is_present("split-on") || matches.is_present("sample") {
+        return Err("can't reload a split or sampled input".to_owned());
+    }
+    if matches.is_present("exec") {
+        return Err("can't reload the output of a command".to_owned());
+    }
+    let fname = matches.value_of("INPUT").ok_or("can't reload stdin".to_owned())?;
+    if let Some(file) = get_mapped_file(matches) {
+        return Ok(Input::Mapped(file));
+    }
+
+    let reader = open_input_file(fname, integrity).map_err(|e| format!("{}: {}", fname, e))?;
+    let lines = reader.lines().map(|l| l.unwrap_or("UNICODE ERROR".to_owned()));
+    Ok(Input::Lines(get_blocks(Box::new(lines), matches)?))
+}
+
+/// Returns the color theme selected on the CLI, or an error if a theme file
+/// can't be read.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_theme(matches: &ArgMatches) -> Result<Theme, String> {
+    if let Some(path) = matches.value_of("theme-file") {
+        return Theme::from_file(path);
+    }
+
+    Ok(matches.value_of("theme")
+        .and_then(Theme::from_name)
+        .unwrap_or(Theme::default()))
+}
+
+/// Returns the filter presets named on the CLI, or an error if the presets
+/// file can't be read.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_presets(matches: &ArgMatches) -> Result<Presets, String> {
+    match matches.value_of("presets") {
+        Some(path) => Presets::from_file(path),
+        None => Ok(Presets::new()),
+    }
+}
+
+/// Returns the configuration read from the file named on the CLI, or failing
+/// that from the user's configuration file if it exists, with the theme and
+/// presets named on the CLI in place of the file's, or an error if a file
+/// can't be read.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_config(matches: &ArgMatches) -> Result<Config, String> {
+    let mut config = Config::new(Theme::default(), Presets::new(), Keymap::default());
+    let path = matches.value_of("config")
+        .map(PathBuf::from)
+        .or_else(|| Config::default_path().filter(|path| path.is_file()));
+
+    if let Some(path) = path {
+        config.import(&path.to_string_lossy())?;
+    }
+
+    if matches.is_present("theme") || matches.is_present("theme-file") {
+        config.theme = get_theme(matches)?;
+    }
+    for (name, template) in get_presets(matches)?.iter() {
+        config.presets.insert(name.clone(), template.clone());
+    }
+    Ok(config)
+}
+
+/// Returns the kind of matcher selected on the CLI, or an error if it isn't
+/// compiled into this build.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_matcher_kind(matches: &ArgMatches) -> Result<MatcherKind, String> {
+    let name = if matches.is_present("regex") {
+        "regex"
+    } else if matches.is_present("json") || is_dataset(matches) {
+        "json"
+    } else {
+        matches.value_of("matcher").unwrap_or(matcher::SUBSTRING.name)
+    };
+
+    matcher::kind(name)
+}
+
+/// Returns the dotted paths of fields listed in `list`, separated by commas.
+fn parse_fields(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|field| field.trim())
+        .filter(|field| !field.is_empty())
+        .map(|field| field.to_owned())
+        .collect()
+}
+
+/// Returns how filter strings are compared with lines, per the CLI.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_match_options(matches: &ArgMatches) -> MatchOptions {
+    MatchOptions {
+        ignore_case: matches.is_present("ignore-case"),
+        ignore_accents: matches.is_present("ignore-accents"),
+    }
+}
+
+/// Returns the filter given on the CLI, either as a filter string or as the
+/// patterns of a patterns file, if any.
+///
+/// ### Parameters
+/// * `matches`: parsed command line arguments
+fn get_predicate(matches: &ArgMatches) -> Result<Option<FilterPredicate>, String> {
+    let kind = get_matcher_kind(matches)?;
+    Ok(match (matches.value_of("filter"), matches.value_of("patterns-file")) {
+        (Some(filter), _) => Some(FilterPredicate::with_kind(
+                &kind, filter, &get_match_options(matches), CONTEXT_LINES)
+            .map_err(|e| format!("invalid filter `{}`: {}", filter, e))?),
+        (None, Some(path)) => {
+            let patterns = PatternsFile::new(path).poll().unwrap_or(Ok(Vec::new()))?;
+            if patterns.is_empty() {
+                None
+            } else {
+                Some(FilterPredicate::any_of(
+                        &kind, &patterns, &get_match_options(matches), CONTEXT_LINES)
+                    .map_err(|e| format!("invalid pattern {}", e))?)
+            }
+        },
+        (None, None) => None,
+    })
+}
+
+/// Writes the lines of `input` selected by the filter named on the CLI to
+/// `path`, returning the number of lines written.
+///
+/// ### Parameters
+/// * `input`: lines of the input
+/// * `path`: path of the file to write, or `-` for stdout
+/// * `matches`: parsed command line arguments
+/// * `options`: which lines to write and how
+fn write_output<T: Iterator<Item=String>>(input: Input<T>, path: &str, matches: &ArgMatches,
+                                          options: &ExportOptions) -> Result<usize, String> {
+    let predicate = get_predicate(matches)?;
+    let mut window_buffer = WindowBuffer::new(input, predicate, 0, 0);
+    let lines = window_buffer.all_lines();
+
+    if matches.is_present("markdown") {
+        let source = get_source_name(matches);
+        return if path == "-" {
+            let stdout = stdout();
+            let mut out = stdout.lock();
+            export::write_markdown(&mut out, lines, &source).map_err(|e| e.to_string())
+        } else {
+            let file = read_only::create(path).map_err(|e| format!("{}: {}", path, e))?;
+            export::write_markdown(&mut BufWriter::new(file), lines, &source)
+                .map_err(|e| format!("{}: {}", path, e))
+        };
+    }
+
+    if path == "-" {
+        let stdout = stdout();
+        let mut out = stdout.lock();
+        export::write_lines(&mut out, lines, options).map_err(|e| e.to_string())
+    } else {
+        let file = read_only::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        export::write_lines(&mut BufWriter::new(file), lines, options)
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+/// Spawns a single-line window at the bottom of the screen in which the user
+/// edits a line of input, as described by `LineEditor`.
+///
+/// Returns the input after the user presses ENTER, or `None` if they press
+/// ESC. If the input was cut short for being too long, e.g. by pasting a
+/// whole log, a warning is left in the status bar.
+///
+/// ### Parameters
+/// * `prompt`: text shown before the user's input
+/// * `height`: height of the terminal in rows
+/// * `status`: the status bar
+/// * `on_key`: called with the input, and whether it changed, after each
+///   keypress or wait for one which timed out
+fn _edit_line<F: FnMut(&str, bool)>(prompt: &str, height: i32, status: &mut StatusBar,
+                                    mut on_key: F) -> Option<String> {
+    let prompt_win = newwin(1, 0, height - 1, 0);
+    let mut editor = LineEditor::new();
+
+    let input = loop {
+        // case: an over-long paste was cut short, which the prompt warns of
+        let prompt = if editor.is_truncated() {
+            Cow::Owned(format!("(truncated to {} characters) {}", line_edit::MAX_CHARS, prompt))
+        } else {
+            Cow::Borrowed(prompt)
+        };
+
+        werase(prompt_win);
+        waddstr(prompt_win, &prompt);
+        waddstr(prompt_win, &editor.text());
+        let typed: String = editor.text().chars().take(editor.cursor()).collect();
+        wmove(prompt_win, 0, (text::width(&prompt) + text::width(&typed)) as i32);
+        wrefresh(prompt_win);
+
+        let changed = match transcript::getch() {
+            ERR => false,
+            key => match editor.handle(key) {
+                Edit::Changed => true,
+                Edit::Unchanged => false,
+                Edit::Submitted => break Some(editor.text()),
+                Edit::Cancelled => break None,
+            },
+        };
+        on_key(&editor.text(), changed);
+    };
+
+    werase(prompt_win);
+    wrefresh(prompt_win);
+    delwin(prompt_win);
+
+    if editor.is_truncated() {
+        status.set_message(format!("Input truncated to {} characters", line_edit::MAX_CHARS));
+    }
+    input
+}
+
 /// Spawns a single-line window at the bottom of the screen, collects user
-/// input, and returns it after user presses ENTER.
+/// input, and returns it after user presses ENTER, or `None` if they press
+/// ESC.
 ///
 /// ### Parameters
-/// * `width`: width of the terminal in columns
+/// * `prompt`: text shown before the user's input
 /// * `height`: height of the terminal in rows
-fn _filter<T: Iterator<Item=String>>(width: i32, height: i32, pager: &mut Pager<T>) {
-    let filter_win = newwin(1, 0, height - 1, 0);
-    wprintw(filter_win, "Filter: ");
-    wrefresh(filter_win);
-    let mut filter_str = String::new();
-    loop {
-        match getch() {
-            ENTER => break,
-            BACKSPACE => {
-                match filter_str.pop() {
-                    Some(_) => {
-                        let mut x = 0;
-                        let mut y = 0;
-                        getyx(filter_win, &mut y, &mut x);
-                        wmove(filter_win, y, x - 1);
-                        wdelch(filter_win);
-                        wrefresh(filter_win);
+/// * `status`: the status bar
+fn _prompt(prompt: &str, height: i32, status: &mut StatusBar) -> Option<String> {
+    _edit_line(prompt, height, status, |_, _| {})
+}
+
+/// Spawns a single-line window at the bottom of the screen asking a yes or no
+/// question, and returns `true` if the user presses `y`.
+///
+/// ### Parameters
+/// * `prompt`: the question, e.g. ending in `(y/n)`
+/// * `height`: height of the terminal in rows
+fn _confirm(prompt: &str, height: i32) -> bool {
+    let prompt_win = newwin(1, 0, height - 1, 0);
+    waddstr(prompt_win, prompt);
+    wrefresh(prompt_win);
+
+    transcript::timeout(-1);
+    let key = transcript::getch();
+
+    werase(prompt_win);
+    wrefresh(prompt_win);
+    delwin(prompt_win);
+    key == 'y' as i32 || key == 'Y' as i32
+}
+
+/// What the commands of the `:` prompt and the control socket act upon.
+struct CommandContext<'a, T: Iterator<Item=String> + 'a> {
+    pager: &'a mut Pager<T>,
+    status: &'a mut StatusBar,
+    /// the theme, presets and key bindings in use
+    config: &'a mut Config,
+    /// name of the input, as given in exported Markdown and HTML
+    source: &'a str,
+    /// how lines are written by the `write` command
+    export_options: &'a ExportOptions,
+}
+
+/// Options turned on and off by the `set` command.
+const SET_OPTIONS: &[&str] = &["numbers", "deltas", "counts", "ordinals", "worddiff",
+                               "ignorecase", "ignoreaccents", "follow"];
+
+/// Returns the commands run at the `:` prompt and over the control socket.
+fn commands<'a, T: Iterator<Item=String>>() -> Vec<Command<CommandContext<'a, T>>> {
+    vec![
+        Command {
+            name: "filter",
+            args: "[PATTERN...]",
+            description: "filters by a pattern, or removes the filter if none is given",
+            run: |c, args| {
+                if args.text.is_empty() {
+                    c.pager.clear_filter();
+                    return Ok(Some("Filter cleared".to_owned()));
+                }
+                c.pager.filter(Some(args.text.to_owned()))
+                    .map(|_| Some(format!("Filter: {}", args.text)))
+                    .map_err(|err| format!("invalid filter `{}`: {}", args.text, err))
+            },
+        },
+        Command {
+            name: "context",
+            args: "N",
+            description: "shows N lines around each match of the filter",
+            run: |c, args| {
+                let lines = args.words[0].parse::<usize>()
+                    .map_err(|_| format!("invalid number of lines `{}`", args.words[0]))?;
+                c.pager.set_context_lines(lines);
+                Ok(Some(format!("Context: {} line{}", lines, if lines == 1 { "" } else { "s" })))
+            },
+        },
+        Command {
+            name: "goto",
+            args: "LINE|#MATCH",
+            description: "goes to a line of the input, or to the Nth match with `#N`",
+            run: |c, args| {
+                if let Some(ordinal) = args.words[0].strip_prefix('#') {
+                    let line_num = ordinal.parse::<usize>().ok()
+                        .and_then(|ordinal| c.pager.line_of_ordinal(ordinal))
+                        .ok_or(format!("no match `{}`", args.words[0]))?;
+                    c.status.following = false;
+                    c.pager.goto_line(line_num);
+                    return Ok(Some(format!("Match {} (line {})", ordinal, line_num)));
+                }
+                let line_num = args.words[0].parse::<usize>().ok().filter(|&n| n > 0)
+                    .ok_or(format!("invalid line number `{}`", args.words[0]))?;
+                c.status.following = false;
+                c.pager.goto_line(line_num);
+                Ok(Some(format!("Line {}", line_num)))
+            },
+        },
+        Command {
+            name: "write",
+            args: "FILE",
+            description: "writes the lines selected by the filter to a file",
+            run: |c, args| {
+                let written = c.pager.save(args.words[0], c.export_options)?;
+                Ok(Some(format!("Wrote {} lines to {}", written, args.words[0])))
+            },
+        },
+        Command {
+            name: "set",
+            args: "[OPTION] [on|off]",
+            description: "turns an option on or off, e.g. `set numbers off`, or lists them",
+            run: |c, args| {
+                let name = match args.words.first() {
+                    Some(name) => *name,
+                    None => {
+                        let states: Vec<String> = SET_OPTIONS.iter()
+                            .map(|name| {
+                                let on = option_state(c, name) == Some(true);
+                                format!("{}={}", name, if on { "on" } else { "off" })
+                            })
+                            .collect();
+                        return Ok(Some(states.join(" ")));
                     },
-                    None => {},
+                };
+                let on = option_state(c, name).ok_or_else(|| {
+                    format!("unknown option `{}`; expected one of: {}",
+                            name, SET_OPTIONS.join(", "))
+                })?;
+                let on = match args.words.get(1) {
+                    Some(&"on") => true,
+                    Some(&"off") => false,
+                    Some(value) => return Err(format!("expected `on` or `off`; got `{}`", value)),
+                    None => on,
+                };
+                set_option(c, name, on);
+                Ok(Some(format!("{}={}", name, if on { "on" } else { "off" })))
+            },
+        },
+        Command {
+            name: "preset",
+            args: "NAME [ARG...]",
+            description: "filters by the expansion of a preset",
+            run: |c, args| {
+                let filter = c.config.presets.expand(args.words[0], &args.words[1..])?;
+                c.pager.filter(Some(filter)).map(|_| None)
+            },
+        },
+        Command {
+            name: "annotate",
+            args: "FILE",
+            description: "writes the lines selected by the filter to a file, each marked with \
+                          how it was classified",
+            run: |c, args| {
+                let written = c.pager.annotate(args.words[0])?;
+                Ok(Some(format!("Wrote {} annotated lines to {}", written, args.words[0])))
+            },
+        },
+        Command {
+            name: "markdown",
+            args: "FILE",
+            description: "writes each context group of the lines selected by the filter to a \
+                          file as a fenced Markdown code block",
+            run: |c, args| {
+                let written = c.pager.write_markdown(args.words[0], c.source)?;
+                Ok(Some(format!("Wrote {} context groups to {}", written, args.words[0])))
+            },
+        },
+        Command {
+            name: "export",
+            args: "html FILE",
+            description: "writes the lines selected by the filter to a file as an HTML page, \
+                          highlighted as in the window",
+            run: |c, args| {
+                if args.words[0] != "html" {
+                    return Err(format!("unknown export format `{}`", args.words[0]));
                 }
+                let written = c.pager.export_html(args.words[1], c.source)?;
+                Ok(Some(format!("Wrote {} lines to {}", written, args.words[1])))
             },
-            ch => {
-                filter_str.push(char::from_u32(ch as u32).unwrap());
-                waddch(filter_win, ch as chtype);
-                wrefresh(filter_win);
+        },
+        Command {
+            name: "fields",
+            args: "[FIELD,...]",
+            description: "shows only the given fields of lines parsed into fields, or whole \
+                          lines if none are given",
+            run: |c, args| {
+                let fields = parse_fields(&args.words.join(","));
+                let message = if fields.is_empty() {
+                    "Showing whole lines".to_owned()
+                } else {
+                    format!("Showing fields {}", fields.join(", "))
+                };
+                c.pager.set_fields(fields);
+                Ok(Some(message))
             },
-        }
+        },
+        Command {
+            name: "format",
+            args: "[NAME|auto]",
+            description: "parses lines in a format, e.g. `logfmt`, into fields, or in the \
+                          format told from the first lines if none or `auto` is given",
+            run: |c, args| {
+                let format = match args.words.first() {
+                    None | Some(&"auto") => c.pager.detect_format(),
+                    Some(name) => Some(parser::format(name)?),
+                };
+                c.pager.set_format(format);
+                Ok(Some(describe_format(format)))
+            },
+        },
+        Command {
+            name: "t",
+            args: "TIME...",
+            description: "goes to the first line whose leading timestamp is at or after a \
+                          time, e.g. `2023-10-05 14:00`",
+            run: |c, args| {
+                let time = args.words.join(" ");
+                let millis = timestamp::parse_time(&time)
+                    .ok_or(format!("invalid time `{}`", time))?;
+                let line_num = c.pager.goto_time(millis)
+                    .ok_or(format!("No line at or after {}", time))?;
+                c.status.following = false;
+                Ok(Some(format!("Line {}", line_num)))
+            },
+        },
+        Command {
+            name: "sort",
+            args: "[-]COLUMN [DELIMITER]",
+            description: "shows the lines of the page sorted by a column until a key is \
+                          pressed, in descending order given `-`, where the column is the \
+                          number of one split by whitespace or a delimiter, e.g. `sort -4` or \
+                          `sort 2 ,`, or the path of a field",
+            run: |c, args| {
+                let delimiter = args.words.get(1).cloned();
+                let key = SortKey::parse(args.words[0], delimiter, c.pager.options().format)?;
+                let lacking = match c.pager.preview_sorted(&key)? {
+                    0 => String::new(),
+                    1 => ", 1 line lacking it last".to_owned(),
+                    n => format!(", {} lines lacking it last", n),
+                };
+                Ok(Some(format!("Sorted by {}{}; any key returns", key, lacking)))
+            },
+        },
+        Command {
+            name: "first",
+            args: "",
+            description: "jumps to the first line matched by the filter, once the scan for \
+                          matches has found it",
+            run: |c, _| _goto_match(MatchEnd::First, c.pager, c.status).map(Some),
+        },
+        Command {
+            name: "last",
+            args: "",
+            description: "jumps to the last line matched by the filter, once the scan for \
+                          matches has found it",
+            run: |c, _| _goto_match(MatchEnd::Last, c.pager, c.status).map(Some),
+        },
+        Command {
+            name: "gaps",
+            args: "[N]",
+            description: "jumps to the largest stretch of lines skipped by the filter, or the \
+                          Nth largest, and lists the sizes of the largest ones",
+            run: |c, args| {
+                let rank = match args.words.first() {
+                    Some(rank) => rank.parse::<usize>().ok().filter(|&n| n > 0)
+                        .ok_or(format!("invalid gap number `{}`", rank))?,
+                    None => 1,
+                };
+                if !c.pager.is_filtered() {
+                    return Err("No filter in use".to_owned());
+                }
 
-        let predicate = if filter_str.len() > 0 {
-            Some(filter_str.to_owned())
-        } else {
-            None
-        };
+                let mut gaps = c.pager.gaps();
+                gaps.sort_by_key(|&(from, to)| (Reverse(to - from), from));
+                let (from, to) = *gaps.get(rank - 1).ok_or(match gaps.len() {
+                    0 => "No gaps".to_owned(),
+                    n => format!("Only {} gap{}", n, if n == 1 { "" } else { "s" }),
+                })?;
+                c.pager.goto_gap(from);
+                c.status.following = false;
 
-        pager.filter(predicate);
-        ncurses::wrefresh(filter_win);
+                let sizes: Vec<String> = gaps.iter().take(GAP_SIZES_LISTED)
+                    .map(|&(from, to)| (to + 1 - from).to_string())
+                    .collect();
+                Ok(Some(format!("Gap {} of {}: lines {}-{}; largest: {}",
+                                rank, gaps.len(), from, to, sizes.join(", "))))
+            },
+        },
+        Command {
+            name: "quiet",
+            args: "LINES PATTERN...",
+            description: "shows only the lines more than LINES lines away from any match of a \
+                          pattern, e.g. where a heartbeat stopped appearing",
+            run: |c, args| {
+                let lines = args.words[0].parse::<usize>()
+                    .map_err(|_| format!("invalid number of lines `{}`", args.words[0]))?;
+                let pattern = args.words[1..].join(" ");
+                c.pager.filter_quiet(&pattern, lines)
+                    .map_err(|err| format!("invalid filter `{}`: {}", pattern, err))?;
+                Ok(Some(format!("Showing lines more than {} lines away from `{}`",
+                                lines, pattern)))
+            },
+        },
+        Command {
+            name: "base",
+            args: "[PATTERN...]",
+            description: "sets the base filter, which lines must match as well as any other \
+                          filter and which is kept when that's cleared, or removes it if no \
+                          pattern is given",
+            run: |c, args| {
+                if args.words.is_empty() {
+                    return Ok(Some(clear_base(c.pager)));
+                }
+                let pattern = args.words.join(" ");
+                let base = FilterPredicate::with_kind(&c.pager.matcher_kind(), &pattern,
+                                                      &c.pager.match_options(), 0)
+                    .map_err(|err| format!("invalid filter `{}`: {}", pattern, err))?;
+                let message = format!("Base filter: {}", base);
+                c.pager.set_base(Some(base));
+                Ok(Some(message))
+            },
+        },
+        Command {
+            name: "level",
+            args: "[LEVEL]",
+            description: "sets the base filter to lines logged at a level or a more severe \
+                          one, e.g. `warn`, or removes it if no level is given",
+            run: |c, args| {
+                let level = match args.words.first() {
+                    Some(level) => level,
+                    None => return Ok(Some(clear_base(c.pager))),
+                };
+                let options = MatchOptions::default();
+                let base = FilterPredicate::with_kind(&matcher::LEVEL, level, &options, 0)?;
+                c.pager.set_base(Some(base));
+                Ok(Some(format!("Showing lines logged at {} or worse", level.to_lowercase())))
+            },
+        },
+        Command {
+            name: "status",
+            args: "[CLASS]",
+            description: "sets the base filter to requests of access logs answered with a \
+                          status of a class, e.g. `5xx` or `404`, or removes it if no class is \
+                          given",
+            run: |c, args| {
+                let class = match args.words.first() {
+                    Some(class) => class,
+                    None => return Ok(Some(clear_base(c.pager))),
+                };
+                c.pager.set_base(Some(status_filter(class)?));
+                Ok(Some(format!("Showing requests answered with {}", class)))
+            },
+        },
+        Command {
+            name: "highlight",
+            args: "[PATTERN...]",
+            description: "highlights occurrences of a pattern in a color of its own, whatever \
+                          the filter, or lists the patterns highlighted if none is given",
+            run: |c, args| {
+                if args.words.is_empty() {
+                    let patterns = c.pager.highlight_patterns();
+                    return if patterns.is_empty() {
+                        Ok(Some("No highlights".to_owned()))
+                    } else {
+                        Ok(Some(format!("Highlights: {}", patterns.join(", "))))
+                    };
+                }
+                let pattern = args.words.join(" ");
+                c.pager.add_highlight(&pattern)
+                    .map_err(|err| format!("invalid highlight `{}`: {}", pattern, err))?;
+                Ok(Some(format!("Highlighting `{}`", pattern)))
+            },
+        },
+        Command {
+            name: "unhighlight",
+            args: "[PATTERN...]",
+            description: "stops highlighting a pattern, or every pattern if none is given",
+            run: |c, args| {
+                if args.words.is_empty() {
+                    c.pager.remove_highlight(None);
+                    return Ok(Some("Highlights cleared".to_owned()));
+                }
+                let pattern = args.words.join(" ");
+                if c.pager.remove_highlight(Some(&pattern)) {
+                    Ok(Some(format!("Stopped highlighting `{}`", pattern)))
+                } else {
+                    Err(format!("`{}` isn't highlighted", pattern))
+                }
+            },
+        },
+        Command {
+            name: "help",
+            args: "[COMMAND]",
+            description: "describes a command, or lists the commands if none is given",
+            run: |_, args| {
+                let commands = commands::<T>();
+                let name = match args.words.first() {
+                    Some(name) => name,
+                    None => {
+                        let names: Vec<&str> = commands.iter().map(|c| c.name).collect();
+                        return Ok(Some(format!("Commands: {}", names.join(", "))));
+                    },
+                };
+                let command = commands.iter().find(|command| command.name == *name)
+                    .ok_or(format!("unknown command `{}`", name))?;
+                Ok(Some(format!("{}: {}", command.usage(), command.description)))
+            },
+        },
+        Command {
+            name: "config",
+            args: "export|import FILE",
+            description: "writes the theme, presets and key bindings to a file, or merges \
+                          those of a file into the current ones",
+            run: |c, args| match args.words[0] {
+                "export" => {
+                    c.config.export(args.words[1])?;
+                    Ok(Some(format!("Exported configuration to {}", args.words[1])))
+                },
+                "import" => {
+                    c.config.import(args.words[1])?;
+                    c.pager.set_theme(c.config.theme.clone());
+                    Ok(Some(format!("Imported configuration from {}", args.words[1])))
+                },
+                _ => Err("usage: config export|import FILE".to_owned()),
+            },
+        },
+    ]
+}
+
+/// Runs a command entered at the `:` prompt: one of `commands()`, or else
+/// `FIRST,LAST`, which restricts the filter to a range of lines, e.g.
+/// `1000,2000` or `1000,` for line 1000 onward, showing every line within it
+/// if there are no filter strings, or `,`, which lifts the restriction.
+///
+/// Returns a message describing the outcome, if any.
+///
+/// ### Parameters
+/// * `command`: the command line, without the leading `:`
+/// * `context`: what the command acts upon
+fn _command<T: Iterator<Item=String>>(command: &str, context: &mut CommandContext<T>)
+                                      -> Result<Option<String>, String> {
+    let command = command.trim();
+    if command == "," {
+        context.pager.set_line_range(None);
+        return Ok(Some("Showing all lines".to_owned()));
     }
+    if command.contains(',') && !command.contains(char::is_whitespace) {
+        let lines = LineRange::parse(command)?;
+        context.status.following = false;
+        context.pager.set_line_range(Some(lines));
+        return Ok(Some(format!("Showing lines {}", lines)));
+    }
+    command::run(&commands(), command, context)
+}
 
-    if filter_str.len() <= 0 {
-        wclear(filter_win);
-        wrefresh(filter_win);
+/// Returns whether an option of the `set` command is on, or `None` if there's
+/// no such option.
+fn option_state<T: Iterator<Item=String>>(context: &CommandContext<T>, name: &str)
+                                          -> Option<bool> {
+    let options = context.pager.options();
+    let match_options = context.pager.match_options();
+    match name {
+        "numbers" => Some(options.line_numbers),
+        "deltas" => Some(options.time_deltas),
+        "counts" => Some(options.match_counts),
+        "ordinals" => Some(options.ordinals),
+        "worddiff" => Some(options.word_diff),
+        "ignorecase" => Some(match_options.ignore_case),
+        "ignoreaccents" => Some(match_options.ignore_accents),
+        "follow" => Some(context.status.following),
+        _ => None,
     }
+}
 
-    delwin(filter_win);
+/// Turns an option of the `set` command on or off, if it isn't already.
+fn set_option<T: Iterator<Item=String>>(context: &mut CommandContext<T>, name: &str, on: bool) {
+    if option_state(context, name) == Some(on) {
+        return;
+    }
+    let mut match_options = context.pager.match_options();
+    match name {
+        "numbers" => context.pager.toggle_line_numbers(),
+        "deltas" => context.pager.toggle_time_deltas(),
+        "counts" => context.pager.toggle_match_counts(),
+        "ordinals" => context.pager.toggle_ordinals(),
+        "worddiff" => { context.pager.toggle_word_diff(); },
+        "ignorecase" => {
+            match_options.ignore_case = on;
+            context.pager.set_match_options(match_options);
+        },
+        "ignoreaccents" => {
+            match_options.ignore_accents = on;
+            context.pager.set_match_options(match_options);
+        },
+        "follow" => {
+            context.status.following = on;
+            if on {
+                context.pager.last_page();
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Returns the filter of requests of access logs answered with a status of a
+/// class, e.g. `5xx` or `404`.
+fn status_filter(class: &str) -> Result<FilterPredicate, String> {
+    let valid = class.len() == 3 && class.bytes().all(|b| b.is_ascii_digit() || b == b'x');
+    if !valid {
+        return Err(format!("invalid status class `{}`; expected e.g. `5xx` or `404`", class));
+    }
+    let options = MatchOptions::default();
+    FilterPredicate::with_kind(&matcher::FIELD, &format!("status={}", class), &options, 0)
+}
+
+/// Removes the base filter, returning a message describing the outcome.
+fn clear_base<T: Iterator<Item=String>>(pager: &mut Pager<T>) -> String {
+    if pager.base().is_none() {
+        return "No base filter".to_owned();
+    }
+    pager.set_base(None);
+    "Base filter cleared".to_owned()
+}
+
+/// Jumps to the first or last line matched by the filter, or leaves the jump
+/// pending until the scan for matches has found the line.
+///
+/// Returns a message describing the outcome.
+fn _goto_match<T: Iterator<Item=String>>(end: MatchEnd, pager: &mut Pager<T>,
+                                         status: &mut StatusBar) -> Result<String, String> {
+    if !pager.is_filtered() {
+        return Err("No filter in use".to_owned());
+    }
+    status.following = false;
+    Ok(match_message(end, pager.goto_match(end)))
+}
+
+/// Describes the view of `pager` as noted in a transcript, e.g. `line 120,
+/// filter `timeout` (3 context lines)`.
+fn describe_view<T: Iterator<Item=String>>(pager: &Pager<T>) -> String {
+    let line = pager.top_line_num().map_or("no lines".to_owned(), |n| format!("line {}", n));
+    match pager.predicate() {
+        Some(predicate) => format!("{}, filter {}", line, predicate),
+        None => format!("{}, no filter", line),
+    }
+}
+
+/// Returns the name and state of each option of `pager` listed by the help
+/// screen, e.g. `("line numbers", "on")`.
+fn describe_options<T: Iterator<Item=String>>(pager: &Pager<T>, status: &StatusBar)
+                                               -> Vec<(&'static str, String)> {
+    let on_off = |on: bool| if on { "on" } else { "off" }.to_owned();
+    let options = pager.options();
+    let match_options = pager.match_options();
+    vec![
+        ("line numbers", on_off(options.line_numbers)),
+        ("time deltas", on_off(options.time_deltas)),
+        ("match counts", on_off(options.match_counts)),
+        ("word diff", on_off(options.word_diff)),
+        ("timestamps", match options.zone {
+            Zone::Original => "original",
+            Zone::Utc => "UTC",
+            Zone::Local => "local time",
+        }.to_owned()),
+        ("syntax", match options.syntax {
+            Syntax::None => "none",
+            Syntax::Log => "log",
+            Syntax::Json => "json",
+            Syntax::Fields => "fields",
+        }.to_owned()),
+        ("format", options.format.map_or("auto", |format| format.name).to_owned()),
+        ("matcher", pager.matcher_kind().description.to_owned()),
+        ("ignore case", on_off(match_options.ignore_case)),
+        ("ignore accents", on_off(match_options.ignore_accents)),
+        ("following", on_off(status.following)),
+        ("filter", pager.predicate().map_or("none".to_owned(), |p| p.to_string())),
+    ]
+}
+
+/// Describes the format in which lines are parsed into fields, e.g. `Lines
+/// parsed as logfmt`.
+fn describe_format(format: Option<Format>) -> String {
+    match format {
+        Some(format) => format!("Lines parsed as {}", format.description),
+        None => "Lines parsed in whichever format each is in".to_owned(),
+    }
+}
+
+/// Describes the outcome of a search for the first or last match of the
+/// filter.
+fn match_message(end: MatchEnd, search: Search) -> String {
+    let which = if end == MatchEnd::First { "first" } else { "last" };
+    match search {
+        Search::Found(Some(line_num)) => format!("The {} match is line {}", which, line_num),
+        Search::Found(None) => "No matches".to_owned(),
+        Search::Pending => format!("Looking for the {} match...", which),
+    }
+}
+
+/// Runs a command received on the control socket: one of those of the `:`
+/// prompt, or `reload`, which reads the input file afresh, keeping the filter
+/// and position.
+///
+/// Returns a message describing the outcome, if any.
+///
+/// ### Parameters
+/// * `command`: the command line
+/// * `context`: what the command acts upon
+/// * `matches`: parsed command line arguments
+/// * `integrity`: checker of what's read from the input file, replaced on
+///   reloading it
+fn _control<'a>(command: &str,
+                context: &mut CommandContext<Box<dyn Iterator<Item=String> + 'a>>,
+                matches: &ArgMatches, integrity: &mut Option<Integrity>)
+                -> Result<Option<String>, String> {
+    if command.trim() == "reload" {
+        context.pager.reload(reopen_input(matches, integrity)?);
+        return Ok(Some("Reloaded".to_owned()));
+    }
+    _command(command, context)
+}
+
+/// Event handler for when a user chooses to begin filtering text.
+///
+/// Spawns a single-line window at the bottom of the screen in which the user
+/// edits the filter, which is applied as it's typed and its matches shown as
+/// they're found. Pressing ESC restores the filter in use beforehand, while
+/// pressing ENTER with no filter typed removes it.
+///
+/// ### Parameters
+/// * `height`: height of the terminal in rows
+/// * `pager`: the pager to filter
+/// * `status`: the status bar
+fn _filter<T: Iterator<Item=String>>(height: i32, pager: &mut Pager<T>, status: &mut StatusBar) {
+    let original = pager.predicate();
+    transcript::timeout(SCAN_TICK_MS);
+
+    let filter = _edit_line("Filter: ", height, status, |filter_str, changed| {
+        if changed {
+            let predicate = if filter_str.is_empty() {
+                None
+            } else {
+                Some(filter_str.to_owned())
+            };
+
+            // an incomplete pattern, e.g. a regular expression with an
+            // unclosed group, is left unapplied until it becomes valid
+            let _ = pager.filter(predicate);
+        }
+        pager.poll_scan();
+    });
+
+    match filter {
+        Some(ref filter_str) if filter_str.is_empty() => { let _ = pager.filter(None); },
+        Some(_) => {},
+        None => pager.apply_predicate(original),
+    }
 }
 
 /// System entry point
 fn main() {
+    if let Err(err) = run() {
+        eprintln!("filterless: {}", err);
+        process::exit(1);
+    }
+    signals::reraise();
+}
+
+/// Runs the pager, or writes the filtered input out without one, returning
+/// the error which stopped it, if any, once the terminal has been restored.
+fn run() -> error::Result<()> {
+    let matches = get_args();
+    if matches.is_present("read-only") {
+        read_only::enable();
+    }
+    let mut config = get_config(&matches)?;
+    let matcher_kind = get_matcher_kind(&matches)?;
+    let metrics = get_metrics(&matches)?;
+    let control = get_control_socket(&matches)?;
     let _stdin = stdin();
-    let reader = get_input(&_stdin);
-    let lines = reader.lines();
+    let mut sections = None;
+    let mut first_section = None;
+    let mut integrity = None;
+    // command run to produce the input, if any
+    let mut exec = None;
+    // thread reading the input, if it's read on one
+    let mut stream = None;
+    // lines are read on a thread of their own only when they're paged, and
+    // not from a file being followed, which is read afresh as it grows
+    let streamed = !matches.is_present("output") && !matches.is_present("plain")
+        && (!matches.is_present("follow") || !matches.is_present("INPUT"));
+    let input: Input<Box<dyn Iterator<Item=String>>> = match get_mapped_file(&matches) {
+        Some(file) => Input::Mapped(file),
+        None if matches.is_present("sample") => {
+            match get_sample(&matches)? {
+                Some(source) => Input::source(source),
+                None => Input::Lines(Box::new(std::iter::empty())),
+            }
+        },
+        None => {
+            let lines: Box<dyn Iterator<Item=String>> = match get_exec(&matches)? {
+                Some((command, lines)) => {
+                    exec = Some(command);
+                    Box::new(lines)
+                },
+                None => {
+                    let dropped = metrics.clone();
+                    let decode = move |l: io::Result<String>| l.unwrap_or_else(|_| {
+                        if let Some(ref metrics) = dropped {
+                            metrics.record_drop();
+                        }
+                        "UNICODE ERROR".to_owned()
+                    });
+                    if let Some(source) = get_evtx(&matches)? {
+                        Box::new(source.map(decode))
+                    } else if streamed {
+                        let (handle, lines) = get_stream(&matches, &mut integrity)?;
+                        stream = Some(handle);
+                        Box::new(lines.map(decode))
+                    } else {
+                        let reader = get_input(&_stdin, &matches, &mut integrity)?;
+                        let lines = reader.lines();
+//                      let iter = lines.map(|l| l.expect("Unicode error encountered on line"));
+                        Box::new(lines.map(decode))
+                    }
+                },
+            };
+            let lines = get_blocks(lines, &matches)?;
 
-    let window: SCREEN = setup_term();
+            match get_marker(&matches)? {
+                Some(marker) => {
+                    let split = Sections::new(lines, marker);
+                    let (name, section) = split.take_new().remove(0);
+                    sections = Some(split);
+                    first_section = Some(name);
+                    Input::Lines(Box::new(section))
+                },
+                None => Input::Lines(Box::new(lines)),
+            }
+        },
+    };
 
-    let mut max_x = 0;
-    let mut max_y = 0;
-    getmaxyx(stdscr(), &mut max_y, &mut max_x);
-    let height = max_y - MARGIN;
-    let width = max_x - MARGIN;
+    let mut export_options = ExportOptions::default();
+    export_options.context = matches.is_present("with-context");
+    export_options.line_numbers = matches.is_present("with-line-numbers");
+    let source_name = get_source_name(&matches);
+
+    if let Some(path) = matches.value_of("output") {
+        write_output(input, path, &matches, &export_options)?;
+        return Ok(());
+    }
+
+    if matches.is_present("plain") {
+        let predicate = get_predicate(&matches)?;
+        plain::run(input, predicate, matcher_kind, get_match_options(&matches), &config.keys)?;
+        return Ok(());
+    }
+
+    if let Some(path) = matches.value_of("record") {
+        transcript::record(path, &source_name)?;
+    }
+    if let Some(path) = matches.value_of("replay") {
+        transcript::replay(path)?;
+    }
+
+    let screen = setup_term()?;
+
+    let (mut width, mut height) = get_term_size();
 
     refresh();
 
     let win = newwin(height - 1, width, MARGIN / 2, MARGIN / 2);
-//  let iter = lines.map(|l| l.expect("Unicode error encountered on line"));
-    let iter = lines.map(|l| l.unwrap_or("UNICODE ERROR".to_owned()));
-    let mut pager = Pager::new(win, iter);
+    let mut options = DisplayOptions::default();
+    options.line_numbers = !matches.is_present("no-line-numbers");
+    options.time_deltas = matches.is_present("time-deltas");
+    options.match_counts = matches.is_present("match-counts");
+    options.ordinals = matches.is_present("match-ordinals");
+    if let Some(secs) = matches.value_of("stall-threshold") {
+        let secs: f64 = secs.parse()
+            .map_err(|_| format!("invalid stall threshold `{}`", secs))?;
+        options.stall_millis = (secs * 1000.0) as i64;
+    }
+
+    options.zone = match matches.value_of("time-zone") {
+        Some("utc") => Zone::Utc,
+        Some("local") => Zone::Local,
+        _ => Zone::Original,
+    };
+
+    let dataset = is_dataset(&matches);
+    options.syntax = match matches.value_of("syntax") {
+        None if dataset => Syntax::Json,
+        name => name.and_then(Syntax::from_name).unwrap_or(Syntax::None),
+    };
+    options.fields = matches.value_of("fields").map_or(Vec::new(), parse_fields);
+    let detect_format = match matches.value_of("format") {
+        None if dataset => {
+            options.format = Some(parser::JSON);
+            false
+        },
+        None | Some("auto") => true,
+        Some(name) => {
+            options.format = Some(parser::format(name)?);
+            false
+        },
+    };
+    if let Some(columns) = matches.value_of("tab-width") {
+        options.tab_width = columns.parse::<usize>().ok().filter(|&columns| columns > 0)
+            .ok_or(format!("invalid tab width `{}`", columns))?;
+    }
+    options.trailing_space = matches.is_present("show-trailing-space");
+    options.word_diff = matches.is_present("word-diff");
+
+    options.bell = match matches.value_of("bell") {
+        Some("beep") => Bell::Beep,
+        Some("flash") => Bell::Flash,
+        _ => Bell::Off,
+    };
+
+    let clipboard_command = matches.value_of("clipboard-command");
+    let mut tee = matches.value_of("tee").map(|path| Tee::open(path, export_options.clone()));
+    let mut watchdog = matches.value_of("watchdog").map(|period| -> Result<Watchdog, String> {
+        let threshold = watchdog::parse_duration(period)
+            .ok_or(format!("invalid watchdog period `{}`", period))?;
+        Ok(Watchdog::new(threshold, matches.value_of("watchdog-command").map(|c| c.to_owned())))
+    }).transpose()?;
+    let mut sparkline = matches.value_of("sparkline").map(Sparkline::new).transpose()?;
+
+    let scroll_step = matches.value_of("scroll-step").map(|lines| {
+        lines.parse::<usize>().ok().filter(|&lines| lines > 0)
+            .ok_or(format!("invalid scroll step `{}`", lines))
+    }).transpose()?;
+
+    let max_bytes = matches.value_of("max-memory").map(|megabytes| -> Result<usize, String> {
+        let megabytes: usize = megabytes.parse()
+            .map_err(|_| format!("invalid memory limit `{}`", megabytes))?;
+        Ok(megabytes * 1024 * 1024)
+    }).transpose()?;
+
+    let block_lines = matches.value_of("block-size").map(|lines| {
+        lines.parse::<usize>().ok().filter(|&lines| lines > 0)
+            .ok_or(format!("invalid block size `{}`", lines))
+    }).transpose()?;
+
+    if let Some(ref stream) = stream {
+        stream.wait_for((height - 1) as usize, STARTUP_WAIT);
+    }
+    let mut pager = Pager::new(win, input, config.theme.clone(), options);
+    pager.set_reading(stream.is_some());
+    if let Some(name) = first_section {
+        pager.set_tab_name(name);
+    }
+    pager.set_matcher_kind(matcher_kind);
+    pager.set_match_options(get_match_options(&matches));
+    pager.set_max_bytes(max_bytes);
+    pager.set_block_lines(block_lines.unwrap_or(1));
+    let detected = if detect_format { pager.detect_format() } else { None };
+    if detected.is_some() {
+        pager.set_format(detected);
+    }
+    // whether the format is yet to be detected from a slow input once a page
+    // of it has been read
+    let mut undetected = detect_format && detected.is_none() && stream.is_some();
+    pager.set_scroll_step(scroll_step);
+    if let Some(pattern) = matches.value_of("token") {
+        let token = Matcher::build("regex", pattern, &MatchOptions::default())
+            .map_err(|err| format!("invalid token pattern `{}`: {}", pattern, err))?;
+        pager.set_token_matcher(token);
+    }
     pager.next_page();
+    if let Some(level) = matches.value_of("min-level") {
+        let options = MatchOptions::default();
+        let base = FilterPredicate::with_kind(&matcher::LEVEL, level, &options, 0)?;
+        pager.set_base(Some(base));
+    }
+    if let Some(class) = matches.value_of("status") {
+        pager.set_base(Some(status_filter(class)?));
+    }
+    if let Some(latency) = matches.value_of("slow") {
+        latency.parse::<f64>().ok().filter(|latency| *latency >= 0.0)
+            .ok_or(format!("invalid latency `{}`", latency))?;
+        pager.add_highlight_with(&matcher::FIELD, &format!("latency>{} AND request", latency))?;
+    }
+    for pattern in matches.values_of("highlight").into_iter().flatten() {
+        pager.add_highlight(pattern)
+            .map_err(|err| format!("invalid highlight `{}`: {}", pattern, err))?;
+    }
+    if let Some(filter) = matches.value_of("filter") {
+        pager.filter(Some(filter.to_owned()))
+            .map_err(|err| format!("invalid filter `{}`: {}", filter, err))?;
+    }
+
+    let mut patterns_file = matches.value_of("patterns-file").map(PatternsFile::new);
+    if let Some(ref mut file) = patterns_file {
+        let patterns = file.poll().unwrap_or(Ok(Vec::new()))?;
+        pager.filter_any(&patterns).map_err(|err| format!("invalid pattern {}", err))?;
+    }
+
+    let session_input = get_session_input(&matches);
+    // state of the session as last saved
+    let mut saved_session = None;
+    if matches.is_present("restore") {
+        let restored = session_input.as_ref()
+            .map_or(Ok(None), |input| Session::load(input))
+            .and_then(|session| session.ok_or("no session to restore".to_owned()))
+            .and_then(|session| {
+                restore_session(&mut pager, &session, &matches).map(|_| session)
+            });
+
+        saved_session = Some(restored?);
+    } else if let Some(path) = matches.value_of("restore-session") {
+        let session = Session::read(Path::new(path))?;
+        restore_session(&mut pager, &session, &matches)?;
+    } else if let Some(ref input) = session_input {
+        // a session which can't be read is left to be replaced by this one
+        let stale = Session::load(input).ok()
+            .and_then(|session| session)
+            .filter(|session| session.is_stale());
+
+        if let Some(session) = stale {
+            let prompt = format!("The last session paging {} ended abnormally. Restore it? (y/n) ",
+                                 input.display());
+            if _confirm(&prompt, height) {
+                restore_session(&mut pager, &session, &matches)?;
+            }
+            pager.refresh();
+            saved_session = Some(session);
+        }
+    }
+
+    let mut status = StatusBar::new(newwin(1, width, height - 1, 0));
+    let mut replaying = transcript::is_replaying();
+    if let Some(format) = detected {
+        status.set_message(describe_format(Some(format)));
+    }
+    if let Some(path) = matches.value_of("replay").filter(|_| replaying) {
+        status.set_message(format!("Replaying {}", path));
+    }
+    if matches.is_present("follow") || exec.is_some() {
+        status.following = true;
+        pager.last_page();
+    }
+    status.filtering = pager.is_filtered();
+    status.draw();
+
+    let mut render = Scheduler::new(matches.is_present("profile-render"));
+    render.frame();
+    transcript::timeout(TICK_MS);
+
+    // number typed ahead of a command, e.g. the 50 of `50%` or the 5 of `5j`
+    let mut count: Option<usize> = None;
+    let mut last_key = Instant::now();
 
     loop {
-        match getch() {
-            LOWER_J => pager.next_line(),
-            LOWER_K => pager.prev_line(),
-            KEY_NPAGE | CTRL_D => pager.next_page(),
-            KEY_PPAGE | CTRL_U => pager.prev_page(),
-            FWD_SLASH => {
-                _filter(width, height, &mut pager);
-                wrefresh(win);
+        if signals::quit_requested() {
+            break;
+        }
+        if signals::suspend_requested() {
+            signals::suspend();
+        }
+
+        let ch = transcript::getch();
+        render.begin();
+        if ch != ERR {
+            status.clear_message();
+            pager.cancel_seek();
+            last_key = Instant::now();
+        }
+
+        let is_digit = (DIGIT_0..=DIGIT_9).contains(&ch);
+        let prefix = if ch == ERR || is_digit { None } else { count.take() };
+
+        match config.keys.action(ch) {
+            // case: any key closes the help or the sorted preview of the page
+            _ if ch != ERR && ch != KEY_RESIZE && pager.close_overlay() => {},
+            _ if is_digit => {
+                let digit = (ch - DIGIT_0) as usize;
+                let n = count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                count = Some(n);
+                status.set_message(n.to_string());
+            },
+            // case: Esc cancels a count rather than running its action
+            _ if ch == ESCAPE && prefix.is_some() => {},
+            Some(Action::GotoPercent) => {
+                status.following = false;
+                let percent = prefix.unwrap_or(0).min(100);
+                pager.goto_fraction(percent as f64 / 100.0);
+            },
+            Some(Action::GotoLine) => {
+                status.following = false;
+                match prefix {
+                    Some(line_num) => pager.goto_line(line_num.max(1)),
+                    None => pager.last_page(),
+                }
+            },
+            Some(Action::NextLine) => {
+                status.following = false;
+                pager.next_lines(prefix.unwrap_or(1));
+            },
+            Some(Action::PrevLine) => {
+                status.following = false;
+                pager.prev_lines(prefix.unwrap_or(1));
+            },
+            Some(Action::NextPage) => {
+                status.following = false;
+                pager.next_page();
+            },
+            Some(Action::PrevPage) => {
+                status.following = false;
+                pager.prev_page();
+            },
+            Some(Action::ScrollDown) => {
+                status.following = false;
+                if prefix.is_some() {
+                    pager.set_scroll_step(prefix);
+                }
+                pager.scroll_down();
+            },
+            Some(Action::ScrollUp) => {
+                status.following = false;
+                if prefix.is_some() {
+                    pager.set_scroll_step(prefix);
+                }
+                pager.scroll_up();
+            },
+            Some(Action::CursorUp) => pager.move_cursor(-1, 0),
+            Some(Action::CursorDown) => pager.move_cursor(1, 0),
+            Some(Action::CursorLeft) => pager.move_cursor(0, -1),
+            Some(Action::CursorRight) => pager.move_cursor(0, 1),
+            Some(Action::FilterToken) => {
+                match pager.filter_token() {
+                    Some(token) => status.set_message(format!("Filter: {}", token)),
+                    None => status.set_message("No token under cursor".to_owned()),
+                }
+                status.filtering = pager.is_filtered();
+                status.reset_match();
+            },
+            Some(Action::CountToken) => {
+                let message = match pager.count_token() {
+                    Some((token, count, lines)) =>
+                        format!("{} occurrences of {} in {} lines", count, token, lines),
+                    None => "No token under cursor".to_owned(),
+                };
+                status.set_message(message);
+            },
+            Some(Action::SetMark) => {
+                status.set_message("Mark: ".to_owned());
+                status.draw();
+                let message = match read_letter() {
+                    Some(mark) => match pager.set_mark(mark) {
+                        Some(line_num) => format!("Marked line {} as '{}", line_num, mark),
+                        None => "Nothing to mark".to_owned(),
+                    },
+                    None => "Marks are named by letters".to_owned(),
+                };
+                status.set_message(message);
+            },
+            Some(Action::GotoMark) => {
+                status.set_message("Go to mark: ".to_owned());
+                status.draw();
+                match read_letter() {
+                    Some(mark) => {
+                        status.following = false;
+                        match pager.goto_mark(mark) {
+                            Some(_) => status.clear_message(),
+                            None => status.set_message(format!("Mark '{} isn't set", mark)),
+                        }
+                    },
+                    None => status.set_message("Marks are named by letters".to_owned()),
+                }
+            },
+            Some(Action::FirstMatch) => {
+                let message = _goto_match(MatchEnd::First, &mut pager, &mut status);
+                status.set_message(message.unwrap_or_else(|err| err));
+            },
+            Some(Action::LastMatch) => {
+                let message = _goto_match(MatchEnd::Last, &mut pager, &mut status);
+                status.set_message(message.unwrap_or_else(|err| err));
+            },
+            Some(Action::ClearFilter) => {
+                if pager.clear_selection() {
+                    status.set_message("Selection cleared".to_owned());
+                } else if pager.clear_filter() {
+                    status.set_message("Filter cleared".to_owned());
+                }
+                status.filtering = pager.is_filtered();
+                status.reset_match();
+            },
+            Some(Action::Zoom) => {
+                let message = if pager.toggle_zoom() {
+                    "Zoomed out: showing every line"
+                } else if pager.is_filtered() {
+                    "Zoomed in: showing matches"
+                } else {
+                    "No filter to zoom out of"
+                };
+                status.set_message(message.to_owned());
+                status.filtering = pager.is_filtered();
+            },
+            Some(Action::ExpandGap) => {
+                let message = match pager.expand_gap() {
+                    Some(1) => "Expanded 1 line".to_owned(),
+                    Some(expanded) => format!("Expanded {} lines", expanded),
+                    None => "No gap to expand".to_owned(),
+                };
+                status.set_message(message);
+            },
+            Some(Action::RevealContext) => {
+                let message = match pager.reveal_context(prefix.unwrap_or(REVEAL_LINES)) {
+                    Some(1) => "Revealed 1 line".to_owned(),
+                    Some(revealed) => format!("Revealed {} lines", revealed),
+                    None => "No gap to expand".to_owned(),
+                };
+                status.set_message(message);
+            },
+            Some(Action::Select) => {
+                let message = if pager.clear_selection() {
+                    "Selection cleared".to_owned()
+                } else {
+                    match pager.start_selection() {
+                        Some(line_num) => format!("Selecting from line {}", line_num),
+                        None => "No line under cursor".to_owned(),
+                    }
+                };
+                status.set_message(message);
+            },
+            Some(Action::Yank) => {
+                let lines = pager.yank();
+                let message = if lines.is_empty() {
+                    "No line under cursor".to_owned()
+                } else {
+                    match clipboard::copy(&lines.join("\n"), clipboard_command) {
+                        Ok(()) if lines.len() == 1 => "Copied 1 line".to_owned(),
+                        Ok(()) => format!("Copied {} lines", lines.len()),
+                        Err(err) => err,
+                    }
+                };
+                status.set_message(message);
+            },
+            Some(Action::TimeDeltas) => pager.toggle_time_deltas(),
+            Some(Action::LineNumbers) => pager.toggle_line_numbers(),
+            Some(Action::MatchCounts) => pager.toggle_match_counts(),
+            Some(Action::WordDiff) => {
+                let message = if pager.toggle_word_diff() {
+                    "Highlighting words changed from the line before"
+                } else {
+                    "Word diff off"
+                };
+                status.set_message(message.to_owned());
+            },
+            Some(Action::NextMatcher) => {
+                let kind = matcher::next_kind(&pager.matcher_kind());
+                pager.set_matcher_kind(kind);
+                status.set_message(format!("Filter: {}", kind.description));
+            },
+            Some(Action::IgnoreAccents) => {
+                let mut options = pager.match_options();
+                options.ignore_accents = !options.ignore_accents;
+                pager.set_match_options(options);
+                let message = if options.ignore_accents {
+                    "Accents: ignored"
+                } else {
+                    "Accents: significant"
+                };
+                status.set_message(message.to_owned());
+            },
+            Some(Action::IgnoreCase) => {
+                let mut options = pager.match_options();
+                options.ignore_case = !options.ignore_case;
+                pager.set_match_options(options);
+                let message = if options.ignore_case { "Case: ignored" } else { "Case: significant" };
+                status.set_message(message.to_owned());
+            },
+            Some(Action::TimeZone) => {
+                let message = match pager.cycle_zone() {
+                    Zone::Original => "Timestamps: original",
+                    Zone::Utc => "Timestamps: UTC",
+                    Zone::Local => "Timestamps: local time",
+                };
+                status.set_message(message.to_owned());
+            },
+            Some(Action::Follow) => {
+                status.following = !status.following;
+                if status.following {
+                    pager.last_page();
+                }
             },
-            LOWER_Q => break,
-            _ => continue,
+            Some(Action::Filter) => {
+                _filter(height, &mut pager, &mut status);
+                pager.refresh();
+                status.filtering = pager.is_filtered();
+                status.reset_match();
+            },
+            Some(Action::Save) => {
+                let path = _prompt("Save to: ", height, &mut status).unwrap_or_default();
+                if !path.is_empty() {
+                    let message = match pager.save(&path, &export_options) {
+                        Ok(written) => format!("Wrote {} lines to {}", written, path),
+                        Err(err) => err,
+                    };
+                    status.set_message(message);
+                }
+                pager.refresh();
+            },
+            Some(Action::Command) => {
+                let command = _prompt(":", height, &mut status).unwrap_or_default();
+                let mut context = CommandContext {
+                    pager: &mut pager,
+                    status: &mut status,
+                    config: &mut config,
+                    source: &source_name,
+                    export_options: &export_options,
+                };
+                match _command(&command, &mut context) {
+                    Ok(Some(message)) | Err(message) => status.set_message(message),
+                    Ok(None) => {},
+                }
+                pager.refresh();
+                status.filtering = pager.is_filtered();
+                status.reset_match();
+            },
+            Some(Action::NextTab) => {
+                if let Some(ref sections) = sections {
+                    if pager.is_last_tab() && sections.discover() {
+                        add_sections(&mut pager, sections);
+                    }
+                }
+                pager.next_tab();
+            },
+            Some(Action::PrevTab) => pager.prev_tab(),
+            _ if ch == KEY_RESIZE => {
+                let (new_width, new_height) = get_term_size();
+                width = new_width;
+                height = new_height;
+                let win = newwin(height - 1, width, MARGIN / 2, MARGIN / 2);
+                pager.resize(win);
+                status.resize(newwin(1, width, height - 1, 0));
+            },
+            Some(Action::Help) => {
+                let options = describe_options(&pager, &status);
+                let lines = help::lines(&config.keys, &options, width as usize,
+                                        height as usize - 1);
+                pager.show_overlay(&lines);
+            },
+            Some(Action::Quit) => break,
+            None => {},
+        }
+
+        if ch != ERR {
+            transcript::note(&describe_view(&pager));
+        }
+        if replaying && !transcript::is_replaying() {
+            replaying = false;
+            status.set_message("Replay finished; keys are read from the keyboard".to_owned());
+        }
+        if let Some(err) = transcript::take_error() {
+            status.set_message(err);
+        }
+
+        // case: the file followed changed other than by having lines appended
+        let changed = match integrity {
+            Some(ref integrity) if status.following => {
+                integrity.check().map(|change| (integrity.path().to_owned(), change))
+            },
+            _ => None,
+        };
+        if let Some((path, change)) = changed {
+            let prompt = format!("{} {}. Reload it? (y/n) ", path.display(), change);
+            if _confirm(&prompt, height) {
+                match reopen_input(&matches, &mut integrity) {
+                    Ok(input) => {
+                        pager.reload(input);
+                        pager.last_page();
+                        status.set_message(format!("Reloaded {}", path.display()));
+                    },
+                    Err(err) => {
+                        status.following = false;
+                        status.set_message(err);
+                    },
+                }
+            } else {
+                status.following = false;
+                integrity = None;
+                status.set_message(format!("Showing {} as read before it changed",
+                                           path.display()));
+            }
+            pager.refresh();
+        }
+
+        if let Some(ref mut exec) = exec {
+            if let Some(exit) = exec.poll_exit() {
+                let outcome = match exit.code() {
+                    Some(code) => format!("exited with status {}", code),
+                    None => "was killed by a signal".to_owned(),
+                };
+                status.set_message(format!("`{}` {}", exec.command(), outcome));
+            }
+        }
+
+        if let Some(ref stream) = stream {
+            if let Some(failure) = stream.take_failure() {
+                status.set_message(format!("Can't read input: {}", failure));
+            }
+            // case: the page ended at the lines read so far; show those read since
+            if stream.has_pending() && !status.following && pager.is_exhausted() {
+                pager.goto_line(pager.top_line_num().unwrap_or(1));
+            }
+            if undetected && (pager.lines_read() >= (height - 1) as usize || !stream.is_reading()) {
+                undetected = false;
+                let format = pager.detect_format();
+                if format.is_some() {
+                    pager.set_format(format);
+                    status.set_message(describe_format(format));
+                }
+            }
+        }
+
+        if status.following {
+            let new_lines = pager.follow();
+            if new_lines.iter().any(|l| l.is_match()) {
+                status.matched();
+            }
+            if let Some(ref mut sparkline) = sparkline {
+                for line in &new_lines {
+                    if let FilteredLine::MatchLine((_, ref text)) |
+                           FilteredLine::UnfilteredLine((_, ref text)) = *line {
+                        sparkline.record(text);
+                    }
+                }
+                status.sparkline = sparkline.render();
+            }
+        }
+
+        if let Some(ref mut file) = patterns_file {
+            if let Some(result) = file.poll() {
+                let message = result
+                    .and_then(|patterns| {
+                        pager.filter_any(&patterns)
+                            .map(|_| format!("Filter: patterns read from {}", file.path()))
+                            .map_err(|err| format!("invalid pattern {}", err))
+                    })
+                    .unwrap_or_else(|err| err);
+                status.set_message(message);
+                status.filtering = pager.is_filtered();
+                status.reset_match();
+            }
+        }
+
+        while let Some(request) = control.as_ref().and_then(ControlSocket::poll) {
+            let mut context = CommandContext {
+                pager: &mut pager,
+                status: &mut status,
+                config: &mut config,
+                source: &source_name,
+                export_options: &export_options,
+            };
+            let result = _control(&request.command, &mut context, &matches, &mut integrity);
+            if let Ok(Some(ref message)) | Err(ref message) = result {
+                status.set_message(message.clone());
+            }
+            request.reply(result);
+            status.filtering = pager.is_filtered();
+            status.reset_match();
+        }
+
+        if let Some(ref sections) = sections {
+            add_sections(&mut pager, sections);
+        }
+
+        if let Some(ref mut watchdog) = watchdog {
+            if status.following {
+                match watchdog.check(pager.lines_read()) {
+                    Ok(idle) => status.idle = idle,
+                    Err(err) => status.set_message(format!("Watchdog command failed: {}", err)),
+                }
+            } else {
+                watchdog.reset(pager.lines_read());
+                status.idle = None;
+            }
+            status.alert_attr = pager.attr(Role::Error);
+        }
+
+        let mirrored = tee.as_mut().map_or(Ok(()), |tee| tee.mirror(&pager.page()));
+        if let Err(err) = mirrored {
+            status.set_message(format!("Stopped mirroring: {}", err));
+            tee = None;
         }
+        status.tab = pager.tab_label();
+        status.scanning = pager.poll_scan();
+        if let Some((end, search)) = pager.poll_seek() {
+            status.set_message(match_message(end, search));
+        }
+        // the totals of input still being read on a thread aren't final
+        let reading = stream.as_ref().is_some_and(Stream::is_reading);
+        pager.set_reading(reading);
+        status.totals = pager.totals().filter(|_| !reading);
+        status.total_lines = pager.total_lines().filter(|_| !reading);
+        status.estimate = pager.estimated_lines();
+        status.at_end = pager.is_exhausted() && !reading;
+        if let Some(ref metrics) = metrics {
+            let lines = status.totals.map_or(pager.lines_read(), |totals| totals.lines);
+            let matches = status.totals.and_then(|totals| totals.matches);
+            metrics.set_counts(lines, matches);
+        }
+        status.top_line = pager.top_line_num();
+
+        match session_input {
+            Some(ref input) if last_key.elapsed() >= AUTOSAVE_IDLE => {
+                let session = get_session(&pager, input);
+                if saved_session.as_ref() != Some(&session) {
+                    if let Err(err) = session.save() {
+                        status.set_message(format!("Can't save session: {}", err));
+                    }
+                    saved_session = Some(session);
+                }
+            },
+            _ => {},
+        }
+
+        // commands on the control socket are picked up as often as matches
+        // found in the background
+        let busy = status.scanning.is_some() || pager.is_tallying() || control.is_some()
+            || stream.as_ref().is_some_and(Stream::has_pending);
+        let tick = if busy { SCAN_TICK_MS } else { TICK_MS };
+
+        status.draw();
+        pager.place_cursor();
+        // case: a frame isn't due yet, so what was drawn is shown once it is
+        transcript::timeout(render.frame().map_or(tick, |wait| min(wait, tick)));
     }
 
-    endwin();
-    delscreen(window);
+    drop(screen);
+    // stops the command producing the input, if it's still running
+    drop(exec);
+
+    if let Some(ref input) = session_input {
+        let mut session = get_session(&pager, input);
+        session.ended = true;
+        if let Err(err) = session.save() {
+            eprintln!("filterless: can't save session: {}", err);
+        }
+    }
+    if let Some(path) = matches.value_of("save-session") {
+        let input = matches.value_of("INPUT").map_or(PathBuf::from("-"), |fname| {
+            Path::new(fname).canonicalize().unwrap_or_else(|_| PathBuf::from(fname))
+        });
+        let mut session = get_session(&pager, &input);
+        session.ended = true;
+        if let Err(err) = session.save_to(Path::new(path)) {
+            eprintln!("filterless: can't save session: {}", err);
+        }
+    }
+    Ok(())
 }
 
@@ -0,0 +1,152 @@
+//! Metrics describing a session, served over HTTP in the Prometheus text
+//! format so that a long-running watcher can itself be monitored.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use libc;
+
+/// Values of the metrics, as last reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Values {
+    /// number of lines read from the input
+    lines: usize,
+    /// number of lines matching the current filter, if filtering and known
+    matches: Option<usize>,
+    /// number of lines which couldn't be decoded and were replaced
+    drops: usize,
+}
+
+/// Metrics shared between the pager, which reports them, and the server.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    values: Mutex<Values>,
+}
+
+impl Metrics {
+    /// Creates metrics with every count at zero.
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Reports the numbers of lines read from the input and, if known, of
+    /// lines matching the current filter.
+    pub fn set_counts(&self, lines: usize, matches: Option<usize>) {
+        let mut values = self.values.lock().unwrap();
+        values.lines = lines;
+        values.matches = matches;
+    }
+
+    /// Reports that a line couldn't be decoded and was replaced.
+    pub fn record_drop(&self) {
+        self.values.lock().unwrap().drops += 1;
+    }
+
+    /// Returns the metrics in the Prometheus text format.
+    ///
+    /// ### Parameters
+    /// * `memory`: resident memory of the process in bytes, if known
+    fn render(&self, memory: Option<usize>) -> String {
+        let values = *self.values.lock().unwrap();
+        let mut text = String::new();
+
+        let mut metric = |name: &str, kind: &str, help: &str, value: usize| {
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n",
+                                   name, help, name, kind, name, value));
+        };
+
+        metric("filterless_lines_ingested_total", "counter",
+               "Lines read from the input.", values.lines);
+        if let Some(matches) = values.matches {
+            metric("filterless_lines_matched", "gauge",
+                   "Lines read from the input which match the current filter.", matches);
+        }
+        metric("filterless_lines_dropped_total", "counter",
+               "Lines which couldn't be decoded and were replaced.", values.drops);
+        if let Some(memory) = memory {
+            metric("filterless_resident_memory_bytes", "gauge",
+                   "Resident memory of the process.", memory);
+        }
+
+        text
+    }
+}
+
+/// Returns the resident memory of the process in bytes, if it can be found.
+fn resident_memory() -> Option<usize> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+
+    if page_size > 0 { Some(pages * page_size as usize) } else { None }
+}
+
+/// Answers a request, whatever its path, with the metrics.
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    // the request itself is of no interest, but is read so that the client
+    // isn't reset by the connection closing with unread data
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request)?;
+
+    let body = metrics.render(resident_memory());
+    write!(stream, "HTTP/1.0 200 OK\r\n\
+                    Content-Type: text/plain; version=0.0.4\r\n\
+                    Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+/// Serves `metrics` on `port` of the loopback interface from a background
+/// thread, returning the address served on.
+///
+/// ### Parameters
+/// * `port`: port to listen on, or 0 for any free port
+/// * `metrics`: the metrics to serve
+pub fn serve(port: u16, metrics: Arc<Metrics>) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let addr = listener.local_addr()?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = respond(stream, &metrics);
+        }
+    });
+
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+
+    use super::{Metrics, serve};
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Arc::new(Metrics::new());
+        metrics.set_counts(120, None);
+        metrics.record_drop();
+        assert_eq!(metrics.render(None), "\
+            # HELP filterless_lines_ingested_total Lines read from the input.\n\
+            # TYPE filterless_lines_ingested_total counter\n\
+            filterless_lines_ingested_total 120\n\
+            # HELP filterless_lines_dropped_total Lines which couldn't be decoded and were replaced.\n\
+            # TYPE filterless_lines_dropped_total counter\n\
+            filterless_lines_dropped_total 1\n");
+
+        metrics.set_counts(150, Some(7));
+        let addr = serve(0, metrics.clone()).unwrap();
+        let mut response = String::new();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.0\r\n\r\n").unwrap();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(response.contains("\nfilterless_lines_ingested_total 150\n"));
+        assert!(response.contains("\nfilterless_lines_matched 7\n"));
+        assert!(response.contains("\nfilterless_resident_memory_bytes "));
+    }
+}
@@ -1,10 +1,135 @@
-use std::cmp::{max};
+use std::borrow::Cow;
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::io::BufWriter;
 
 use ncurses;
 
-use iter::{FilteredLine, FilterPredicate, WindowBuffer};
+use diff;
+use export::{self, ExportOptions};
+use filterless::{matcher, parser, FilteredLine, FilterPredicate, FilterSource, Format, Input,
+                 LineRange, MatchOptions, Matcher, MatcherKind, Search, Totals, WindowBuffer};
+use filterless::matcher::Span;
+use filterless::timestamp::{self, Delta, Zone};
+use highlight::Highlights;
+use read_only;
+use render::{self, Phase};
+use sort::SortKey;
+use syntax::{self, Syntax};
+use text;
+use theme::{Role, Theme};
 
 
+/// Maximum number of lines to look back for a timestamp when computing the
+/// time delta of a line.
+const MAX_DELTA_LOOKBACK: usize = 1000;
+
+/// Width in columns of the time delta gutter, excluding its trailing space.
+const DELTA_WIDTH: usize = 8;
+
+/// Width in columns of the match count gutter, excluding its trailing space.
+const COUNT_WIDTH: usize = 3;
+
+/// Width in columns of the marks around the ordinal of a match, i.e. the `(#`
+/// and `)` of `(#7)`.
+const ORDINAL_MARKS: usize = 3;
+
+/// Number of context lines shown above and below each matching line.
+pub const CONTEXT_LINES: usize = 3;
+
+/// Number of lines revealed at either end of a gap unless a count is given.
+pub const REVEAL_LINES: usize = 10;
+
+/// Most lines away from its old position at which a line is sought when the
+/// input is reloaded.
+const REANCHOR_LINES: usize = 1000;
+
+
+/// Which match of the filter `Pager::goto_match()` jumps to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatchEnd {
+    First,
+    Last,
+}
+
+/// Feedback given when the window can't move as asked, e.g. paging down at
+/// the end of the input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bell {
+    /// do nothing
+    Off,
+    /// sound the terminal's bell
+    Beep,
+    /// flash the screen
+    Flash,
+}
+
+/// Settings which affect how lines are rendered.
+
+#[derive(Clone, Debug)]
+pub struct DisplayOptions {
+    /// whether to show the line number gutter
+    pub line_numbers: bool,
+    /// whether to show the time delta gutter
+    pub time_deltas: bool,
+    /// whether to show the number of matches within each line in a gutter
+    pub match_counts: bool,
+    /// whether to show the ordinal of each match among the matches after its
+    /// line number, e.g. `42 (#7)` for the seventh match, while filtering
+    pub ordinals: bool,
+    /// time deltas of at least this many milliseconds are highlighted
+    pub stall_millis: i64,
+    /// time zone in which leading timestamps are displayed
+    pub zone: Zone,
+    /// feedback given when the window can't move
+    pub bell: Bell,
+    /// format in which lines are highlighted
+    pub syntax: Syntax,
+    /// format in which lines are parsed into fields, or `None` for the first
+    /// format each line is in
+    pub format: Option<Format>,
+    /// dotted paths of the fields shown of lines parsed into fields, in
+    /// place of the whole line, unless empty
+    pub fields: Vec<String>,
+    /// number of columns between the tab stops to which tabs are expanded
+    pub tab_width: usize,
+    /// whether the spaces ending a line are drawn as a visible mark
+    pub trailing_space: bool,
+    /// whether the words in which a line differs from the similar line
+    /// before it are highlighted
+    pub word_diff: bool,
+}
+
+impl DisplayOptions {
+    pub fn default() -> DisplayOptions {
+        DisplayOptions {
+            line_numbers: true,
+            time_deltas: false,
+            match_counts: false,
+            ordinals: false,
+            stall_millis: 1000,
+            zone: Zone::Original,
+            bell: Bell::Off,
+            syntax: Syntax::None,
+            format: None,
+            fields: Vec::new(),
+            tab_width: 8,
+            trailing_space: false,
+            word_diff: false,
+        }
+    }
+}
+
+/// One of the inputs between which the pager switches when the input is
+/// split into sections.
+struct Tab<T: Iterator<Item=String>> {
+    /// name of the section shown, if the input is split
+    name: Option<String>,
+    /// lines of the tab, unless it's the current tab, whose lines are in
+    /// `Pager::window_buffer`
+    window_buffer: Option<WindowBuffer<T>>,
+}
+
 pub struct Pager<T: Iterator<Item=String>> {
     window: ncurses::WINDOW,
     height: usize,
@@ -12,28 +137,77 @@ pub struct Pager<T: Iterator<Item=String>> {
     num_digits: usize,
     window_buffer: Option<WindowBuffer<T>>,
     predicate: Option<FilterPredicate>,
+    theme: Theme,
+    options: DisplayOptions,
+    /// how filter strings are interpreted
+    matcher_kind: MatcherKind,
+    /// how filter strings are compared with lines
+    match_options: MatchOptions,
+    /// 0-indexed row of the focused position within the window
+    cursor_row: usize,
+    /// 0-indexed column of the focused position within the window
+    cursor_col: usize,
+    /// matcher describing the token picked up from the focused position
+    token: Matcher,
+    /// whether matches were being scanned for in the background when last
+    /// polled
+    scanning: bool,
+    /// match jumped to by `goto_match()` once the background scan for
+    /// matches has found it
+    seeking: Option<MatchEnd>,
+    /// every tab, including the current one
+    tabs: Vec<Tab<T>>,
+    /// 0-based index of the current tab
+    tab: usize,
+    /// limit on the bytes of line text held in memory by each tab
+    max_bytes: Option<usize>,
+    /// number of lines read off the input of each tab at a time
+    block_lines: usize,
+    /// tab and line number within the tab's input of each mark
+    marks: HashMap<char, (usize, usize)>,
+    /// filter set aside while zoomed out to show every line, whose matches
+    /// remain highlighted
+    zoomed_out: Option<FilterPredicate>,
+    /// filter which lines must match as well as any other, kept while others
+    /// are applied and cleared
+    base: Option<FilterPredicate>,
+    /// patterns highlighted within the lines shown, whatever the filter
+    highlights: Highlights,
+    /// whether the `(END)` marker is drawn, in which case the lines shown
+    /// may have been scrolled up a row to make room for it
+    end_shown: bool,
+    /// whether more lines of the input are still being read, e.g. on a
+    /// thread of its own, in which case the `(END)` marker isn't drawn yet
+    reading: bool,
+    /// number of lines moved by `scroll_down()` and `scroll_up()`, or `None`
+    /// for half the height of the window
+    scroll_step: Option<usize>,
+    /// line numbers of the line at which a selection was started and of the
+    /// cursor line, if selecting
+    selection: Option<(usize, usize)>,
+    /// whether the page is shown sorted by `preview_sorted()`, in place of
+    /// the lines in their order
+    previewing: bool,
+    /// window drawn over this one by `show_overlay()`, if shown
+    overlay: Option<ncurses::WINDOW>,
+    /// number of lines shown around each match of the filters applied
+    context_lines: usize,
 }
 
 impl<T: Iterator<Item=String>> Pager<T> {
-    pub fn new(window: ncurses::WINDOW, iter: T) -> Pager<T> {
-        ncurses::start_color();
-        ncurses::init_pair(1, ncurses::constants::COLOR_BLACK,
-                           ncurses::constants::COLOR_YELLOW);
-        ncurses::init_pair(2, ncurses::constants::COLOR_GREEN,
-                           ncurses::constants::COLOR_BLACK);
-        ncurses::init_pair(3, ncurses::constants::COLOR_RED,
-                           ncurses::constants::COLOR_BLACK);
+    pub fn new(window: ncurses::WINDOW, input: Input<T>, mut theme: Theme,
+               options: DisplayOptions) -> Pager<T> {
+        theme.init();
 
         let mut height = 0;
         let mut width = 0;
         ncurses::getmaxyx(window, &mut height, &mut width);
         ncurses::wclear(window);
-        ncurses::scrollok(window, true);
         ncurses::idlok(window, true);
 
         let predicate = None;
         let window_buffer = WindowBuffer::new(
-            iter, predicate.clone(), width as usize, height as usize);
+            input, predicate.clone(), width as usize, height as usize);
 
         Pager {
             window: window,
@@ -42,133 +216,1757 @@ impl<T: Iterator<Item=String>> Pager<T> {
             num_digits: 1,
             predicate: predicate,
             window_buffer: Some(window_buffer),
+            theme: theme,
+            options: options,
+            matcher_kind: matcher::SUBSTRING,
+            match_options: MatchOptions::default(),
+            cursor_row: 0,
+            cursor_col: 0,
+            token: Matcher::word(),
+            scanning: false,
+            seeking: None,
+            tabs: vec![Tab { name: None, window_buffer: None }],
+            tab: 0,
+            max_bytes: None,
+            block_lines: 1,
+            marks: HashMap::new(),
+            zoomed_out: None,
+            base: None,
+            highlights: Highlights::default(),
+            end_shown: false,
+            reading: false,
+            scroll_step: None,
+            selection: None,
+            previewing: false,
+            overlay: None,
+            context_lines: CONTEXT_LINES,
         }
     }
 
+    /// Names the current tab after the section of the input it shows.
+    pub fn set_tab_name(&mut self, name: String) {
+        self.tabs[self.tab].name = Some(name);
+    }
+
+    /// Adds a tab showing another section of the input, filtered by the
+    /// current filter.
+    ///
+    /// ### Parameters
+    /// * `name`: name of the section
+    /// * `input`: lines of the section
+    pub fn add_tab(&mut self, name: String, input: Input<T>) {
+        let mut window_buffer = WindowBuffer::new(
+            input, self.predicate.clone(), self.width, self.height);
+        window_buffer.set_max_bytes(self.max_bytes);
+        window_buffer.set_block_lines(self.block_lines);
+
+        self.tabs.push(Tab { name: Some(name), window_buffer: Some(window_buffer) });
+    }
+
+    /// Reads the current tab's input afresh from `input`, e.g. after the file
+    /// it was read from was rewritten, keeping the filter and the line at
+    /// the top of the window.
+    ///
+    /// The line at the top of the window and the tab's marks follow their
+    /// text to the nearest line having it, so that they stay put when lines
+    /// are inserted or removed before them; those whose text can't be found
+    /// nearby keep their line numbers.
+    ///
+    /// ### Parameters
+    /// * `input`: lines of the input as they now are
+    pub fn reload(&mut self, input: Input<T>) {
+        let mut top_line_num = self.top_line_num();
+        // the text of the line at the top of the window and of each mark of
+        // the tab, by which they're found again in the new input
+        let mut anchors: Vec<(Option<char>, usize, String)> = Vec::new();
+        let current_tab = self.tab;
+        if let Some(wb) = self.window_buffer.as_mut() {
+            let marks = self.marks.iter()
+                .filter(|&(_, &(tab, _))| tab == current_tab)
+                .map(|(&mark, &(_, line_num))| (Some(mark), line_num));
+            for (mark, line_num) in top_line_num.map(|n| (None, n)).into_iter().chain(marks) {
+                if let Some((_, text)) = wb.get_raw_line(line_num) {
+                    anchors.push((mark, line_num, text));
+                }
+            }
+        }
+
+        let mut window_buffer = WindowBuffer::new(
+            input, self.predicate.clone(), self.width, self.height);
+        window_buffer.set_max_bytes(self.max_bytes);
+        window_buffer.set_block_lines(self.block_lines);
+
+        for (mark, old_line_num, text) in anchors {
+            let new_line_num = window_buffer.find_line_near(old_line_num, &text, REANCHOR_LINES)
+                .unwrap_or(old_line_num);
+            match mark {
+                Some(mark) => { self.marks.insert(mark, (self.tab, new_line_num)); },
+                None => top_line_num = Some(new_line_num),
+            }
+        }
+
+        self.window_buffer = Some(window_buffer);
+        self.scanning = self.predicate.is_some();
+        self.selection = None;
+        match top_line_num {
+            Some(line_num) => self.goto_line(line_num),
+            None => self.next_page(),
+        }
+    }
+
+    /// Returns `true` if the current tab is the last one.
+    pub fn is_last_tab(&self) -> bool {
+        self.tab + 1 == self.tabs.len()
+    }
+
+    /// Switches to the next tab, wrapping around to the first.
+    pub fn next_tab(&mut self) {
+        let tab = (self.tab + 1) % self.tabs.len();
+        self.switch_tab(tab);
+    }
+
+    /// Switches to the previous tab, wrapping around to the last.
+    pub fn prev_tab(&mut self) {
+        let tab = (self.tab + self.tabs.len() - 1) % self.tabs.len();
+        self.switch_tab(tab);
+    }
+
+    /// Returns a description of the current tab for display, e.g.
+    /// `[2/3] two.log`, or `None` if the input isn't split.
+    pub fn tab_label(&self) -> Option<String> {
+        self.tabs[self.tab].name.as_ref().map(|name| {
+            let name = if name.is_empty() { "-" } else { name.as_str() };
+            format!("[{}/{}] {}", self.tab + 1, self.tabs.len(), name)
+        })
+    }
+
+    /// Makes the tab at index `tab` current and redraws its current page.
+    fn switch_tab(&mut self, tab: usize) {
+        if tab == self.tab {
+            return;
+        }
+
+        self.tabs[self.tab].window_buffer = self.window_buffer.take();
+        self.window_buffer = self.tabs[tab].window_buffer.take();
+        self.tab = tab;
+        self.scanning = false;
+        self.selection = None;
+
+        if let Some(wb) = self.window_buffer.as_mut() {
+            wb.resize(self.width, self.height);
+        }
+        self.redraw();
+    }
+
     pub fn next_line(&mut self) {
-        let maybe_line = self.window_buffer.as_mut().and_then(|wb| {
-            wb.next_line()
-        });
+        let maybe_line = self.classify(|wb| wb.next_line()).and_then(|line| line);
 
         if let Some(filtered_line) = maybe_line {
-            ncurses::wscrl(self.window, 1);
-            ncurses::wmove(self.window, self.height as i32 - 1, 0);
-            self.print_line(&filtered_line);
-            ncurses::wrefresh(self.window);
+            if self.widen_gutter(&[filtered_line.to_owned()]) || self.end_shown {
+                self.redraw();
+                return;
+            }
+
+            self.scroll(1);
+            self.follow_scroll(1);
+            let bottom = self.height - 1;
+            self.print_row(bottom, &filtered_line);
+            ncurses::wnoutrefresh(self.window);
+        } else if !self.show_end() {
+            self.ring_bell();
         }
     }
 
     pub fn prev_line(&mut self) {
-        let maybe_line = self.window_buffer.as_mut().and_then(|wb| {
-            wb.prev_line()
-        });
+        let maybe_line = self.classify(|wb| wb.prev_line()).and_then(|line| line);
 
         if let Some(filtered_line) = maybe_line {
-            ncurses::wscrl(self.window, -1);
-            ncurses::wmove(self.window, 0, 0);
-            self.print_line(&filtered_line);
-            ncurses::wprintw(self.window, "\n");
-            ncurses::wrefresh(self.window);
+            if self.widen_gutter(&[filtered_line.to_owned()]) || self.end_shown {
+                self.redraw();
+                return;
+            }
+
+            self.scroll(-1);
+            self.follow_scroll(-1);
+            self.print_row(0, &filtered_line);
+            ncurses::wnoutrefresh(self.window);
+        } else {
+            self.ring_bell();
         }
     }
 
-    pub fn next_page(&mut self){
-        let maybe_lines = self.window_buffer.as_mut().map(|wb| {
-            wb.next_page()
-        });
+    /// Scrolls down `count` lines, or as many as follow the window.
+    pub fn next_lines(&mut self, count: usize) {
+        if count <= 1 {
+            return self.next_line();
+        }
 
-        if let Some(lines) = maybe_lines {
-            ncurses::wclear(self.window);
+        self.scroll_by(count as i64);
+    }
+
+    /// Scrolls up `count` lines, or as many as precede the window.
+    pub fn prev_lines(&mut self, count: usize) {
+        if count <= 1 {
+            return self.prev_line();
+        }
 
-            for (i, filtered_line) in lines.iter().enumerate() {
-                self.print_line(&filtered_line);
+        self.scroll_by(-(count as i64));
+    }
 
-                if i < lines.len() - 1 {
-                    ncurses::wprintw(self.window, "\n");
+    /// Sets the number of lines moved by `scroll_down()` and `scroll_up()`,
+    /// or restores the default of half the height of the window if `lines`
+    /// is `None`.
+    pub fn set_scroll_step(&mut self, lines: Option<usize>) {
+        self.scroll_step = lines;
+    }
+
+    /// Scrolls down by the scroll step.
+    pub fn scroll_down(&mut self) {
+        let step = self.scroll_step();
+        self.scroll_by(step as i64);
+    }
+
+    /// Scrolls up by the scroll step.
+    pub fn scroll_up(&mut self) {
+        let step = self.scroll_step();
+        self.scroll_by(-(step as i64));
+    }
+
+    pub fn next_page(&mut self){
+        let maybe_lines = self.classify(|wb| wb.next_page());
+
+        match maybe_lines {
+            Some(ref lines) if !lines.is_empty() => self.print_page(lines),
+            _ => {
+                if !self.show_end() {
+                    self.ring_bell();
                 }
+            },
+        }
+    }
+
+    pub fn prev_page(&mut self) {
+        let top_line_num = self.top_line_num();
+        let maybe_lines = self.classify(|wb| wb.prev_page());
+
+        if let Some(lines) = maybe_lines {
+            if self.top_line_num() == top_line_num && !self.end_shown {
+                // case: already at the top
+                self.ring_bell();
+                return;
             }
+            self.print_page(&lines);
+        }
+    }
+
+    /// Jumps to the end of the input.
+    pub fn last_page(&mut self) {
+        let maybe_lines = self.classify(|wb| wb.last_page());
 
-            ncurses::wrefresh(self.window);
+        if let Some(lines) = maybe_lines {
+            self.print_page(&lines);
         }
     }
 
-    pub fn prev_page(&mut self) {
-        let maybe_lines = self.window_buffer.as_mut().map(|wb| {
-            wb.prev_page()
+    /// Jumps to the line lying `fraction` of the way through the input.
+    ///
+    /// ### Parameters
+    /// * `fraction`: position within the input, from 0.0 for the first line
+    ///   to 1.0 for the last
+    pub fn goto_fraction(&mut self, fraction: f64) {
+        let maybe_lines = self.classify(|wb| wb.goto_fraction(fraction));
+
+        if let Some(lines) = maybe_lines {
+            self.print_page(&lines);
+        }
+    }
+
+    /// Jumps to the first line whose leading timestamp is at or after
+    /// `millis`, or the first line after it selected by the filter.
+    ///
+    /// Returns the number of the line, or `None` if there's no such line.
+    ///
+    /// ### Parameters
+    /// * `millis`: milliseconds since the Unix epoch
+    pub fn goto_time(&mut self, millis: i64) -> Option<usize> {
+        let line_num = self.window_buffer.as_mut()?.line_at_time(millis)?;
+        self.goto_line(line_num);
+        Some(line_num)
+    }
+
+    /// Shows in place of a gap the lines it stands for: the gap under the
+    /// cursor, or else the first gap in the window.
+    ///
+    /// Returns the number of lines shown in its place, or `None` if the window
+    /// shows no gaps. A gap of more than a thousand lines is expanded a
+    /// thousand lines at a time.
+    pub fn expand_gap(&mut self) -> Option<usize> {
+        let expanded = {
+            let wb = self.window_buffer.as_mut()?;
+            let row = wb.gap_row(self.cursor_row)?;
+            wb.expand_gap(row)?
+        };
+
+        self.redraw();
+        Some(expanded)
+    }
+
+    /// Reveals up to `num_lines` lines at either end of a gap: the gap under
+    /// the cursor, or else the first gap in the window.
+    ///
+    /// Returns the number of lines revealed, or `None` if the window shows no
+    /// gaps.
+    pub fn reveal_context(&mut self, num_lines: usize) -> Option<usize> {
+        let revealed = {
+            let wb = self.window_buffer.as_mut()?;
+            let row = wb.gap_row(self.cursor_row)?;
+            wb.reveal_context(row, num_lines)?
+        };
+
+        self.redraw();
+        Some(revealed)
+    }
+
+    /// Returns the first and last lines of each stretch of the current tab's
+    /// input skipped by the filter, in order, including that after the last
+    /// line selected; or none if no filter is in use.
+    ///
+    /// Every line currently available is read.
+    pub fn gaps(&mut self) -> Vec<(usize, usize)> {
+        self.window_buffer.as_mut().map(|wb| wb.gaps()).unwrap_or_default()
+    }
+
+    /// Shows the gap standing for the lines skipped from line `from` onward
+    /// below the line preceding it, and moves the cursor onto the gap.
+    pub fn goto_gap(&mut self, from: usize) {
+        self.goto_line(from.saturating_sub(1).max(1));
+        let row = self.window_buffer.as_mut().and_then(|wb| {
+            wb.current_page().iter()
+                .position(|line| matches!(*line, FilteredLine::Gap { from: f, .. } if f == from))
+                .or_else(|| wb.row_of_line_num(from - 1))
         });
+        self.set_cursor_row(row.unwrap_or(0));
+    }
+
+    /// Moves the cursor onto the first or last line matched by the filter,
+    /// showing it below its leading context lines.
+    ///
+    /// If the background scan for matches hasn't found the line yet, the
+    /// window is left as it is and `Search::Pending` returned; the jump is
+    /// then made by `poll_seek()` once the line has been found.
+    pub fn goto_match(&mut self, end: MatchEnd) -> Search {
+        let search = match self.window_buffer.as_mut() {
+            Some(wb) if end == MatchEnd::First => wb.first_match(),
+            Some(wb) => wb.last_match(),
+            None => Search::Found(None),
+        };
+
+        self.seeking = None;
+        match search {
+            Search::Found(Some(line_num)) => {
+                let context_lines = self.predicate.as_ref().map_or(0, |p| p.context_lines);
+                self.goto_line(line_num.saturating_sub(context_lines).max(1));
+                let row = self.window_buffer.as_ref().and_then(|wb| wb.row_of_line_num(line_num));
+                self.set_cursor_row(row.unwrap_or(0));
+            },
+            Search::Found(None) => {},
+            Search::Pending => self.seeking = Some(end),
+        }
+        search
+    }
+
+    /// Makes the jump to a match left pending by `goto_match()` if the line
+    /// has been found since, returning which match it was and the outcome of
+    /// the search; or returns `None` if no jump is pending or it still is.
+    pub fn poll_seek(&mut self) -> Option<(MatchEnd, Search)> {
+        let end = self.seeking?;
+        match self.goto_match(end) {
+            Search::Pending => None,
+            search => Some((end, search)),
+        }
+    }
+
+    /// Gives up on the jump to a match left pending by `goto_match()`, if
+    /// any, e.g. once another key is pressed.
+    pub fn cancel_seek(&mut self) {
+        self.seeking = None;
+    }
+
+    /// Remembers the line under the cursor as `mark`, or the line at the top
+    /// of the window if the cursor is on a gap.
+    ///
+    /// Returns the number of the line within the input, or `None` if the
+    /// window shows no lines.
+    pub fn set_mark(&mut self, mark: char) -> Option<usize> {
+        let line_num = self.cursor_line().and_then(|line| line.line_num())
+            .or_else(|| self.window_buffer.as_ref().and_then(|wb| wb.top_line_num()))?;
+        self.marks.insert(mark, (self.tab, line_num));
+        Some(line_num)
+    }
+
+    /// Jumps back to the line remembered as `mark`, switching to its tab if
+    /// need be.
+    ///
+    /// The line needn't be selected by the current filter; if it isn't, the
+    /// page begins at the next line which is. Returns the number of the line
+    /// within the input, or `None` if `mark` hasn't been set.
+    pub fn goto_mark(&mut self, mark: char) -> Option<usize> {
+        let (tab, line_num) = self.marks.get(&mark).cloned()?;
+        self.switch_tab(tab);
+        self.goto_line(line_num);
+        let row = self.window_buffer.as_ref().and_then(|wb| wb.row_of_line_num(line_num));
+        self.set_cursor_row(row.unwrap_or(0));
+        Some(line_num)
+    }
+
+    /// Returns the name, tab and line number of each mark, in order of their
+    /// names.
+    pub fn marks(&self) -> Vec<(char, usize, usize)> {
+        let mut marks: Vec<(char, usize, usize)> = self.marks.iter()
+            .map(|(&mark, &(tab, line_num))| (mark, tab, line_num))
+            .collect();
+        marks.sort();
+        marks
+    }
+
+    /// Remembers line `line_num` of tab `tab` as `mark`, e.g. as set by an
+    /// earlier session.
+    pub fn restore_mark(&mut self, mark: char, tab: usize, line_num: usize) {
+        self.marks.insert(mark, (tab, line_num));
+    }
+
+    /// Shows a page beginning at line `line_num` of the input, or at the
+    /// first line after it selected by the filter.
+    pub fn goto_line(&mut self, line_num: usize) {
+        let maybe_lines = self.classify(|wb| wb.goto_line(line_num));
 
         if let Some(lines) = maybe_lines {
-            ncurses::wclear(self.window);
+            self.print_page(&lines);
+        }
+    }
 
-            for (i, filtered_line) in lines.iter().enumerate() {
-                self.print_line(&filtered_line);
+    /// Jumps to the end of the input, showing any lines which have been
+    /// appended to it since it was last read.
+    ///
+    /// Returns the newly read lines.
+    pub fn follow(&mut self) -> Vec<FilteredLine> {
+        let maybe_lines = self.classify(|wb| {
+            let new_lines = wb.read_to_end();
+            (new_lines, wb.last_page())
+        });
 
-                if i < lines.len() - 1 {
-                    ncurses::wprintw(self.window, "\n");
+        match maybe_lines {
+            Some((new_lines, lines)) => {
+                if !new_lines.is_empty() {
+                    self.print_page(&lines);
                 }
+                new_lines
+            },
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns how lines are displayed.
+    pub fn options(&self) -> &DisplayOptions {
+        &self.options
+    }
+
+    /// Shows or hides the time delta gutter and redraws the current page.
+    pub fn toggle_time_deltas(&mut self) {
+        self.options.time_deltas = !self.options.time_deltas;
+        self.redraw();
+    }
+
+    /// Shows or hides the line number gutter and redraws the current page.
+    pub fn toggle_line_numbers(&mut self) {
+        self.options.line_numbers = !self.options.line_numbers;
+        self.redraw();
+    }
+
+    /// Shows or hides the match count gutter and redraws the current page.
+    pub fn toggle_match_counts(&mut self) {
+        self.options.match_counts = !self.options.match_counts;
+        self.redraw();
+    }
+
+    /// Shows or hides the ordinals of matches after their line numbers and
+    /// redraws the current page.
+    pub fn toggle_ordinals(&mut self) {
+        self.options.ordinals = !self.options.ordinals;
+        self.redraw();
+    }
+
+    /// Turns the highlighting of the words in which each line differs from
+    /// the one before it on or off, and redraws the current page.
+    ///
+    /// Returns whether the words are now highlighted.
+    pub fn toggle_word_diff(&mut self) -> bool {
+        self.options.word_diff = !self.options.word_diff;
+        self.redraw();
+        self.options.word_diff
+    }
+
+    /// Cycles the display of leading timestamps between their original form,
+    /// UTC, and local time, and redraws the current page.
+    ///
+    /// Returns the zone now in use.
+    pub fn cycle_zone(&mut self) -> Zone {
+        self.options.zone = self.options.zone.next();
+        self.redraw();
+        self.options.zone
+    }
+
+    /// Redraws the current page.
+    pub fn redraw(&mut self) {
+        let maybe_lines = self.classify(|wb| wb.current_page());
+
+        if let Some(lines) = maybe_lines {
+            self.print_page(&lines);
+        }
+    }
+
+    /// Shows lines found by the background scan for matches since the last
+    /// call, if the current page isn't yet full.
+    ///
+    /// Returns the percentage of the input scanned so far, or `None` if no
+    /// scan is in progress.
+    pub fn poll_scan(&mut self) -> Option<usize> {
+        let progress = self.window_buffer.as_ref().and_then(|wb| wb.scan_progress());
+        let was_scanning = self.scanning;
+        self.scanning = progress.is_some();
+
+        if was_scanning {
+            let lines = self.classify(|wb| wb.current_page()).unwrap_or_default();
+            if lines.len() < self.height || !self.scanning {
+                self.print_page(&lines);
             }
+        }
+
+        progress
+    }
 
-            ncurses::wrefresh(self.window);
+    /// Returns the numbers of lines and matches in the whole input, if
+    /// they're known yet, continuing to compute them otherwise.
+    pub fn totals(&mut self) -> Option<Totals> {
+        self.window_buffer.as_mut().and_then(|wb| wb.totals())
+    }
+
+    /// Returns an estimate of the number of lines in the input, if it's a
+    /// memory-mapped file of which some has been read.
+    pub fn estimated_lines(&self) -> Option<usize> {
+        self.window_buffer.as_ref().and_then(|wb| wb.estimated_lines())
+    }
+
+    /// Returns the line number within the input of the match with the given
+    /// ordinal among the matches, e.g. 7 for the seventh, or of the line with
+    /// that number if there's no filter, or `None` if there are fewer.
+    pub fn line_of_ordinal(&mut self, ordinal: usize) -> Option<usize> {
+        self.window_buffer.as_mut().and_then(|wb| wb.line_of_ordinal(ordinal))
+    }
+
+    /// Returns the line number within the input of the line at the top of
+    /// the window, if any.
+    pub fn top_line_num(&self) -> Option<usize> {
+        self.window_buffer.as_ref().and_then(|wb| wb.top_line_num())
+    }
+
+    /// Returns the number of lines read from the input so far.
+    pub fn lines_read(&self) -> usize {
+        self.window_buffer.as_ref().map_or(0, |wb| wb.lines_read())
+    }
+
+    /// Returns the number of lines in the input, if it's known yet.
+    pub fn total_lines(&self) -> Option<usize> {
+        self.window_buffer.as_ref().and_then(|wb| wb.total_lines())
+    }
+
+    /// Returns `true` if no more lines follow those shown, rather than the
+    /// input not having been read or scanned that far yet.
+    pub fn is_exhausted(&self) -> bool {
+        self.window_buffer.as_ref().is_some_and(|wb| wb.is_exhausted())
+    }
+
+    /// Returns `true` while the totals of the input are being computed.
+    pub fn is_tallying(&self) -> bool {
+        self.window_buffer.as_ref().is_some_and(|wb| wb.is_tallying())
+    }
+
+    /// Returns `true` if a filter predicate is in use.
+    pub fn is_filtered(&self) -> bool {
+        self.predicate.is_some()
+    }
+
+    /// Replaces the window lines are drawn into, e.g. after the terminal has
+    /// been resized, and redraws the current page at the new dimensions.
+    ///
+    /// ### Parameters
+    /// * `window`: the new window; the old one is deleted
+    pub fn resize(&mut self, window: ncurses::WINDOW) {
+        ncurses::delwin(self.window);
+        if let Some(overlay) = self.overlay.take() {
+            ncurses::delwin(overlay);
+        }
+
+        let mut height = 0;
+        let mut width = 0;
+        ncurses::getmaxyx(window, &mut height, &mut width);
+        ncurses::idlok(window, true);
+
+        self.window = window;
+        self.width = width as usize;
+        self.height = height as usize;
+
+        if let Some(wb) = self.window_buffer.as_mut() {
+            wb.resize(width as usize, height as usize);
+        }
+
+        self.redraw();
+    }
+
+    /// Redraws the window, e.g. after another window was drawn over it.
+    pub fn refresh(&mut self) {
+        ncurses::touchwin(self.window);
+        ncurses::wnoutrefresh(self.window);
+    }
+
+    /// Moves the focused position by `rows` rows and `cols` columns, keeping
+    /// it within the window.
+    pub fn move_cursor(&mut self, rows: i32, cols: i32) {
+        let row = min(self.cursor_row as i32 + rows, self.height as i32 - 1);
+        let col = min(self.cursor_col as i32 + cols, self.width as i32 - 1);
+        self.set_cursor_row(max(row, 0) as usize);
+        self.cursor_col = max(col, 0) as usize;
+        self.place_cursor();
+    }
+
+    /// Moves the cursor line to row `row`, redrawing the row it leaves and
+    /// the row it enters, or the whole page if the selection changes.
+    fn set_cursor_row(&mut self, row: usize) {
+        if row == self.cursor_row {
+            return;
+        }
+
+        let prev_row = self.cursor_row;
+        self.cursor_row = row;
+        if self.extend_selection() {
+            return self.redraw();
+        }
+        self.reprint_row(prev_row);
+        self.reprint_row(row);
+        ncurses::wnoutrefresh(self.window);
+    }
+
+    /// Keeps the cursor on the line it was on as the window's contents are
+    /// scrolled up by `rows` rows, or down if `rows` is negative, unless that
+    /// line has left the window, in which case the cursor stays in the edge
+    /// row and the line now in it is drawn as the cursor line.
+    fn follow_scroll(&mut self, rows: i32) {
+        let row = self.cursor_row as i32 - rows;
+        if row >= 0 && row < self.height as i32 {
+            self.cursor_row = row as usize;
+        } else if self.extend_selection() {
+            self.redraw();
+        } else {
+            let row = self.cursor_row;
+            self.reprint_row(row);
+        }
+    }
+
+    /// Starts selecting lines at the cursor line, to be copied by `yank()`
+    /// once the cursor has been moved to the other end of the selection.
+    ///
+    /// Returns the number of the line within the input, or `None` if the
+    /// cursor isn't on a line.
+    pub fn start_selection(&mut self) -> Option<usize> {
+        let line_num = self.cursor_line().and_then(|line| line.line_num())?;
+        self.selection = Some((line_num, line_num));
+        Some(line_num)
+    }
+
+    /// Stops selecting lines, returning `false` if none were being selected.
+    pub fn clear_selection(&mut self) -> bool {
+        if self.selection.take().is_none() {
+            return false;
+        }
+        self.redraw();
+        true
+    }
+
+    /// Moves the end of the selection to the cursor line, returning `true` if
+    /// the lines selected changed.
+    fn extend_selection(&mut self) -> bool {
+        let (start, end) = match self.selection {
+            Some(selection) => selection,
+            None => return false,
+        };
+        let line_num = self.cursor_line().and_then(|line| line.line_num()).unwrap_or(end);
+        self.selection = Some((start, line_num));
+        line_num != end
+    }
+
+    /// Returns `true` if `filtered_line` lies within the selection.
+    fn is_selected(&self, filtered_line: &FilteredLine) -> bool {
+        let (first, last) = match self.selection {
+            Some((start, end)) => (min(start, end), max(start, end)),
+            None => return false,
+        };
+        match *filtered_line {
+            FilteredLine::Gap { from, to } => from > first && to < last,
+            _ => filtered_line.line_num().is_some_and(|n| n >= first && n <= last),
         }
     }
 
-    pub fn filter(&mut self, target: Option<String>) {
-        let predicate = target.map(|p| {
-            FilterPredicate {
-                filter_string: p,
-                context_lines: 3,
+    /// Returns the text of the lines selected, or of the cursor line if none
+    /// are, to be copied to the clipboard, ending the selection.
+    pub fn yank(&mut self) -> Vec<String> {
+        let lines = match self.selection {
+            Some((start, end)) => {
+                let window_buffer = self.window_buffer.as_ref().expect("window_buffer is None");
+                window_buffer.lines_between(min(start, end), max(start, end))
+            },
+            None => self.cursor_line().into_iter().collect(),
+        };
+        self.clear_selection();
+
+        lines.into_iter()
+            .filter_map(|line| match line {
+                FilteredLine::Gap { .. } => None,
+                FilteredLine::ContextLine((_, line)) |
+                FilteredLine::MatchLine((_, line)) |
+                FilteredLine::UnfilteredLine((_, line)) => Some(line),
+            })
+            .collect()
+    }
+
+    /// Returns the lines shown in the window.
+    pub fn page(&mut self) -> Vec<FilteredLine> {
+        self.window_buffer.as_mut().map(|wb| wb.current_page()).unwrap_or_default()
+    }
+
+    /// Returns the line under the cursor, or `None` if the cursor is below
+    /// the last line shown.
+    pub fn cursor_line(&mut self) -> Option<FilteredLine> {
+        let row = self.cursor_row;
+        self.window_buffer.as_mut().and_then(|wb| wb.line_at_row(row))
+    }
+
+    /// Shows the lines of the current page sorted by a column over the page,
+    /// leaving the lines in their order beneath, until `close_overlay()` or
+    /// until the page is drawn again.
+    ///
+    /// Returns the number of lines shown which lack the column, or an error
+    /// if none of them has it.
+    pub fn preview_sorted(&mut self, key: &SortKey) -> Result<usize, String> {
+        let lines: Vec<FilteredLine> = self.classify(|wb| wb.current_page())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|line| !line.is_gap())
+            .collect();
+        let texts: Vec<&str> = lines.iter()
+            .map(|line| match *line {
+                FilteredLine::ContextLine((_, ref text)) |
+                FilteredLine::MatchLine((_, ref text)) |
+                FilteredLine::UnfilteredLine((_, ref text)) => text.as_str(),
+                FilteredLine::Gap { .. } => "",
+            })
+            .collect();
+        let order = key.sort(&texts);
+        let lacking = texts.iter().filter(|text| !key.has_column(text)).count();
+        if lacking == lines.len() {
+            return Err(format!("No line shown has {}", key));
+        }
+
+        ncurses::werase(self.window);
+        for (row, &idx) in order.iter().enumerate() {
+            let _timer = render::timer(Phase::Draw);
+            ncurses::wmove(self.window, row as i32, 0);
+            self.print_line(&lines[idx]);
+        }
+        ncurses::wnoutrefresh(self.window);
+        self.previewing = true;
+        Ok(lacking)
+    }
+
+    /// Covers the page with a window showing `lines`, e.g. the help screen,
+    /// until `close_overlay()`; the page beneath is kept as it is, and any
+    /// lines appended meanwhile are drawn into it unseen.
+    ///
+    /// ### Parameters
+    /// * `lines`: text of each row, cut off at the width of the window
+    pub fn show_overlay(&mut self, lines: &[String]) {
+        let overlay = match self.overlay {
+            Some(overlay) => overlay,
+            None => {
+                let (mut top, mut left) = (0, 0);
+                ncurses::getbegyx(self.window, &mut top, &mut left);
+                let overlay = ncurses::newwin(
+                    self.height as i32, self.width as i32, top, left);
+                self.overlay = Some(overlay);
+                overlay
+            },
+        };
+
+        ncurses::werase(overlay);
+        for (row, line) in lines.iter().take(self.height).enumerate() {
+            ncurses::mvwaddstr(overlay, row as i32, 0, text::truncate(line, self.width));
+        }
+        ncurses::wnoutrefresh(overlay);
+    }
+
+    /// Uncovers the page covered by `show_overlay()`, or draws it in place of
+    /// the preview shown by `preview_sorted()`, returning `false` if there
+    /// was neither.
+    pub fn close_overlay(&mut self) -> bool {
+        if let Some(overlay) = self.overlay.take() {
+            ncurses::delwin(overlay);
+            self.refresh();
+            true
+        } else if self.previewing {
+            self.redraw();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the terminal cursor to the focused position, as of the next frame.
+    pub fn place_cursor(&self) {
+        ncurses::wmove(self.window, self.cursor_row as i32, self.cursor_col as i32);
+        ncurses::wnoutrefresh(self.window);
+        // case: the page was drawn to beneath the overlay, which stays on top
+        if let Some(overlay) = self.overlay {
+            ncurses::touchwin(overlay);
+            ncurses::wnoutrefresh(overlay);
+        }
+    }
+
+    /// Sets the matcher describing the token picked up from the focused
+    /// position by `filter_token()`.
+    pub fn set_token_matcher(&mut self, token: Matcher) {
+        self.token = token;
+    }
+
+    /// Returns the token under the focused position, if any.
+    pub fn token_under_cursor(&mut self) -> Option<String> {
+        let text = match self.cursor_line() {
+            Some(FilteredLine::ContextLine((_, text))) |
+            Some(FilteredLine::MatchLine((_, text))) |
+            Some(FilteredLine::UnfilteredLine((_, text))) => text,
+            _ => return None,
+        };
+
+        let gutter_width = self.gutter_width();
+        if self.cursor_col < gutter_width {
+            return None;
+        }
+
+        let text = self.display_text(&text);
+        text::token_at(&text, self.cursor_col - gutter_width, &self.token)
+            .map(|token| token.to_owned())
+    }
+
+    /// Filters by the token under the focused position, compared with lines
+    /// as filter strings are, e.g. ignoring case.
+    ///
+    /// Returns the token, or `None` if there's no token under the focused
+    /// position.
+    pub fn filter_token(&mut self) -> Option<String> {
+        let token = self.token_under_cursor();
+
+        let predicate = token.as_ref().and_then(|token| FilterPredicate::with_kind(
+            &matcher::SUBSTRING, token, &self.match_options, self.context_lines).ok());
+        if let Some(predicate) = predicate {
+            self.apply_predicate(Some(predicate.with_lines(self.line_range())));
+        }
+
+        token
+    }
+
+    /// Counts the occurrences of the token under the focused position within
+    /// the lines read so far, found as filter strings are, e.g. ignoring
+    /// case, leaving the view unchanged.
+    ///
+    /// Returns the token, its number of occurrences, and the number of lines
+    /// searched, or `None` if there's no token under the focused position.
+    pub fn count_token(&mut self) -> Option<(String, usize, usize)> {
+        let token = self.token_under_cursor()?;
+
+        let matcher = Matcher::build(matcher::SUBSTRING.name, &token, &self.match_options).ok()?;
+        let window_buffer = self.window_buffer.as_mut().expect("window_buffer is None");
+        let num_lines = window_buffer.lines_read();
+        let count = (1..num_lines + 1)
+            .filter_map(|line_num| window_buffer.get_raw_line(line_num))
+            .map(|(_, line)| matcher.spans(&line).len())
+            .sum();
+
+        Some((token, count, num_lines))
+    }
+
+    /// Sets the limit on the bytes of line text held in memory, beyond which
+    /// lines are spilled to a temporary file, or removes the limit if
+    /// `max_bytes` is `None`.
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_bytes = max_bytes;
+
+        if let Some(wb) = self.window_buffer.as_mut() {
+            wb.set_max_bytes(max_bytes);
+        }
+
+        for tab in self.tabs.iter_mut() {
+            if let Some(wb) = tab.window_buffer.as_mut() {
+                wb.set_max_bytes(max_bytes);
             }
+        }
+    }
+
+    /// Sets whether more lines of the input are still being read, redrawing
+    /// the current page once they've all been, to show the `(END)` marker.
+    pub fn set_reading(&mut self, reading: bool) {
+        let finished = self.reading && !reading;
+        self.reading = reading;
+        if finished {
+            self.redraw();
+        }
+    }
+
+    /// Sets the number of lines read at a time off the input of each tab
+    /// which is read line by line, rather than as they're shown.
+    pub fn set_block_lines(&mut self, lines: usize) {
+        self.block_lines = lines;
+
+        if let Some(wb) = self.window_buffer.as_mut() {
+            wb.set_block_lines(lines);
+        }
+
+        for tab in self.tabs.iter_mut() {
+            if let Some(wb) = tab.window_buffer.as_mut() {
+                wb.set_block_lines(lines);
+            }
+        }
+    }
+
+    /// Sets the fields shown of lines parsed into fields, in place of the
+    /// whole line, or shows whole lines if `fields` is empty, and redraws the
+    /// current page.
+    ///
+    /// ### Parameters
+    /// * `fields`: dotted paths of the fields to show, in order
+    pub fn set_fields(&mut self, fields: Vec<String>) {
+        self.options.fields = fields;
+        self.redraw();
+    }
+
+    /// Sets the format in which lines are parsed into fields, for display,
+    /// highlighting and sorting, and redraws the current page.
+    ///
+    /// ### Parameters
+    /// * `format`: the format, or `None` for the first format each line is in
+    pub fn set_format(&mut self, format: Option<Format>) {
+        self.options.format = format;
+        self.redraw();
+    }
+
+    /// Returns the format most of the first lines of the input are in, as
+    /// many as fit in the window, or `None` if they're plain text.
+    pub fn detect_format(&mut self) -> Option<Format> {
+        let height = self.height;
+        let lines: Vec<String> = match self.window_buffer.as_mut() {
+            Some(wb) => (1..=height).map_while(|n| wb.get_raw_line(n)).map(|(_, line)| line)
+                .collect(),
+            None => Vec::new(),
+        };
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        parser::detect(&lines)
+    }
+
+    /// Highlights the occurrences of `pattern`, interpreted by the current
+    /// kind of matcher, within whatever lines are shown, in a color of its
+    /// own, and redraws the current page.
+    ///
+    /// Returns an error if `pattern` isn't valid for the current kind of
+    /// matcher.
+    pub fn add_highlight(&mut self, pattern: &str) -> Result<(), String> {
+        let kind = self.matcher_kind;
+        self.add_highlight_with(&kind, pattern)
+    }
+
+    /// Highlights the occurrences of `pattern`, interpreted by the given kind
+    /// of matcher, as does `add_highlight()`.
+    pub fn add_highlight_with(&mut self, kind: &MatcherKind, pattern: &str)
+                              -> Result<(), String> {
+        let matcher = (kind.build)(pattern, &self.match_options)?;
+        self.highlights.add(pattern, matcher);
+        self.redraw();
+        Ok(())
+    }
+
+    /// Stops highlighting `pattern`, or every pattern if `pattern` is `None`,
+    /// and redraws the current page.
+    ///
+    /// Returns `false` if `pattern` wasn't highlighted.
+    pub fn remove_highlight(&mut self, pattern: Option<&str>) -> bool {
+        let removed = match pattern {
+            Some(pattern) => self.highlights.remove(pattern),
+            None => {
+                self.highlights.clear();
+                true
+            },
+        };
+        self.redraw();
+        removed
+    }
+
+    /// Returns the patterns highlighted, in the order in which they were
+    /// added.
+    pub fn highlight_patterns(&self) -> Vec<&str> {
+        self.highlights.patterns()
+    }
+
+    /// Returns the attributes in which the color theme draws `role`.
+    pub fn attr(&self, role: Role) -> ncurses::attr_t {
+        self.theme.attr(role)
+    }
+
+    /// Replaces the color theme and redraws the current page.
+    pub fn set_theme(&mut self, mut theme: Theme) {
+        theme.init();
+        self.theme = theme;
+        self.redraw();
+    }
+
+    /// Sets how filter strings are interpreted.
+    pub fn set_matcher_kind(&mut self, kind: MatcherKind) {
+        self.matcher_kind = kind;
+    }
+
+    /// Returns how filter strings are interpreted.
+    pub fn matcher_kind(&self) -> MatcherKind {
+        self.matcher_kind
+    }
+
+    /// Sets how filter strings are compared with lines.
+    pub fn set_match_options(&mut self, options: MatchOptions) {
+        self.match_options = options;
+    }
+
+    /// Returns how filter strings are compared with lines.
+    pub fn match_options(&self) -> MatchOptions {
+        self.match_options
+    }
+
+    /// Applies a filter, or removes the filter strings if `target` is
+    /// `None`, in either case keeping any range of lines to which the filter
+    /// is restricted.
+    ///
+    /// Returns an error and leaves the current filter in place if `target`
+    /// isn't valid for the current kind of matcher.
+    pub fn filter(&mut self, target: Option<String>) -> Result<(), String> {
+        let predicate = match target {
+            Some(p) => Some(FilterPredicate::with_kind(
+                    &self.matcher_kind, &p, &self.match_options, self.context_lines)?),
+            None => {
+                self.clear_patterns();
+                return Ok(());
+            },
+        };
+
+        self.apply_predicate(predicate.map(|p| p.with_lines(self.line_range())));
+        Ok(())
+    }
+
+    /// Shows the lines which are more than `lines` lines away from any match
+    /// of `pattern`, e.g. the stretches of a log in which a heartbeat
+    /// stopped appearing, keeping any range of lines to which the filter is
+    /// restricted.
+    ///
+    /// Returns an error and leaves the current filter in place if `pattern`
+    /// isn't valid for the current kind of matcher.
+    pub fn filter_quiet(&mut self, pattern: &str, lines: usize) -> Result<(), String> {
+        let predicate = FilterPredicate::with_kind(
+                &self.matcher_kind, pattern, &self.match_options, lines)?
+            .with_inverse(true)
+            .with_lines(self.line_range());
+        self.apply_predicate(Some(predicate));
+        Ok(())
+    }
+
+    /// Sets the number of lines shown around each match of the filters
+    /// applied from now on and of the filter in use, if any, keeping the line
+    /// at the top of the window in view where it can be.
+    ///
+    /// The filter set aside while zoomed out, and one showing the lines away
+    /// from matches, whose distance isn't context, are left as they are.
+    pub fn set_context_lines(&mut self, context_lines: usize) {
+        self.context_lines = context_lines;
+        let mut predicate = match self.text_predicate() {
+            Some(predicate) if self.zoomed_out.is_none() && !predicate.inverse => predicate,
+            _ => return,
+        };
+        predicate.context_lines = context_lines;
+
+        let line_num = self.top_line_num();
+        self.apply_predicate(Some(predicate));
+        if let Some(line_num) = line_num {
+            self.goto_line(line_num);
+        }
+    }
+
+    /// Removes the filter strings, if any, leaving any range of lines in
+    /// place.
+    fn clear_patterns(&mut self) {
+        let only_lines = self.text_predicate().is_some_and(|p| p.source.patterns.is_empty());
+        if !only_lines {
+            self.clear_filter();
+        }
+    }
+
+    /// Removes the filter strings, showing the input from the line which was
+    /// at the top of the window, or removes the range of lines to which the
+    /// filter is restricted if there are no filter strings. The base filter,
+    /// if any, stays in place.
+    ///
+    /// Returns `false` if no filter but the base filter was in use.
+    pub fn clear_filter(&mut self) -> bool {
+        if self.predicate.is_none() {
+            // case: zoomed out, so only the highlighting remains to be cleared
+            if self.zoomed_out.take().is_none() {
+                return false;
+            }
+            self.redraw();
+            return true;
+        }
+
+        let text = match self.text_predicate() {
+            Some(text) => text,
+            None => return false,
+        };
+        let predicate = match text.source.lines {
+            Some(lines) if !text.source.patterns.is_empty() => {
+                Some(FilterPredicate::within(lines, 0))
+            },
+            _ => None,
+        };
+        let line_num = self.top_line_num();
+        self.apply_predicate(predicate);
+
+        if let Some(line_num) = line_num {
+            self.goto_line(line_num);
+        }
+        true
+    }
+
+    /// Returns the range of lines to which the filter in use, or the filter
+    /// set aside while zoomed out, is restricted, if any.
+    pub fn line_range(&self) -> Option<LineRange> {
+        self.text_predicate().and_then(|p| p.source.lines)
+    }
+
+    /// Returns the filter in use, or the filter set aside while zoomed out,
+    /// without the base filter, or `None` if there's no filter but the base
+    /// filter.
+    fn text_predicate(&self) -> Option<FilterPredicate> {
+        self.predicate.as_ref().or(self.zoomed_out.as_ref())
+            .filter(|&predicate| Some(predicate) != self.base.as_ref())
+            .map(|predicate| predicate.clone().with_base(None))
+    }
+
+    /// Sets the base filter, which lines must match as well as any other
+    /// filter and which stays in place while others are applied and cleared,
+    /// e.g. to hide lines logged below a level; or removes it if `base` is
+    /// `None`.
+    ///
+    /// The line at the top of the window is kept in view where it can be.
+    pub fn set_base(&mut self, base: Option<FilterPredicate>) {
+        let text = self.text_predicate();
+        self.base = base;
+        let line_num = self.top_line_num();
+        self.apply_predicate(text);
+
+        if let Some(line_num) = line_num {
+            self.goto_line(line_num);
+        }
+    }
+
+    /// Returns the base filter, if any.
+    pub fn base(&self) -> Option<&FilterPredicate> {
+        self.base.as_ref()
+    }
+
+    /// Restricts the filter to a range of lines, showing every line within
+    /// it if there are no filter strings, and shows the first line of the
+    /// range; or lifts the restriction if `lines` is `None`.
+    pub fn set_line_range(&mut self, lines: Option<LineRange>) {
+        let patterns = self.text_predicate().filter(|p| !p.source.patterns.is_empty());
+        let predicate = match (patterns, lines) {
+            (Some(predicate), lines) => Some(predicate.with_lines(lines)),
+            (None, Some(lines)) => Some(FilterPredicate::within(lines, 0)),
+            (None, None) => None,
+        };
+        let line_num = lines.map(|lines| lines.first).or(self.top_line_num());
+        self.apply_predicate(predicate);
+
+        if let Some(line_num) = line_num {
+            self.goto_line(line_num);
+        }
+    }
+
+    /// Switches between the filtered view and a view of every line in which
+    /// matches remain highlighted, keeping in view the match nearest the
+    /// cursor when zooming out, and the line under the cursor when zooming
+    /// back in.
+    ///
+    /// Returns `true` if now zoomed out, or `false` if zoomed back in or no
+    /// filter is in use.
+    pub fn toggle_zoom(&mut self) -> bool {
+        if let Some(predicate) = self.zoomed_out.take() {
+            let anchor = self.window_buffer.as_ref().and_then(|wb| wb.line_num_at_row(self.cursor_row));
+            let context_lines = predicate.context_lines;
+            self.apply_predicate(Some(predicate));
+
+            if let Some(anchor) = anchor {
+                self.goto_line(anchor.saturating_sub(context_lines).max(1));
+                let row = self.window_buffer.as_ref().and_then(|wb| wb.row_of_line_num(anchor));
+                self.set_cursor_row(row.unwrap_or(0));
+            }
+            return false;
+        }
+
+        let predicate = match self.predicate.clone() {
+            Some(predicate) => predicate,
+            None => return false,
+        };
+        let anchor = self.window_buffer.as_ref().and_then(|wb| {
+            wb.nearest_match(self.cursor_row).or(wb.line_num_at_row(self.cursor_row))
         });
+        self.set_predicate(None);
+        self.zoomed_out = Some(predicate);
+
+        match anchor {
+            Some(anchor) => {
+                // the anchor is kept in the cursor's row where possible
+                let row = min(self.cursor_row, anchor - 1);
+                self.goto_line(anchor - row);
+                let row = self.window_buffer.as_ref().and_then(|wb| wb.row_of_line_num(anchor));
+                self.set_cursor_row(row.unwrap_or(0));
+            },
+            None => self.redraw(),
+        }
+        true
+    }
 
+    /// Filters by several filter strings at once, showing lines which match
+    /// any of them, or removes the filter if `patterns` is empty.
+    ///
+    /// Unlike `filter()`, this keeps the line at the top of the window in
+    /// view where it can, as the filter may be changed from outside, e.g. by
+    /// rewriting a patterns file, while the user is reading.
+    pub fn filter_any(&mut self, patterns: &[String]) -> Result<(), String> {
+        if patterns.is_empty() {
+            self.clear_patterns();
+            return Ok(());
+        }
+
+        let predicate = FilterPredicate::any_of(
+            &self.matcher_kind, patterns, &self.match_options, self.context_lines)?
+            .with_lines(self.line_range());
+        let line_num = self.top_line_num();
+        self.apply_predicate(Some(predicate));
+
+        if let Some(line_num) = line_num {
+            self.goto_line(line_num);
+        }
+        Ok(())
+    }
+
+    /// Returns the filter predicate in use, if any, e.g. to restore it with
+    /// `apply_predicate()` after trying out others.
+    pub fn predicate(&self) -> Option<FilterPredicate> {
+        self.predicate.clone()
+    }
+
+    /// Returns the filter strings of the filter in use, or of the filter set
+    /// aside while zoomed out, leaving out the base filter.
+    pub fn filter_source(&self) -> Option<FilterSource> {
+        self.text_predicate().map(|p| p.source)
+    }
+
+    /// Replaces the filter predicate of every tab, requiring lines to match
+    /// the base filter as well, if any, and shows the first page of the
+    /// current tab's lines.
+    pub fn apply_predicate(&mut self, predicate: Option<FilterPredicate>) {
+        let predicate = match predicate {
+            Some(ref predicate) if Some(predicate) == self.base.as_ref() => Some(predicate.clone()),
+            Some(predicate) => Some(predicate.with_base(self.base.clone())),
+            None => self.base.clone(),
+        };
+        self.set_predicate(predicate);
+    }
+
+    /// Replaces the filter predicate of every tab as it is, e.g. leaving out
+    /// the base filter to show every line while zoomed out, and shows the
+    /// first page of the current tab's lines.
+    fn set_predicate(&mut self, predicate: Option<FilterPredicate>) {
         {
             let window_buffer = self.window_buffer.as_mut().expect("window_buffer is None");
             window_buffer.set_predicate(predicate.clone());
         }
 
+        for tab in self.tabs.iter_mut() {
+            if let Some(wb) = tab.window_buffer.as_mut() {
+                wb.set_predicate(predicate.clone());
+            }
+        }
+
+        // the first page may be drawn before the background scan for matches
+        // has found any, and the scan may finish before it's next polled, so
+        // the page is redrawn when it is
+        self.scanning = predicate.is_some();
         self.predicate = predicate;
+        self.zoomed_out = None;
+        self.selection = None;
         self.next_page();
     }
 
-    fn print_line_num(&mut self, line_num: usize) {
-        self.num_digits = max(
-            self.num_digits, (line_num as f32).log10().floor() as usize + 1);
-        ncurses::wattron(self.window, ncurses::COLOR_PAIR(2));
-        ncurses::wprintw(self.window,
-                         &format!("{:>1$} ", line_num, self.num_digits));
-        ncurses::wattroff(self.window, ncurses::COLOR_PAIR(2));
+    /// Writes the lines of the input selected by the current filter to a
+    /// file, returning the number of lines written.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the file to create or overwrite
+    /// * `options`: which lines to write and how
+    pub fn save(&mut self, path: &str, options: &ExportOptions) -> Result<usize, String> {
+        let window_buffer = self.window_buffer.as_mut().expect("window_buffer is None");
+        let file = read_only::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut file = BufWriter::new(file);
+
+        export::write_lines(&mut file, window_buffer.all_lines(), options)
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Writes the lines of the input selected by the current filter to a
+    /// file, each marked with how it was classified, returning the number of
+    /// lines written.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the file to create or overwrite
+    pub fn annotate(&mut self, path: &str) -> Result<usize, String> {
+        let window_buffer = self.window_buffer.as_mut().expect("window_buffer is None");
+        let file = read_only::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut file = BufWriter::new(file);
+
+        export::write_annotated(&mut file, window_buffer.all_lines())
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Writes each context group of the lines selected by the current filter
+    /// to a file as a fenced Markdown code block, returning the number of
+    /// blocks written.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the file to create or overwrite
+    /// * `source`: name of the input, given in each block's header
+    pub fn write_markdown(&mut self, path: &str, source: &str) -> Result<usize, String> {
+        let window_buffer = self.window_buffer.as_mut().expect("window_buffer is None");
+        let file = read_only::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut file = BufWriter::new(file);
+
+        export::write_markdown(&mut file, window_buffer.all_lines(), source)
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Writes the lines of the input selected by the current filter to a
+    /// file as an HTML page drawing them as the window does, with matches
+    /// highlighted in the colors of the theme, returning the number of lines
+    /// written.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the file to create or overwrite
+    /// * `title`: title of the page, e.g. the name of the input
+    pub fn export_html(&mut self, path: &str, title: &str) -> Result<usize, String> {
+        let predicate = self.predicate.as_ref().or(self.zoomed_out.as_ref());
+        let matcher = predicate.map(|predicate| &predicate.matcher);
+        let window_buffer = self.window_buffer.as_mut().expect("window_buffer is None");
+        let file = read_only::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut file = BufWriter::new(file);
+
+        export::write_html(&mut file, window_buffer.all_lines(), matcher, &self.theme, title,
+                           self.options.line_numbers)
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Scrolls the contents of the window by `lines` rows.
+    ///
+    /// Scrolling is only enabled for the duration of the call so that
+    /// drawing in the bottom right corner doesn't scroll the window.
+    fn scroll(&mut self, lines: i32) {
+        ncurses::scrollok(self.window, true);
+        ncurses::wscrl(self.window, lines);
+        ncurses::scrollok(self.window, false);
+    }
+
+    /// Returns the number of lines moved by `scroll_down()` and
+    /// `scroll_up()`.
+    fn scroll_step(&self) -> usize {
+        self.scroll_step.unwrap_or(self.height / 2).max(1)
+    }
+
+    /// Moves the window down `lines` lines, or up if `lines` is negative, and
+    /// redraws it.
+    fn scroll_by(&mut self, lines: i64) {
+        let moved = self.window_buffer.as_mut().map(|wb| wb.scroll(lines)).unwrap_or_default();
+        if !moved.is_empty() {
+            self.redraw();
+        } else if lines < 0 || !self.show_end() {
+            self.ring_bell();
+        }
+    }
+
+    /// Gives the feedback set by `DisplayOptions::bell` for a movement which
+    /// couldn't be made.
+    fn ring_bell(&self) {
+        match self.options.bell {
+            Bell::Off => {},
+            Bell::Beep => { ncurses::beep(); },
+            Bell::Flash => { ncurses::flash(); },
+        }
+    }
+
+    /// Runs `f` on the window buffer of the current tab, if any, timing it as
+    /// the classification of the lines it filters.
+    fn classify<R, F: FnOnce(&mut WindowBuffer<T>) -> R>(&mut self, f: F) -> Option<R> {
+        let _timer = render::timer(Phase::Classify);
+        self.window_buffer.as_mut().map(f)
+    }
+
+    /// Widens the line number gutter if needed to fit the numbers of `lines`
+    /// and of every line read from the input so far.
+    ///
+    /// The gutter never narrows, so line content stays aligned while
+    /// scrolling. Returns `true` if the gutter was widened.
+    fn widen_gutter(&mut self, lines: &[FilteredLine]) -> bool {
+        let lines_read = self.window_buffer.as_ref().map(|wb| wb.lines_read()).unwrap_or(0);
+        let max_line_num = lines.iter()
+            .filter_map(|line| line.line_num())
+            .fold(lines_read, max);
+
+        let num_digits = max(self.num_digits, text::num_digits(max_line_num));
+        let widened = num_digits > self.num_digits;
+        self.num_digits = num_digits;
+
+        widened
+    }
+
+    fn print_page(&mut self, lines: &[FilteredLine]) {
+        self.widen_gutter(lines);
+        ncurses::wclear(self.window);
+        self.end_shown = false;
+        self.previewing = false;
+
+        for (i, filtered_line) in lines.iter().enumerate() {
+            self.print_row(i, filtered_line);
+        }
+
+        if lines.len() < self.height && self.is_exhausted() && !self.reading {
+            self.print_end(lines.len());
+        }
+
+        ncurses::wnoutrefresh(self.window);
+    }
+
+    /// Draws the `(END)` marker below the last line shown once no more lines
+    /// follow, scrolling the lines up a row to make room for it if the window
+    /// is full.
+    ///
+    /// Nothing is drawn if the marker is already shown, or if more lines may
+    /// yet be read or scanned, so that keys pressed at the end don't redraw
+    /// the window. Returns `true` if the marker was drawn.
+    fn show_end(&mut self) -> bool {
+        if self.end_shown || !self.is_exhausted() || self.reading || self.height == 0 {
+            return false;
+        }
+
+        let num_lines = self.window_buffer.as_mut().map_or(0, |wb| wb.current_page().len());
+        if num_lines < self.height {
+            self.print_end(num_lines);
+        } else {
+            self.scroll(1);
+            self.print_end(self.height - 1);
+            self.follow_scroll(1);
+        }
+        ncurses::wnoutrefresh(self.window);
+        true
+    }
+
+    /// Draws `filtered_line` in row `row` of the window, across the whole row
+    /// in the cursor style if it's the cursor row or is selected.
+    fn print_row(&mut self, row: usize, filtered_line: &FilteredLine) {
+        let _timer = render::timer(Phase::Draw);
+        ncurses::wmove(self.window, row as i32, 0);
+        ncurses::wclrtoeol(self.window);
+        if row != self.cursor_row && !self.is_selected(filtered_line) {
+            return self.print_line(filtered_line);
+        }
+
+        let attr = self.theme.attr(Role::Cursor);
+        ncurses::wattron(self.window, attr);
+        self.print_line(filtered_line);
+        let width = self.remaining_width();
+        ncurses::waddstr(self.window, &" ".repeat(width));
+        ncurses::wattroff(self.window, attr);
+    }
+
+    /// Draws row `row` of the window again, e.g. as the cursor enters or
+    /// leaves it.
+    fn reprint_row(&mut self, row: usize) {
+        let lines = self.window_buffer.as_mut().map(|wb| wb.current_page()).unwrap_or_default();
+        // case: the lines were scrolled up a row to make room for `(END)`
+        let offset = if self.end_shown && lines.len() == self.height { 1 } else { 0 };
+        if let Some(line) = lines.get(row + offset) {
+            self.print_row(row, line);
+        }
+    }
+
+    /// Draws the `(END)` marker in row `row` of the window.
+    fn print_end(&mut self, row: usize) {
+        ncurses::wmove(self.window, row as i32, 0);
+        ncurses::wclrtoeol(self.window);
+        ncurses::wattron(self.window, ncurses::A_REVERSE());
+        ncurses::waddstr(self.window, "(END)");
+        ncurses::wattroff(self.window, ncurses::A_REVERSE());
+        self.end_shown = true;
+    }
+
+    /// Returns the number of columns taken up by the gutters preceding line
+    /// text.
+    fn gutter_width(&self) -> usize {
+        let mut width = 0;
+        if self.options.time_deltas {
+            width += DELTA_WIDTH + 1;
+        }
+        if self.options.line_numbers {
+            width += self.num_digits + 1;
+        }
+        if self.shows_ordinals() {
+            width += self.num_digits + ORDINAL_MARKS + 1;
+        }
+        if self.options.match_counts {
+            width += COUNT_WIDTH + 1;
+        }
+        width
+    }
+
+    /// Returns the number of columns remaining in the current row.
+    fn remaining_width(&self) -> usize {
+        let mut x = 0;
+        let mut y = 0;
+        ncurses::getyx(self.window, &mut y, &mut x);
+        self.width.saturating_sub(x as usize)
+    }
+
+    /// Returns the milliseconds elapsed between the timestamp of a line and
+    /// that of the closest preceding line having a timestamp.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number within the underlying input
+    fn time_delta(&mut self, line_num: usize) -> Option<i64> {
+        let window_buffer = self.window_buffer.as_mut().expect("window_buffer is None");
+
+        let current = window_buffer.get_raw_line(line_num)
+            .and_then(|(_, line)| timestamp::parse_leading(&line));
+
+        current.and_then(|current| {
+            let first = if line_num > MAX_DELTA_LOOKBACK { line_num - MAX_DELTA_LOOKBACK } else { 1 };
+            (first..line_num).rev()
+                .filter_map(|prev_num| {
+                    window_buffer.get_raw_line(prev_num)
+                        .and_then(|(_, line)| timestamp::parse_leading(&line))
+                })
+                .next()
+                .map(|prev| current.millis_since(&prev))
+        })
+    }
+
+    fn print_time_delta(&mut self, line_num: usize) {
+        match self.time_delta(line_num) {
+            Some(delta) => {
+                let text = format!("{:>1$} ", Delta(delta), DELTA_WIDTH);
+                if delta >= self.options.stall_millis {
+                    let attr = self.theme.attr(Role::Stall);
+                    ncurses::wattron(self.window, attr);
+                    ncurses::waddstr(self.window, &text);
+                    ncurses::wattroff(self.window, attr);
+                } else {
+                    ncurses::waddstr(self.window, &text);
+                }
+            },
+            None => {
+                ncurses::waddstr(self.window, &" ".repeat(DELTA_WIDTH + 1));
+            },
+        }
+    }
+
+    /// Returns `true` if the ordinals of matches are shown, which they are
+    /// only while filtering.
+    fn shows_ordinals(&self) -> bool {
+        self.options.ordinals && self.predicate.is_some()
+    }
+
+    fn print_line_num(&mut self, line_num: usize, num_matches: usize, is_match: bool) {
+        if self.options.time_deltas {
+            self.print_time_delta(line_num);
+        }
+
+        let attr = self.theme.attr(Role::LineNum);
+        ncurses::wattron(self.window, attr);
+        if self.options.line_numbers {
+            ncurses::wprintw(self.window,
+                             &format!("{:>1$} ", line_num, self.num_digits));
+        }
+        if self.shows_ordinals() {
+            // case: a context line, which has no ordinal
+            let ordinal = self.window_buffer.as_mut()
+                .and_then(|wb| if is_match { wb.ordinal_of_line(line_num) } else { None })
+                .map_or(String::new(), |ordinal| format!("(#{})", ordinal));
+            ncurses::waddstr(self.window,
+                             &format!("{:<1$} ", ordinal, self.num_digits + ORDINAL_MARKS));
+        }
+        if self.options.match_counts {
+            let count = text::format_count(num_matches, COUNT_WIDTH);
+            ncurses::waddstr(self.window, &format!("{} ", count));
+        }
+        ncurses::wattroff(self.window, attr);
     }
 
     fn print_line(&mut self, filtered_line: &FilteredLine) {
         match *filtered_line {
-            FilteredLine::Gap => {
-                ncurses::wprintw(self.window, "-----");
+            FilteredLine::Gap { from, to } => {
+                let omission = self.window_buffer.as_mut()
+                    .and_then(|window_buffer| window_buffer.omission(from, to));
+                let skipped = filtered_line.skipped().unwrap_or(0);
+                let label = format!("----- {} -----", omission
+                    .unwrap_or_else(|| text::describe_skipped(skipped)));
+                let width = self.remaining_width();
+                let attr = self.theme.attr(Role::Gap);
+                ncurses::wattron(self.window, attr);
+                ncurses::waddstr(self.window, text::truncate(&label, width));
+                ncurses::wattroff(self.window, attr);
             },
             FilteredLine::ContextLine((ref line_num, ref line)) => {
-                self.print_line_num(*line_num);
-                ncurses::wprintw(self.window, line);
-
+                self.print_highlighted(*line_num, line, None, Some(Role::Context));
             },
             FilteredLine::MatchLine((ref line_num, ref line)) => {
                 let predicate = self.predicate.as_ref().expect(
                     "Filter predicate was None.").to_owned();
-                self.print_line_num(*line_num);
-
-                let frags: Vec<&str> = line.split(&predicate.filter_string).collect();
-
-                for (i, frag) in frags.iter().enumerate() {
-                    ncurses::wprintw(self.window, frag);
-                    if i < frags.len() - 1 {
-                        ncurses::wattron(self.window, ncurses::COLOR_PAIR(1));
-                        ncurses::wprintw(self.window, &predicate.filter_string);
-                        ncurses::wattroff(self.window, ncurses::COLOR_PAIR(1));
-                    }
-                }
+                self.print_highlighted(*line_num, line, Some(&predicate.matcher), None);
             },
             FilteredLine::UnfilteredLine((ref line_num, ref line)) => {
-                self.print_line_num(*line_num);
-                ncurses::wprintw(self.window, line);
+                let predicate = self.zoomed_out.clone();
+                self.print_highlighted(*line_num, line, predicate.as_ref().map(|p| &p.matcher),
+                                       None);
             },
         }
 
     }
+
+    /// Prints line `line_num`, the text of which is `line`, highlighted in
+    /// the syntax in use and with the highlight patterns over it, with every
+    /// match of `matcher` highlighted over them and counted in the match
+    /// count gutter.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number within the underlying input
+    /// * `line`: text of the line
+    /// * `matcher`: matcher whose matches are highlighted, if any
+    /// * `base`: role in which the rest of the line is drawn, if any
+    fn print_highlighted(&mut self, line_num: usize, line: &str, matcher: Option<&Matcher>,
+                         base: Option<Role>) {
+        let line = self.display_text(line);
+        let matches: Vec<(Span, Role)> = {
+            let _timer = render::timer(Phase::Classify);
+            matcher.map_or(Vec::new(), |matcher| matcher.spans(&line))
+                .into_iter()
+                .map(|span| (span, Role::Match))
+                .collect()
+        };
+        let changed = self.word_diff(line_num, &line);
+        // case: fields are parsed from the whole line, even if cut off
+        let syntax = {
+            let _timer = render::timer(Phase::Classify);
+            self.options.syntax.spans(&line, self.options.format)
+        };
+        // case: only context lines are drawn in a role of their own
+        self.print_line_num(line_num, matches.len(), base.is_none());
+        let width = self.remaining_width();
+        let line = text::truncate(&line, width);
+        let under: Vec<(Span, Role)> = {
+            let _timer = render::timer(Phase::Classify);
+            let highlighted = syntax::compose(line.len(), &syntax, &self.highlights.spans(line));
+            let highlighted: Vec<(Span, Role)> = highlighted.into_iter()
+                .filter_map(|(span, role)| role.map(|role| (span, role)))
+                .collect();
+            syntax::compose(line.len(), &highlighted, &changed).into_iter()
+                .filter_map(|(span, role)| role.map(|role| (span, role)))
+                .collect()
+        };
+
+        for ((start, end), role) in syntax::compose(line.len(), &under, &matches) {
+            match role.or(base) {
+                Some(role) => {
+                    let attr = self.theme.attr(role);
+                    ncurses::wattron(self.window, attr);
+                    ncurses::waddstr(self.window, &line[start..end]);
+                    ncurses::wattroff(self.window, attr);
+                },
+                None => {
+                    ncurses::waddstr(self.window, &line[start..end]);
+                },
+            }
+        }
+    }
+
+    /// Returns the spans of the words in which line `line_num` differs from
+    /// the line of input before it, if they're to be highlighted and the two
+    /// lines are alike.
+    ///
+    /// ### Parameters
+    /// * `line_num`: 1-indexed line number within the underlying input
+    /// * `line`: text of the line as it's displayed
+    fn word_diff(&mut self, line_num: usize, line: &str) -> Vec<(Span, Role)> {
+        if !self.options.word_diff || line_num <= 1 {
+            return Vec::new();
+        }
+
+        let _timer = render::timer(Phase::Classify);
+        let prev = self.window_buffer.as_mut()
+            .and_then(|window_buffer| window_buffer.get_raw_line(line_num - 1))
+            .map(|(_, prev)| prev);
+        prev.map_or(Vec::new(), |prev| {
+            diff::changed_words(line, &self.display_text(&prev)).into_iter()
+                .map(|span| (span, Role::Diff))
+                .collect()
+        })
+    }
+
+    /// Returns the text of a line as it should be displayed, safe to draw
+    /// whatever control characters it contains.
+    fn display_text<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let structured = if self.options.fields.is_empty() {
+            None
+        } else {
+            parser::parse(self.options.format, line)
+        };
+        let line = match structured {
+            Some(structured) => Cow::Owned(structured.render(&self.options.fields)),
+            None => Cow::Borrowed(line),
+        };
+
+        let line = match self.options.zone.render(&line) {
+            Some(rendered) => Cow::Owned(rendered),
+            None => line,
+        };
+
+        match text::sanitize(&line, self.options.tab_width, self.options.trailing_space) {
+            Cow::Owned(sanitized) => Cow::Owned(sanitized),
+            Cow::Borrowed(_) => line,
+        }
+    }
 }
@@ -0,0 +1,97 @@
+//! Filter strings read from a file, which is watched for changes so that
+//! another process can steer what a running session shows.
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::time::SystemTime;
+
+
+/// File listing filter strings, one per line; lines matching any of them are
+/// shown.
+///
+/// Blank lines and lines beginning with `#` are ignored.
+pub struct PatternsFile {
+    path: String,
+    /// modification time and length of the file when last read, by which
+    /// changes are noticed, or `None` if it couldn't be found
+    stamp: Option<(SystemTime, u64)>,
+    /// whether the file has been polled yet
+    polled: bool,
+}
+
+impl PatternsFile {
+    /// Creates a watcher of the file at `path`, which is first read by
+    /// `poll()`.
+    pub fn new(path: &str) -> PatternsFile {
+        PatternsFile { path: path.to_owned(), stamp: None, polled: false }
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Reads the filter strings of the file if it has changed since it was
+    /// last read, returning `None` if it hasn't.
+    ///
+    /// A file which can't be found is reported once, then read again once
+    /// it reappears.
+    pub fn poll(&mut self) -> Option<Result<Vec<String>, String>> {
+        let stamp = fs::metadata(&self.path)
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+            .ok();
+
+        if self.polled && stamp == self.stamp {
+            return None;
+        }
+
+        self.polled = true;
+        self.stamp = stamp;
+        Some(self.read())
+    }
+
+    /// Reads the filter strings of the file.
+    fn read(&self) -> Result<Vec<String>, String> {
+        let file = File::open(&self.path).map_err(|e| format!("{}: {}", self.path, e))?;
+        let mut patterns = Vec::new();
+
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("{}: {}", self.path, e))?;
+            if !line.trim().is_empty() && !line.starts_with('#') {
+                patterns.push(line);
+            }
+        }
+
+        Ok(patterns)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process;
+
+    use super::PatternsFile;
+
+    #[test]
+    fn test_poll() {
+        let path = env::temp_dir().join(format!("filterless-{}-patterns.txt", process::id()));
+        let path_str = path.to_str().unwrap();
+        let mut patterns = PatternsFile::new(path_str);
+
+        assert!(patterns.poll().unwrap().is_err());
+        assert_eq!(patterns.poll(), None);
+
+        File::create(&path).unwrap().write_all(b"# errors\nERROR\n\n  \nWARN\n").unwrap();
+        assert_eq!(patterns.poll(), Some(Ok(vec!["ERROR".to_owned(), "WARN".to_owned()])));
+        assert_eq!(patterns.poll(), None);
+
+        File::create(&path).unwrap().write_all(b"timeout\n").unwrap();
+        assert_eq!(patterns.poll(), Some(Ok(vec!["timeout".to_owned()])));
+
+        fs::remove_file(&path).unwrap();
+        assert!(patterns.poll().unwrap().is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! Plain output mode, for screen readers and other terminals on which
+//! cursor-addressed drawing is unwelcome: instead of redrawing a window, each
+//! page is written to stdout below the last, with matches and gaps marked in
+//! words, while keys are read from the terminal and run the same commands as
+//! in the pager.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::thread;
+use std::time::Duration;
+
+use libc;
+use ncurses::{KEY_BTAB, KEY_DOWN, KEY_END, KEY_HOME, KEY_LEFT, KEY_NPAGE, KEY_PPAGE, KEY_RIGHT,
+              KEY_UP};
+
+use filterless::{FilteredLine, FilterPredicate, Input, MatchOptions, MatcherKind, WindowBuffer};
+use keymap::{Action, Keymap};
+use line_edit;
+use pager::CONTEXT_LINES;
+use terminal;
+use text;
+
+const ESCAPE: u8 = 27;
+const DIGIT_0: i32 = 0x30;
+const DIGIT_9: i32 = 0x39;
+
+/// Height of a page when the terminal's can't be found out.
+const DEFAULT_HEIGHT: usize = 24;
+
+/// Milliseconds to wait for the rest of an escape sequence after `Esc`.
+const ESCAPE_DELAY_MS: i32 = 25;
+
+/// Terminal from which keys are read one at a time, without being echoed,
+/// until it's dropped.
+struct Tty {
+    file: File,
+    /// settings of the terminal beforehand, restored when dropped
+    saved: libc::termios,
+}
+
+impl Tty {
+    /// Opens the terminal from which keys are read and stops it from
+    /// buffering lines and echoing keys.
+    fn open() -> Result<Tty, String> {
+        let file = terminal::open_tty()?;
+        let fd = file.as_raw_fd();
+        let unusable = |err: io::Error| format!("can't set up the terminal: {}", err);
+
+        let mut saved: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(unusable(io::Error::last_os_error()));
+        }
+
+        let tty = Tty { file: file, saved: saved };
+        tty.set_cooked(false).map_err(unusable)?;
+        Ok(tty)
+    }
+
+    /// Restores the settings of the terminal if `cooked`, so a line may be
+    /// typed and edited as usual, or else stops it from buffering lines and
+    /// echoing keys.
+    fn set_cooked(&self, cooked: bool) -> io::Result<()> {
+        let mut termios = self.saved;
+        if !cooked {
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+        }
+
+        match unsafe { libc::tcsetattr(self.file.as_raw_fd(), libc::TCSANOW, &termios) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Returns the `(width, height)` of the terminal, if known.
+    fn size(&self) -> Option<(usize, usize)> {
+        let mut size: libc::winsize = unsafe { mem::zeroed() };
+        match unsafe { libc::ioctl(self.file.as_raw_fd(), libc::TIOCGWINSZ, &mut size) } {
+            0 if size.ws_row > 0 => Some((size.ws_col as usize, size.ws_row as usize)),
+            _ => None,
+        }
+    }
+
+    /// Waits for a keypress, returning its code as `getch()` would, or `None`
+    /// for an escape sequence which isn't understood.
+    fn read_key(&mut self) -> io::Result<Option<i32>> {
+        let byte = self.read_byte()?;
+        if byte != ESCAPE {
+            return Ok(Some(byte as i32));
+        }
+
+        let mut sequence = Vec::new();
+        while sequence.len() < 4 && self.is_readable(ESCAPE_DELAY_MS) {
+            let byte = self.read_byte()?;
+            sequence.push(byte);
+            if sequence.len() > 1 && (byte.is_ascii_alphabetic() || byte == b'~') {
+                break;
+            }
+        }
+
+        if sequence.is_empty() {
+            return Ok(Some(ESCAPE as i32));
+        }
+        Ok(decode_escape(&sequence))
+    }
+
+    fn read_byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0];
+        self.file.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    /// Returns `true` if a byte may be read within `millis` milliseconds.
+    fn is_readable(&self, millis: i32) -> bool {
+        let mut poll_fd = libc::pollfd {
+            fd: self.file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut poll_fd, 1, millis) > 0 }
+    }
+
+    /// Reads a line typed after `prompt`, or `None` if the line is empty.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+
+        self.set_cooked(true)?;
+        let mut line = String::new();
+        let result = BufReader::new(&self.file).read_line(&mut line);
+        self.set_cooked(false)?;
+        result?;
+
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+        if line.chars().count() > line_edit::MAX_CHARS {
+            println!("Input truncated to {} characters", line_edit::MAX_CHARS);
+            return Ok(Some(line.chars().take(line_edit::MAX_CHARS).collect()));
+        }
+        Ok(Some(line.to_owned()).filter(|line| !line.is_empty()))
+    }
+}
+
+impl Drop for Tty {
+    fn drop(&mut self) {
+        let _ = self.set_cooked(true);
+    }
+}
+
+/// Returns the code of the key sending `ESC` followed by `sequence`, as
+/// `getch()` would return it.
+fn decode_escape(sequence: &[u8]) -> Option<i32> {
+    let key = match sequence {
+        b"[A" | b"OA" => KEY_UP,
+        b"[B" | b"OB" => KEY_DOWN,
+        b"[C" | b"OC" => KEY_RIGHT,
+        b"[D" | b"OD" => KEY_LEFT,
+        b"[H" | b"OH" | b"[1~" => KEY_HOME,
+        b"[F" | b"OF" | b"[4~" => KEY_END,
+        b"[5~" => KEY_PPAGE,
+        b"[6~" => KEY_NPAGE,
+        b"[Z" => KEY_BTAB,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Returns a line as written in plain output: its number, whether it's a
+/// match, and its text.
+fn format_line(line: &FilteredLine) -> String {
+    match *line {
+        FilteredLine::Gap { .. } => {
+            format!("({})", text::describe_skipped(line.skipped().unwrap_or(0)))
+        },
+        FilteredLine::MatchLine((line_num, ref text)) => format!("{} match: {}", line_num, text),
+        FilteredLine::ContextLine((line_num, ref text)) |
+        FilteredLine::UnfilteredLine((line_num, ref text)) => format!("{}: {}", line_num, text),
+    }
+}
+
+/// Lines of the input written to stdout as they're paged through.
+struct PlainPager<T: Iterator<Item=String>> {
+    window_buffer: WindowBuffer<T>,
+    height: usize,
+    /// how filter strings are interpreted
+    matcher_kind: MatcherKind,
+    /// how filter strings are compared with lines
+    match_options: MatchOptions,
+    /// whether `(END)` was written since the window last moved
+    end_shown: bool,
+}
+
+impl<T: Iterator<Item=String>> PlainPager<T> {
+    /// Writes lines which followed those written last, then `(END)` if no
+    /// more lines follow.
+    fn write_next(&mut self, lines: &[FilteredLine]) {
+        for line in lines {
+            println!("{}", format_line(line));
+        }
+
+        if !lines.is_empty() {
+            self.end_shown = false;
+        }
+        if self.window_buffer.is_exhausted() && !self.end_shown {
+            println!("(END)");
+            self.end_shown = true;
+        }
+    }
+
+    /// Writes the page now in the window, introduced by `heading`, as after
+    /// moving back or jumping.
+    fn write_page(&mut self, heading: &str) {
+        let lines = self.window_buffer.current_page();
+        match self.window_buffer.top_line_num() {
+            Some(line_num) => println!("-- {} from line {} --", heading, line_num),
+            None => println!("-- {}: no lines --", heading),
+        }
+        self.end_shown = false;
+        self.write_next(&lines);
+    }
+
+    /// Filters by `filter`, or removes the filter if it's `None`, waiting for
+    /// the input to be scanned for matches.
+    fn filter(&mut self, filter: Option<String>) -> Result<(), String> {
+        let predicate = match filter {
+            Some(ref filter) => Some(FilterPredicate::with_kind(
+                    &self.matcher_kind, filter, &self.match_options, CONTEXT_LINES)
+                .map_err(|err| format!("invalid filter `{}`: {}", filter, err))?),
+            None => None,
+        };
+
+        self.window_buffer.set_predicate(predicate);
+        while self.window_buffer.scan_progress().is_some() {
+            thread::sleep(Duration::from_millis(50));
+        }
+        Ok(())
+    }
+}
+
+/// Pages through `input` in plain output mode until the user quits.
+///
+/// ### Parameters
+/// * `input`: lines of the input
+/// * `predicate`: the filter given on the CLI, if any
+/// * `matcher_kind`: how filter strings typed are interpreted
+/// * `match_options`: how filter strings typed are compared with lines
+/// * `keys`: the key bindings
+pub fn run<T: Iterator<Item=String>>(input: Input<T>, predicate: Option<FilterPredicate>,
+                                     matcher_kind: MatcherKind, match_options: MatchOptions,
+                                     keys: &Keymap) -> Result<(), String> {
+    let mut tty = Tty::open()?;
+    let (width, height) = tty.size()
+        .map(|(width, height)| (width, height.saturating_sub(1).max(1)))
+        .unwrap_or((80, DEFAULT_HEIGHT));
+
+    let mut pager = PlainPager {
+        window_buffer: WindowBuffer::new(input, None, width, height),
+        height: height,
+        matcher_kind: matcher_kind,
+        match_options: match_options,
+        end_shown: false,
+    };
+    if let Some(predicate) = predicate {
+        pager.window_buffer.set_predicate(Some(predicate));
+        while pager.window_buffer.scan_progress().is_some() {
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    let lines = pager.window_buffer.next_page();
+    pager.write_next(&lines);
+
+    // number typed ahead of a command, e.g. the 5 of `5j`
+    let mut count: Option<usize> = None;
+
+    loop {
+        io::stdout().flush().map_err(|err| err.to_string())?;
+        let key = match tty.read_key().map_err(|err| format!("/dev/tty: {}", err))? {
+            Some(key) => key,
+            None => continue,
+        };
+
+        if (DIGIT_0..=DIGIT_9).contains(&key) {
+            let digit = (key - DIGIT_0) as usize;
+            count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+            continue;
+        }
+        let prefix = count.take();
+        let step = prefix.unwrap_or(pager.height / 2).max(1);
+
+        match keys.action(key) {
+            Some(Action::NextLine) => {
+                let lines = pager.window_buffer.next_lines(prefix.unwrap_or(1));
+                pager.write_next(&lines);
+            },
+            Some(Action::NextPage) => {
+                let lines = pager.window_buffer.next_page();
+                pager.write_next(&lines);
+            },
+            Some(Action::ScrollDown) => {
+                let lines = pager.window_buffer.next_lines(step);
+                pager.write_next(&lines);
+            },
+            Some(Action::PrevLine) => {
+                pager.window_buffer.prev_lines(prefix.unwrap_or(1));
+                pager.write_page("Back");
+            },
+            Some(Action::PrevPage) => {
+                pager.window_buffer.prev_page();
+                pager.write_page("Back");
+            },
+            Some(Action::ScrollUp) => {
+                pager.window_buffer.prev_lines(step);
+                pager.write_page("Back");
+            },
+            Some(Action::GotoLine) => {
+                match prefix {
+                    Some(line_num) => pager.window_buffer.goto_line(line_num.max(1)),
+                    None => pager.window_buffer.last_page(),
+                };
+                pager.write_page("Jumped");
+            },
+            Some(Action::GotoPercent) => {
+                let percent = prefix.unwrap_or(0).min(100);
+                pager.window_buffer.goto_fraction(percent as f64 / 100.0);
+                pager.write_page("Jumped");
+            },
+            Some(Action::Filter) => {
+                let filter = tty.read_line("Filter: ").map_err(|err| format!("/dev/tty: {}", err))?;
+                match pager.filter(filter) {
+                    Ok(_) => pager.write_page("Filtered"),
+                    Err(err) => println!("{}", err),
+                }
+            },
+            Some(Action::ClearFilter) => {
+                pager.filter(None)?;
+                pager.write_page("Filter cleared");
+            },
+            Some(Action::Quit) => break,
+            Some(_) => println!("Not available in plain mode"),
+            None => {},
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use ncurses::{KEY_NPAGE, KEY_UP};
+
+    use filterless::FilteredLine;
+
+    use super::{decode_escape, format_line};
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(format_line(&FilteredLine::MatchLine((12, "error".to_owned()))),
+                   "12 match: error");
+        assert_eq!(format_line(&FilteredLine::ContextLine((13, "".to_owned()))), "13: ");
+        assert_eq!(format_line(&FilteredLine::UnfilteredLine((1, "a".to_owned()))), "1: a");
+        assert_eq!(format_line(&FilteredLine::Gap { from: 2, to: 11 }), "(10 lines skipped)");
+
+        assert_eq!(decode_escape(b"[6~"), Some(KEY_NPAGE));
+        assert_eq!(decode_escape(b"OA"), Some(KEY_UP));
+        assert_eq!(decode_escape(b"[99~"), None);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+
+use config;
+
+
+/// Named filter templates which may contain positional placeholders.
+///
+/// A placeholder takes the form `%1` through `%9` and is replaced by the
+/// corresponding argument when the preset is expanded; `%%` yields a literal
+/// `%`.
+#[derive(Clone, Debug, Default)]
+pub struct Presets {
+    templates: BTreeMap<String, String>,
+}
+
+impl Presets {
+    /// Returns an empty set of presets.
+    pub fn new() -> Presets {
+        Presets::default()
+    }
+
+    /// Reads presets from a file.
+    ///
+    /// Each non-blank line not beginning with `#` takes the form
+    /// `name = "template"`, e.g. `req = "request_id=%1"`. Templates may
+    /// instead be single-quoted, in which case backslashes are never treated
+    /// as escapes. The quotes are optional unless the template has leading or
+    /// trailing whitespace.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the presets file
+    pub fn from_file(path: &str) -> Result<Presets, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut presets = Presets::new();
+
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", path, e))?;
+            presets.apply_line(&line)
+                .map_err(|e| format!("{}:{}: {}", path, i + 1, e))?;
+        }
+
+        Ok(presets)
+    }
+
+    /// Applies a single line of a presets file.
+    pub fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next()
+            .ok_or(format!("expected `name = \"template\"`; got `{}`", line))?
+            .trim();
+
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("invalid preset name `{}`", name));
+        }
+
+        let template = config::unquote(value);
+        self.insert(name.to_owned(), template.into_owned());
+        Ok(())
+    }
+
+    /// Adds a preset, replacing any existing preset of the same name.
+    pub fn insert(&mut self, name: String, template: String) {
+        self.templates.insert(name, template);
+    }
+
+    /// Returns an iterator over the names and templates of the presets,
+    /// ordered by name.
+    pub fn iter<'a>(&'a self) -> btree_map::Iter<'a, String, String> {
+        self.templates.iter()
+    }
+
+    /// Returns the template of the named preset with its placeholders
+    /// replaced by `args`.
+    ///
+    /// ### Parameters
+    /// * `name`: name of the preset
+    /// * `args`: values of the placeholders `%1`, `%2`, etc.
+    pub fn expand(&self, name: &str, args: &[&str]) -> Result<String, String> {
+        let template = self.templates.get(name)
+            .ok_or(format!("unknown preset `{}`", name))?;
+        expand(template, args)
+    }
+}
+
+/// Replaces the placeholders of `template` with `args`.
+fn expand(template: &str, args: &[&str]) -> Result<String, String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('%') => expanded.push('%'),
+            Some(d) if ('1'..='9').contains(&d) => {
+                let idx = d as usize - '1' as usize;
+                let arg = args.get(idx)
+                    .ok_or(format!("missing argument %{}", d))?;
+                expanded.push_str(arg);
+            },
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            },
+            None => expanded.push('%'),
+        }
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{expand, Presets};
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand("request_id=%1", &["abc123"]).unwrap(), "request_id=abc123");
+        assert_eq!(expand("%2 before %1", &["a", "b"]).unwrap(), "b before a");
+        assert_eq!(expand("100%% %1", &["done"]).unwrap(), "100% done");
+        assert_eq!(expand("50%x%", &[]).unwrap(), "50%x%");
+        assert!(expand("%1 %2", &["a"]).is_err());
+    }
+
+    #[test]
+    fn test_apply_line() {
+        let mut presets = Presets::new();
+
+        presets.apply_line("# comment").unwrap();
+        presets.apply_line("").unwrap();
+        presets.apply_line("req = \"request_id=%1\"").unwrap();
+        presets.apply_line("user=user %1 ").unwrap();
+
+        assert_eq!(presets.expand("req", &["abc123"]).unwrap(), "request_id=abc123");
+        assert_eq!(presets.expand("user", &["bob"]).unwrap(), "user bob");
+        assert!(presets.expand("bogus", &[]).is_err());
+
+        assert!(presets.apply_line("req").is_err());
+        assert!(presets.apply_line("two words = x").is_err());
+    }
+}
@@ -0,0 +1,43 @@
+//! The guarantee of `--read-only` that nothing is written to the file
+//! system, e.g. on a system under forensic examination: no session, export,
+//! mirror, configuration file or control socket is ever created.
+//!
+//! Everything which writes to the file system consults the switch here
+//! before doing so, and fails if it's on.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the guarantee is on.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+
+/// Turns the guarantee on for the rest of the process.
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Returns `true` if the guarantee is on.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Returns an error if the guarantee is on; to be called before writing
+/// anything to the file system.
+pub fn check() -> io::Result<()> {
+    if is_enabled() {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                  "not written, as filterless is read-only (--read-only)"));
+    }
+    Ok(())
+}
+
+/// Creates or truncates the file at `path` for writing, as `File::create()`
+/// does, unless the guarantee is on.
+pub fn create<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    check()?;
+    File::create(path)
+}
+
@@ -0,0 +1,308 @@
+//! Scheduling of the updates of the terminal, which are limited to one a
+//! frame, and the profile of the time they take shown with `--profile-render`.
+//!
+//! Windows are drawn with `ncurses::wnoutrefresh()`, which only updates
+//! ncurses' idea of the screen; the main loop calls `Scheduler::frame()` once
+//! it's handled a key, which sends the changes to the terminal once per frame
+//! at most, so that keys held down or lines streaming in are drawn in as few
+//! updates as the terminal can show. Prompts refresh their windows at once, and
+//! with them whatever was drawn beforehand.
+//!
+//! The time spent on a frame is split into that spent classifying lines, i.e.
+//! filtering them and finding the spans of their matches, syntax and highlights
+//! to draw, that spent drawing them into windows, and that spent flushing the
+//! windows to the terminal, timed with `timer()` as it's spent.
+
+use std::cmp::max;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ncurses;
+
+/// Time allotted to each frame, of 60 a second; a frame is sent once this much
+/// time has passed since the last, and the work for it is slow if it takes
+/// longer.
+pub const FRAME_BUDGET: Duration = Duration::from_millis(16);
+
+/// Period over which the profile shown is measured.
+const PROFILE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Whether the time spent classifying and drawing lines is measured.
+static PROFILING: AtomicBool = AtomicBool::new(false);
+
+/// Time spent classifying and drawing lines since the profile was last shown.
+static TIMING: Mutex<Timing> = Mutex::new(Timing {
+    spent: Spent {
+        classify: Duration::from_secs(0),
+        draw: Duration::from_secs(0),
+    },
+    running: None,
+});
+
+
+/// Kind of work timed by a `Timer`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    /// filtering lines and finding the spans of their matches, syntax and
+    /// highlights
+    Classify,
+    /// drawing lines into windows, less the classification of their text
+    Draw,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Spent {
+    classify: Duration,
+    draw: Duration,
+}
+
+impl Spent {
+    fn add(&mut self, phase: Phase, time: Duration) {
+        match phase {
+            Phase::Classify => self.classify += time,
+            Phase::Draw => self.draw += time,
+        }
+    }
+}
+
+struct Timing {
+    spent: Spent,
+    /// phase of the innermost timer running, and when it began or resumed
+    running: Option<(Phase, Instant)>,
+}
+
+/// Times the work it's created for until it's dropped, adding the time to
+/// the profile if `--profile-render` is in use.
+///
+/// Timers nest: the time of an inner timer, e.g. classifying the text of a
+/// line as it's drawn, is left out of that of the outer.
+pub struct Timer {
+    profiled: bool,
+    /// phase of the timer this one paused, if any
+    outer: Option<Phase>,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if !self.profiled {
+            return;
+        }
+
+        let mut timing = TIMING.lock().expect("render profile lock poisoned");
+        let now = Instant::now();
+        if let Some((phase, started)) = timing.running.take() {
+            timing.spent.add(phase, now.duration_since(started));
+        }
+        timing.running = self.outer.map(|outer| (outer, now));
+    }
+}
+
+/// Returns a `Timer` timing the work of `phase` which follows, until the end
+/// of the scope it's bound in, e.g. `let _timer = render::timer(Phase::Draw);`.
+pub fn timer(phase: Phase) -> Timer {
+    if !PROFILING.load(Ordering::Relaxed) {
+        return Timer { profiled: false, outer: None };
+    }
+
+    let mut timing = TIMING.lock().expect("render profile lock poisoned");
+    let now = Instant::now();
+    let outer = timing.running.take().map(|(outer, started)| {
+        timing.spent.add(outer, now.duration_since(started));
+        outer
+    });
+    timing.running = Some((phase, now));
+    Timer { profiled: true, outer: outer }
+}
+
+/// Profile of the frames sent over the current period, shown in the top right
+/// corner of the screen as of the last period.
+struct Profile {
+    /// when the current period began
+    since: Instant,
+    frames: u32,
+    /// frames the work for which exceeded `FRAME_BUDGET`
+    slow: u32,
+    flush: Duration,
+    /// profile of the last period
+    shown: String,
+    /// window showing `shown`, and the width of the screen it was placed in
+    overlay: Option<(ncurses::WINDOW, i32)>,
+}
+
+impl Profile {
+    /// Counts a frame which took `work` to prepare and `flush` to send.
+    fn count(&mut self, work: Duration, flush: Duration) {
+        self.frames += 1;
+        self.flush += flush;
+        if work > FRAME_BUDGET {
+            self.slow += 1;
+        }
+    }
+
+    /// Describes the current period in place of the last once it's over, and
+    /// begins the next.
+    fn roll(&mut self) {
+        let period = self.since.elapsed();
+        if period < PROFILE_PERIOD {
+            return;
+        }
+
+        let spent = {
+            let mut timing = TIMING.lock().expect("render profile lock poisoned");
+            let taken = timing.spent;
+            timing.spent = Spent::default();
+            taken
+        };
+        self.shown = describe(period, self.frames, spent, self.flush, self.slow);
+        self.since = Instant::now();
+        self.frames = 0;
+        self.slow = 0;
+        self.flush = Duration::from_secs(0);
+    }
+
+    /// Draws the profile shown over the top right corner of the screen, placing
+    /// its window anew if the profile's width or that of the screen changed.
+    fn draw(&mut self) {
+        let cols = ncurses::COLS();
+        let width = self.shown.len() as i32 + 2;
+        match self.overlay {
+            Some((window, placed)) if placed == cols && ncurses::getmaxx(window) == width => {},
+            _ => self.place(),
+        }
+
+        if let Some((window, _)) = self.overlay {
+            ncurses::werase(window);
+            ncurses::waddstr(window, &format!(" {} ", self.shown));
+            ncurses::touchwin(window);
+            ncurses::wnoutrefresh(window);
+        }
+    }
+
+    fn place(&mut self) {
+        if let Some((window, _)) = self.overlay.take() {
+            ncurses::delwin(window);
+        }
+
+        let cols = ncurses::COLS();
+        let width = self.shown.len() as i32 + 2;
+        if self.shown.is_empty() || width > cols {
+            return;
+        }
+        let window = ncurses::newwin(1, width, 0, cols - width);
+        ncurses::wbkgd(window, ncurses::A_REVERSE());
+        // the terminal's cursor is left where the pager placed it
+        ncurses::leaveok(window, true);
+        self.overlay = Some((window, cols));
+    }
+}
+
+/// Describes the profile of the frames sent over `period`, giving the time
+/// spent on each on average, e.g. `60 fps  classify 1.2ms  draw 0.4ms  flush
+/// 0.3ms  2 slow`.
+fn describe(period: Duration, frames: u32, spent: Spent, flush: Duration, slow: u32) -> String {
+    let per_frame = |time: Duration| {
+        format!("{:.1}ms", time.as_secs_f64() * 1000.0 / f64::from(max(frames, 1)))
+    };
+    let fps = f64::from(frames) / period.as_secs_f64();
+
+    format!("{:.0} fps  classify {}  draw {}  flush {}  {} slow",
+            fps, per_frame(spent.classify), per_frame(spent.draw), per_frame(flush), slow)
+}
+
+/// Sends the changes drawn to the terminal once per frame.
+pub struct Scheduler {
+    /// when the last frame was sent
+    last_frame: Instant,
+    /// when the work in hand began, e.g. handling a key, if any
+    work_started: Option<Instant>,
+    /// time spent on work for the next frame so far, while handling the keys
+    /// pressed since the last
+    work: Duration,
+    /// profile of the frames sent, if `--profile-render` is in use
+    profile: Option<Profile>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler, which profiles the frames it sends if `profile`
+    /// is `true`.
+    pub fn new(profile: bool) -> Scheduler {
+        PROFILING.store(profile, Ordering::Relaxed);
+        let now = Instant::now();
+        let profile = if profile {
+            Some(Profile {
+                since: now,
+                frames: 0,
+                slow: 0,
+                flush: Duration::from_secs(0),
+                shown: String::new(),
+                overlay: None,
+            })
+        } else {
+            None
+        };
+
+        Scheduler {
+            last_frame: now - FRAME_BUDGET,
+            work_started: None,
+            work: Duration::from_secs(0),
+            profile: profile,
+        }
+    }
+
+    /// Notes that work for the next frame begins, e.g. as a key is handled,
+    /// which lasts until `frame()` is called.
+    pub fn begin(&mut self) {
+        self.work_started = Some(Instant::now());
+    }
+
+    /// Sends the changes drawn since the last frame to the terminal if a frame
+    /// is due, and returns `None`; or returns the milliseconds until one is,
+    /// after which this is to be called again.
+    pub fn frame(&mut self) -> Option<i32> {
+        if let Some(work_started) = self.work_started.take() {
+            self.work += work_started.elapsed();
+        }
+
+        let since_last = self.last_frame.elapsed();
+        if since_last < FRAME_BUDGET {
+            let wait = (FRAME_BUDGET - since_last).as_millis() as i32;
+            return Some(max(wait, 1));
+        }
+
+        let started = Instant::now();
+        if let Some(ref mut profile) = self.profile {
+            profile.roll();
+            profile.draw();
+        }
+        ncurses::doupdate();
+        self.last_frame = Instant::now();
+
+        let flush = self.last_frame.duration_since(started);
+        if let Some(ref mut profile) = self.profile {
+            profile.count(self.work + flush, flush);
+        }
+        self.work = Duration::from_secs(0);
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{describe, Spent};
+
+    #[test]
+    fn test_describe() {
+        let spent = Spent {
+            classify: Duration::from_millis(60),
+            draw: Duration::from_millis(24),
+        };
+        assert_eq!(describe(Duration::from_secs(2), 120, spent, Duration::from_millis(36), 3),
+                   "60 fps  classify 0.5ms  draw 0.2ms  flush 0.3ms  3 slow");
+
+        assert_eq!(describe(Duration::from_secs(1), 0, Spent::default(), Duration::from_secs(0), 0),
+                   "0 fps  classify 0.0ms  draw 0.0ms  flush 0.0ms  0 slow");
+    }
+}
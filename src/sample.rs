@@ -0,0 +1,266 @@
+//! Sampling of the lines at either end of a file, for a quick look at a file
+//! too big to read through.
+
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+use filterless::{LineSource, VecSource};
+use memchr::memrchr;
+
+
+/// Number of bytes read at a time while searching backward from the end of
+/// a file for the beginning of its last lines.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Numbers of lines to read from the start and end of a file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// number of lines at the start of the file
+    pub head: usize,
+    /// number of lines at the end of the file
+    pub tail: usize,
+}
+
+/// Lines sampled from a file, which are seekable.
+///
+/// Unless the two ends overlap, the lines of the head are separated from
+/// those of the tail by a line noting the number of bytes skipped, e.g. `510
+/// bytes skipped`, which stands for the lines skipped, and is shown as a gap.
+pub struct SampleSource {
+    lines: VecSource,
+    /// index of the line noting the bytes skipped, if any
+    omitted: Option<usize>,
+}
+
+impl Sample {
+    /// Parses a comma-separated list of `head:N` and `tail:N`, e.g.
+    /// `head:100,tail:100`; an end which isn't listed isn't sampled.
+    pub fn parse(spec: &str) -> Result<Sample, String> {
+        let mut sample = Sample { head: 0, tail: 0 };
+
+        for part in spec.split(',') {
+            let mut fields = part.splitn(2, ':');
+            let end = fields.next().unwrap_or("").trim();
+            let count = fields.next()
+                .and_then(|count| count.trim().parse().ok())
+                .ok_or(format!("expected `head:N` or `tail:N`; got `{}`", part))?;
+
+            match end {
+                "head" => sample.head = count,
+                "tail" => sample.tail = count,
+                _ => return Err(format!("expected `head` or `tail`; got `{}`", end)),
+            }
+        }
+
+        Ok(sample)
+    }
+
+    /// Reads the sampled lines of `file`.
+    ///
+    /// The tail is found by reading backward from the end of the file, so
+    /// only the sampled lines are read.
+    pub fn read<R: Read + Seek>(&self, file: R) -> io::Result<SampleSource> {
+        let mut reader = BufReader::new(file);
+        let mut lines = Vec::new();
+        let mut head_end = 0;
+        let mut buf = Vec::new();
+
+        while lines.len() < self.head {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                return Ok(SampleSource { lines: VecSource::from(lines), omitted: None });
+            }
+            head_end += read;
+            lines.push(decode(&buf));
+        }
+
+        let mut file = reader.into_inner();
+        let len = file.seek(SeekFrom::End(0))? as usize;
+        let (tail_start, tail) = read_tail(&mut file, len, head_end, self.tail)?;
+
+        let mut omitted = None;
+        if tail_start > head_end {
+            omitted = Some(lines.len());
+            lines.push(format!("{} bytes skipped", tail_start - head_end));
+        }
+        lines.extend(tail);
+        Ok(SampleSource { lines: VecSource::from(lines), omitted: omitted })
+    }
+}
+
+impl Iterator for SampleSource {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        self.lines.next()
+    }
+}
+
+impl LineSource for SampleSource {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn read_line(&mut self, idx: usize) -> Option<io::Result<String>> {
+        self.lines.read_line(idx)
+    }
+
+    fn omitted(&self) -> Option<usize> {
+        self.omitted
+    }
+}
+
+/// Reads the last `count` lines of `file`, which is `len` bytes long,
+/// without reading before byte `floor`.
+///
+/// Returns the byte offset at which the lines begin, along with the lines.
+fn read_tail<R: Read + Seek>(file: &mut R, len: usize, floor: usize, count: usize)
+    -> io::Result<(usize, Vec<String>)> {
+
+    if count == 0 || len <= floor {
+        return Ok((len, Vec::new()));
+    }
+
+    // bytes from `start` to the end of the file, less the final line ending
+    let mut tail = Vec::new();
+    let mut start = len;
+    let mut end = len;
+
+    loop {
+        let read_from = start.saturating_sub(CHUNK_BYTES).max(floor);
+        let mut chunk = vec![0; start - read_from];
+        file.seek(SeekFrom::Start(read_from as u64))?;
+        file.read_exact(&mut chunk)?;
+
+        if end == len && chunk.last() == Some(&b'\n') {
+            chunk.pop();
+            end -= 1;
+        }
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+        start = read_from;
+
+        // search for the line ending preceding the `count`th last line
+        let mut found = 0;
+        let mut search = &tail[..];
+        while found < count {
+            match memrchr(b'\n', search) {
+                Some(pos) => {
+                    found += 1;
+                    search = &search[..pos];
+                },
+                None => break,
+            }
+        }
+
+        if found == count {
+            let skip = search.len() + 1;
+            return Ok((start + skip, split_lines(&tail[skip..])));
+        }
+
+        if start == floor {
+            return Ok((start, split_lines(&tail)));
+        }
+    }
+}
+
+/// Decodes the lines of `bytes`, which don't end with a line ending.
+fn split_lines(bytes: &[u8]) -> Vec<String> {
+    bytes.split(|&b| b == b'\n').map(decode).collect()
+}
+
+/// Decodes a line, less its line ending.
+pub fn decode(line: &[u8]) -> String {
+    let mut line = line;
+    if line.last() == Some(&b'\n') {
+        line = &line[..line.len() - 1];
+    }
+    if line.last() == Some(&b'\r') {
+        line = &line[..line.len() - 1];
+    }
+    String::from_utf8_lossy(line).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use filterless::{FilteredLine, FilterPredicate, Input, LineSource, WindowBuffer};
+
+    use super::Sample;
+
+    type Lines = ::std::vec::IntoIter<String>;
+
+    /// Returns the lines sampled from `bytes`, along with the index of the
+    /// line noting the bytes skipped, if any.
+    fn read(sample: Sample, bytes: &[u8]) -> (Vec<String>, Option<usize>) {
+        let source = sample.read(Cursor::new(bytes)).unwrap();
+        let omitted = source.omitted();
+        (source.map(Result::unwrap).collect(), omitted)
+    }
+
+    #[test]
+    fn test_sample() {
+        assert_eq!(Sample::parse("head:100,tail:20"), Ok(Sample { head: 100, tail: 20 }));
+        assert_eq!(Sample::parse("tail:5"), Ok(Sample { head: 0, tail: 5 }));
+        assert!(Sample::parse("middle:5").is_err());
+        assert!(Sample::parse("head").is_err());
+
+        let text: String = (1..1001).map(|i| format!("line {}\n", i)).collect();
+        assert_eq!(read(Sample { head: 2, tail: 3 }, text.as_bytes()), (vec![
+            "line 1".to_owned(), "line 2".to_owned(), "8851 bytes skipped".to_owned(),
+            "line 998".to_owned(), "line 999".to_owned(), "line 1000".to_owned(),
+        ], Some(2)));
+
+        // a tail spanning several chunks, without a final line ending
+        let sample = Sample { head: 0, tail: 9000 };
+        let (lines, omitted) = read(sample, text.trim_end().repeat(100).as_bytes());
+        assert_eq!(lines.len(), 9001);
+        assert_eq!(omitted, Some(0));
+        assert_eq!(lines[0], "809091 bytes skipped");
+        assert_eq!(lines[1], "line 992");
+        assert_eq!(lines[9], "line 1000line 1");
+        assert_eq!(lines[9000], "line 1000");
+
+        // overlapping ends
+        let sample = Sample { head: 2, tail: 3 };
+        assert_eq!(read(sample, b"a\r\nb\nc\nd\n"),
+                   (vec!["a".to_owned(), "b".to_owned(), "c".to_owned(), "d".to_owned()], None));
+        assert_eq!(read(sample, b"a\nb"), (vec!["a".to_owned(), "b".to_owned()], None));
+        assert_eq!(read(Sample { head: 1, tail: 0 }, b"a\nb\n"),
+                   (vec!["a".to_owned(), "2 bytes skipped".to_owned()], Some(1)));
+    }
+
+    #[test]
+    fn test_sample_gap() {
+        let text: String = (1..1001).map(|i| format!("line {}\n", i)).collect();
+        let sample = Sample { head: 2, tail: 2 };
+        let source = || sample.read(Cursor::new(text.as_bytes().to_vec())).unwrap();
+
+        // the line noting the bytes skipped is shown as a gap
+        let mut window_buffer = WindowBuffer::new(Input::<Lines>::source(source()), None, 80, 10);
+        assert_eq!(window_buffer.all_lines().iter().cloned().collect::<Vec<_>>(), vec![
+            FilteredLine::UnfilteredLine((1, "line 1".to_owned())),
+            FilteredLine::UnfilteredLine((2, "line 2".to_owned())),
+            FilteredLine::Gap { from: 3, to: 3 },
+            FilteredLine::UnfilteredLine((4, "line 999".to_owned())),
+            FilteredLine::UnfilteredLine((5, "line 1000".to_owned())),
+        ]);
+        assert_eq!(window_buffer.omission(3, 3), Some("8860 bytes skipped".to_owned()));
+        assert_eq!(window_buffer.get_raw_line(3), None);
+
+        // and never matched
+        let predicate = FilterPredicate::new("line 1".to_owned(), 0);
+        let mut window_buffer = WindowBuffer::new(Input::<Lines>::source(source()), Some(predicate),
+                                                  80, 10);
+        assert_eq!(window_buffer.all_lines().iter().cloned().collect::<Vec<_>>(), vec![
+            FilteredLine::MatchLine((1, "line 1".to_owned())),
+            FilteredLine::Gap { from: 2, to: 4 },
+            FilteredLine::MatchLine((5, "line 1000".to_owned())),
+        ]);
+        let predicate = FilterPredicate::new("bytes".to_owned(), 0);
+        let mut window_buffer = WindowBuffer::new(Input::<Lines>::source(source()), Some(predicate),
+                                                  80, 10);
+        assert!(window_buffer.all_lines().iter().all(FilteredLine::is_gap));
+    }
+}
@@ -0,0 +1,328 @@
+//! State of a session paging a file, saved so that it may be restored by a
+//! later session with `--restore`: the position within the file, the filter
+//! and the marks set.
+//!
+//! A session saved while it was running, which didn't go on to save itself
+//! on quitting, ended abnormally, e.g. by being killed; a later session
+//! offers to restore it.
+//!
+//! A session may also be saved to a file named with `--save-session` and
+//! restored from one with `--restore-session`, e.g. to keep several sessions
+//! investigating the same file, or one paging stdin.
+//!
+//! Each file's session is saved in a file of its own, named after a hash of
+//! the file's path, in the same subset of TOML as configuration files:
+//!
+//! ```text
+//! input = '/var/log/syslog'
+//! pid = 4242
+//! ended = false
+//! top_line = 120
+//! matcher = 'regex'
+//! ignore_case = true
+//! ignore_accents = false
+//! pattern = 'error|warn'
+//! mark = 'a 0 57'
+//! ```
+
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use filterless::{matcher, FilterSource, LineRange, MatchOptions};
+use libc;
+
+use config::{quote, unquote};
+use read_only;
+
+
+/// State of the session paging a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    /// absolute path of the file paged
+    pub input: PathBuf,
+    /// ID of the process which saved the session, if known
+    pub pid: Option<u32>,
+    /// whether the session was saved on quitting, rather than while running
+    pub ended: bool,
+    /// number of the line at the top of the window
+    pub top_line: Option<usize>,
+    /// filter in use, if any
+    pub filter: Option<FilterSource>,
+    /// name, tab and line number of each mark, in order of their names
+    pub marks: Vec<(char, usize, usize)>,
+}
+
+impl Session {
+    /// Creates the state of a session of this process which has yet to move,
+    /// filter or mark anything.
+    pub fn new(input: PathBuf) -> Session {
+        Session {
+            input: input,
+            pid: Some(process::id()),
+            ended: false,
+            top_line: None,
+            filter: None,
+            marks: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the session ended abnormally: it was saved while
+    /// running and its process is no longer running.
+    pub fn is_stale(&self) -> bool {
+        !self.ended && !self.pid.is_some_and(is_running)
+    }
+
+    /// Returns the path of the file in which the session paging `input` is
+    /// saved: a file within `filterless/sessions` in `$XDG_STATE_HOME`, or
+    /// else in `~/.local/state`.
+    ///
+    /// ### Parameters
+    /// * `input`: absolute path of the file paged
+    pub fn path_for(input: &Path) -> Option<PathBuf> {
+        env::var_os("XDG_STATE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+            })
+            .map(|dir| {
+                let name = format!("{:016x}", fnv1a(input.to_string_lossy().as_bytes()));
+                dir.join("filterless").join("sessions").join(name)
+            })
+    }
+
+    /// Reads the session saved for `input`, if any.
+    ///
+    /// ### Parameters
+    /// * `input`: absolute path of the file paged
+    pub fn load(input: &Path) -> Result<Option<Session>, String> {
+        let path = match Session::path_for(input) {
+            Some(ref path) if path.is_file() => path.clone(),
+            _ => return Ok(None),
+        };
+        let session = Session::read(&path)?;
+
+        // case: another file's path hashed to the same name
+        Ok(Some(session).filter(|session| session.input == input))
+    }
+
+    /// Reads a session saved in the file at `path`.
+    pub fn read(path: &Path) -> Result<Session, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        Session::parse(&text).map_err(|e| format!("{}:{}", path.display(), e))
+    }
+
+    /// Saves the session, replacing the one saved before for the same file.
+    pub fn save(&self) -> Result<(), String> {
+        let path = Session::path_for(&self.input)
+            .ok_or("neither $XDG_STATE_HOME nor $HOME is set".to_owned())?;
+        self.save_to(&path)
+    }
+
+    /// Saves the session in the file at `path`, replacing whatever it held.
+    ///
+    /// The session is written to a temporary file which then takes the place
+    /// of the old one, so a session is never left half written.
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+
+        read_only::check()
+            .and_then(|_| path.parent().map_or(Ok(()), fs::create_dir_all))
+            .and_then(|_| read_only::create(&temp_path))
+            .and_then(|mut file| write!(file, "{}", self))
+            .and_then(|_| fs::rename(&temp_path, path))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parses the text of a saved session.
+    fn parse(text: &str) -> Result<Session, String> {
+        let mut session = Session::new(PathBuf::new());
+        // sessions saved before these were recorded are taken to have ended
+        session.pid = None;
+        session.ended = true;
+        let mut kind = matcher::SUBSTRING.name.to_owned();
+        let mut options = MatchOptions::default();
+        let mut patterns = Vec::new();
+        let mut lines = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next()
+                .map(|value| unquote(value.trim()).into_owned())
+                .ok_or(format!("{}: expected `key = value`; got `{}`", i + 1, line))?;
+            let invalid = || format!("{}: invalid {} `{}`", i + 1, key, value);
+
+            match key {
+                "input" => session.input = PathBuf::from(&value),
+                "pid" => session.pid = Some(value.parse().map_err(|_| invalid())?),
+                "ended" => session.ended = value.parse().map_err(|_| invalid())?,
+                "top_line" => session.top_line = Some(value.parse().map_err(|_| invalid())?),
+                "matcher" => kind = value.clone(),
+                "ignore_case" => options.ignore_case = value.parse().map_err(|_| invalid())?,
+                "ignore_accents" => options.ignore_accents = value.parse().map_err(|_| invalid())?,
+                "pattern" => patterns.push(value.clone()),
+                "lines" => lines = Some(LineRange::parse(&value).map_err(|_| invalid())?),
+                "mark" => {
+                    let fields: Vec<&str> = value.split_whitespace().collect();
+                    let mark = match fields.as_slice() {
+                        &[name, tab, line_num] if name.chars().count() == 1 => {
+                            tab.parse().and_then(|tab| {
+                                line_num.parse().map(|line_num| {
+                                    (name.chars().next().unwrap(), tab, line_num)
+                                })
+                            }).map_err(|_| invalid())?
+                        },
+                        _ => return Err(invalid()),
+                    };
+                    session.marks.push(mark);
+                },
+                _ => return Err(format!("{}: unknown key `{}`", i + 1, key)),
+            }
+        }
+
+        if !patterns.is_empty() || lines.is_some() {
+            session.filter = Some(FilterSource {
+                kind: kind,
+                patterns: patterns,
+                options: options,
+                lines: lines,
+            });
+        }
+        Ok(session)
+    }
+}
+
+impl fmt::Display for Session {
+    /// Formats the session as the text of a saved session.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "input = {}", quote(&self.input.to_string_lossy()))?;
+        if let Some(pid) = self.pid {
+            writeln!(f, "pid = {}", pid)?;
+        }
+        writeln!(f, "ended = {}", self.ended)?;
+        if let Some(top_line) = self.top_line {
+            writeln!(f, "top_line = {}", top_line)?;
+        }
+        if let Some(ref filter) = self.filter {
+            writeln!(f, "matcher = {}", quote(&filter.kind))?;
+            writeln!(f, "ignore_case = {}", filter.options.ignore_case)?;
+            writeln!(f, "ignore_accents = {}", filter.options.ignore_accents)?;
+            for pattern in &filter.patterns {
+                writeln!(f, "pattern = {}", quote(pattern))?;
+            }
+            if let Some(lines) = filter.lines {
+                writeln!(f, "lines = '{}'", lines)?;
+            }
+        }
+        for &(mark, tab, line_num) in &self.marks {
+            writeln!(f, "mark = '{} {} {}'", mark, tab, line_num)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if a process with ID `pid` is running, even if it belongs to
+/// another user.
+fn is_running(pid: u32) -> bool {
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Returns the 64-bit FNV-1a hash of `bytes`, which unlike the hashers of the
+/// standard library is the same from one build to the next.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    use filterless::{FilterSource, LineRange, MatchOptions};
+
+    use super::{fnv1a, Session};
+
+    #[test]
+    fn test_round_trip() {
+        let mut session = Session::new(PathBuf::from("/var/log/it's.log"));
+        session.top_line = Some(120);
+        session.filter = Some(FilterSource {
+            kind: "regex".to_owned(),
+            patterns: vec!["error|warn".to_owned(), "say \"hi\"".to_owned()],
+            options: MatchOptions { ignore_case: true, ignore_accents: false },
+            lines: None,
+        });
+        session.marks = vec![('a', 0, 57), ('b', 1, 3)];
+
+        assert_eq!(Session::parse(&session.to_string()), Ok(session.clone()));
+
+        // a range of lines is kept with or without patterns
+        session.filter = Some(FilterSource {
+            kind: "substring".to_owned(),
+            patterns: Vec::new(),
+            options: MatchOptions::default(),
+            lines: Some(LineRange { first: 1000, last: 2000 }),
+        });
+        assert_eq!(Session::parse(&session.to_string()), Ok(session));
+        assert!(Session::parse("lines = '2,1'").is_err());
+
+        let parsed = Session::parse("input = '/a'\n").unwrap();
+        assert_eq!((parsed.input, parsed.pid, parsed.ended), (PathBuf::from("/a"), None, true));
+
+        assert!(Session::parse("top_line = -1").is_err());
+        assert!(Session::parse("mark = 'ab 0 1'").is_err());
+        assert!(Session::parse("mark = 'a 0'").is_err());
+        assert!(Session::parse("position = 5").is_err());
+    }
+
+    #[test]
+    fn test_save_to() {
+        let path = env::temp_dir().join(format!("filterless-{}.session", process::id()));
+        let mut session = Session::new(PathBuf::from("-"));
+        session.top_line = Some(7);
+        session.marks = vec![('a', 0, 3)];
+
+        session.save_to(&path).unwrap();
+        assert_eq!(Session::read(&path), Ok(session));
+        fs::remove_file(&path).unwrap();
+        assert!(Session::read(&path).is_err());
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let mut session = Session::new(PathBuf::from("/a"));
+        assert!(!session.is_stale());
+
+        session.pid = None;
+        assert!(session.is_stale());
+
+        session.ended = true;
+        assert!(!session.is_stale());
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
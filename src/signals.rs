@@ -0,0 +1,116 @@
+//! Handling of the signals which suspend or stop the pager, e.g. on Ctrl-Z
+//! or Ctrl-C, so that the terminal is left as the shell expects it.
+//!
+//! The signal handlers only note which signal was received; the main loop
+//! acts upon it once it gets round to it, as ncurses can't safely be called
+//! from a handler.
+
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use libc;
+use ncurses;
+
+/// Whether `SIGTSTP` was received since the pager was last suspended.
+static SUSPEND: AtomicBool = AtomicBool::new(false);
+
+/// Number of the signal which asked the pager to quit, or 0 if none has.
+static QUIT: AtomicI32 = AtomicI32::new(0);
+
+
+/// Handler of `SIGTSTP`, e.g. sent by the terminal on Ctrl-Z.
+extern "C" fn on_stop(_signal: libc::c_int) {
+    SUSPEND.store(true, Ordering::SeqCst);
+}
+
+/// Handler of `SIGINT` and `SIGTERM`.
+extern "C" fn on_quit(signal: libc::c_int) {
+    if QUIT.swap(signal, Ordering::SeqCst) != 0 {
+        // case: asked again, e.g. while the pager is busy reading its input,
+        // so a clean shutdown is given up on
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+}
+
+/// Catches `signal` with `handler`, unless it's ignored, e.g. `SIGTSTP` by
+/// a shell without job control or `SIGINT` by one running the pager in the
+/// background.
+fn catch(signal: libc::c_int, handler: extern "C" fn(libc::c_int)) {
+    let previous = unsafe { libc::signal(signal, handler as libc::sighandler_t) };
+    if previous == libc::SIG_IGN {
+        unsafe { libc::signal(signal, libc::SIG_IGN) };
+    }
+}
+
+/// Catches the signals which suspend or stop the pager, so that they're
+/// left to `suspend()` and the main loop.
+///
+/// Called before ncurses sets up the terminal, this also keeps ncurses from
+/// catching them itself.
+pub fn install() {
+    catch(libc::SIGTSTP, on_stop);
+    catch(libc::SIGINT, on_quit);
+    catch(libc::SIGTERM, on_quit);
+}
+
+/// Returns `true` if suspension was asked for since the last call.
+pub fn suspend_requested() -> bool {
+    SUSPEND.swap(false, Ordering::SeqCst)
+}
+
+/// Returns `true` if a signal has asked the pager to quit.
+pub fn quit_requested() -> bool {
+    QUIT.load(Ordering::SeqCst) != 0
+}
+
+/// Restores the terminal and stops the pager, returning once it's resumed,
+/// e.g. by `fg`, with the terminal taken back.
+///
+/// The screen isn't redrawn; a `KEY_RESIZE` is queued instead, so that the
+/// windows are redrawn at the terminal's size, which may have changed
+/// meanwhile.
+pub fn suspend() {
+    ncurses::endwin();
+    unsafe {
+        libc::signal(libc::SIGTSTP, libc::SIG_DFL);
+        libc::raise(libc::SIGTSTP);
+    }
+    catch(libc::SIGTSTP, on_stop);
+
+    ncurses::refresh();
+    ncurses::ungetch(ncurses::KEY_RESIZE);
+}
+
+/// Ends the program by the signal which asked it to quit, if any, so that
+/// whatever ran it sees how it ended; to be called once the terminal has
+/// been restored.
+pub fn reraise() {
+    let signal = QUIT.load(Ordering::SeqCst);
+    if signal != 0 {
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use libc;
+
+    use super::{catch, on_stop, suspend_requested};
+
+    #[test]
+    fn test_suspend_requested() {
+        catch(libc::SIGTSTP, on_stop);
+        assert!(!suspend_requested());
+
+        unsafe { libc::raise(libc::SIGTSTP) };
+        assert!(suspend_requested());
+        assert!(!suspend_requested());
+
+        unsafe { libc::signal(libc::SIGTSTP, libc::SIG_DFL) };
+    }
+}
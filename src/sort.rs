@@ -0,0 +1,176 @@
+//! Sorting of the lines shown by a column, for a preview of the page in that
+//! order which leaves the order of the input as it is, e.g. to see at a
+//! glance which request shown took longest.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use filterless::{parser, Format};
+
+
+/// Column by which lines are sorted.
+#[derive(Clone, Debug, PartialEq)]
+enum Column {
+    /// 1-indexed column of lines split by a delimiter, or by whitespace if
+    /// none is given
+    Index(usize, Option<String>),
+    /// dotted path of a field of lines parsed in a format, or in the first
+    /// format each line is in if none is given
+    Field(String, Option<Format>),
+}
+
+/// Column by which lines are sorted, and the direction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortKey {
+    column: Column,
+    descending: bool,
+}
+
+/// Value of a column, by which lines are ordered: numbers, ignoring any unit
+/// following them, e.g. the `ms` of `95ms`, before text.
+#[derive(Debug, PartialEq, PartialOrd)]
+enum Value<'a> {
+    Number(f64),
+    Text(&'a str),
+}
+
+impl SortKey {
+    /// Parses the column given to the `sort` command: the number of a column,
+    /// counted from 1, or else the dotted path of a field, preceded by `-` to
+    /// sort in descending order.
+    ///
+    /// ### Parameters
+    /// * `column`: the column, e.g. `3`, `-latency` or `-req.ms`
+    /// * `delimiter`: text between the columns of a line, if not whitespace
+    /// * `format`: format in which lines are parsed into fields, or `None`
+    ///   for the first format each line is in
+    pub fn parse(column: &str, delimiter: Option<&str>, format: Option<Format>)
+                 -> Result<SortKey, String> {
+        let (descending, name) = match column.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, column),
+        };
+
+        let column = match name.parse::<usize>() {
+            Ok(0) => return Err("columns are counted from 1".to_owned()),
+            Ok(index) => Column::Index(index, delimiter.map(str::to_owned)),
+            Err(_) if name.is_empty() => return Err(format!("invalid column `{}`", column)),
+            Err(_) if delimiter.is_some() => {
+                return Err("a delimiter is given with the number of a column".to_owned());
+            },
+            Err(_) => Column::Field(name.to_owned(), format),
+        };
+        Ok(SortKey { column: column, descending: descending })
+    }
+
+    /// Returns the text of this column of `line`, if it has the column.
+    fn text<'a>(&self, line: &'a str) -> Option<&'a str> {
+        match self.column {
+            Column::Index(index, Some(ref delimiter)) => line.split(delimiter.as_str())
+                .nth(index - 1)
+                .map(str::trim),
+            Column::Index(index, None) => line.split_whitespace().nth(index - 1),
+            Column::Field(ref path, format) => {
+                let structured = parser::parse(format, line)?;
+                let span = structured.get(path)?.span;
+                Some(&line[span.0..span.1])
+            },
+        }
+    }
+
+    /// Returns whether `line` has this column.
+    pub fn has_column(&self, line: &str) -> bool {
+        self.text(line).is_some()
+    }
+
+    /// Returns the order in which to show `lines` sorted by this column: the
+    /// indices of the lines, those lacking the column last, and lines whose
+    /// values are equal in the order they're given.
+    pub fn sort(&self, lines: &[&str]) -> Vec<usize> {
+        let values: Vec<Option<Value>> = lines.iter()
+            .map(|line| self.text(line).map(value))
+            .collect();
+
+        let mut order: Vec<usize> = (0..lines.len()).collect();
+        order.sort_by(|&a, &b| match (&values[a], &values[b]) {
+            (&Some(ref a), &Some(ref b)) if self.descending => compare(b, a),
+            (&Some(ref a), &Some(ref b)) => compare(a, b),
+            (&Some(_), &None) => Ordering::Less,
+            (&None, &Some(_)) => Ordering::Greater,
+            (&None, &None) => Ordering::Equal,
+        });
+        order
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.column {
+            Column::Index(index, _) => write!(f, "column {}", index)?,
+            Column::Field(ref path, _) => write!(f, "{}", path)?,
+        }
+        if self.descending {
+            write!(f, ", descending")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the value of the text of a column, a number if it begins with one.
+fn value(text: &str) -> Value<'_> {
+    let end = text.char_indices()
+        .find(|&(idx, c)| !(c.is_ascii_digit() || c == '.' || (idx == 0 && c == '-')))
+        .map_or(text.len(), |(idx, _)| idx);
+    match text[..end].parse::<f64>() {
+        Ok(number) => Value::Number(number),
+        Err(_) => Value::Text(text),
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod test {
+    use filterless::parser;
+
+    use super::{value, SortKey, Value};
+
+    #[test]
+    fn test_value() {
+        assert_eq!(value("95ms"), Value::Number(95.0));
+        assert_eq!(value("-1.5"), Value::Number(-1.5));
+        assert_eq!(value("GET"), Value::Text("GET"));
+        assert_eq!(value("-"), Value::Text("-"));
+        assert!(value("120") > value("95"));
+        assert!(value("GET") > value("95"));
+    }
+
+    #[test]
+    fn test_sort() {
+        let lines = ["GET /a 200 95ms", "GET /b 500 1200ms", "-", "POST /c 200 120ms"];
+        let key = SortKey::parse("4", None, None).unwrap();
+        assert_eq!(key.sort(&lines), vec![0, 3, 1, 2]);
+        let key = SortKey::parse("-4", None, None).unwrap();
+        assert_eq!(key.sort(&lines), vec![1, 3, 0, 2]);
+        assert_eq!(key.to_string(), "column 4, descending");
+
+        let lines = ["b,2", "a,10", "c"];
+        assert_eq!(SortKey::parse("2", Some(","), None).unwrap().sort(&lines), vec![0, 1, 2]);
+        assert_eq!(SortKey::parse("1", Some(","), None).unwrap().sort(&lines), vec![1, 0, 2]);
+
+        let lines = [r#"{"req": {"ms": 30}}"#, "not json", r#"{"req": {"ms": 4}}"#];
+        let key = SortKey::parse("-req.ms", None, None).unwrap();
+        assert_eq!(key.sort(&lines), vec![0, 2, 1]);
+        assert_eq!(key.to_string(), "req.ms, descending");
+
+        let lines = ["ms=30 path=/a", r#"{"ms": 1}"#, "ms=4 path=/b"];
+        let key = SortKey::parse("ms", None, Some(parser::LOGFMT)).unwrap();
+        assert_eq!(key.sort(&lines), vec![2, 0, 1]);
+
+        assert!(SortKey::parse("0", None, None).is_err());
+        assert!(SortKey::parse("-", None, None).is_err());
+        assert!(SortKey::parse("latency", Some(","), None).is_err());
+    }
+}
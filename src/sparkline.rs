@@ -0,0 +1,115 @@
+//! Sparkline of the recent values of a numeric field of the lines appended
+//! to a followed input, e.g. the latency of each request logged, shown in
+//! the status bar as a poor man's live monitor.
+
+use std::collections::VecDeque;
+
+use filterless::{MatchOptions, Matcher};
+
+/// Number of recent values drawn, one a column.
+const WIDTH: usize = 20;
+
+/// Bars drawing values from the least to the greatest of those drawn.
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+
+/// Recent values of the number matched by a regex within lines.
+pub struct Sparkline {
+    /// matcher of the text containing the number, e.g. `latency=\d+`
+    matcher: Matcher,
+    /// the values of the lines recorded, the latest last
+    values: VecDeque<f64>,
+}
+
+impl Sparkline {
+    /// Creates a sparkline of the values matched by a regex, or returns an
+    /// error if the regex is invalid.
+    ///
+    /// ### Parameters
+    /// * `pattern`: regex whose first match within a line contains the value,
+    ///   being the first number within the match, e.g. `latency=\d+ms`
+    pub fn new(pattern: &str) -> Result<Sparkline, String> {
+        let matcher = Matcher::build("regex", pattern, &MatchOptions::default())
+            .map_err(|err| format!("invalid sparkline pattern `{}`: {}", pattern, err))?;
+        Ok(Sparkline { matcher: matcher, values: VecDeque::with_capacity(WIDTH) })
+    }
+
+    /// Records the value within a line, if it has one, forgetting the oldest
+    /// value once there are more than can be drawn.
+    pub fn record(&mut self, line: &str) {
+        let value = self.matcher.spans(line).first()
+            .and_then(|&(start, end)| first_number(&line[start..end]));
+        if let Some(value) = value {
+            if self.values.len() == WIDTH {
+                self.values.pop_front();
+            }
+            self.values.push_back(value);
+        }
+    }
+
+    /// Draws the values recorded, each as a bar scaled between the least and
+    /// the greatest of them, followed by the latest, e.g. `▁▃█▂ 95`; or
+    /// returns `None` if none have been recorded.
+    pub fn render(&self) -> Option<String> {
+        let latest = *self.values.back()?;
+        let least = self.values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let greatest = self.values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let range = greatest - least;
+
+        let bars: String = self.values.iter()
+            .map(|&value| {
+                if range > 0.0 {
+                    let level = ((value - least) / range * (BARS.len() - 1) as f64).round();
+                    BARS[level as usize]
+                } else {
+                    BARS[BARS.len() / 2]
+                }
+            })
+            .collect();
+        Some(format!("{} {}", bars, latest))
+    }
+}
+
+/// Returns the first number within `text`, e.g. `-1.5` within `x=-1.5ms`.
+fn first_number(text: &str) -> Option<f64> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let start = if text[..start].ends_with('-') { start - 1 } else { start };
+    let len = text[start + 1..]
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map_or(text.len() - start, |len| len + 1);
+    let number = text[start..start + len].trim_end_matches('.');
+    number.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{first_number, Sparkline, WIDTH};
+
+    #[test]
+    fn test_first_number() {
+        assert_eq!(first_number("latency=120ms"), Some(120.0));
+        assert_eq!(first_number("x=-1.5ms"), Some(-1.5));
+        assert_eq!(first_number("took 3. seconds"), Some(3.0));
+        assert_eq!(first_number("v1.2.3"), None);
+        assert_eq!(first_number("none"), None);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_sparkline() {
+        let mut sparkline = Sparkline::new(r"latency=\d+").unwrap();
+        assert_eq!(sparkline.render(), None);
+
+        for line in &["latency=10ms", "no value", "latency=80ms", "latency=45ms"] {
+            sparkline.record(line);
+        }
+        assert_eq!(sparkline.render(), Some("▁█▅ 45".to_owned()));
+
+        for _ in 0..WIDTH {
+            sparkline.record("id=7 latency=5ms");
+        }
+        assert_eq!(sparkline.render(), Some(format!("{} 5", "▅".repeat(WIDTH))));
+
+        assert!(Sparkline::new("(").is_err());
+    }
+}
@@ -0,0 +1,325 @@
+use std::time::{Duration, Instant};
+
+use libc;
+use ncurses;
+
+use filterless::Totals;
+use text;
+
+
+/// Single-line bar at the bottom of the screen which shows the session's
+/// mode and timing information.
+pub struct StatusBar {
+    window: ncurses::WINDOW,
+    width: usize,
+    /// when the session began
+    started: Instant,
+    /// when a matching line was last read while following
+    last_match: Option<Instant>,
+    /// whether the pager is following the input for new lines
+    pub following: bool,
+    /// whether a filter is currently applied
+    pub filtering: bool,
+    /// percentage of the input scanned for matches, while a scan is in
+    /// progress
+    pub scanning: Option<usize>,
+    /// numbers of lines and matches in the whole input, once known
+    pub totals: Option<Totals>,
+    /// number of lines in the input, once known, which may be before
+    /// `totals` is
+    pub total_lines: Option<usize>,
+    /// estimate of the number of lines in the input, until `total_lines` is
+    /// known
+    pub estimate: Option<usize>,
+    /// whether no more lines follow those shown
+    pub at_end: bool,
+    /// line number within the input of the line at the top of the window
+    pub top_line: Option<usize>,
+    /// description of the section of the input shown, if it's split
+    pub tab: Option<String>,
+    /// how long a followed input has produced no lines, once that's long
+    /// enough to warn of
+    pub idle: Option<Duration>,
+    /// attributes in which the warning of an idle input is drawn
+    pub alert_attr: ncurses::attr_t,
+    /// sparkline of the recent values of a field, shown while following
+    pub sparkline: Option<String>,
+    /// transient message shown in place of the session information
+    message: Option<String>,
+}
+
+impl StatusBar {
+    /// Creates a new `StatusBar` drawn into `window`.
+    pub fn new(window: ncurses::WINDOW) -> StatusBar {
+        let mut height = 0;
+        let mut width = 0;
+        ncurses::getmaxyx(window, &mut height, &mut width);
+
+        StatusBar {
+            window: window,
+            width: width as usize,
+            started: Instant::now(),
+            last_match: None,
+            following: false,
+            filtering: false,
+            scanning: None,
+            totals: None,
+            total_lines: None,
+            estimate: None,
+            at_end: false,
+            top_line: None,
+            tab: None,
+            idle: None,
+            alert_attr: ncurses::A_BOLD(),
+            sparkline: None,
+            message: None,
+        }
+    }
+
+    /// Replaces the window the bar is drawn into, e.g. after the terminal has
+    /// been resized.
+    ///
+    /// ### Parameters
+    /// * `window`: the new window; the old one is deleted
+    pub fn resize(&mut self, window: ncurses::WINDOW) {
+        ncurses::delwin(self.window);
+
+        let mut height = 0;
+        let mut width = 0;
+        ncurses::getmaxyx(window, &mut height, &mut width);
+
+        self.window = window;
+        self.width = width as usize;
+        self.draw();
+    }
+
+    /// Records that a matching line was just read.
+    pub fn matched(&mut self) {
+        self.last_match = Some(Instant::now());
+    }
+
+    /// Forgets when the last matching line was read, e.g. because the
+    /// filter changed.
+    pub fn reset_match(&mut self) {
+        self.last_match = None;
+    }
+
+    /// Shows a message until `clear_message()` is called.
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    /// Removes the message set by `set_message()`.
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+
+    /// Redraws the bar.
+    pub fn draw(&self) {
+        let mut left = String::new();
+
+        if let Some(ref message) = self.message {
+            left.push_str(message);
+        } else if let Some(ref tab) = self.tab {
+            left.push_str(tab);
+            left.push_str("  ");
+        }
+
+        if self.message.is_none() && self.following {
+            left.push_str("[FOLLOW]  ");
+            left.push_str(&format!("up {}", format_duration(self.started.elapsed())));
+
+            if self.filtering {
+                match self.last_match {
+                    Some(instant) => left.push_str(&format!(
+                        "  last match {} ago", format_duration(instant.elapsed()))),
+                    None => left.push_str("  no match yet"),
+                }
+            }
+            if let Some(ref sparkline) = self.sparkline {
+                left.push_str("  ");
+                left.push_str(sparkline);
+            }
+        }
+
+        if self.message.is_none() {
+            if let Some(percent) = self.scanning {
+                if !left.is_empty() {
+                    left.push_str("  ");
+                }
+                left.push_str(&format!("scanning... {}%", percent));
+            }
+
+            let count = match (&self.totals, self.total_lines, self.estimate) {
+                (&Some(ref totals), _, _) => Some(format_totals(totals)),
+                (&None, Some(lines), _) => Some(format!("{} lines", lines)),
+                (&None, None, Some(estimate)) => {
+                    Some(format!("~{} lines", format_approx(estimate)))
+                },
+                (&None, None, None) => None,
+            };
+            if let Some(count) = count {
+                if !left.is_empty() {
+                    left.push_str("  ");
+                }
+                left.push_str(&count);
+            }
+
+            let total = self.totals
+                .map(|totals| totals.lines)
+                .or(self.total_lines)
+                .or(self.estimate);
+            if let Some(position) = format_position(self.top_line, total, self.at_end,
+                                                    self.filtering) {
+                left.push_str("  ");
+                left.push_str(&position);
+            }
+        }
+
+        let banner = self.idle
+            .map(|idle| format!(" NO DATA FOR {} ", format_short_duration(idle)))
+            .unwrap_or_default();
+        let banner = text::truncate(&banner, self.width.saturating_sub(1));
+        let right = local_time_of_day();
+        let left_width = self.width.saturating_sub(banner.len() + right.len() + 2);
+        let left = text::truncate(&left, left_width);
+        let padding = self.width
+            .saturating_sub(banner.len() + text::width(left) + right.len() + 1);
+
+        ncurses::werase(self.window);
+        ncurses::wattron(self.window, ncurses::A_REVERSE());
+        if !banner.is_empty() {
+            ncurses::wattron(self.window, self.alert_attr);
+            ncurses::waddstr(self.window, banner);
+            ncurses::wattroff(self.window, self.alert_attr);
+            ncurses::wattron(self.window, ncurses::A_REVERSE());
+        }
+        ncurses::waddstr(self.window, &format!("{}{}", left, " ".repeat(padding)));
+        ncurses::waddstr(self.window, &right[..right.len().min(self.width.saturating_sub(1))]);
+        ncurses::wattroff(self.window, ncurses::A_REVERSE());
+        ncurses::wnoutrefresh(self.window);
+    }
+}
+
+/// Formats a duration as `H:MM:SS`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Formats a duration briefly, to the largest whole unit, e.g. `45s`, `5m` or
+/// `2h 5m`.
+pub fn format_short_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        _ if secs % 3600 < 60 => format!("{}h", secs / 3600),
+        _ => format!("{}h {}m", secs / 3600, secs / 60 % 60),
+    }
+}
+
+/// Formats the numbers of lines and matches in the input, e.g. `12 of 340
+/// lines match`.
+pub fn format_totals(totals: &Totals) -> String {
+    match totals.matches {
+        Some(matches) => format!("{} of {} lines match", matches, totals.lines),
+        None => format!("{} lines", totals.lines),
+    }
+}
+
+/// Formats the position of the window within the input: how far through it
+/// the top line lies, or that no more lines follow.
+///
+/// ### Parameters
+/// * `top_line`: line number of the line at the top of the window
+/// * `total`: number of lines in the input, exact or estimated
+/// * `at_end`: whether no more lines follow those shown
+/// * `filtering`: whether a filter is applied
+pub fn format_position(top_line: Option<usize>, total: Option<usize>, at_end: bool,
+                       filtering: bool) -> Option<String> {
+    match (at_end, top_line, total) {
+        (true, _, _) if filtering => Some("no more matches below".to_owned()),
+        (true, _, _) => Some("end of input".to_owned()),
+        (false, Some(line_num), Some(total)) => {
+            Some(format!("{}%", (line_num * 100 / total.max(1)).min(100)))
+        },
+        _ => None,
+    }
+}
+
+/// Formats a count to two significant figures or so, e.g. `4.2M`.
+pub fn format_approx(count: usize) -> String {
+    let units = [(1e9, "G"), (1e6, "M"), (1e3, "K")];
+
+    for &(scale, unit) in units.iter() {
+        if count as f64 >= scale {
+            return format!("{:.1}{}", count as f64 / scale, unit);
+        }
+    }
+    count.to_string()
+}
+
+/// Returns the current local wall-clock time as `HH:MM:SS`.
+fn local_time_of_day() -> String {
+    let mut tm: libc::tm = unsafe { ::std::mem::zeroed() };
+
+    unsafe {
+        let now = libc::time(::std::ptr::null_mut());
+        libc::localtime_r(&now, &mut tm);
+    }
+
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use filterless::Totals;
+
+    use super::{format_approx, format_duration, format_position, format_short_duration,
+                format_totals};
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0:00:00");
+        assert_eq!(format_duration(Duration::from_secs(59)), "0:00:59");
+        assert_eq!(format_duration(Duration::from_secs(61)), "0:01:01");
+        assert_eq!(format_duration(Duration::from_secs(3600 * 26 + 62)), "26:01:02");
+    }
+
+    #[test]
+    fn test_format_short_duration() {
+        assert_eq!(format_short_duration(Duration::from_secs(45)), "45s");
+        assert_eq!(format_short_duration(Duration::from_secs(300)), "5m");
+        assert_eq!(format_short_duration(Duration::from_secs(359)), "5m");
+        assert_eq!(format_short_duration(Duration::from_secs(7200 + 59)), "2h");
+        assert_eq!(format_short_duration(Duration::from_secs(7200 + 300)), "2h 5m");
+    }
+
+    #[test]
+    fn test_format_approx() {
+        assert_eq!(format_approx(0), "0");
+        assert_eq!(format_approx(999), "999");
+        assert_eq!(format_approx(4210), "4.2K");
+        assert_eq!(format_approx(4_249_999), "4.2M");
+        assert_eq!(format_approx(12_000_000_000), "12.0G");
+    }
+
+    #[test]
+    fn test_format_totals() {
+        assert_eq!(format_totals(&Totals { lines: 340, matches: None }), "340 lines");
+        assert_eq!(format_totals(&Totals { lines: 340, matches: Some(12) }),
+                   "12 of 340 lines match");
+    }
+
+    #[test]
+    fn test_format_position() {
+        assert_eq!(format_position(Some(17), Some(340), false, false), Some("5%".to_owned()));
+        assert_eq!(format_position(Some(17), None, false, true), None);
+        assert_eq!(format_position(None, Some(340), true, false), Some("end of input".to_owned()));
+        assert_eq!(format_position(Some(17), None, true, true),
+                   Some("no more matches below".to_owned()));
+    }
+}
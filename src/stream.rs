@@ -0,0 +1,187 @@
+//! Input read line by line on a thread of its own, e.g. standard input or a
+//! compressed file, so that keystrokes are never kept waiting for a slow
+//! input, such as a pipe from across a network, to produce lines.
+
+use std::io::{self, BufRead};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of lines which may be read ahead of those taken by the pager,
+/// beyond which the reading thread waits for the pager to take more.
+const READ_AHEAD_LINES: usize = 4096;
+
+
+/// Handle on the thread reading the input, by which the pager learns whether
+/// lines have been read which it hasn't taken yet.
+pub struct Stream {
+    progress: Arc<Progress>,
+}
+
+/// Lines read by the thread, yielded as they're taken; the iterator returns
+/// `None` while there are none to be had, and yields more once they're read,
+/// like a file being followed.
+pub struct StreamLines {
+    receiver: Receiver<io::Result<String>>,
+    progress: Arc<Progress>,
+}
+
+/// What the reading thread and the pager know of each other.
+struct Progress {
+    /// number of lines read by the thread
+    read: AtomicUsize,
+    /// number of lines taken by the pager
+    taken: AtomicUsize,
+    /// whether the thread has read the whole input or failed to
+    finished: AtomicBool,
+    /// whether the pager has stopped taking lines, e.g. to reload the input
+    closed: AtomicBool,
+    /// why the input couldn't be opened, e.g. it's compressed in a format
+    /// which isn't supported, until the pager is told
+    failure: Mutex<Option<String>>,
+}
+
+/// Reads the lines of the input opened by `open` on a thread of its own,
+/// returning a handle on the thread along with the lines read.
+///
+/// The input is opened on the thread, so that opening it, e.g. reading the
+/// first bytes of a pipe to tell whether it's compressed, doesn't keep the
+/// pager waiting either.
+pub fn spawn<F>(open: F) -> (Stream, StreamLines)
+    where F: FnOnce() -> io::Result<Box<dyn BufRead>> + Send + 'static {
+
+    let progress = Arc::new(Progress {
+        read: AtomicUsize::new(0),
+        taken: AtomicUsize::new(0),
+        finished: AtomicBool::new(false),
+        closed: AtomicBool::new(false),
+        failure: Mutex::new(None),
+    });
+    let (sender, receiver) = mpsc::sync_channel(READ_AHEAD_LINES);
+
+    let shared = progress.clone();
+    thread::spawn(move || {
+        read(open, &sender, &shared);
+        shared.finished.store(true, Ordering::SeqCst);
+    });
+
+    let lines = StreamLines { receiver: receiver, progress: progress.clone() };
+    (Stream { progress: progress }, lines)
+}
+
+/// Sends each line of the input opened by `open` until the input ends or the
+/// pager stops taking lines.
+fn read<F>(open: F, sender: &SyncSender<io::Result<String>>, progress: &Progress)
+    where F: FnOnce() -> io::Result<Box<dyn BufRead>> {
+
+    let reader = match open() {
+        Ok(reader) => reader,
+        Err(err) => {
+            *progress.failure.lock().unwrap() = Some(err.to_string());
+            return;
+        },
+    };
+
+    for line in reader.lines() {
+        progress.read.fetch_add(1, Ordering::SeqCst);
+        if sender.send(line).is_err() {
+            return;
+        }
+    }
+}
+
+impl Stream {
+    /// Returns `true` if lines have been read which the pager hasn't taken
+    /// yet.
+    pub fn has_pending(&self) -> bool {
+        let progress = &self.progress;
+        !progress.closed.load(Ordering::SeqCst)
+            && progress.taken.load(Ordering::SeqCst) < progress.read.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` while there may be lines of the input which the pager
+    /// hasn't taken yet, read or not.
+    pub fn is_reading(&self) -> bool {
+        let progress = &self.progress;
+        let stopped = progress.finished.load(Ordering::SeqCst)
+            || progress.closed.load(Ordering::SeqCst);
+        !stopped || self.has_pending()
+    }
+
+    /// Waits until `lines` lines have been read or the input ends, but no
+    /// longer than `timeout`.
+    pub fn wait_for(&self, lines: usize, timeout: Duration) {
+        let start = Instant::now();
+        while self.progress.read.load(Ordering::SeqCst) < lines
+            && !self.progress.finished.load(Ordering::SeqCst) && start.elapsed() < timeout {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Returns why the input couldn't be opened the first time it's found
+    /// not to have been, or `None` if it was or that was already told.
+    pub fn take_failure(&self) -> Option<String> {
+        self.progress.failure.lock().unwrap().take()
+    }
+}
+
+impl Iterator for StreamLines {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        match self.receiver.try_recv() {
+            Ok(line) => {
+                self.progress.taken.fetch_add(1, Ordering::SeqCst);
+                Some(line)
+            },
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        }
+    }
+}
+
+impl Drop for StreamLines {
+    fn drop(&mut self) {
+        self.progress.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, BufRead, Cursor};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::spawn;
+
+    #[test]
+    fn test_spawn() {
+        let (stream, mut lines) = spawn(|| {
+            Ok(Box::new(Cursor::new(b"one\ntwo\r\n".to_vec())) as Box<dyn BufRead>)
+        });
+
+        let start = Instant::now();
+        stream.wait_for(2, Duration::from_secs(5));
+        assert!(stream.has_pending());
+        let mut read = Vec::new();
+        while stream.is_reading() && start.elapsed() < Duration::from_secs(5) {
+            match lines.next() {
+                Some(line) => read.push(line.unwrap()),
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        assert_eq!(read, vec!["one".to_owned(), "two".to_owned()]);
+        assert!(!stream.has_pending());
+        assert!(lines.next().is_none());
+
+        let (stream, lines) = spawn(|| Err(io::Error::other("unsupported compression")));
+        while stream.is_reading() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(stream.take_failure(), Some("unsupported compression".to_owned()));
+        assert_eq!(stream.take_failure(), None);
+        drop(lines);
+        assert!(!stream.has_pending());
+    }
+}
@@ -0,0 +1,296 @@
+//! Syntax highlighting of lines in recognized formats, which picks out the
+//! parts of a line worth telling apart at a glance, e.g. log levels, drawn
+//! beneath the highlighting of matches.
+
+use filterless::{parser, Format};
+use filterless::matcher::Span;
+use filterless::timestamp;
+
+use theme::Role;
+
+/// Names of the formats, as given to `--syntax`.
+pub const NAMES: &[&str] = &["none", "log", "json", "fields"];
+
+/// Words naming log levels, in lower case, and the roles in which they're
+/// drawn.
+const LEVELS: &[(&str, Role)] = &[
+    ("emerg", Role::Error),
+    ("alert", Role::Error),
+    ("fatal", Role::Error),
+    ("critical", Role::Error),
+    ("crit", Role::Error),
+    ("panic", Role::Error),
+    ("error", Role::Error),
+    ("err", Role::Error),
+    ("warning", Role::Warning),
+    ("warn", Role::Warning),
+    ("notice", Role::Info),
+    ("info", Role::Info),
+    ("debug", Role::Debug),
+    ("trace", Role::Debug),
+];
+
+/// Keys whose values name log levels, e.g. in `level=info`.
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity"];
+
+/// Names of fields whose values are timestamps.
+const TIME_KEYS: &[&str] = &["time", "timestamp", "ts"];
+
+
+/// Format in which lines are highlighted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+    /// lines aren't highlighted
+    None,
+    /// leading timestamps and log levels, e.g. `ERROR` or `level=error`
+    Log,
+    /// object keys, and log levels given as the values of keys such as
+    /// `"level"`
+    Json,
+    /// the values of fields naming log levels or holding timestamps, of
+    /// lines in any format parsed into fields
+    Fields,
+}
+
+impl Syntax {
+    /// Returns the format having the given name.
+    ///
+    /// ### Parameters
+    /// * `name`: one of `NAMES`
+    pub fn from_name(name: &str) -> Option<Syntax> {
+        match name {
+            "none" => Some(Syntax::None),
+            "log" => Some(Syntax::Log),
+            "json" => Some(Syntax::Json),
+            "fields" => Some(Syntax::Fields),
+            _ => None,
+        }
+    }
+
+    /// Returns the byte ranges of the parts of `line` to highlight and the
+    /// roles in which they're drawn, ascending and non-overlapping.
+    ///
+    /// ### Parameters
+    /// * `line`: text of the line
+    /// * `format`: format in which lines are parsed into fields, or `None`
+    ///   for the first format each line is in
+    pub fn spans(&self, line: &str, format: Option<Format>) -> Vec<(Span, Role)> {
+        match *self {
+            Syntax::None => Vec::new(),
+            Syntax::Log => log_spans(line),
+            Syntax::Json => json_spans(line),
+            Syntax::Fields => field_spans(line, format),
+        }
+    }
+}
+
+/// Returns the role of a log level named by `word`, if it names one.
+fn level_role(word: &str) -> Option<Role> {
+    LEVELS.iter()
+        .find(|&&(level, _)| word.eq_ignore_ascii_case(level))
+        .map(|&(_, role)| role)
+}
+
+/// Highlights the leading timestamp of a log line, log levels written in
+/// capitals, and those given as the value of a key, e.g. `level=info`.
+fn log_spans(line: &str) -> Vec<(Span, Role)> {
+    let mut spans = Vec::new();
+    let mut pos = 0;
+
+    if let Some(ts) = timestamp::parse_leading(line) {
+        spans.push(((ts.start, ts.end), Role::Timestamp));
+        pos = ts.end;
+    }
+
+    let bytes = line.as_bytes();
+    while pos < bytes.len() {
+        if !bytes[pos].is_ascii_alphabetic() {
+            pos += 1;
+            continue;
+        }
+
+        let start = pos;
+        while pos < bytes.len() && bytes[pos].is_ascii_alphanumeric() {
+            pos += 1;
+        }
+        let word = &line[start..pos];
+        let after_key = line[..start].strip_suffix('=')
+            .is_some_and(|key| LEVEL_KEYS.iter().any(|k| key.ends_with(k)));
+
+        if word.bytes().all(|b| b.is_ascii_uppercase()) || after_key {
+            if let Some(role) = level_role(word) {
+                spans.push(((start, pos), role));
+            }
+        }
+    }
+    spans
+}
+
+/// Highlights the keys of JSON objects, and log levels given as the string
+/// values of keys such as `"level"`.
+fn json_spans(line: &str) -> Vec<(Span, Role)> {
+    let mut spans = Vec::new();
+    let bytes = line.as_bytes();
+    let mut pos = 0;
+    // whether the string being read is the value of a key naming log levels
+    let mut level_key = false;
+
+    while pos < bytes.len() {
+        if bytes[pos] != b'"' {
+            if !bytes[pos].is_ascii_whitespace() && bytes[pos] != b':' {
+                level_key = false;
+            }
+            pos += 1;
+            continue;
+        }
+
+        let start = pos;
+        pos += 1;
+        while pos < bytes.len() && bytes[pos] != b'"' {
+            pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+        }
+        // case: the string is unterminated
+        if pos >= bytes.len() {
+            break;
+        }
+        let contents = &line[start + 1..pos];
+        pos += 1;
+        let end = pos;
+
+        let rest = line[end..].trim_start();
+        if rest.starts_with(':') {
+            spans.push(((start, end), Role::Key));
+            level_key = LEVEL_KEYS.contains(&contents);
+        } else {
+            if level_key {
+                if let Some(role) = level_role(contents) {
+                    spans.push(((start, end), role));
+                }
+            }
+            level_key = false;
+        }
+    }
+    spans
+}
+
+/// Highlights the values of the fields of a line which name log levels, e.g.
+/// `level`, or hold timestamps, e.g. `time`, whether nested or not.
+fn field_spans(line: &str, format: Option<Format>) -> Vec<(Span, Role)> {
+    let structured = match parser::parse(format, line) {
+        Some(structured) => structured,
+        None => return Vec::new(),
+    };
+
+    let mut spans: Vec<(Span, Role)> = structured.fields().iter()
+        .filter_map(|field| {
+            let key = field.path.rsplit('.').next().unwrap_or("");
+            if LEVEL_KEYS.contains(&key) {
+                level_role(&field.value).map(|role| (field.span, role))
+            } else if TIME_KEYS.contains(&key) {
+                Some((field.span, Role::Timestamp))
+            } else {
+                None
+            }
+        })
+        .collect();
+    spans.sort_by_key(|&((start, _), _)| start);
+    spans
+}
+
+/// Splits the first `len` bytes of a line into consecutive ranges, each
+/// drawn in the role of the span of `over` covering it, else of the span of
+/// `under` covering it, if any.
+///
+/// ### Parameters
+/// * `len`: number of bytes of the line drawn
+/// * `under`: ascending, non-overlapping spans, e.g. from `Syntax::spans()`
+/// * `over`: ascending, non-overlapping spans drawn over those of `under`,
+///   e.g. of matches
+pub fn compose(len: usize, under: &[(Span, Role)], over: &[(Span, Role)])
+               -> Vec<(Span, Option<Role>)> {
+    let mut bounds: Vec<usize> = under.iter().chain(over.iter())
+        .flat_map(|&((start, end), _)| vec![start, end])
+        .filter(|&bound| bound < len)
+        .collect();
+    bounds.push(0);
+    bounds.push(len);
+    bounds.sort();
+    bounds.dedup();
+
+    let role_at = |spans: &[(Span, Role)], pos: usize| {
+        spans.iter().find(|&&((start, end), _)| start <= pos && pos < end).map(|&(_, role)| role)
+    };
+
+    let mut ranges: Vec<(Span, Option<Role>)> = Vec::new();
+    for pair in bounds.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let role = role_at(over, start).or(role_at(under, start));
+        match ranges.last_mut() {
+            Some(&mut ((_, ref mut last_end), last_role)) if last_role == role => *last_end = end,
+            _ => ranges.push(((start, end), role)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod test {
+    use filterless::parser;
+
+    use theme::Role;
+
+    use super::{compose, Syntax};
+
+    #[test]
+    fn test_log_spans() {
+        let line = "2017-10-05 14:00:01 ERROR db: error, level=warn Info";
+        assert_eq!(Syntax::Log.spans(line, None), vec![
+            ((0, 19), Role::Timestamp),
+            ((20, 25), Role::Error),
+            ((43, 47), Role::Warning),
+        ]);
+        assert_eq!(Syntax::Log.spans("[DEBUG] INFORMATION", None), vec![((1, 6), Role::Debug)]);
+        assert_eq!(Syntax::None.spans(line, None), vec![]);
+    }
+
+    #[test]
+    fn test_json_spans() {
+        let line = r#"{"level": "error", "msg": "a \"level\": b", "n": {"info": "warn"}}"#;
+        assert_eq!(Syntax::Json.spans(line, None), vec![
+            ((1, 8), Role::Key),
+            ((10, 17), Role::Error),
+            ((19, 24), Role::Key),
+            ((44, 47), Role::Key),
+            ((50, 56), Role::Key),
+        ]);
+        // case: an unterminated string
+        assert_eq!(Syntax::Json.spans(r#"{"a": "é\"#, None), vec![((1, 4), Role::Key)]);
+    }
+
+    #[test]
+    fn test_field_spans() {
+        let line = r#"ts=2017-10-05T14:00:01Z level=warn msg="level=error" lvl=x"#;
+        assert_eq!(Syntax::Fields.spans(line, None), vec![
+            ((3, 23), Role::Timestamp),
+            ((30, 34), Role::Warning),
+        ]);
+        assert_eq!(Syntax::Fields.spans(r#"{"log": {"level": "error"}}"#, None),
+                   vec![((19, 24), Role::Error)]);
+        assert_eq!(Syntax::Fields.spans(line, Some(parser::CSV)), vec![]);
+        assert_eq!(Syntax::Fields.spans("plain text", None), vec![]);
+    }
+
+    #[test]
+    fn test_compose() {
+        let under = [((0, 4), Role::Timestamp), ((6, 9), Role::Error)];
+        let over = [((3, 7), Role::Match)];
+        assert_eq!(compose(12, &under, &over), vec![
+            ((0, 3), Some(Role::Timestamp)),
+            ((3, 7), Some(Role::Match)),
+            ((7, 9), Some(Role::Error)),
+            ((9, 12), None),
+        ]);
+        assert_eq!(compose(5, &under, &[]), vec![((0, 4), Some(Role::Timestamp)), ((4, 5), None)]);
+        assert_eq!(compose(0, &under, &over), vec![]);
+    }
+}
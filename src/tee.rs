@@ -0,0 +1,182 @@
+//! Mirroring of the filtered lines shown by the pager to a file or named
+//! pipe as they're shown, so that another program may consume them while the
+//! pager is still in use.
+
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use filterless::FilteredLine;
+
+use export::{self, ExportOptions};
+use read_only;
+
+
+/// Writer of the filtered lines shown to a file or named pipe.
+///
+/// Lines are written from a background thread, so that the pager neither
+/// waits for a reader to open a named pipe nor for a slow one to keep up.
+pub struct Tee {
+    /// path of the file or named pipe written to
+    path: String,
+    /// lines yet to be written, in the form they're written in
+    sender: Sender<Vec<u8>>,
+    /// error which stopped the background thread writing, if any
+    error: Arc<Mutex<Option<String>>>,
+    /// which lines are written and how
+    options: ExportOptions,
+    /// number of the last line of the input mirrored so far
+    last_line_num: usize,
+    /// whether any lines have been written so far
+    wrote_lines: bool,
+    /// whether a gap was mirrored after the last line written, which is
+    /// written before the next one
+    pending_gap: bool,
+}
+
+impl Tee {
+    /// Starts mirroring lines to `path`, which is created or truncated if
+    /// it's a regular file.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the file or named pipe to write to
+    /// * `options`: which lines to write and how
+    pub fn open(path: &str, options: ExportOptions) -> Tee {
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        let error = Arc::new(Mutex::new(None));
+
+        let thread_path = path.to_owned();
+        let thread_error = error.clone();
+        thread::spawn(move || {
+            // opening a named pipe waits for a reader to open it
+            let written = read_only::check()
+                .and_then(|_| {
+                    OpenOptions::new().write(true).create(true).truncate(true).open(&thread_path)
+                })
+                .map(BufWriter::new)
+                .and_then(|mut file| {
+                    for bytes in receiver {
+                        file.write_all(&bytes)?;
+                        file.flush()?;
+                    }
+                    Ok(())
+                });
+
+            if let Err(err) = written {
+                *thread_error.lock().unwrap() = Some(format!("{}: {}", thread_path, err));
+            }
+        });
+
+        Tee {
+            path: path.to_owned(),
+            sender: sender,
+            error: error,
+            options: options,
+            last_line_num: 0,
+            wrote_lines: false,
+            pending_gap: false,
+        }
+    }
+
+    /// Writes those of `lines` which follow the last line written, e.g. the
+    /// lines of the page shown, so that lines are written once each, in
+    /// order, as the pager moves through the input.
+    ///
+    /// Returns an error if writing has failed, e.g. because the reader of a
+    /// named pipe went away, in which case nothing more is written.
+    ///
+    /// ### Parameters
+    /// * `lines`: filtered lines, in order
+    pub fn mirror(&mut self, lines: &[FilteredLine]) -> Result<(), String> {
+        if let Some(ref err) = *self.error.lock().unwrap() {
+            return Err(err.clone());
+        }
+
+        let last_line_num = self.last_line_num;
+        let new_lines: Vec<&FilteredLine> = lines.iter()
+            .filter(|line| match **line {
+                FilteredLine::Gap { from, .. } => from > last_line_num,
+                _ => line.line_num().is_some_and(|n| n > last_line_num),
+            })
+            .collect();
+        if new_lines.is_empty() {
+            return Ok(());
+        }
+
+        for line in &new_lines {
+            self.last_line_num = match **line {
+                FilteredLine::Gap { to, .. } => to,
+                _ => line.line_num().unwrap_or(self.last_line_num),
+            };
+        }
+
+        // gaps at either end of the lines are written only once lines are
+        // written on both sides of them, which may be by a later call
+        let leading_gap = new_lines[0].is_gap();
+        let trailing_gap = new_lines[new_lines.len() - 1].is_gap();
+        let mut bytes = Vec::new();
+        export::write_lines(&mut bytes, new_lines, &self.options).map_err(|e| e.to_string())?;
+        if bytes.is_empty() {
+            self.pending_gap |= leading_gap || trailing_gap;
+            return Ok(());
+        }
+        if self.options.context && self.wrote_lines && (self.pending_gap || leading_gap) {
+            bytes.splice(0..0, b"--\n".iter().cloned());
+        }
+        self.wrote_lines = true;
+        self.pending_gap = trailing_gap;
+
+        self.sender.send(bytes).map_err(|_| format!("{}: closed", self.path))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use filterless::FilteredLine;
+
+    use export::ExportOptions;
+    use super::Tee;
+
+    #[test]
+    fn test_mirror() {
+        let path = env::temp_dir().join(format!("filterless-tee-{}", process::id()));
+        let path = path.to_str().unwrap();
+        let options = ExportOptions { context: true, line_numbers: true };
+        let mut tee = Tee::open(path, options);
+
+        tee.mirror(&[
+            FilteredLine::ContextLine((1, "before".to_owned())),
+            FilteredLine::MatchLine((2, "match".to_owned())),
+        ]).unwrap();
+        // lines already written aren't written again
+        tee.mirror(&[
+            FilteredLine::MatchLine((2, "match".to_owned())),
+            FilteredLine::Gap { from: 3, to: 8 },
+            FilteredLine::MatchLine((9, "again".to_owned())),
+        ]).unwrap();
+        tee.mirror(&[FilteredLine::MatchLine((2, "match".to_owned()))]).unwrap();
+        // gaps at the ends of the lines mirrored are written between groups
+        tee.mirror(&[FilteredLine::Gap { from: 10, to: 11 }]).unwrap();
+        tee.mirror(&[
+            FilteredLine::Gap { from: 12, to: 12 },
+            FilteredLine::MatchLine((13, "last".to_owned())),
+            FilteredLine::Gap { from: 14, to: 20 },
+        ]).unwrap();
+
+        let expected = "1-before\n2:match\n--\n9:again\n--\n13:last\n";
+        let start = Instant::now();
+        while fs::read_to_string(path).unwrap_or_default() != expected {
+            assert!(start.elapsed() < Duration::from_secs(5), "lines weren't written");
+            thread::sleep(Duration::from_millis(10));
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+//! Acquisition of the terminal from which keys are read, which can't be the
+//! standard input when that's the data being paged, and of the streams
+//! ncurses reads keys from and draws to.
+
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
+
+use libc;
+use ncurses::{self, SCREEN};
+
+/// Path of the controlling terminal of the process on POSIX systems.
+const TTY_PATH: &str = "/dev/tty";
+
+
+/// Opens the terminal from which keys are read: the controlling terminal,
+/// or failing that, e.g. where `/dev/tty` isn't available, the terminal to
+/// which the standard input, output or error is connected.
+///
+/// Returns an error telling why if there's no terminal to be had, e.g. when
+/// run without a controlling terminal and with none of the standard streams
+/// connected to one.
+pub fn open_tty() -> Result<File, String> {
+    let err = match File::open(TTY_PATH) {
+        Ok(file) => return Ok(file),
+        Err(err) => err,
+    };
+
+    [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO].iter()
+        .filter_map(|&fd| reopen(fd))
+        .next()
+        .ok_or_else(|| {
+            format!("can't open a terminal to read keys from ({}: {}), and none of stdin, \
+                     stdout and stderr is a terminal", TTY_PATH, err)
+        })
+}
+
+/// Opens anew the terminal to which `fd` is connected, by its name if it
+/// has one and can be opened, or else as a duplicate of `fd`; or returns
+/// `None` if `fd` isn't connected to a terminal.
+fn reopen(fd: RawFd) -> Option<File> {
+    if unsafe { libc::isatty(fd) } != 1 {
+        return None;
+    }
+
+    let name = unsafe { libc::ttyname(fd) };
+    if !name.is_null() {
+        let path = unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned();
+        if let Ok(file) = File::open(path) {
+            return Some(file);
+        }
+    }
+
+    match unsafe { libc::dup(fd) } {
+        -1 => None,
+        dup => Some(unsafe { File::from_raw_fd(dup) }),
+    }
+}
+
+/// Returns a duplicate of the standard output, which stays open however the
+/// standard output is used meanwhile.
+pub fn stdout() -> io::Result<File> {
+    match unsafe { libc::dup(libc::STDOUT_FILENO) } {
+        -1 => Err(io::Error::last_os_error()),
+        dup => Ok(unsafe { File::from_raw_fd(dup) }),
+    }
+}
+
+/// Sets the character type of the C library's locale from the environment,
+/// e.g. `LANG=en_US.UTF-8`, so that ncurses draws the characters of that
+/// encoding rather than each of their bytes.
+///
+/// `ncurses::setlocale()` isn't used, as it passes a dangling pointer and
+/// the category numbers of another platform.
+pub fn use_locale() {
+    let empty = CString::default();
+    unsafe { libc::setlocale(libc::LC_CTYPE, empty.as_ptr()) };
+}
+
+/// Screen set up by ncurses, which restores the terminal when it's dropped,
+/// however the program stops.
+pub struct Screen {
+    screen: SCREEN,
+}
+
+impl Screen {
+    /// Takes charge of `screen`, as returned by `newterm()`.
+    pub fn new(screen: SCREEN) -> Screen {
+        Screen { screen: screen }
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_VISIBLE);
+        ncurses::endwin();
+        ncurses::delscreen(self.screen);
+    }
+}
+
+/// Turns `file` into a C stream, e.g. for ncurses to read from or write to,
+/// which takes over closing it.
+///
+/// ### Parameters
+/// * `file`: the file
+/// * `mode`: the mode in which the stream is opened, as given to `fdopen()`,
+///   e.g. `r`
+pub fn stream(file: File, mode: &str) -> io::Result<*mut libc::FILE> {
+    let mode = CString::new(mode).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let fd = file.into_raw_fd();
+
+    let stream = unsafe { libc::fdopen(fd, mode.as_ptr()) };
+    if stream.is_null() {
+        let err = io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(err);
+    }
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::os::unix::io::FromRawFd;
+
+    use libc;
+
+    use super::{reopen, stream};
+
+    #[test]
+    fn test_reopen() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let (read_end, write_end) = unsafe {
+            (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))
+        };
+
+        // a pipe isn't a terminal
+        assert!(reopen(fds[0]).is_none());
+
+        let read_stream = stream(read_end, "r").unwrap();
+        assert!(!read_stream.is_null());
+        unsafe { libc::fclose(read_stream) };
+
+        // the write end of a pipe can't be read from
+        assert!(stream(write_end, "r").is_err());
+    }
+}
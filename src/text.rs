@@ -0,0 +1,236 @@
+//! Helpers for fitting line text into the columns of a window.
+
+use std::borrow::Cow;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use filterless::Matcher;
+
+/// Character drawn in place of each space ending a line, where trailing
+/// whitespace is shown.
+pub const TRAILING_SPACE_MARK: char = '\u{b7}';
+
+/// Returns the number of columns `text` takes up in the terminal, e.g. two
+/// for each CJK character or emoji, and none for a combining accent.
+pub fn width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Returns the number of columns `c` takes up in the terminal, counting
+/// control characters, which `sanitize()` replaces, as none.
+fn char_width(c: char) -> usize {
+    UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+/// Returns the longest prefix of `line` which fits in `width` columns,
+/// leaving out a wide character which would straddle the last.
+///
+/// ### Parameters
+/// * `line`: text to truncate
+/// * `width`: number of columns available
+pub fn truncate(line: &str, width: usize) -> &str {
+    let mut cols = 0;
+    for (idx, c) in line.char_indices() {
+        cols += char_width(c);
+        if cols > width {
+            return &line[..idx];
+        }
+    }
+    line
+}
+
+/// Returns `line` as it's safe to draw, borrowed if it already is: with tabs
+/// expanded to spaces up to the next tab stop, and control characters, which
+/// would otherwise be passed to the terminal, shown as `cat -v` does, e.g.
+/// Esc as `^[` and U+0085 as `M-^E`.
+///
+/// ### Parameters
+/// * `line`: text of the line
+/// * `tab_width`: number of columns between tab stops
+/// * `mark_trailing`: whether the spaces ending the line, tabs expanded
+///   included, are drawn as `TRAILING_SPACE_MARK`
+pub fn sanitize(line: &str, tab_width: usize, mark_trailing: bool) -> Cow<'_, str> {
+    let ends_in_space = line.ends_with([' ', '\t']);
+    if !(line.chars().any(char::is_control) || mark_trailing && ends_in_space) {
+        return Cow::Borrowed(line);
+    }
+
+    let mut sanitized = String::with_capacity(line.len() + 8);
+    let mut col = 0;
+    for c in line.chars() {
+        let code = c as u32;
+        if c == '\t' {
+            let spaces = tab_width - col % tab_width;
+            sanitized.extend((0..spaces).map(|_| ' '));
+            col += spaces;
+        } else if code < 0x20 || code == 0x7f {
+            // the caret and the character 64 away, e.g. `^[` for 0x1b
+            sanitized.push('^');
+            sanitized.push((code as u8 ^ 0x40) as char);
+            col += 2;
+        } else if (0x80..0xa0).contains(&code) {
+            sanitized.push_str("M-^");
+            sanitized.push(((code - 0x80) as u8 ^ 0x40) as char);
+            col += 4;
+        } else {
+            sanitized.push(c);
+            col += char_width(c);
+        }
+    }
+
+    if mark_trailing {
+        let content = sanitized.trim_end_matches(' ').len();
+        let spaces = sanitized.len() - content;
+        sanitized.truncate(content);
+        sanitized.extend((0..spaces).map(|_| TRAILING_SPACE_MARK));
+    }
+    Cow::Owned(sanitized)
+}
+
+/// Returns the number of decimal digits needed to print `num`.
+pub fn num_digits(num: usize) -> usize {
+    let mut digits = 1;
+    let mut rest = num / 10;
+    while rest > 0 {
+        digits += 1;
+        rest /= 10;
+    }
+    digits
+}
+
+/// Returns `num` with its digits grouped in threes by commas, e.g. `1,234`.
+pub fn group_digits(num: usize) -> String {
+    let digits = num.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Describes the number of lines a gap stands for, e.g. `1,234 lines skipped`.
+pub fn describe_skipped(num_lines: usize) -> String {
+    let noun = if num_lines == 1 { "line" } else { "lines" };
+    format!("{} {} skipped", group_digits(num_lines), noun)
+}
+
+/// Returns the number of matches within a line as printed in a gutter
+/// `width` columns wide: right-aligned, blank if there are none, and capped
+/// at e.g. `99+` if too wide.
+pub fn format_count(count: usize, width: usize) -> String {
+    if count == 0 {
+        " ".repeat(width)
+    } else if num_digits(count) > width {
+        format!("{}+", "9".repeat(width.saturating_sub(1)))
+    } else {
+        format!("{:>1$}", count, width)
+    }
+}
+
+/// Returns the token of `line` which covers column `col`, if any.
+///
+/// ### Parameters
+/// * `line`: text to search
+/// * `col`: 0-indexed column within `line`, either of those of a wide
+///   character standing for it
+/// * `token`: matcher describing what constitutes a token, e.g. a word
+pub fn token_at<'a>(line: &'a str, col: usize, token: &Matcher) -> Option<&'a str> {
+    let mut cols = 0;
+    let idx = line.char_indices()
+        .find(|&(_, c)| {
+            cols += char_width(c);
+            cols > col
+        })
+        .map(|(idx, _)| idx)?;
+
+    token.spans(line).into_iter()
+        .find(|&(start, end)| start <= idx && idx < end)
+        .map(|(start, end)| &line[start..end])
+}
+
+#[cfg(test)]
+mod test {
+    use filterless::Matcher;
+
+    use super::{describe_skipped, format_count, group_digits, num_digits, sanitize, token_at,
+                truncate, width};
+
+    #[test]
+    fn test_num_digits() {
+        assert_eq!(num_digits(0), 1);
+        assert_eq!(num_digits(9), 1);
+        assert_eq!(num_digits(10), 2);
+        assert_eq!(num_digits(99), 2);
+        assert_eq!(num_digits(100), 3);
+        assert_eq!(num_digits(123_456_789), 9);
+    }
+
+    #[test]
+    fn test_group_digits() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(1234), "1,234");
+        assert_eq!(group_digits(123_456_789), "123,456,789");
+        assert_eq!(describe_skipped(1), "1 line skipped");
+        assert_eq!(describe_skipped(1234), "1,234 lines skipped");
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0, 3), "   ");
+        assert_eq!(format_count(7, 3), "  7");
+        assert_eq!(format_count(999, 3), "999");
+        assert_eq!(format_count(1000, 3), "99+");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello", 3), "hel");
+        assert_eq!(truncate("hello", 0), "");
+        assert_eq!(truncate("héllo", 2), "hé");
+        assert_eq!(truncate("日本語", 4), "日本");
+        assert_eq!(truncate("日本語", 5), "日本");
+        assert_eq!(truncate("e\u{301}t\u{e9}", 2), "e\u{301}t");
+        assert_eq!(width("日本 text 😀"), 12);
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("plain text  ", 8, false), "plain text  ");
+        assert_eq!(sanitize("a\tbc\td", 4, false), "a   bc  d");
+        assert_eq!(sanitize("\x1b[31mred\x1b[0m", 8, false), "^[[31mred^[[0m");
+        assert_eq!(sanitize("del\x7f nel\u{85}", 8, false), "del^? nelM-^E");
+        // tab stops count the columns of caret notation
+        assert_eq!(sanitize("\x07\tx", 4, false), "^G  x");
+        assert_eq!(sanitize("end \t", 8, true), "end\u{b7}\u{b7}\u{b7}\u{b7}\u{b7}");
+        assert_eq!(sanitize("  ", 8, true), "\u{b7}\u{b7}");
+        // tab stops count the columns of wide characters
+        assert_eq!(sanitize("日本\tx", 8, false), "日本    x");
+    }
+
+    #[test]
+    fn test_token_at() {
+        let word = Matcher::word();
+        let line = "héllo wörld, id=abc-123";
+
+        assert_eq!(token_at(line, 0, &word), Some("héllo"));
+        assert_eq!(token_at(line, 4, &word), Some("héllo"));
+        assert_eq!(token_at(line, 5, &word), None);
+        assert_eq!(token_at(line, 7, &word), Some("wörld"));
+        assert_eq!(token_at(line, 11, &word), None);
+        assert_eq!(token_at(line, 100, &word), None);
+
+        let id = Matcher::substring("abc-123".to_owned());
+        assert_eq!(token_at(line, 18, &id), Some("abc-123"));
+
+        let line = "日本 wörld";
+        assert_eq!(token_at(line, 3, &word), Some("日本"));
+        assert_eq!(token_at(line, 4, &word), None);
+        assert_eq!(token_at(line, 5, &word), Some("wörld"));
+    }
+}
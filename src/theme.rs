@@ -0,0 +1,646 @@
+use std::fmt;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+
+use ncurses;
+
+use config;
+
+/// Names of the built-in themes, as given to `--theme`.
+pub const NAMES: &[&str] = &["default", "none", "high-contrast", "deuteranopia"];
+
+/// Number of styles in which highlight patterns are drawn, which are reused
+/// in turn by further patterns.
+pub const HIGHLIGHTS: usize = 4;
+
+/// Names of the roles as used in theme files, in the order in which they're
+/// written.
+const ROLES: &[(&str, Role)] = &[
+    ("match", Role::Match),
+    ("context", Role::Context),
+    ("line_num", Role::LineNum),
+    ("gap", Role::Gap),
+    ("stall", Role::Stall),
+    ("cursor", Role::Cursor),
+    ("timestamp", Role::Timestamp),
+    ("error", Role::Error),
+    ("warning", Role::Warning),
+    ("info", Role::Info),
+    ("debug", Role::Debug),
+    ("key", Role::Key),
+    ("diff", Role::Diff),
+    ("highlight1", Role::Highlight(0)),
+    ("highlight2", Role::Highlight(1)),
+    ("highlight3", Role::Highlight(2)),
+    ("highlight4", Role::Highlight(3)),
+];
+
+/// Kinds of on-screen text which are drawn in a distinct style.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    /// occurrences of the filter string within a matched line
+    Match,
+    /// lines shown as context around a matched line
+    Context,
+    /// the line number gutter
+    LineNum,
+    /// context gap markers
+    Gap,
+    /// time deltas in the gutter which exceed the stall threshold
+    Stall,
+    /// the row of the cursor line, drawn over the styles of the other roles
+    Cursor,
+    /// timestamps, when highlighting the syntax of logs
+    Timestamp,
+    /// error and fatal log levels
+    Error,
+    /// warning log levels
+    Warning,
+    /// info and notice log levels
+    Info,
+    /// debug and trace log levels
+    Debug,
+    /// keys of JSON objects
+    Key,
+    /// words in which a line differs from the similar line before it
+    Diff,
+    /// occurrences of a highlight pattern, by the index of its style, less
+    /// than `HIGHLIGHTS`
+    Highlight(usize),
+}
+
+impl Role {
+    /// Returns the ncurses color pair number reserved for this role.
+    fn pair(&self) -> i16 {
+        match *self {
+            Role::Match => 1,
+            Role::LineNum => 2,
+            Role::Gap => 3,
+            Role::Stall => 4,
+            Role::Context => 5,
+            Role::Cursor => 6,
+            Role::Timestamp => 7,
+            Role::Error => 8,
+            Role::Warning => 9,
+            Role::Info => 10,
+            Role::Debug => 11,
+            Role::Key => 12,
+            Role::Highlight(i) => 13 + (i % HIGHLIGHTS) as i16,
+            Role::Diff => 13 + HIGHLIGHTS as i16,
+        }
+    }
+
+    /// Returns the role corresponding to a name as used in theme files.
+    fn from_name(name: &str) -> Option<Role> {
+        ROLES.iter().find(|&&(n, _)| n == name).map(|&(_, role)| role)
+    }
+}
+
+/// Foreground/background colors and extra attributes used to draw a `Role`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Style {
+    /// ncurses foreground color, or -1 for the terminal default
+    pub fg: i16,
+    /// ncurses background color, or -1 for the terminal default
+    pub bg: i16,
+    /// attributes (e.g., bold, reverse) applied in addition to the colors
+    pub attrs: ncurses::attr_t,
+}
+
+impl Style {
+    pub fn new(fg: i16, bg: i16, attrs: ncurses::attr_t) -> Style {
+        Style { fg: fg, bg: bg, attrs: attrs }
+    }
+}
+
+/// Mapping of on-screen roles to the styles used to draw them.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub match_style: Style,
+    pub context_style: Style,
+    pub line_num_style: Style,
+    pub gap_style: Style,
+    pub stall_style: Style,
+    pub cursor_style: Style,
+    pub timestamp_style: Style,
+    pub error_style: Style,
+    pub warning_style: Style,
+    pub info_style: Style,
+    pub debug_style: Style,
+    pub key_style: Style,
+    pub diff_style: Style,
+    pub highlight_styles: [Style; HIGHLIGHTS],
+    /// when false, only attributes are used; colors are ignored
+    pub use_color: bool,
+}
+
+impl Theme {
+    /// The stock theme: black on yellow matches, green line numbers.
+    pub fn default() -> Theme {
+        Theme {
+            match_style: Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_YELLOW, 0),
+            context_style: Style::new(-1, -1, 0),
+            line_num_style: Style::new(ncurses::COLOR_GREEN, ncurses::COLOR_BLACK, 0),
+            gap_style: Style::new(ncurses::COLOR_RED, ncurses::COLOR_BLACK, 0),
+            stall_style: Style::new(ncurses::COLOR_RED, ncurses::COLOR_BLACK, ncurses::A_BOLD()),
+            cursor_style: Style::new(-1, -1, ncurses::A_REVERSE()),
+            timestamp_style: Style::new(ncurses::COLOR_CYAN, -1, 0),
+            error_style: Style::new(ncurses::COLOR_RED, -1, ncurses::A_BOLD()),
+            warning_style: Style::new(ncurses::COLOR_YELLOW, -1, ncurses::A_BOLD()),
+            info_style: Style::new(ncurses::COLOR_GREEN, -1, 0),
+            debug_style: Style::new(-1, -1, ncurses::A_DIM()),
+            key_style: Style::new(ncurses::COLOR_BLUE, -1, ncurses::A_BOLD()),
+            diff_style: Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_MAGENTA, ncurses::A_BOLD()),
+            highlight_styles: [
+                Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_CYAN, 0),
+                Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_GREEN, 0),
+                Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_MAGENTA, 0),
+                Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLUE, 0),
+            ],
+            use_color: true,
+        }
+    }
+
+    /// A theme for monochrome terminals which relies on attributes only.
+    pub fn monochrome() -> Theme {
+        Theme {
+            match_style: Style::new(-1, -1, ncurses::A_REVERSE()),
+            context_style: Style::new(-1, -1, 0),
+            line_num_style: Style::new(-1, -1, ncurses::A_BOLD()),
+            gap_style: Style::new(-1, -1, ncurses::A_DIM()),
+            stall_style: Style::new(-1, -1, ncurses::A_BOLD() | ncurses::A_UNDERLINE()),
+            // matches are already in reverse video
+            cursor_style: Style::new(-1, -1, ncurses::A_UNDERLINE()),
+            timestamp_style: Style::new(-1, -1, ncurses::A_DIM()),
+            error_style: Style::new(-1, -1, ncurses::A_BOLD() | ncurses::A_UNDERLINE()),
+            warning_style: Style::new(-1, -1, ncurses::A_BOLD()),
+            info_style: Style::new(-1, -1, 0),
+            debug_style: Style::new(-1, -1, ncurses::A_DIM()),
+            key_style: Style::new(-1, -1, ncurses::A_UNDERLINE()),
+            diff_style: Style::new(-1, -1, ncurses::A_BOLD() | ncurses::A_UNDERLINE()),
+            highlight_styles: [
+                Style::new(-1, -1, ncurses::A_REVERSE() | ncurses::A_UNDERLINE()),
+                Style::new(-1, -1, ncurses::A_REVERSE() | ncurses::A_BOLD()),
+                Style::new(-1, -1, ncurses::A_REVERSE() | ncurses::A_DIM()),
+                Style::new(-1, -1,
+                           ncurses::A_REVERSE() | ncurses::A_BOLD() | ncurses::A_UNDERLINE()),
+            ],
+            use_color: false,
+        }
+    }
+
+    /// A theme of bold white and black, with matches in reverse video, for
+    /// low vision or washed-out displays.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            match_style: Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_WHITE, ncurses::A_BOLD()),
+            context_style: Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLACK, 0),
+            line_num_style: Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLACK,
+                                       ncurses::A_BOLD()),
+            gap_style: Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLACK,
+                                  ncurses::A_BOLD() | ncurses::A_UNDERLINE()),
+            stall_style: Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_YELLOW,
+                                    ncurses::A_BOLD()),
+            cursor_style: Style::new(-1, -1, ncurses::A_REVERSE() | ncurses::A_BOLD()),
+            timestamp_style: Style::new(ncurses::COLOR_CYAN, ncurses::COLOR_BLACK, 0),
+            error_style: Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_MAGENTA,
+                                    ncurses::A_BOLD()),
+            warning_style: Style::new(ncurses::COLOR_YELLOW, ncurses::COLOR_BLACK,
+                                      ncurses::A_BOLD()),
+            info_style: Style::new(ncurses::COLOR_CYAN, ncurses::COLOR_BLACK, ncurses::A_BOLD()),
+            debug_style: Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLACK, ncurses::A_DIM()),
+            key_style: Style::new(ncurses::COLOR_YELLOW, ncurses::COLOR_BLACK, 0),
+            diff_style: Style::new(ncurses::COLOR_YELLOW, ncurses::COLOR_BLUE, ncurses::A_BOLD()),
+            highlight_styles: [
+                Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_CYAN, ncurses::A_BOLD()),
+                Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_YELLOW, 0),
+                Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLUE, ncurses::A_BOLD()),
+                Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_MAGENTA, ncurses::A_BOLD()),
+            ],
+            use_color: true,
+        }
+    }
+
+    /// A theme which tells its roles apart by blue and yellow, and by
+    /// attributes, rather than by red and green, for red-green color
+    /// blindness such as deuteranopia.
+    pub fn deuteranopia() -> Theme {
+        Theme {
+            match_style: Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_YELLOW, ncurses::A_BOLD()),
+            context_style: Style::new(ncurses::COLOR_CYAN, -1, 0),
+            line_num_style: Style::new(ncurses::COLOR_BLUE, -1, 0),
+            gap_style: Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLUE, 0),
+            stall_style: Style::new(ncurses::COLOR_YELLOW, -1,
+                                    ncurses::A_BOLD() | ncurses::A_UNDERLINE()),
+            cursor_style: Style::new(-1, -1, ncurses::A_REVERSE()),
+            timestamp_style: Style::new(ncurses::COLOR_BLUE, -1, ncurses::A_BOLD()),
+            error_style: Style::new(ncurses::COLOR_MAGENTA, -1,
+                                    ncurses::A_BOLD() | ncurses::A_UNDERLINE()),
+            warning_style: Style::new(ncurses::COLOR_YELLOW, -1, ncurses::A_BOLD()),
+            info_style: Style::new(ncurses::COLOR_CYAN, -1, ncurses::A_BOLD()),
+            debug_style: Style::new(-1, -1, ncurses::A_DIM()),
+            key_style: Style::new(ncurses::COLOR_BLUE, -1, ncurses::A_UNDERLINE()),
+            diff_style: Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_WHITE,
+                                   ncurses::A_BOLD() | ncurses::A_UNDERLINE()),
+            highlight_styles: [
+                Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_CYAN, 0),
+                Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_MAGENTA, 0),
+                Style::new(ncurses::COLOR_BLACK, ncurses::COLOR_WHITE, 0),
+                Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLUE, ncurses::A_BOLD()),
+            ],
+            use_color: true,
+        }
+    }
+
+    /// Returns the built-in theme having the given name.
+    ///
+    /// ### Parameters
+    /// * `name`: one of `NAMES`
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::default()),
+            "none" => Some(Theme::monochrome()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "deuteranopia" => Some(Theme::deuteranopia()),
+            _ => None,
+        }
+    }
+
+    /// Reads a theme file, overriding styles of the default theme.
+    ///
+    /// Each non-blank line not beginning with `#` takes the form
+    /// `role = fg bg [attr ...]`, e.g. `match = black yellow bold`.
+    ///
+    /// ### Parameters
+    /// * `path`: path of the theme file
+    pub fn from_file(path: &str) -> Result<Theme, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut theme = Theme::default();
+
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", path, e))?;
+            theme.apply_line(&line)
+                .map_err(|e| format!("{}:{}: {}", path, i + 1, e))?;
+        }
+
+        Ok(theme)
+    }
+
+    /// Applies a single line of a theme file to this theme.
+    ///
+    /// The style may be quoted, e.g. `match = "black yellow bold"`.
+    pub fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().ok_or(format!("expected `role = style`; got `{}`", line))?;
+        let value = config::unquote(value.trim());
+
+        if name == "color" {
+            self.use_color = match value.trim() {
+                "on" | "true" => true,
+                "off" | "false" => false,
+                other => return Err(format!("unknown color setting `{}`", other)),
+            };
+            return Ok(());
+        }
+
+        let role = Role::from_name(name).ok_or(format!("unknown role `{}`", name))?;
+        let style = parse_style(&value)?;
+
+        match role {
+            Role::Match => self.match_style = style,
+            Role::Context => self.context_style = style,
+            Role::LineNum => self.line_num_style = style,
+            Role::Gap => self.gap_style = style,
+            Role::Stall => self.stall_style = style,
+            Role::Cursor => self.cursor_style = style,
+            Role::Timestamp => self.timestamp_style = style,
+            Role::Error => self.error_style = style,
+            Role::Warning => self.warning_style = style,
+            Role::Info => self.info_style = style,
+            Role::Debug => self.debug_style = style,
+            Role::Key => self.key_style = style,
+            Role::Diff => self.diff_style = style,
+            Role::Highlight(i) => self.highlight_styles[i % HIGHLIGHTS] = style,
+        }
+
+        Ok(())
+    }
+
+    /// Returns the style for a role.
+    pub fn style(&self, role: Role) -> &Style {
+        match role {
+            Role::Match => &self.match_style,
+            Role::Context => &self.context_style,
+            Role::LineNum => &self.line_num_style,
+            Role::Gap => &self.gap_style,
+            Role::Stall => &self.stall_style,
+            Role::Cursor => &self.cursor_style,
+            Role::Timestamp => &self.timestamp_style,
+            Role::Error => &self.error_style,
+            Role::Warning => &self.warning_style,
+            Role::Info => &self.info_style,
+            Role::Debug => &self.debug_style,
+            Role::Key => &self.key_style,
+            Role::Diff => &self.diff_style,
+            Role::Highlight(i) => &self.highlight_styles[i % HIGHLIGHTS],
+        }
+    }
+
+    /// Registers this theme's color pairs with ncurses.
+    ///
+    /// Must be called after the screen has been initialized. Falls back to
+    /// attribute-only drawing if the terminal lacks color support.
+    pub fn init(&mut self) {
+        if !ncurses::has_colors() {
+            self.use_color = false;
+        }
+
+        if !self.use_color {
+            return;
+        }
+
+        ncurses::start_color();
+        ncurses::use_default_colors();
+
+        for &(_, role) in ROLES.iter() {
+            let style = self.style(role).to_owned();
+            ncurses::init_pair(role.pair(), style.fg, style.bg);
+        }
+    }
+
+    /// Returns CSS rules which draw the text of each role, given the class
+    /// named after the role as in theme files, as this theme draws it on a
+    /// terminal whose default colors are `fg` and `bg`, e.g. `#e5e5e5` and
+    /// `#000000`.
+    pub fn css(&self, fg: &str, bg: &str) -> String {
+        let mut css = String::new();
+        for &(name, role) in ROLES.iter() {
+            let style = self.style(role).css(self.use_color, fg, bg);
+            css.push_str(&format!(".{} {{ {}}}\n", name, style));
+        }
+        css
+    }
+
+    /// Returns the ncurses attributes with which to draw text of a role.
+    pub fn attr(&self, role: Role) -> ncurses::attr_t {
+        let style = self.style(role);
+        if self.use_color {
+            ncurses::COLOR_PAIR(role.pair()) | style.attrs
+        } else {
+            style.attrs
+        }
+    }
+}
+
+/// Names of the attributes which may be given in a style.
+fn attr_names() -> [(&'static str, ncurses::attr_t); 4] {
+    [
+        ("bold", ncurses::A_BOLD()),
+        ("dim", ncurses::A_DIM()),
+        ("reverse", ncurses::A_REVERSE()),
+        ("underline", ncurses::A_UNDERLINE()),
+    ]
+}
+
+/// Names of the colors which may be given in a style.
+const COLOR_NAMES: [(&str, i16); 9] = [
+    ("default", -1),
+    ("black", ncurses::COLOR_BLACK),
+    ("red", ncurses::COLOR_RED),
+    ("green", ncurses::COLOR_GREEN),
+    ("yellow", ncurses::COLOR_YELLOW),
+    ("blue", ncurses::COLOR_BLUE),
+    ("magenta", ncurses::COLOR_MAGENTA),
+    ("cyan", ncurses::COLOR_CYAN),
+    ("white", ncurses::COLOR_WHITE),
+];
+
+impl Style {
+    /// Returns the CSS declarations which draw text in this style on a
+    /// terminal whose default colors are `fg` and `bg`.
+    ///
+    /// ### Parameters
+    /// * `use_color`: whether colors are drawn, or only attributes
+    /// * `fg`: CSS color of the terminal's default foreground
+    /// * `bg`: CSS color of the terminal's default background
+    fn css(&self, use_color: bool, fg: &str, bg: &str) -> String {
+        let color = |color: i16, default: &str| {
+            Some(color).filter(|_| use_color).and_then(css_color).unwrap_or(default.to_owned())
+        };
+        let (mut fg, mut bg) = (color(self.fg, fg), color(self.bg, bg));
+        if self.attrs & ncurses::A_REVERSE() != 0 {
+            ::std::mem::swap(&mut fg, &mut bg);
+        }
+
+        let mut css = format!("color: {}; background: {}; ", fg, bg);
+        if self.attrs & ncurses::A_BOLD() != 0 {
+            css.push_str("font-weight: bold; ");
+        }
+        if self.attrs & ncurses::A_DIM() != 0 {
+            css.push_str("opacity: 0.6; ");
+        }
+        if self.attrs & ncurses::A_UNDERLINE() != 0 {
+            css.push_str("text-decoration: underline; ");
+        }
+        css
+    }
+}
+
+/// Returns the CSS color of a terminal color as xterm draws it, or `None` for
+/// the terminal default.
+fn css_color(color: i16) -> Option<String> {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
+        (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
+        (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
+        (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
+    ];
+    let level = |n: i16| if n == 0 { 0 } else { (55 + n * 40) as u8 };
+
+    let (r, g, b) = match color {
+        0..=15 => BASIC[color as usize],
+        // the 6x6x6 color cube
+        16..=231 => {
+            let n = color - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        },
+        // the grayscale ramp
+        232..=255 => {
+            let gray = (8 + (color - 232) * 10) as u8;
+            (gray, gray, gray)
+        },
+        _ => return None,
+    };
+    Some(format!("#{:02x}{:02x}{:02x}", r, g, b))
+}
+
+impl fmt::Display for Style {
+    /// Formats the style as parsed by `parse_style()`, e.g.
+    /// `black yellow bold`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", color_name(self.fg), color_name(self.bg))?;
+        for &(name, attr) in attr_names().iter() {
+            if self.attrs & attr == attr {
+                write!(f, " {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Theme {
+    /// Formats the theme as lines of a theme file.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "color = \"{}\"", if self.use_color { "on" } else { "off" })?;
+        for &(name, role) in ROLES.iter() {
+            writeln!(f, "{} = \"{}\"", name, self.style(role))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of a color as parsed by `parse_color()`.
+fn color_name(color: i16) -> String {
+    COLOR_NAMES.iter()
+        .find(|&&(_, c)| c == color)
+        .map(|&(name, _)| name.to_owned())
+        .unwrap_or(color.to_string())
+}
+
+/// Parses a style of the form `fg bg [attr ...]`.
+fn parse_style(value: &str) -> Result<Style, String> {
+    let words: Vec<&str> = value.split_whitespace().collect();
+    if words.len() < 2 {
+        return Err(format!("expected `fg bg [attr ...]`; got `{}`", value.trim()));
+    }
+
+    let fg = parse_color(words[0])?;
+    let bg = parse_color(words[1])?;
+    let mut attrs = 0;
+
+    for word in &words[2..] {
+        attrs |= parse_attr(word)?;
+    }
+
+    Ok(Style::new(fg, bg, attrs))
+}
+
+/// Parses a color name, or a color number for terminals supporting more than
+/// the eight named colors.
+fn parse_color(name: &str) -> Result<i16, String> {
+    COLOR_NAMES.iter()
+        .find(|&&(n, _)| n == name)
+        .map(|&(_, color)| color)
+        .or(name.parse::<i16>().ok().filter(|&color| color >= 0))
+        .ok_or(format!("unknown color `{}`", name))
+}
+
+fn parse_attr(name: &str) -> Result<ncurses::attr_t, String> {
+    attr_names().iter()
+        .find(|&&(n, _)| n == name)
+        .map(|&(_, attr)| attr)
+        .ok_or(format!("unknown attribute `{}`", name))
+}
+
+#[cfg(test)]
+mod test {
+    use ncurses;
+
+    use super::{css_color, Role, Style, Theme, NAMES, ROLES};
+
+    #[test]
+    fn test_apply_line() {
+        let mut theme = Theme::default();
+
+        theme.apply_line("# comment").unwrap();
+        theme.apply_line("").unwrap();
+        theme.apply_line("match = white red bold").unwrap();
+        theme.apply_line("line_num=default default").unwrap();
+        theme.apply_line("highlight2 = white blue").unwrap();
+        theme.apply_line("color = off").unwrap();
+
+        assert_eq!(theme.style(Role::Highlight(1)),
+                   &Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_BLUE, 0));
+        assert_eq!(theme.match_style,
+                   Style::new(ncurses::COLOR_WHITE, ncurses::COLOR_RED, ncurses::A_BOLD()));
+        assert_eq!(theme.line_num_style, Style::new(-1, -1, 0));
+        assert!(!theme.use_color);
+    }
+
+    #[test]
+    fn test_apply_line_errors() {
+        let mut theme = Theme::default();
+
+        assert!(theme.apply_line("match").is_err());
+        assert!(theme.apply_line("bogus = red black").is_err());
+        assert!(theme.apply_line("match = red").is_err());
+        assert!(theme.apply_line("match = red black blink").is_err());
+        assert!(theme.apply_line("match = mauve black").is_err());
+    }
+
+    #[test]
+    fn test_presets() {
+        for name in NAMES {
+            assert!(Theme::from_name(name).is_some(), "no theme named {}", name);
+        }
+
+        // the colorblind-safe themes use neither red nor green, and no two of
+        // their roles share a style
+        for theme in [Theme::high_contrast(), Theme::deuteranopia()].iter() {
+            let styles: Vec<&Style> = ROLES.iter().map(|&(_, role)| theme.style(role)).collect();
+            for style in &styles {
+                for color in [style.fg, style.bg].iter() {
+                    assert!(*color != ncurses::COLOR_RED && *color != ncurses::COLOR_GREEN,
+                            "{} relies on red or green", style);
+                }
+            }
+            for (i, a) in styles.iter().enumerate() {
+                for b in &styles[i + 1..] {
+                    assert!(a != b, "{} used for two roles", a);
+                }
+            }
+            assert!(theme.style(Role::Match).attrs != 0);
+        }
+    }
+
+    #[test]
+    fn test_css() {
+        assert_eq!(css_color(-1), None);
+        assert_eq!(css_color(ncurses::COLOR_YELLOW), Some("#cdcd00".to_owned()));
+        assert_eq!(css_color(208), Some("#ff8700".to_owned()));
+        assert_eq!(css_color(244), Some("#808080".to_owned()));
+
+        let mut theme = Theme::default();
+        theme.apply_line("gap = blue default bold underline").unwrap();
+        let css = theme.css("#fff", "#000");
+        assert!(css.contains(".context { color: #fff; background: #000; }\n"));
+        assert!(css.contains(".cursor { color: #000; background: #fff; }\n"));
+        assert!(css.contains(
+            ".gap { color: #0000ee; background: #000; font-weight: bold; \
+             text-decoration: underline; }\n"));
+
+        theme.apply_line("color = off").unwrap();
+        assert!(theme.css("#fff", "#000").contains(".match { color: #fff; background: #000; }"));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut theme = Theme::monochrome();
+        theme.apply_line("match = \"white 208 bold underline\"").unwrap();
+
+        let mut parsed = Theme::default();
+        for line in theme.to_string().lines() {
+            parsed.apply_line(line).unwrap();
+        }
+
+        assert_eq!(theme.match_style.to_string(), "white 208 bold underline");
+        assert_eq!(parsed.to_string(), theme.to_string());
+    }
+}
@@ -0,0 +1,456 @@
+//! Parsing of timestamps found at the beginning of log lines.
+//!
+//! Two layouts are recognized:
+//!
+//! * ISO 8601-ish: `2017-10-05 14:00:01`, `2017-10-05T14:00:01.123Z`,
+//!   `[2017-10-05 14:00:01,123 +0200]`
+//! * syslog: `Oct  5 14:00:01`; these carry no year, so 1970 is assumed
+//!
+//! Timestamps may be displayed in their original form, in UTC, or in local
+//! time; see `Zone`.
+
+use std::fmt;
+
+use libc;
+
+
+/// Function which parses the timestamp at the beginning of a line, e.g.
+/// `parse_leading()`, by which the layouts of timestamps recognized may be
+/// changed.
+pub type Parser = fn(&str) -> Option<Timestamp>;
+
+/// Point in time parsed from the beginning of a line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timestamp {
+    /// milliseconds since the Unix epoch; timestamps without a UTC offset
+    /// are treated as if they were UTC
+    pub millis: i64,
+    /// offset from UTC in minutes, if the timestamp specified one
+    pub utc_offset: Option<i32>,
+    /// byte offset of the timestamp's first character within the line
+    pub start: usize,
+    /// byte offset just past the timestamp's last character within the line
+    pub end: usize,
+}
+
+impl Timestamp {
+    /// Returns the number of milliseconds from `earlier` to this timestamp.
+    pub fn millis_since(&self, earlier: &Timestamp) -> i64 {
+        self.millis - earlier.millis
+    }
+}
+
+/// Time zone in which timestamps are displayed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    /// as written in the input
+    Original,
+    /// converted to UTC
+    Utc,
+    /// converted to the local time zone
+    Local,
+}
+
+impl Zone {
+    /// Returns the zone following this one in the display toggle cycle.
+    pub fn next(&self) -> Zone {
+        match *self {
+            Zone::Original => Zone::Utc,
+            Zone::Utc => Zone::Local,
+            Zone::Local => Zone::Original,
+        }
+    }
+
+    /// Returns `line` with its leading timestamp re-rendered in this zone.
+    ///
+    /// Lines without a timestamp, and timestamps which don't specify their
+    /// UTC offset (and so can't be converted reliably), are returned as-is.
+    pub fn render(&self, line: &str) -> Option<String> {
+        if *self == Zone::Original {
+            return None;
+        }
+
+        parse_leading(line)
+            .and_then(|ts| ts.utc_offset.map(|_| ts))
+            .map(|ts| {
+                let offset = match *self {
+                    Zone::Local => local_utc_offset(ts.millis),
+                    _ => 0,
+                };
+                format!("{}{}{}", &line[..ts.start], format_iso(ts.millis, offset), &line[ts.end..])
+            })
+    }
+}
+
+/// Parses the timestamp at the beginning of `line`, if there is one.
+///
+/// Leading whitespace and a single opening bracket are skipped.
+pub fn parse_leading(line: &str) -> Option<Timestamp> {
+    let bytes = line.as_bytes();
+    let mut start = 0;
+
+    while start < bytes.len() && bytes[start] == b' ' {
+        start += 1;
+    }
+    if start < bytes.len() && bytes[start] == b'[' {
+        start += 1;
+    }
+
+    parse_iso(bytes, start).or_else(|| parse_syslog(bytes, start))
+}
+
+/// Parses a point in time written in one of the layouts recognized at the
+/// beginning of lines, e.g. as typed to jump to it, allowing the seconds or
+/// the whole time of day to be left out: `2017-10-05`, `2017-10-05 14:00`,
+/// `Oct  5 14:00`.
+///
+/// Returns milliseconds since the Unix epoch, treating a time without a UTC
+/// offset as UTC, as `parse_leading()` does.
+pub fn parse_time(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let candidates = [text.to_owned(), format!("{}:00", text), format!("{} 00:00:00", text)];
+
+    candidates.iter()
+        .filter_map(|candidate| parse_leading(candidate).filter(|ts| ts.end == candidate.len()))
+        .map(|ts| ts.millis)
+        .next()
+}
+
+/// Cursor over the bytes of a line.
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    /// Consumes exactly `n` ASCII digits, returning their value. Nothing is
+    /// consumed if fewer than `n` digits are next.
+    fn digits(&mut self, n: usize) -> Option<i64> {
+        let mut value = 0;
+        for i in 0..n {
+            match self.bytes.get(self.pos + i) {
+                Some(&b) if b.is_ascii_digit() => {
+                    value = value * 10 + (b - b'0') as i64;
+                },
+                _ => return None,
+            }
+        }
+        self.pos += n;
+        Some(value)
+    }
+
+    /// Consumes the byte `b` if it is next.
+    fn byte(&mut self, b: u8) -> bool {
+        if self.bytes.get(self.pos) == Some(&b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes a `HH:MM:SS[.fff]` time of day, returning milliseconds
+    /// since midnight.
+    fn time_of_day(&mut self) -> Option<i64> {
+        let hour = self.digits(2)?;
+        if !self.byte(b':') { return None; }
+        let minute = self.digits(2)?;
+        if !self.byte(b':') { return None; }
+        let second = self.digits(2)?;
+
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        let mut millis = 0;
+        if self.byte(b'.') || self.byte(b',') {
+            let mut scale = 100;
+            while let Some(&b) = self.bytes.get(self.pos) {
+                if !b.is_ascii_digit() {
+                    break;
+                }
+                millis += (b - b'0') as i64 * scale;
+                scale /= 10;
+                self.pos += 1;
+            }
+        }
+
+        Some(((hour * 60 + minute) * 60 + second) * 1000 + millis)
+    }
+
+    /// Consumes a `Z`, `+HH:MM`, or `-HHMM` UTC offset, optionally preceded
+    /// by a space, returning it in minutes.
+    fn utc_offset(&mut self) -> Option<i32> {
+        let saved = self.pos;
+        self.byte(b' ');
+
+        if self.byte(b'Z') {
+            return Some(0);
+        }
+
+        let sign = if self.byte(b'+') { 1 } else if self.byte(b'-') { -1 } else {
+            self.pos = saved;
+            return None;
+        };
+
+        let offset = self.digits(2).and_then(|hours| {
+            self.byte(b':');
+            self.digits(2).map(|minutes| sign * (hours * 60 + minutes) as i32)
+        });
+
+        if offset.is_none() {
+            self.pos = saved;
+        }
+        offset
+    }
+}
+
+fn parse_iso(bytes: &[u8], start: usize) -> Option<Timestamp> {
+    let mut scanner = Scanner { bytes: bytes, pos: start };
+
+    let year = scanner.digits(4)?;
+    if !scanner.byte(b'-') { return None; }
+    let month = scanner.digits(2)?;
+    if !scanner.byte(b'-') { return None; }
+    let day = scanner.digits(2)?;
+    if !(scanner.byte(b'T') || scanner.byte(b' ')) { return None; }
+    let time = scanner.time_of_day()?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let utc_offset = scanner.utc_offset();
+    let local_millis = days_from_civil(year, month, day) * 86_400_000 + time;
+    let millis = local_millis - utc_offset.unwrap_or(0) as i64 * 60_000;
+
+    Some(Timestamp {
+        millis: millis,
+        utc_offset: utc_offset,
+        start: start,
+        end: scanner.pos,
+    })
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+fn parse_syslog(bytes: &[u8], start: usize) -> Option<Timestamp> {
+    let month_name = bytes.get(start..start + 3)?;
+    let month = MONTHS.iter().position(|m| m.as_bytes() == month_name)? as i64 + 1;
+
+    let mut scanner = Scanner { bytes: bytes, pos: start + 3 };
+    if !scanner.byte(b' ') { return None; }
+    scanner.byte(b' ');
+
+    let day = scanner.digits(2).or_else(|| scanner.digits(1))?;
+    if !scanner.byte(b' ') { return None; }
+    let time = scanner.time_of_day()?;
+
+    if !(1..=31).contains(&day) {
+        return None;
+    }
+
+    Some(Timestamp {
+        millis: days_from_civil(1970, month, day) * 86_400_000 + time,
+        utc_offset: None,
+        start: start,
+        end: scanner.pos,
+    })
+}
+
+/// Returns the number of days from 1970-01-01 to the given date in the
+/// proleptic Gregorian calendar.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the `(year, month, day)` of the date which is `days` days after
+/// 1970-01-01; the inverse of `days_from_civil`.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = (if days >= 0 { days } else { days - 146_096 }) / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+                       - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Formats an instant as `YYYY-MM-DDTHH:MM:SS.fff` followed by `Z` or the
+/// UTC offset.
+///
+/// ### Parameters
+/// * `millis`: milliseconds since the Unix epoch
+/// * `utc_offset`: offset from UTC in minutes in which to express the instant
+pub fn format_iso(millis: i64, utc_offset: i32) -> String {
+    let local = millis + utc_offset as i64 * 60_000;
+    let days = if local >= 0 { local / 86_400_000 } else { (local - 86_399_999) / 86_400_000 };
+    let (year, month, day) = civil_from_days(days);
+    let of_day = local - days * 86_400_000;
+    let secs = of_day / 1000;
+
+    let zone = if utc_offset == 0 {
+        "Z".to_owned()
+    } else {
+        let sign = if utc_offset < 0 { '-' } else { '+' };
+        format!("{}{:02}:{:02}", sign, utc_offset.abs() / 60, utc_offset.abs() % 60)
+    };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}{}",
+            year, month, day, secs / 3600, secs / 60 % 60, secs % 60, of_day % 1000, zone)
+}
+
+/// Returns the local time zone's offset from UTC in minutes at an instant.
+///
+/// ### Parameters
+/// * `millis`: milliseconds since the Unix epoch
+fn local_utc_offset(millis: i64) -> i32 {
+    let mut tm: libc::tm = unsafe { ::std::mem::zeroed() };
+    let secs = (millis / 1000) as libc::time_t;
+
+    unsafe {
+        libc::localtime_r(&secs, &mut tm);
+    }
+
+    (tm.tm_gmtoff / 60) as i32
+}
+
+/// Signed span of time between two timestamps, formatted compactly for
+/// display in a narrow gutter, e.g. `+0.250s`, `+12.3s`, `+4m05s`, `+2h14m`.
+pub struct Delta(pub i64);
+
+impl fmt::Display for Delta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "+" };
+        let millis = self.0.abs();
+        let secs = millis / 1000;
+
+        let text = if millis < 10_000 {
+            format!("{}{}.{:03}s", sign, secs, millis % 1000)
+        } else if millis < 60_000 {
+            format!("{}{}.{}s", sign, secs, millis % 1000 / 100)
+        } else if secs < 3600 {
+            format!("{}{}m{:02}s", sign, secs / 60, secs % 60)
+        } else {
+            format!("{}{}h{:02}m", sign, secs / 3600, secs / 60 % 60)
+        };
+
+        f.pad(&text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Delta, Zone, civil_from_days, days_from_civil, format_iso, parse_leading,
+                parse_time};
+
+    #[test]
+    fn test_days_from_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(days_from_civil(2016, 2, 29)), (2016, 2, 29));
+    }
+
+    #[test]
+    fn test_format_iso() {
+        assert_eq!(format_iso(1_507_212_001_250, 0), "2017-10-05T14:00:01.250Z");
+        assert_eq!(format_iso(1_507_212_001_250, 120), "2017-10-05T16:00:01.250+02:00");
+        assert_eq!(format_iso(1_507_212_001_250, -870), "2017-10-04T23:30:01.250-14:30");
+        assert_eq!(format_iso(-1, 0), "1969-12-31T23:59:59.999Z");
+    }
+
+    #[test]
+    fn test_zone_render() {
+        let line = "[2017-10-05 16:00:01,5 +02:00] started";
+        assert_eq!(Zone::Original.render(line), None);
+        assert_eq!(Zone::Utc.render(line),
+                   Some("[2017-10-05T14:00:01.500Z] started".to_owned()));
+
+        assert_eq!(Zone::Utc.render("2017-10-05 16:00:01 no offset"), None);
+        assert_eq!(Zone::Utc.render("no timestamp"), None);
+    }
+
+    #[test]
+    fn test_parse_iso() {
+        let ts = parse_leading("2017-10-05 14:00:01 INFO started").unwrap();
+        assert_eq!(ts.millis, 1_507_212_001_000);
+        assert_eq!(ts.utc_offset, None);
+        assert_eq!((ts.start, ts.end), (0, 19));
+
+        let ts = parse_leading("2017-10-05T14:00:01.25Z started").unwrap();
+        assert_eq!(ts.millis, 1_507_212_001_250);
+        assert_eq!(ts.utc_offset, Some(0));
+        assert_eq!((ts.start, ts.end), (0, 23));
+
+        let ts = parse_leading("[2017-10-05 16:00:01,5 +02:00] started").unwrap();
+        assert_eq!(ts.millis, 1_507_212_001_500);
+        assert_eq!(ts.utc_offset, Some(120));
+        assert_eq!((ts.start, ts.end), (1, 29));
+
+        let ts = parse_leading("2017-10-05T09:00:01-0500 started").unwrap();
+        assert_eq!(ts.millis, 1_507_212_001_000);
+        assert_eq!(ts.utc_offset, Some(-300));
+    }
+
+    #[test]
+    fn test_parse_syslog() {
+        let ts = parse_leading("Jan  2 00:00:01 host sshd[1]: hello").unwrap();
+        assert_eq!(ts.millis, 86_401_000);
+        assert_eq!((ts.start, ts.end), (0, 15));
+
+        let ts = parse_leading("Jan 12 00:00:00 host").unwrap();
+        assert_eq!(ts.millis, 11 * 86_400_000);
+    }
+
+    #[test]
+    fn test_parse_none() {
+        assert_eq!(parse_leading(""), None);
+        assert_eq!(parse_leading("hello world"), None);
+        assert_eq!(parse_leading("2017-13-05 14:00:01"), None);
+        assert_eq!(parse_leading("2017-10-05 25:00:01"), None);
+        assert_eq!(parse_leading("Foo  5 14:00:01"), None);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("2017-10-05 14:00:01"), Some(1_507_212_001_000));
+        assert_eq!(parse_time(" 2017-10-05 14:00 "), Some(1_507_212_000_000));
+        assert_eq!(parse_time("2017-10-05"), Some(1_507_161_600_000));
+        assert_eq!(parse_time("2017-10-05T16:00:01+02:00"), Some(1_507_212_001_000));
+        assert_eq!(parse_time("Jan  2 00:00"), Some(86_400_000));
+        assert_eq!(parse_time("2017-10-05 14"), None);
+        assert_eq!(parse_time("2017-10-05 14:00:01 trailing"), None);
+        assert_eq!(parse_time(""), None);
+    }
+
+    #[test]
+    fn test_delta_display() {
+        assert_eq!(format!("{}", Delta(250)), "+0.250s");
+        assert_eq!(format!("{}", Delta(-1500)), "-1.500s");
+        assert_eq!(format!("{}", Delta(12_345)), "+12.3s");
+        assert_eq!(format!("{}", Delta(245_000)), "+4m05s");
+        assert_eq!(format!("{}", Delta(8_040_000)), "+2h14m");
+        assert_eq!(format!("{:>8}", Delta(250)), " +0.250s");
+    }
+}
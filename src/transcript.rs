@@ -0,0 +1,237 @@
+//! Transcripts of the keys pressed while paging, recorded with `--record`
+//! and replayed with `--replay`, e.g. to show a colleague the way to the
+//! cause of a problem, or to reproduce a bug in the pager.
+//!
+//! Every key read goes through `getch()` here, prompts included, so a
+//! transcript replays exactly what was typed, at the pace it was typed. Each
+//! key is recorded on a line of its own, as the milliseconds since the key
+//! before it, its code as returned by `ncurses::getch()`, and its name:
+//!
+//! ```text
+//! +1520 47 /
+//! +310 69 E
+//! # line 1, filter `E`
+//! ```
+//!
+//! Lines beginning with `#` describe the view the keys before them led to,
+//! for whoever reads the transcript, and are ignored when it's replayed.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{LineWriter, Read, Write};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ncurses;
+
+use keymap;
+use read_only;
+
+/// Longest pause between keys replayed, however long the recorded one.
+const MAX_PAUSE: Duration = Duration::from_secs(2);
+
+/// What becomes of the keys read.
+enum State {
+    /// keys are read from the keyboard and written to a transcript
+    Recording {
+        file: LineWriter<File>,
+        /// when the last key was read
+        last_key: Instant,
+    },
+    /// keys are read from a transcript rather than the keyboard
+    Replaying {
+        /// the keys yet to be replayed, each after a pause
+        keys: VecDeque<(Duration, i32)>,
+        /// when the next key is due
+        due: Instant,
+    },
+}
+
+struct Transcript {
+    state: Option<State>,
+    /// milliseconds `getch()` waits for a key, or a negative number if it
+    /// waits indefinitely, as set by `timeout()`
+    delay: i32,
+    /// error which stopped recording, if any, not yet taken by `take_error()`
+    error: Option<String>,
+}
+
+static TRANSCRIPT: Mutex<Transcript> = Mutex::new(Transcript {
+    state: None,
+    delay: -1,
+    error: None,
+});
+
+
+/// Starts recording the keys read to a transcript file.
+///
+/// ### Parameters
+/// * `path`: path of the file to create or overwrite
+/// * `source`: name of the input paged, given in the transcript's header
+pub fn record(path: &str, source: &str) -> Result<(), String> {
+    let mut file = read_only::create(path)
+        .map(LineWriter::new)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    writeln!(file, "# filterless transcript paging {}", source)
+        .map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut transcript = TRANSCRIPT.lock().expect("transcript lock poisoned");
+    transcript.state = Some(State::Recording { file: file, last_key: Instant::now() });
+    Ok(())
+}
+
+/// Starts replaying the keys recorded in a transcript file in place of those
+/// pressed, which are read once the transcript has been replayed.
+///
+/// ### Parameters
+/// * `path`: path of the transcript file
+pub fn replay(path: &str) -> Result<(), String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let keys = parse(&text).map_err(|e| format!("{}:{}", path, e))?;
+
+    let mut transcript = TRANSCRIPT.lock().expect("transcript lock poisoned");
+    let due = Instant::now() + keys.front().map_or(Duration::from_secs(0), |&(pause, _)| pause);
+    transcript.state = Some(State::Replaying { keys: keys, due: due });
+    Ok(())
+}
+
+/// Parses the keys of a transcript, each with the pause before it, capped
+/// at `MAX_PAUSE`; or returns an error giving the number of the line which
+/// can't be parsed.
+fn parse(text: &str) -> Result<VecDeque<(Duration, i32)>, String> {
+    let mut keys = VecDeque::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let pause = fields.next()
+            .and_then(|pause| pause.strip_prefix('+'))
+            .and_then(|millis| millis.parse().ok())
+            .map(Duration::from_millis);
+        let key = fields.next().and_then(|key| key.parse().ok());
+        match (pause, key) {
+            (Some(pause), Some(key)) => keys.push_back((pause.min(MAX_PAUSE), key)),
+            _ => return Err(format!("{}: expected `+MILLIS KEY`, found `{}`", idx + 1, line)),
+        }
+    }
+    Ok(keys)
+}
+
+/// Sets how long `getch()` waits for a key, as `ncurses::timeout()` does.
+///
+/// ### Parameters
+/// * `delay`: milliseconds to wait, or a negative number to wait
+///   indefinitely
+pub fn timeout(delay: i32) {
+    ncurses::timeout(delay);
+    TRANSCRIPT.lock().expect("transcript lock poisoned").delay = delay;
+}
+
+/// Reads a key as `ncurses::getch()` does, recording it if a transcript is
+/// being recorded, or returns the next key of one being replayed once it's
+/// due.
+pub fn getch() -> i32 {
+    let mut transcript = TRANSCRIPT.lock().expect("transcript lock poisoned");
+    let delay = transcript.delay;
+
+    let (key, failed) = match transcript.state {
+        Some(State::Replaying { ref mut keys, ref mut due }) if !keys.is_empty() => {
+            let now = Instant::now();
+            let wait = due.saturating_duration_since(now);
+            if delay >= 0 && wait > Duration::from_millis(delay as u64) {
+                thread::sleep(Duration::from_millis(delay as u64));
+                return ncurses::ERR;
+            }
+
+            thread::sleep(wait);
+            let (_, key) = keys.pop_front().expect("no key to replay");
+            *due = Instant::now() + keys.front().map_or(Duration::from_secs(0), |&(p, _)| p);
+            return key;
+        },
+        Some(State::Recording { ref mut file, ref mut last_key }) => {
+            record_key(file, last_key)
+        },
+        _ => (ncurses::getch(), None),
+    };
+
+    if let Some(err) = failed {
+        transcript.state = None;
+        transcript.error = Some(err);
+    }
+    if let Some(State::Replaying { .. }) = transcript.state {
+        // case: the transcript has been replayed
+        transcript.state = None;
+    }
+    key
+}
+
+/// Reads a key from the keyboard and records it in `file`, returning it
+/// along with the error which stopped recording, if any.
+fn record_key(file: &mut LineWriter<File>, last_key: &mut Instant) -> (i32, Option<String>) {
+    let key = ncurses::getch();
+    if key == ncurses::ERR {
+        return (key, None);
+    }
+
+    let now = Instant::now();
+    let pause = now.duration_since(*last_key);
+    *last_key = now;
+    let written = writeln!(file, "+{} {} {}", pause.as_millis(), key, keymap::key_name(key));
+    (key, written.err().map(|err| format!("Stopped recording: {}", err)))
+}
+
+/// Notes the view the keys recorded so far led to, if a transcript is being
+/// recorded, e.g. `line 120, filter `timeout``.
+pub fn note(view: &str) {
+    let mut transcript = TRANSCRIPT.lock().expect("transcript lock poisoned");
+    let written = match transcript.state {
+        Some(State::Recording { ref mut file, .. }) => writeln!(file, "# {}", view),
+        _ => return,
+    };
+    if let Err(err) = written {
+        transcript.state = None;
+        transcript.error = Some(format!("Stopped recording: {}", err));
+    }
+}
+
+/// Returns `true` while keys are being replayed from a transcript.
+pub fn is_replaying() -> bool {
+    let transcript = TRANSCRIPT.lock().expect("transcript lock poisoned");
+    matches!(transcript.state, Some(State::Replaying { .. }))
+}
+
+/// Returns the error which stopped recording, if any, the first time it's
+/// called after the error.
+pub fn take_error() -> Option<String> {
+    TRANSCRIPT.lock().expect("transcript lock poisoned").error.take()
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{parse, MAX_PAUSE};
+
+    #[test]
+    fn test_parse() {
+        let keys = parse("# filterless transcript paging app.log\n\
+                          +1520 47 /\n\
+                          +10 69 E\n\
+                          # line 1, filter `E`\n\
+                          \n\
+                          +60000 113 q\n").unwrap();
+        assert_eq!(keys, vec![(Duration::from_millis(1520), 47), (Duration::from_millis(10), 69),
+                              (MAX_PAUSE, 113)]);
+
+        assert_eq!(parse("+10 47\nj\n"), Err("2: expected `+MILLIS KEY`, found `j`".to_owned()));
+        assert_eq!(parse("10 47\n"),
+                   Err("1: expected `+MILLIS KEY`, found `10 47`".to_owned()));
+    }
+}
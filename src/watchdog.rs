@@ -0,0 +1,156 @@
+//! Watchdog which notices when a followed input stops producing lines, since
+//! the silent death of a log pipe is otherwise easy to miss.
+
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+
+/// Watcher of the number of lines read from a followed input, which raises
+/// the alarm once none have been read for a while.
+pub struct Watchdog {
+    /// time without new lines after which the input is considered stuck
+    threshold: Duration,
+    /// shell command run each time the input becomes stuck, if any
+    command: Option<String>,
+    /// number of lines read when new lines were last seen
+    lines_read: usize,
+    /// when new lines were last seen, or watching last resumed
+    last_data: Instant,
+    /// whether the alarm has been raised for the current stall
+    alerted: bool,
+}
+
+impl Watchdog {
+    /// Creates a `Watchdog`.
+    ///
+    /// ### Parameters
+    /// * `threshold`: time without new lines after which the input is
+    ///   considered stuck
+    /// * `command`: shell command to run each time the input becomes stuck,
+    ///   with the seconds since the last line in `FILTERLESS_IDLE_SECONDS`
+    pub fn new(threshold: Duration, command: Option<String>) -> Watchdog {
+        Watchdog {
+            threshold: threshold,
+            command: command,
+            lines_read: 0,
+            last_data: Instant::now(),
+            alerted: false,
+        }
+    }
+
+    /// Starts the period without new lines afresh, e.g. while the input isn't
+    /// being followed.
+    ///
+    /// ### Parameters
+    /// * `lines_read`: number of lines read from the input so far
+    pub fn reset(&mut self, lines_read: usize) {
+        self.lines_read = lines_read;
+        self.last_data = Instant::now();
+        self.alerted = false;
+    }
+
+    /// Records the number of lines read from the input so far, returning how
+    /// long it's been since new lines were last read if that's at least the
+    /// threshold.
+    ///
+    /// Runs the command, if any, the first time the threshold is reached
+    /// after new lines were read, returning an error if it can't be run.
+    ///
+    /// ### Parameters
+    /// * `lines_read`: number of lines read from the input so far
+    pub fn check(&mut self, lines_read: usize) -> Result<Option<Duration>, String> {
+        let idle = self.observe(lines_read, Instant::now());
+
+        match (idle, self.command.as_ref()) {
+            (Some(idle), Some(command)) if !self.alerted => {
+                self.alerted = true;
+                run(command, idle)?;
+            },
+            (Some(_), _) => self.alerted = true,
+            (None, _) => {},
+        }
+        Ok(idle)
+    }
+
+    /// Records the number of lines read as of `now`, returning how long it's
+    /// been since new lines were last read if that's at least the threshold.
+    fn observe(&mut self, lines_read: usize, now: Instant) -> Option<Duration> {
+        if lines_read != self.lines_read {
+            self.lines_read = lines_read;
+            self.last_data = now;
+            self.alerted = false;
+        }
+
+        let idle = now.saturating_duration_since(self.last_data);
+        if idle >= self.threshold { Some(idle) } else { None }
+    }
+}
+
+/// Starts `command` with `sh -c`, without waiting for it to finish.
+fn run(command: &str, idle: Duration) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("FILTERLESS_IDLE_SECONDS", idle.as_secs().to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("{}: {}", command, e))?;
+
+    // reaps the command once it finishes
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Parses a period such as `90s`, `5m` or `2h`, given in seconds if it has
+/// no unit.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let (number, scale) = match text.char_indices().last() {
+        Some((i, 's')) => (&text[..i], 1),
+        Some((i, 'm')) => (&text[..i], 60),
+        Some((i, 'h')) => (&text[..i], 3600),
+        _ => (text, 1),
+    };
+
+    number.parse::<u64>().ok()
+        .filter(|&n| n > 0)
+        .and_then(|n| n.checked_mul(scale))
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{parse_duration, Watchdog};
+
+    #[test]
+    fn test_observe() {
+        let mut watchdog = Watchdog::new(Duration::from_secs(60), None);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(watchdog.observe(10, at(0)), None);
+        assert_eq!(watchdog.observe(10, at(59)), None);
+        assert_eq!(watchdog.observe(10, at(61)), Some(Duration::from_secs(61)));
+        // new lines start the period afresh
+        assert_eq!(watchdog.observe(12, at(100)), None);
+        assert_eq!(watchdog.observe(12, at(159)), None);
+        assert_eq!(watchdog.observe(12, at(170)), Some(Duration::from_secs(70)));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("5d"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}